@@ -0,0 +1,263 @@
+use crate::XMachine;
+use std::fmt::Debug;
+
+/// A store split into named regions whose changes can be detected.
+///
+/// Implemented on the `Memory` type, typically with one region per field, by
+/// hand or with `sxm_memory_regions!`.
+pub trait MemoryRegions {
+    type Region: Copy + PartialEq + Debug + 'static;
+
+    /// Every region of the store.
+    fn all_regions() -> &'static [Self::Region];
+
+    /// `true` if `region` holds different values in `self` and `other`.
+    fn region_differs(&self, other: &Self, region: Self::Region) -> bool;
+
+    /// `self` with `region` holding its value in `donor`.
+    fn with_region_of(&self, donor: &Self, region: Self::Region) -> Self;
+}
+
+/// Declares which regions of the store each processing function may read
+/// and write.
+///
+/// Reads leave no trace in the store, so they are detected by transplanting:
+/// `phi` reads a region if giving it the value it holds in another store
+/// changes what `phi` emits, whether its guard holds, or what it writes.
+pub trait AccessControl: XMachine
+where
+    Self::Memory: MemoryRegions,
+{
+    fn writable_regions(phi: Self::Phi) -> &'static [<Self::Memory as MemoryRegions>::Region];
+
+    /// Regions `phi` may read. Every region by default, so only writes are checked.
+    fn readable_regions(_phi: Self::Phi) -> &'static [<Self::Memory as MemoryRegions>::Region] {
+        Self::Memory::all_regions()
+    }
+}
+
+/// Regions that changed between `before` and `after` although `phi` may not write them.
+pub fn undeclared_writes<T>(
+    phi: T::Phi,
+    before: &T::Memory,
+    after: &T::Memory,
+) -> Vec<<T::Memory as MemoryRegions>::Region>
+where
+    T: AccessControl,
+    T::Memory: MemoryRegions,
+{
+    let allowed = T::writable_regions(phi);
+    T::Memory::all_regions()
+        .iter()
+        .copied()
+        .filter(|region| !allowed.contains(region) && before.region_differs(after, *region))
+        .collect()
+}
+
+/// Regions `phi` may not read but does, as told by running it for `input` on
+/// `store` and on `store` with each such region taken from `donor`.
+///
+/// Only regions that differ between `store` and `donor` can be caught, so
+/// callers try several donors to widen the check.
+pub fn undeclared_reads<T>(
+    phi: T::Phi,
+    store: &T::Memory,
+    input: &T::Input,
+    donor: &T::Memory,
+) -> Vec<<T::Memory as MemoryRegions>::Region>
+where
+    T: AccessControl,
+    T::Memory: MemoryRegions,
+{
+    let allowed = T::readable_regions(phi);
+    let run = |store: &T::Memory| {
+        let mut after = store.clone();
+        let result = T::execute_phi(phi, &mut after, input);
+        (result, after)
+    };
+    let (result, after) = run(store);
+    T::Memory::all_regions()
+        .iter()
+        .copied()
+        .filter(|&region| !allowed.contains(&region) && store.region_differs(donor, region))
+        .filter(|&region| {
+            let transplanted = store.with_region_of(donor, region);
+            let (other_result, other_after) = run(&transplanted);
+            let written = store.region_differs(&after, region) || transplanted.region_differs(&other_after, region);
+            other_result != result
+                || T::Memory::all_regions()
+                    .iter()
+                    .any(|&other| (other != region || written) && after.region_differs(&other_after, other))
+        })
+        .collect()
+}
+
+/// Declares the regions of a memory struct as a fieldless enum, one variant
+/// per field, with `Copy`, `Clone`, `PartialEq`, `Eq` and `Debug` derived,
+/// and implements `access::MemoryRegions` for the struct.
+///
+/// Each variant names the field it stands for, e.g.
+/// `pub enum DigicodeRegion for DigicodeMemory { Sequence => current_sequence, Code => valid_code }`.
+/// Fields must be `Clone` and `PartialEq`.
+#[macro_export]
+macro_rules! sxm_memory_regions {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident for $memory:ty {
+            $($variant:ident => $field:ident),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+        $vis enum $name {
+            $($variant),*
+        }
+
+        impl $crate::access::MemoryRegions for $memory {
+            type Region = $name;
+
+            fn all_regions() -> &'static [$name] {
+                &[$($name::$variant),*]
+            }
+
+            fn region_differs(&self, other: &Self, region: $name) -> bool {
+                match region {
+                    $($name::$variant => self.$field != other.$field),*
+                }
+            }
+
+            fn with_region_of(&self, donor: &Self, region: $name) -> Self {
+                let mut store = self.clone();
+                match region {
+                    $($name::$variant => store.$field = donor.$field.clone()),*
+                }
+                store
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::access_violations;
+    use std::borrow::Cow;
+
+    crate::sxm_store! {
+        #[derive(Debug)]
+        struct Budget {
+            spent: u32,
+            limit: u32,
+        }
+    }
+
+    crate::sxm_memory_regions! {
+        enum BudgetRegion for Budget {
+            Spent => spent,
+            Limit => limit,
+        }
+    }
+
+    /// Spends one unit per input while under the limit, which `Raise` lifts.
+    struct Wallet;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Op {
+        Spend,
+        Raise,
+    }
+
+    impl XMachine for Wallet {
+        type Input = Op;
+        type Output = u32;
+        type State = ();
+        type Memory = Budget;
+        type Phi = Op;
+
+        fn next_state(_state: (), _phi: Op) -> Option<()> {
+            Some(())
+        }
+
+        fn initial_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn final_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn initial_store() -> Budget {
+            Budget { spent: 0, limit: 1 }
+        }
+
+        fn execute_phi(phi: Op, store: &mut Budget, _input: &Op) -> Result<Option<u32>, ()> {
+            match phi {
+                Op::Spend if store.spent < store.limit => store.spent += 1,
+                Op::Spend => return Err(()),
+                Op::Raise => store.limit += 1,
+            }
+            Ok(Some(store.spent))
+        }
+
+        fn all_inputs() -> Cow<'static, [Op]> {
+            Cow::Borrowed(&[Op::Spend, Op::Raise])
+        }
+
+        fn all_outputs() -> Cow<'static, [u32]> {
+            Cow::Borrowed(&[])
+        }
+
+        fn all_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn all_phis() -> &'static [Op] {
+            &[Op::Spend, Op::Raise]
+        }
+
+        fn get_phi_for_input(_state: (), input: &Op) -> Option<Op> {
+            Some(*input)
+        }
+    }
+
+    /// `Spend` claims not to look at the limit, `Raise` not to touch it.
+    impl AccessControl for Wallet {
+        fn writable_regions(phi: Op) -> &'static [BudgetRegion] {
+            match phi {
+                Op::Spend => &[BudgetRegion::Spent],
+                Op::Raise => &[],
+            }
+        }
+
+        fn readable_regions(phi: Op) -> &'static [BudgetRegion] {
+            match phi {
+                Op::Spend => &[BudgetRegion::Spent],
+                Op::Raise => &[BudgetRegion::Spent, BudgetRegion::Limit],
+            }
+        }
+    }
+
+    #[test]
+    fn reads_are_caught_by_transplanting_regions() {
+        let store = Budget { spent: 1, limit: 1 };
+        let donor = Budget { spent: 0, limit: 2 };
+        assert_eq!(undeclared_reads::<Wallet>(Op::Spend, &store, &Op::Spend, &donor), [BudgetRegion::Limit]);
+        assert_eq!(undeclared_reads::<Wallet>(Op::Raise, &store, &Op::Raise, &donor), []);
+    }
+
+    #[test]
+    fn access_violations_report_reads_and_writes() {
+        let violations = access_violations::<Wallet>(2);
+        assert!(violations.iter().any(|v| v.phi == Op::Spend && v.reads == [BudgetRegion::Limit]));
+        assert!(violations.iter().all(|v| v.phi != Op::Spend || v.writes.is_empty()));
+        assert!(violations.iter().any(|v| v.phi == Op::Raise && v.writes == [BudgetRegion::Limit]));
+        assert!(violations.iter().all(|v| v.phi != Op::Raise || v.reads.is_empty()));
+    }
+
+    #[test]
+    #[should_panic(expected = "read undeclared memory regions")]
+    fn checked_steps_panic_on_undeclared_reads() {
+        let mut runner = crate::runner::MachineRunner::<Wallet>::from_parts((), Budget { spent: 1, limit: 2 });
+        let _ = runner.step_checked(&Op::Spend);
+    }
+}
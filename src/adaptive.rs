@@ -0,0 +1,366 @@
+use crate::configuration::Configuration;
+use crate::executor::{Executor, Verdict};
+use crate::mbt::{Expectation, TestCase, TestCaseOf};
+use crate::runner::MachineRunner;
+use crate::store::Store;
+use crate::XMachine;
+
+/// An adaptive distinguishing sequence: a decision tree that picks the next
+/// input based on the outputs observed so far, until the state the machine
+/// was in when the walk started is identified.
+///
+/// Compared with a fixed characterization sequence, each branch only applies
+/// the inputs needed to separate the states still in question.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DistinguishingTree<Input, Output, State> {
+    /// The observations identify the starting state.
+    Leaf(State),
+    /// Apply `input`, then follow the branch of the observed output (`None`
+    /// when the machine produced no output or rejected the input).
+    Node {
+        input: Input,
+        branches: Vec<(Option<Output>, DistinguishingTree<Input, Output, State>)>,
+    },
+    /// The remaining states could not be separated within the depth bound.
+    Unresolved(Vec<State>),
+}
+
+/// `DistinguishingTree` specialised to the symbols of machine `T`.
+pub type DistinguishingTreeOf<T> =
+    DistinguishingTree<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Input, Output: PartialEq, State> DistinguishingTree<Input, Output, State> {
+    /// The subtree to follow after observing `output` at a node.
+    pub fn branch(&self, output: Option<&Output>) -> Option<&Self> {
+        match self {
+            DistinguishingTree::Node { branches, .. } => branches
+                .iter()
+                .find(|(observed, _)| observed.as_ref() == output)
+                .map(|(_, subtree)| subtree),
+            _ => None,
+        }
+    }
+
+    /// Length of the longest input sequence the tree can apply.
+    pub fn height(&self) -> usize {
+        match self {
+            DistinguishingTree::Node { branches, .. } => {
+                1 + branches.iter().map(|(_, t)| t.height()).max().unwrap_or(0)
+            }
+            _ => 0,
+        }
+    }
+
+    /// `true` if every leaf identifies a single state.
+    pub fn is_complete(&self) -> bool {
+        match self {
+            DistinguishingTree::Leaf(_) => true,
+            DistinguishingTree::Node { branches, .. } => branches.iter().all(|(_, t)| t.is_complete()),
+            DistinguishingTree::Unresolved(_) => false,
+        }
+    }
+}
+
+/// Builds a tree separating the states of `candidates`, applying at most
+/// `depth` inputs along any branch.
+///
+/// Candidates in the same state need not be told apart, so several
+/// configurations per state may be given to cover different memories. Inputs
+/// are chosen greedily: the one splitting the candidates into the most output
+/// groups, or failing that, the first one that changes some configuration.
+pub fn build_tree<T>(candidates: &[Configuration<T>], depth: usize) -> DistinguishingTreeOf<T>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let tracked = candidates.iter().map(|c| (c.state, c.clone())).collect();
+    split::<T>(tracked, depth, &T::all_inputs())
+}
+
+/// Builds a tree over every distinct configuration reachable within
+/// `explore_depth` inputs.
+pub fn tree_for_reachable<T>(explore_depth: usize, depth: usize) -> DistinguishingTreeOf<T>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    build_tree::<T>(&reachable_configurations::<T>(explore_depth), depth)
+}
+
+/// Every distinct configuration reachable within `explore_depth` inputs of an
+/// initial one, in breadth-first order, e.g. as the candidates of `build_tree`
+/// or `homing::homing_sequence`.
+pub fn reachable_configurations<T>(explore_depth: usize) -> Vec<Configuration<T>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut candidates = Configuration::<T>::initial();
+    let mut frontier = candidates.clone();
+    let inputs = T::all_inputs();
+    for _ in 0..explore_depth {
+        let mut next_frontier = Vec::new();
+        for config in &frontier {
+            for input in inputs.iter() {
+                if let Ok((_, next)) = config.step(input) {
+                    if !candidates.contains(&next) {
+                        candidates.push(next.clone());
+                        next_frontier.push(next);
+                    }
+                }
+            }
+        }
+        frontier = next_frontier;
+    }
+    candidates
+}
+
+/// An adaptive test: inputs to apply after the setup, each next one chosen
+/// by the output just observed, down to a verdict.
+///
+/// It replaces the fixed verification sequence of a linear test case, whose
+/// every input is applied whatever the SUT answers, by a walk that applies
+/// only the inputs needed, which matters against slow hardware.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TestTree<Input, Output, State> {
+    /// Apply `input` and follow the first branch whose expectation the output
+    /// satisfies. An output no branch accepts fails the test.
+    Apply {
+        input: Input,
+        branches: Vec<(Expectation<Output>, TestTree<Input, Output, State>)>,
+    },
+    /// The walk is over.
+    Verdict(Verdict<Output, State>),
+}
+
+/// `TestTree` specialised to the symbols of machine `T`.
+pub type TestTreeOf<T> = TestTree<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Input, Output, State> TestTree<Input, Output, State> {
+    /// Length of the longest input sequence the tree can apply.
+    pub fn height(&self) -> usize {
+        match self {
+            TestTree::Apply { branches, .. } => 1 + branches.iter().map(|(_, t)| t.height()).max().unwrap_or(0),
+            TestTree::Verdict(_) => 0,
+        }
+    }
+}
+
+/// A test case whose test input and state verification form a `TestTree`,
+/// run by `Executor::run_adaptive_case`.
+#[derive(Debug)]
+pub struct AdaptiveTestCase<Input, Output, State, Memory> {
+    pub name: String,
+    /// Inputs reaching the state under test, as in `TestCase`.
+    pub setup_sequence: Vec<Input>,
+    /// Injected instead of replaying `setup_sequence` when the SUT supports it.
+    pub setup_configuration: Option<(State, Memory)>,
+    pub requirements: Vec<&'static str>,
+    /// State the spec is in after the first input of the tree, the one
+    /// before it if it rejects the input. `None` if the setup is rejected.
+    pub expected_state: Option<State>,
+    /// Rooted at the test input.
+    pub tree: TestTree<Input, Output, State>,
+}
+
+/// `AdaptiveTestCase` specialised to the symbols and memory of machine `T`.
+pub type AdaptiveTestCaseOf<T> = AdaptiveTestCase<
+    <T as XMachine>::Input,
+    <T as XMachine>::Output,
+    <T as XMachine>::State,
+    <T as XMachine>::Memory,
+>;
+
+impl<Input, Output, State, Memory> AdaptiveTestCase<Input, Output, State, Memory>
+where
+    Input: Clone,
+    Output: Clone + PartialEq,
+    State: Copy + PartialEq,
+    Memory: Clone,
+{
+    /// Turns `case` into an adaptive case verifying the state it reaches with
+    /// `tree` instead of its verification sequence: leaves naming the
+    /// expected state pass, other leaves give a `StateMismatch`, and
+    /// unresolved ones `Unidentified`. Where the spec rejects the test input,
+    /// the SUT must still be in the state it was in before it.
+    pub fn from_case<T>(case: &TestCaseOf<T>, tree: &DistinguishingTreeOf<T>) -> Self
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+    {
+        let expected_state = Executor::expected_state::<T>(case);
+        let verification = match expected_state {
+            Some(expected) => verification_tree::<T>(tree, expected),
+            None => TestTree::Verdict(Verdict::Pass),
+        };
+        AdaptiveTestCase {
+            name: case.name.clone(),
+            setup_sequence: case.setup_sequence.clone(),
+            setup_configuration: case.setup_configuration.clone(),
+            requirements: case.requirements.clone(),
+            expected_state,
+            tree: TestTree::Apply {
+                input: case.test_input.clone(),
+                branches: vec![(case.expected_output.clone(), verification)],
+            },
+        }
+    }
+
+    /// The linear case for rigs that cannot branch: the path through the
+    /// tree the spec takes from the setup configuration, its first input as
+    /// test input and the rest as verification sequence, which
+    /// `Executor::run_case` judges by outputs when the state is hidden.
+    /// `None` if the tree applies no input.
+    pub fn flatten<T>(&self) -> Option<TestCase<Input, Output, State, Memory>>
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+    {
+        let mut spec = match &self.setup_configuration {
+            Some((state, store)) => Some(MachineRunner::<T>::from_parts(*state, store.clone())),
+            None => {
+                let mut spec = MachineRunner::<T>::new();
+                spec.run(&self.setup_sequence).ok().map(|_| spec)
+            }
+        };
+        let TestTree::Apply { input: test_input, branches } = &self.tree else {
+            return None;
+        };
+        let mut step = |input: &Input| spec.as_mut().and_then(|spec| spec.step(input).ok()).and_then(|t| t.output);
+
+        let output = step(test_input);
+        let (expected_output, mut node) = branches
+            .iter()
+            .find(|(expected, _)| expected.matches(output.as_ref()))
+            .or(branches.first())
+            .map_or((Expectation::None, None), |(expected, next)| (expected.clone(), Some(next)));
+        let mut verification_sequence = Vec::new();
+        while let Some(TestTree::Apply { input, branches }) = node {
+            verification_sequence.push(input.clone());
+            let output = step(input);
+            node = branches.iter().find(|(expected, _)| expected.matches(output.as_ref())).map(|(_, next)| next);
+        }
+
+        Some(TestCase {
+            name: self.name.clone(),
+            setup_sequence: self.setup_sequence.clone(),
+            test_input: test_input.clone(),
+            expected_output,
+            verification_sequence,
+            requirements: self.requirements.clone(),
+            setup_configuration: self.setup_configuration.clone(),
+        })
+    }
+}
+
+/// `AdaptiveTestCase::from_case` over a whole suite, sharing one tree.
+pub fn adaptive_suite<T: XMachine>(
+    cases: &[TestCaseOf<T>],
+    tree: &DistinguishingTreeOf<T>,
+) -> Vec<AdaptiveTestCaseOf<T>> {
+    cases.iter().map(|case| AdaptiveTestCase::from_case::<T>(case, tree)).collect()
+}
+
+fn verification_tree<T: XMachine>(tree: &DistinguishingTreeOf<T>, expected: T::State) -> TestTreeOf<T> {
+    match tree {
+        DistinguishingTree::Leaf(state) if *state == expected => TestTree::Verdict(Verdict::Pass),
+        DistinguishingTree::Leaf(state) => TestTree::Verdict(Verdict::StateMismatch {
+            expected,
+            actual: *state,
+        }),
+        DistinguishingTree::Unresolved(_) => TestTree::Verdict(Verdict::Unidentified { expected }),
+        DistinguishingTree::Node { input, branches } => TestTree::Apply {
+            input: input.clone(),
+            branches: branches
+                .iter()
+                .map(|(output, subtree)| (output.clone().into(), verification_tree::<T>(subtree, expected)))
+                .collect(),
+        },
+    }
+}
+
+/// Candidate configurations, each paired with the state it started the walk in.
+type Tracked<T> = Vec<(<T as XMachine>::State, Configuration<T>)>;
+
+/// Candidates grouped by the output they produced.
+type Groups<T> = Vec<(Option<<T as XMachine>::Output>, Tracked<T>)>;
+
+fn split<T>(
+    candidates: Tracked<T>,
+    depth: usize,
+    inputs: &[T::Input],
+) -> DistinguishingTreeOf<T>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut origins: Vec<T::State> = Vec::new();
+    for (origin, _) in &candidates {
+        if !origins.contains(origin) {
+            origins.push(*origin);
+        }
+    }
+    match origins.as_slice() {
+        [origin] => return DistinguishingTree::Leaf(*origin),
+        [] => return DistinguishingTree::Unresolved(origins),
+        _ if depth == 0 => return DistinguishingTree::Unresolved(origins),
+        _ => {}
+    }
+
+    let mut best: Option<(usize, &T::Input, Groups<T>)> = None;
+    for input in inputs {
+        let mut groups: Groups<T> = Vec::new();
+        let mut progress = false;
+        for (origin, config) in &candidates {
+            let (output, next) = match config.step(input) {
+                Ok((transition, next)) => (transition.output, next),
+                Err(_) => (None, config.clone()),
+            };
+            progress |= next != *config;
+            match groups.iter_mut().find(|(o, _)| *o == output) {
+                Some((_, group)) => group.push((*origin, next)),
+                None => groups.push((output, vec![(*origin, next)])),
+            }
+        }
+        let score = if groups.len() > 1 {
+            groups.len() + 1
+        } else {
+            usize::from(progress)
+        };
+        if score > 0 && best.as_ref().map_or(true, |(s, _, _)| score > *s) {
+            best = Some((score, input, groups));
+        }
+    }
+
+    let Some((_, input, groups)) = best else {
+        return DistinguishingTree::Unresolved(origins);
+    };
+    DistinguishingTree::Node {
+        input: input.clone(),
+        branches: groups
+            .into_iter()
+            .map(|(output, group)| (output, split::<T>(group, depth - 1, inputs)))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::TrafficLight;
+    use crate::suite::TestSuiteBuilder;
+
+    #[test]
+    fn adaptive_cases_pass_on_the_spec() {
+        let tree = tree_for_reachable::<TrafficLight>(3, 3);
+        assert!(tree.is_complete());
+        assert!(tree.height() <= 3);
+        let suite = TestSuiteBuilder::<TrafficLight>::new().robustness().build();
+        let cases = adaptive_suite::<TrafficLight>(&suite.cases, &tree);
+        let report = Executor::run_adaptive_suite::<TrafficLight, _>(&cases, MachineRunner::<TrafficLight>::new);
+        assert_eq!(report.passed(), cases.len());
+        for case in &cases {
+            let linear = case.flatten::<TrafficLight>().unwrap();
+            let result = Executor::run_case::<TrafficLight, _>(&mut MachineRunner::<TrafficLight>::new(), &linear);
+            assert_eq!(result.verdict, Verdict::Pass, "{}", case.name);
+        }
+    }
+}
@@ -0,0 +1,1163 @@
+use crate::access::{undeclared_reads, undeclared_writes, AccessControl, MemoryRegions};
+use crate::configuration::Configuration;
+use crate::isolation;
+use crate::labels;
+use crate::search::DEFAULT_MAX_LEN;
+use crate::store::Store;
+use crate::{TransitionSpec, XMachine};
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+
+/// Drift between the declared output alphabet Γ and what the machine emits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputCompleteness<Output> {
+    /// Declared in `all_outputs()` but never produced within the explored bound.
+    pub never_produced: Vec<Output>,
+    /// Produced by a reachable step but missing from `all_outputs()`.
+    pub undeclared: Vec<Output>,
+}
+
+impl<Output> OutputCompleteness<Output> {
+    /// `true` when declared and produced outputs coincide.
+    pub fn is_complete(&self) -> bool {
+        self.never_produced.is_empty() && self.undeclared.is_empty()
+    }
+}
+
+/// Compares `all_outputs()` against the outputs of every step taken from a
+/// configuration reachable in at most `depth` inputs.
+///
+/// `never_produced` is bounded evidence only: an output may still be produced
+/// deeper than `depth`.
+pub fn output_completeness<T: XMachine>(depth: usize) -> OutputCompleteness<T::Output>
+where
+    T::Memory: Store,
+{
+    let mut produced: Vec<T::Output> = Vec::new();
+    for_each_reachable_step::<T>(depth, |step| {
+        if let Ok(Some(output)) = step.result {
+            if !produced.contains(output) {
+                produced.push(output.clone());
+            }
+        }
+    });
+
+    OutputCompleteness {
+        never_produced: T::all_outputs()
+            .iter()
+            .filter(|o| !produced.contains(o))
+            .cloned()
+            .collect(),
+        undeclared: produced
+            .into_iter()
+            .filter(|o| !T::all_outputs().contains(o))
+            .collect(),
+    }
+}
+
+/// A transition of the associated automaton leading into a given state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Predecessor<State, Input, Phi> {
+    pub from: State,
+    pub input: Input,
+    pub phi: Phi,
+}
+
+/// `Predecessor` specialised to the symbols of machine `T`.
+pub type PredecessorOf<T> = Predecessor<<T as XMachine>::State, <T as XMachine>::Input, <T as XMachine>::Phi>;
+
+/// Every (state, input) pair whose phi leads into `state`, ignoring guards,
+/// in `all_states()` then `all_inputs()` order.
+pub fn predecessors<T: XMachine>(state: T::State) -> Vec<PredecessorOf<T>> {
+    let inputs = T::all_inputs();
+    let mut predecessors = Vec::new();
+    for &from in T::all_states() {
+        for input in inputs.iter() {
+            let Some(phi) = T::get_phi_for_input(from, input) else {
+                continue;
+            };
+            if T::next_state(from, phi) == Some(state) {
+                predecessors.push(Predecessor {
+                    from,
+                    input: input.clone(),
+                    phi,
+                });
+            }
+        }
+    }
+    predecessors
+}
+
+/// Every input sequence of at most `max_len` inputs that the spec accepts
+/// from an initial configuration and that ends in `state`, shortest first.
+///
+/// Sequences are executed, so guards on memory are honoured. The search only
+/// extends prefixes from which `predecessors` can still lead into `state`
+/// within the remaining length, but the result grows with the number of
+/// paths, so keep `max_len` small. A sequence passing through `state` before
+/// ending there is reported alongside its prefix.
+pub fn all_paths_to<T: XMachine>(state: T::State, max_len: usize) -> Vec<Vec<T::Input>> {
+    // Fewest transitions from each state into `state`, by a backward
+    // breadth-first search over the associated automaton.
+    let mut distances: Vec<(T::State, usize)> = vec![(state, 0)];
+    let mut next = 0;
+    while let Some(&(target, distance)) = distances.get(next) {
+        next += 1;
+        for predecessor in predecessors::<T>(target) {
+            if !distances.iter().any(|(s, _)| *s == predecessor.from) {
+                distances.push((predecessor.from, distance + 1));
+            }
+        }
+    }
+    let distance = |s: T::State| distances.iter().find(|(d, _)| *d == s).map(|(_, d)| *d);
+
+    let inputs = T::all_inputs();
+    let mut paths = Vec::new();
+    let mut frontier: Vec<(Configuration<T>, Vec<T::Input>)> = Configuration::<T>::initial()
+        .into_iter()
+        .filter(|config| distance(config.state).is_some_and(|d| d <= max_len))
+        .map(|config| (config, Vec::new()))
+        .collect();
+    for len in 0..=max_len {
+        let mut extended = Vec::new();
+        for (config, path) in frontier {
+            if config.state == state {
+                paths.push(path.clone());
+            }
+            if len == max_len {
+                continue;
+            }
+            for input in inputs.iter() {
+                let Ok((_, next)) = config.step(input) else {
+                    continue;
+                };
+                if distance(next.state).is_some_and(|d| len + 1 + d <= max_len) {
+                    let mut path = path.clone();
+                    path.push(input.clone());
+                    extended.push((next, path));
+                }
+            }
+        }
+        frontier = extended;
+    }
+    paths
+}
+
+/// Inputs the spec leaves `TransitionSpec::Unspecified`, grouped by state.
+/// States without such inputs are omitted.
+pub fn unspecified_inputs<T: XMachine>() -> Vec<(T::State, Vec<T::Input>)> {
+    T::all_states()
+        .iter()
+        .map(|&state| {
+            let inputs: Vec<T::Input> = T::all_inputs()
+                .iter()
+                .filter(|input| T::transition_spec(state, input) == TransitionSpec::Unspecified)
+                .cloned()
+                .collect();
+            (state, inputs)
+        })
+        .filter(|(_, inputs)| !inputs.is_empty())
+        .collect()
+}
+
+/// How the spec handles an input in a state, as seen by the environment.
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputHandling<Phi, Output> {
+    /// The input selects `phi`.
+    Transition(Phi),
+    /// The input selects a self-loop phi that `XMachine::ignore_output`
+    /// declares an explicit ignore, which must emit `output`.
+    Ignored { phi: Phi, output: Output },
+    /// The input has no phi and must be rejected.
+    Forbidden,
+    /// The spec leaves the input open.
+    Unspecified,
+}
+
+/// `InputHandling` specialised to the symbols of machine `T`.
+pub type InputHandlingOf<T> = InputHandling<<T as XMachine>::Phi, <T as XMachine>::Output>;
+
+/// How `input` is handled in `state`. A phi declared an ignore that leaves
+/// the state counts as a `Transition`.
+pub fn input_handling<T: XMachine>(state: T::State, input: &T::Input) -> InputHandlingOf<T> {
+    match T::transition_spec(state, input) {
+        TransitionSpec::Defined(phi) => match T::ignore_output(phi) {
+            Some(output) if T::next_state(state, phi) == Some(state) => InputHandling::Ignored { phi, output },
+            _ => InputHandling::Transition(phi),
+        },
+        TransitionSpec::Forbidden => InputHandling::Forbidden,
+        TransitionSpec::Unspecified => InputHandling::Unspecified,
+    }
+}
+
+/// Inputs handled by an explicit ignore, grouped by state. States without
+/// such inputs are omitted.
+pub fn ignored_inputs<T: XMachine>() -> Vec<(T::State, Vec<T::Input>)> {
+    T::all_states()
+        .iter()
+        .map(|&state| {
+            let inputs: Vec<T::Input> = T::all_inputs()
+                .iter()
+                .filter(|input| matches!(input_handling::<T>(state, input), InputHandling::Ignored { .. }))
+                .cloned()
+                .collect();
+            (state, inputs)
+        })
+        .filter(|(_, inputs)| !inputs.is_empty())
+        .collect()
+}
+
+/// A reachable configuration whose memory breaks an invariant of its state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvariantViolation<State, Phi> {
+    /// The state entered.
+    pub state: State,
+    /// The step that entered it; `None` for an initial configuration.
+    pub via: Option<(State, Phi)>,
+    /// `StateInvariant::doc` of the broken invariant.
+    pub invariant: &'static str,
+}
+
+/// `InvariantViolation` specialised to the symbols of machine `T`.
+pub type InvariantViolationOf<T> = InvariantViolation<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// Checks `XMachine::state_invariants` on every configuration entered within
+/// `depth` inputs, including the initial ones.
+pub fn invariant_violations<T: XMachine>(depth: usize) -> Vec<InvariantViolationOf<T>>
+where
+    T::Memory: Store,
+{
+    let mut violations = Vec::new();
+    let mut check = |state: T::State, store: &T::Memory, via: Option<(T::State, T::Phi)>| {
+        for invariant in T::state_invariants(state) {
+            let violation = InvariantViolation {
+                state,
+                via,
+                invariant: invariant.doc,
+            };
+            if !(invariant.check)(store) && !violations.contains(&violation) {
+                violations.push(violation);
+            }
+        }
+    };
+
+    for &start in T::initial_states() {
+        check(start, &T::initial_store(), None);
+    }
+    for_each_reachable_step::<T>(depth, |step| {
+        if step.result.is_err() {
+            return;
+        }
+        if let Some(next_state) = T::next_state(step.state, step.phi) {
+            check(next_state, step.next_store, Some((step.state, step.phi)));
+        }
+    });
+    violations
+}
+
+/// How soon the guard of `phi` can first be satisfied in `state`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DReachability<State, Phi> {
+    pub state: State,
+    pub phi: Phi,
+    /// Fewest inputs applied before a step in which `phi` succeeds from
+    /// `state`, or `None` if there is none within the explored bound.
+    pub steps: Option<usize>,
+}
+
+/// `DReachability` specialised to the symbols of machine `T`.
+pub type DReachabilityOf<T> = DReachability<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// The result of `d_reachability`: one entry per transition of the
+/// associated automaton, in `all_states()` then `all_phis()` order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DReachabilityReport<State, Phi> {
+    /// Inputs explored from the initial configurations.
+    pub depth: usize,
+    pub entries: Vec<DReachability<State, Phi>>,
+}
+
+/// `DReachabilityReport` specialised to the symbols of machine `T`.
+pub type DReachabilityReportOf<T> = DReachabilityReport<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+impl<State: Debug, Phi: Debug> DReachabilityReport<State, Phi> {
+    /// Transitions whose guard was never satisfied within the bound.
+    pub fn unreached(&self) -> impl Iterator<Item = &DReachability<State, Phi>> {
+        self.entries.iter().filter(|entry| entry.steps.is_none())
+    }
+
+    /// One line per transition, e.g. `Accepting --Finish--> depth 3`, with
+    /// symbols named by `T::labeling()`.
+    pub fn to_text<T: XMachine<State = State, Phi = Phi>>(&self) -> String
+    where
+        State: Copy,
+        Phi: Copy,
+    {
+        let mut out = String::new();
+        for entry in &self.entries {
+            let (state, phi) = (labels::state::<T>(entry.state), labels::phi::<T>(entry.phi));
+            match entry.steps {
+                Some(steps) => writeln!(out, "{} --{}--> depth {}", state, phi, steps),
+                None => writeln!(out, "{} --{}--> not within depth {}", state, phi, self.depth),
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// For every `(state, phi)` with a target in `next_state`, the length of the
+/// shortest input sequence after which `phi` can be taken from `state` with
+/// its guard holding, over configurations reachable within `depth` inputs.
+///
+/// Transitions needing long setups, or none within the bound, are the
+/// expensive parts of the model to test and candidates for test hooks such
+/// as `setup_configuration`.
+pub fn d_reachability<T: XMachine>(depth: usize) -> DReachabilityReportOf<T>
+where
+    T::Memory: Store,
+{
+    let mut entries: Vec<DReachabilityOf<T>> = Vec::new();
+    for &state in T::all_states() {
+        for &phi in T::all_phis() {
+            if T::next_state(state, phi).is_some() {
+                entries.push(DReachability { state, phi, steps: None });
+            }
+        }
+    }
+
+    // Levels are visited in increasing order, so the first success is the shortest.
+    for_each_reachable_step::<T>(depth, |step| {
+        if step.result.is_err() {
+            return;
+        }
+        let kind = T::phi_kind(step.phi);
+        let entry = entries.iter_mut().find(|e| e.state == step.state && e.phi == kind);
+        if let Some(entry) = entry.filter(|e| e.steps.is_none()) {
+            entry.steps = Some(step.level);
+        }
+    });
+    DReachabilityReport { depth, entries }
+}
+
+/// A structural inconsistency between the tables of a machine, typically a
+/// copy-paste mistake.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpecIssue<State, Phi> {
+    /// An initial or final state missing from `all_states()`.
+    UndeclaredState(State),
+    /// A state produced by `next_state` that is missing from `all_states()`.
+    UndeclaredTarget { state: State, phi: Phi, target: State },
+    /// A phi returned by `get_phi_for_input` that is missing from `all_phis()`.
+    UndeclaredPhi { state: State, phi: Phi },
+    /// `next_state` defines `(state, phi)` but no input selects `phi` in `state`.
+    UnreachablePhi { state: State, phi: Phi },
+    /// `get_phi_for_input` selects `phi` in `state` but `next_state` is undefined for it.
+    MissingTarget { state: State, phi: Phi },
+    /// `reset_input` does not lead from `state`, in a configuration reachable
+    /// within `search::DEFAULT_MAX_LEN` inputs, to an initial configuration:
+    /// an initial state with `initial_store()`.
+    UnreliableReset { state: State },
+}
+
+/// `SpecIssue` specialised to the symbols of machine `T`.
+pub type SpecIssueOf<T> = SpecIssue<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// Cross-checks `all_states`, `all_phis`, `initial_states`, `final_states`,
+/// `next_state`, `get_phi_for_input` and `reset_input` against each other.
+///
+/// Only the finite tables are inspected, except for `reset_input`, which is
+/// also run from every configuration reachable within
+/// `search::DEFAULT_MAX_LEN` inputs, so a reset that leaves memory behind or
+/// is refused by a guard is reported. `sxm_well_formed_test!` wraps this
+/// into a unit test.
+pub fn well_formedness<T: XMachine>() -> Vec<SpecIssueOf<T>>
+where
+    T::Memory: Store,
+{
+    let mut issues = Vec::new();
+    let declared = |state: &T::State| T::all_states().contains(state);
+
+    for state in T::initial_states().iter().chain(T::final_states()) {
+        let issue = SpecIssue::UndeclaredState(*state);
+        if !declared(state) && !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    }
+
+    let inputs = T::all_inputs();
+    for &state in T::all_states() {
+        let mut dispatched: Vec<T::Phi> = Vec::new();
+        for input in inputs.iter() {
+            if let Some(phi) = T::get_phi_for_input(state, input).map(T::phi_kind) {
+                if !dispatched.contains(&phi) {
+                    dispatched.push(phi);
+                }
+            }
+        }
+        for &phi in &dispatched {
+            if !T::all_phis().contains(&phi) {
+                issues.push(SpecIssue::UndeclaredPhi { state, phi });
+            }
+            if T::next_state(state, phi).is_none() {
+                issues.push(SpecIssue::MissingTarget { state, phi });
+            }
+        }
+        for &phi in T::all_phis() {
+            let Some(target) = T::next_state(state, phi) else {
+                continue;
+            };
+            if !declared(&target) {
+                issues.push(SpecIssue::UndeclaredTarget { state, phi, target });
+            }
+            if !dispatched.contains(&phi) {
+                issues.push(SpecIssue::UnreachablePhi { state, phi });
+            }
+        }
+        if let Some(reset) = T::reset_input() {
+            let target = T::get_phi_for_input(state, &reset).and_then(|phi| T::next_state(state, phi));
+            if !target.is_some_and(|target| T::initial_states().contains(&target)) {
+                issues.push(SpecIssue::UnreliableReset { state });
+            }
+        }
+    }
+
+    if let Some(reset) = T::reset_input() {
+        let initial = Configuration::<T>::initial();
+        for_each_reachable_step::<T>(DEFAULT_MAX_LEN, |step| {
+            if *step.input != reset {
+                return;
+            }
+            let target = step.result.as_ref().ok().and_then(|_| T::next_state(step.state, step.phi));
+            let resets = target.is_some_and(|to| initial.iter().any(|c| c.state == to && c.store == *step.next_store));
+            let issue = SpecIssue::UnreliableReset { state: step.state };
+            if !resets && !issues.contains(&issue) {
+                issues.push(issue);
+            }
+        });
+    }
+    issues
+}
+
+/// Defines a unit test named `$name` that fails if `analysis::well_formedness`
+/// reports any issue for `$machine`, other than those listed after `allow`.
+///
+/// The machine tables are ordinary functions, so these checks cannot run at
+/// compile time; this turns them into a test failure instead. Listing an
+/// issue as allowed documents a deliberate gap, such as a transition kept in
+/// `next_state` that `get_phi_for_input` does not dispatch yet, e.g.
+/// `sxm_well_formed_test!(digicode, Digicode, allow [SpecIssue::UnreachablePhi { state, phi }])`.
+#[macro_export]
+macro_rules! sxm_well_formed_test {
+    ($name:ident, $machine:ty) => {
+        $crate::sxm_well_formed_test!($name, $machine, allow []);
+    };
+    ($name:ident, $machine:ty, allow [$($allowed:expr),* $(,)?]) => {
+        #[test]
+        fn $name() {
+            let allowed: Vec<$crate::analysis::SpecIssueOf<$machine>> = vec![$($allowed),*];
+            let issues: Vec<_> = $crate::analysis::well_formedness::<$machine>()
+                .into_iter()
+                .filter(|issue| !allowed.contains(issue))
+                .collect();
+            assert!(
+                issues.is_empty(),
+                "{} is not well formed: {:#?}",
+                stringify!($machine),
+                issues
+            );
+        }
+    };
+}
+
+/// Size and shape of a specification, as reported by `stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpecStats {
+    pub states: usize,
+    pub inputs: usize,
+    pub outputs: usize,
+    pub phis: usize,
+    /// Transitions of the associated automaton: `(state, phi)` pairs with a target.
+    pub transitions: usize,
+    /// Transitions per state.
+    pub branching_factor: f64,
+    /// Longest shortest path, in transitions, between two states of the
+    /// control graph where the second is reachable from the first.
+    pub diameter: usize,
+    /// Transitions whose phi documents a guard on memory in `phi_guard_doc`.
+    pub data_dependent_transitions: usize,
+    pub suite_sizes: SuiteSizes,
+}
+
+/// Cases each generation method yields, counted from the finite tables.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuiteSizes {
+    /// `SxMTester::generate_logic_tests`: one case per defined input of each reachable state.
+    pub logic: usize,
+    /// `SxMTester::generate_phi_coverage_tests`: an upper bound, as cases
+    /// whose guard cannot be satisfied are left out.
+    pub phi_coverage: usize,
+    /// `SxMTester::generate_robustness_tests`: one case per forbidden input of each reachable state.
+    pub robustness: usize,
+}
+
+impl SpecStats {
+    /// One `metric: value` line per figure, for tracking across releases.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "states: {}", self.states).unwrap();
+        writeln!(out, "inputs: {}", self.inputs).unwrap();
+        writeln!(out, "outputs: {}", self.outputs).unwrap();
+        writeln!(out, "phis: {}", self.phis).unwrap();
+        writeln!(out, "transitions: {}", self.transitions).unwrap();
+        writeln!(out, "branching factor: {:.2}", self.branching_factor).unwrap();
+        writeln!(out, "diameter: {}", self.diameter).unwrap();
+        writeln!(out, "data-dependent transitions: {}", self.data_dependent_transitions).unwrap();
+        writeln!(out, "logic tests: {}", self.suite_sizes.logic).unwrap();
+        writeln!(out, "phi coverage tests: at most {}", self.suite_sizes.phi_coverage).unwrap();
+        writeln!(out, "robustness tests: {}", self.suite_sizes.robustness).unwrap();
+        out
+    }
+}
+
+/// Complexity figures of `T`, from the finite tables only: memory is never
+/// explored, so reachability ignores guards.
+pub fn stats<T: XMachine>() -> SpecStats {
+    let states = T::all_states();
+    let inputs = T::all_inputs();
+    let successors = |state: T::State| T::all_phis().iter().filter_map(move |&phi| T::next_state(state, phi));
+
+    let mut transitions = 0;
+    let mut data_dependent_transitions = 0;
+    for &state in states {
+        for &phi in T::all_phis() {
+            if T::next_state(state, phi).is_some() {
+                transitions += 1;
+                if T::phi_guard_doc(phi).is_some() {
+                    data_dependent_transitions += 1;
+                }
+            }
+        }
+    }
+
+    // Breadth-first distances from every state; the largest finite one is the diameter.
+    let distances = |sources: &[T::State]| {
+        let mut distance: Vec<Option<usize>> = vec![None; states.len()];
+        let mut queue = VecDeque::new();
+        for &source in sources {
+            if let Some(idx) = T::state_index(source).filter(|idx| distance[idx.0].is_none()) {
+                distance[idx.0] = Some(0);
+                queue.push_back((source, 0));
+            }
+        }
+        while let Some((state, d)) = queue.pop_front() {
+            for target in successors(state) {
+                if let Some(idx) = T::state_index(target).filter(|idx| distance[idx.0].is_none()) {
+                    distance[idx.0] = Some(d + 1);
+                    queue.push_back((target, d + 1));
+                }
+            }
+        }
+        distance
+    };
+    let diameter = states
+        .iter()
+        .flat_map(|&state| distances(&[state]))
+        .flatten()
+        .max()
+        .unwrap_or(0);
+
+    let reachable = distances(T::initial_states());
+    let mut suite_sizes = SuiteSizes {
+        logic: 0,
+        phi_coverage: 0,
+        robustness: 0,
+    };
+    for (idx, &state) in states.iter().enumerate() {
+        for input in inputs.iter() {
+            if T::get_phi_for_input(state, input).is_some_and(|phi| T::next_state(state, phi).is_some()) {
+                suite_sizes.phi_coverage += 1;
+                if reachable[idx].is_some() {
+                    suite_sizes.logic += 1;
+                }
+            }
+            if reachable[idx].is_some() && T::transition_spec(state, input) == TransitionSpec::Forbidden {
+                suite_sizes.robustness += 1;
+            }
+        }
+    }
+
+    SpecStats {
+        states: states.len(),
+        inputs: inputs.len(),
+        outputs: T::all_outputs().len(),
+        phis: T::all_phis().len(),
+        transitions,
+        branching_factor: if states.is_empty() {
+            0.0
+        } else {
+            transitions as f64 / states.len() as f64
+        },
+        diameter,
+        data_dependent_transitions,
+        suite_sizes,
+    }
+}
+
+/// An elementary cycle of the control graph: each step is a transition
+/// `(state, phi)` leading to the state of the next step, the last one back to
+/// the first. No state repeats.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cycle<State, Phi> {
+    pub steps: Vec<(State, Phi)>,
+}
+
+/// `Cycle` specialised to the symbols of machine `T`.
+pub type CycleOf<T> = Cycle<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+impl<State: PartialEq + Debug, Phi: PartialEq + Debug> Cycle<State, Phi> {
+    /// `true` if the cycle takes `phi`, or another instance of its kind, in
+    /// `state`. Phis are compared by `T::phi_kind`.
+    pub fn contains<T: XMachine<State = State, Phi = Phi>>(&self, state: &State, phi: &Phi) -> bool
+    where
+        Phi: Copy,
+    {
+        let kind = T::phi_kind(*phi);
+        self.steps.iter().any(|(s, p)| s == state && T::phi_kind(*p) == kind)
+    }
+
+    /// The cycle as `Accepting --InputDigit--> Accepting`, with symbols
+    /// named by `T::labeling()`.
+    pub fn to_text<T: XMachine<State = State, Phi = Phi>>(&self) -> String
+    where
+        State: Copy,
+        Phi: Copy,
+    {
+        let mut out = String::new();
+        for &(state, phi) in &self.steps {
+            write!(out, "{} --{}--> ", labels::state::<T>(state), labels::phi::<T>(phi)).unwrap();
+        }
+        if let Some(&(first, _)) = self.steps.first() {
+            write!(out, "{}", labels::state::<T>(first)).unwrap();
+        }
+        out
+    }
+}
+
+/// Every elementary cycle of the associated automaton, ignoring guards.
+/// Parallel transitions under different phis make different cycles.
+///
+/// Cycles are listed by their first state in `all_states()` order, which is
+/// the earliest state they pass through, so each appears once. Their number
+/// can grow exponentially with the size of the control graph.
+pub fn cycles<T: XMachine>() -> Vec<CycleOf<T>> {
+    let states = T::all_states();
+    let mut cycles = Vec::new();
+    for (start_idx, &start) in states.iter().enumerate() {
+        // Depth-first search for paths back to `start` through later states only.
+        let mut path: Vec<(T::State, T::Phi)> = Vec::new();
+        let mut stack: Vec<(T::State, usize)> = vec![(start, 0)];
+        while let Some((state, next_phi)) = stack.pop() {
+            let Some(&phi) = T::all_phis().get(next_phi) else {
+                path.pop();
+                continue;
+            };
+            stack.push((state, next_phi + 1));
+            let Some(target) = T::next_state(state, phi) else {
+                continue;
+            };
+            if target == start {
+                let mut steps = path.clone();
+                steps.push((state, phi));
+                cycles.push(Cycle { steps });
+            } else if T::state_index(target).is_some_and(|idx| idx.0 > start_idx)
+                && target != state
+                && !path.iter().any(|(s, _)| *s == target)
+            {
+                path.push((state, phi));
+                stack.push((target, 0));
+            }
+        }
+    }
+    cycles
+}
+
+/// A property of the memory that must hold after every transition of `cycle`,
+/// e.g. "at most the code length entered" around the `Accepting` self-loop.
+pub struct LoopInvariant<State, Phi, Memory> {
+    pub cycle: Cycle<State, Phi>,
+    pub doc: &'static str,
+    pub check: fn(&Memory) -> bool,
+}
+
+/// `LoopInvariant` specialised to the symbols and memory of machine `T`.
+pub type LoopInvariantOf<T> = LoopInvariant<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Memory>;
+
+impl<State: Clone, Phi: Clone, Memory> Clone for LoopInvariant<State, Phi, Memory> {
+    fn clone(&self) -> Self {
+        Self {
+            cycle: self.cycle.clone(),
+            doc: self.doc,
+            check: self.check,
+        }
+    }
+}
+
+impl<State: Debug, Phi: Debug, Memory> Debug for LoopInvariant<State, Phi, Memory> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoopInvariant")
+            .field("cycle", &self.cycle)
+            .field("doc", &self.doc)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A transition of a cycle after which its `LoopInvariant` did not hold.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoopInvariantViolation<State, Phi> {
+    /// `LoopInvariant::doc` of the broken invariant.
+    pub invariant: &'static str,
+    pub state: State,
+    pub phi: Phi,
+    /// Inputs applied before the violating step, the fewest found.
+    pub level: usize,
+}
+
+/// `LoopInvariantViolation` specialised to the symbols of machine `T`.
+pub type LoopInvariantViolationOf<T> = LoopInvariantViolation<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// Simulates every step reachable within `depth` inputs and checks each of
+/// `invariants` after the transitions of its cycle. Reports each broken
+/// invariant once per transition, at its shallowest occurrence.
+pub fn loop_invariant_violations<T: XMachine>(
+    invariants: &[LoopInvariantOf<T>],
+    depth: usize,
+) -> Vec<LoopInvariantViolationOf<T>>
+where
+    T::Memory: Store,
+{
+    let mut violations: Vec<LoopInvariantViolationOf<T>> = Vec::new();
+    for_each_reachable_step::<T>(depth, |step| {
+        if step.result.is_err() {
+            return;
+        }
+        for invariant in invariants {
+            if !invariant.cycle.contains::<T>(&step.state, &step.phi) || (invariant.check)(step.next_store) {
+                continue;
+            }
+            let known = violations
+                .iter()
+                .any(|v| v.invariant == invariant.doc && v.state == step.state && v.phi == step.phi);
+            if !known {
+                violations.push(LoopInvariantViolation {
+                    invariant: invariant.doc,
+                    state: step.state,
+                    phi: step.phi,
+                    level: step.level,
+                });
+            }
+        }
+    });
+    violations
+}
+
+/// A phi that read or wrote memory regions outside its `AccessControl` declaration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccessViolation<State, Phi, Input, Region> {
+    pub state: State,
+    pub phi: Phi,
+    pub input: Input,
+    /// Regions changed by the step without being declared writable.
+    pub writes: Vec<Region>,
+    /// Regions the step depends on without being declared readable.
+    pub reads: Vec<Region>,
+}
+
+/// `AccessViolation` specialised to the symbols and memory regions of machine `T`.
+pub type AccessViolationOf<T> = AccessViolation<
+    <T as XMachine>::State,
+    <T as XMachine>::Phi,
+    <T as XMachine>::Input,
+    <<T as XMachine>::Memory as MemoryRegions>::Region,
+>;
+
+/// Executes every step reachable within `depth` inputs and reports those whose
+/// phi changed memory regions it does not declare as writable, or depends on
+/// regions it does not declare as readable.
+///
+/// Reads are found by running each step again with a region taken from every
+/// other store reachable within `depth`, see `access::undeclared_reads`. A
+/// rejected step is checked for reads only: its guard may not consult
+/// undeclared regions either.
+pub fn access_violations<T>(
+    depth: usize,
+) -> Vec<AccessViolationOf<T>>
+where
+    T: AccessControl,
+    T::Memory: MemoryRegions + Store,
+{
+    let mut stores: Vec<T::Memory> = Vec::new();
+    for_each_reachable_step::<T>(depth, |step| {
+        if !stores.contains(step.store) {
+            stores.push(step.store.clone());
+        }
+    });
+
+    let mut violations = Vec::new();
+    for_each_reachable_step::<T>(depth, |step| {
+        let writes = if step.result.is_ok() {
+            undeclared_writes::<T>(step.phi, step.store, step.next_store)
+        } else {
+            Vec::new()
+        };
+        let mut reads = Vec::new();
+        for donor in &stores {
+            for region in undeclared_reads::<T>(step.phi, step.store, step.input, donor) {
+                if !reads.contains(&region) {
+                    reads.push(region);
+                }
+            }
+        }
+        if !writes.is_empty() || !reads.is_empty() {
+            violations.push(AccessViolation {
+                state: step.state,
+                phi: step.phi,
+                input: step.input.clone(),
+                writes,
+                reads,
+            });
+        }
+    });
+    violations
+}
+
+/// One step taken during `for_each_reachable_step`.
+pub(crate) struct VisitedStep<'a, T: XMachine> {
+    pub state: T::State,
+    pub store: &'a T::Memory,
+    pub input: &'a T::Input,
+    pub phi: T::Phi,
+    /// Inputs taken to reach the configuration the step starts from.
+    pub level: usize,
+    pub result: &'a Result<Option<T::Output>, ()>,
+    /// The store after `execute_phi`, meaningful when `result` is `Ok`.
+    pub next_store: &'a T::Memory,
+}
+
+/// Breadth-first walk over distinct (state, memory) configurations reachable
+/// within `depth` inputs, calling `visit` for every (configuration, input) pair
+/// that has a phi.
+pub(crate) fn for_each_reachable_step<T: XMachine>(depth: usize, mut visit: impl FnMut(VisitedStep<'_, T>))
+where
+    T::Memory: Store,
+{
+    // Seen memories bucketed by state ordinal, so deduplication only scans
+    // configurations that share a control state.
+    let mut seen: Vec<Vec<T::Memory>> = vec![Vec::new(); T::all_states().len()];
+    let mut mark_seen = |state: T::State, store: &T::Memory| -> bool {
+        match T::state_index(state) {
+            Some(idx) if seen[idx.0].contains(store) => false,
+            Some(idx) => {
+                seen[idx.0].push(store.clone());
+                true
+            }
+            None => true,
+        }
+    };
+
+    let mut queue = VecDeque::new();
+    for config in Configuration::<T>::initial() {
+        if mark_seen(config.state, &config.store) {
+            queue.push_back((config, 0));
+        }
+    }
+
+    let inputs = T::all_inputs();
+    while let Some((config, level)) = queue.pop_front() {
+        let (state, store) = (config.state, &config.store);
+        for input in inputs.iter() {
+            let Some(phi) = T::get_phi_for_input(state, input) else {
+                continue;
+            };
+            let mut next_store = store.clone();
+            let result = isolation::execute_phi::<T>(state, phi, &mut next_store, input);
+            visit(VisitedStep {
+                state,
+                store,
+                input,
+                phi,
+                level,
+                result: &result,
+                next_store: &next_store,
+            });
+
+            if result.is_ok() && level < depth {
+                if let Some(next_state) = T::next_state(state, phi) {
+                    if mark_seen(next_state, &next_store) {
+                        queue.push_back((Configuration::new(next_state, next_store), level + 1));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Latch {
+        Off,
+        On,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum Command {
+        Set,
+        Reset,
+    }
+
+    /// Counts how often it was set; `Reset` turns it off but keeps the count.
+    struct Counted;
+
+    impl XMachine for Counted {
+        type Input = Command;
+        type Output = ();
+        type State = Latch;
+        type Memory = u32;
+        type Phi = Latch;
+
+        fn next_state(_state: Latch, phi: Latch) -> Option<Latch> {
+            Some(phi)
+        }
+
+        fn initial_states() -> &'static [Latch] {
+            &[Latch::Off]
+        }
+
+        fn final_states() -> &'static [Latch] {
+            &[Latch::Off]
+        }
+
+        fn initial_store() -> u32 {
+            0
+        }
+
+        fn execute_phi(phi: Latch, store: &mut u32, _input: &Command) -> Result<Option<()>, ()> {
+            if phi == Latch::On {
+                *store = (*store + 1).min(2);
+            }
+            Ok(None)
+        }
+
+        fn all_inputs() -> Cow<'static, [Command]> {
+            Cow::Borrowed(&[Command::Set, Command::Reset])
+        }
+
+        fn all_outputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[])
+        }
+
+        fn all_states() -> &'static [Latch] {
+            &[Latch::Off, Latch::On]
+        }
+
+        fn all_phis() -> &'static [Latch] {
+            &[Latch::Off, Latch::On]
+        }
+
+        fn get_phi_for_input(_state: Latch, input: &Command) -> Option<Latch> {
+            Some(match input {
+                Command::Set => Latch::On,
+                Command::Reset => Latch::Off,
+            })
+        }
+
+        fn reset_input() -> Option<Command> {
+            Some(Command::Reset)
+        }
+    }
+
+    #[test]
+    fn a_reset_keeping_memory_is_unreliable() {
+        let issues = well_formedness::<Counted>();
+        assert_eq!(
+            issues,
+            [
+                SpecIssue::UnreliableReset { state: Latch::On },
+                SpecIssue::UnreliableReset { state: Latch::Off },
+            ]
+        );
+    }
+
+    /// Chimes `1` when set and `2` when reset, but declares `1` and `3`.
+    struct Chime;
+
+    impl XMachine for Chime {
+        type Input = Command;
+        type Output = u8;
+        type State = Latch;
+        type Memory = ();
+        type Phi = Latch;
+
+        fn next_state(_state: Latch, phi: Latch) -> Option<Latch> {
+            Some(phi)
+        }
+
+        fn initial_states() -> &'static [Latch] {
+            &[Latch::Off]
+        }
+
+        fn final_states() -> &'static [Latch] {
+            &[Latch::Off]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(phi: Latch, _store: &mut (), _input: &Command) -> Result<Option<u8>, ()> {
+            Ok(Some(match phi {
+                Latch::On => 1,
+                Latch::Off => 2,
+            }))
+        }
+
+        fn all_inputs() -> Cow<'static, [Command]> {
+            Cow::Borrowed(&[Command::Set, Command::Reset])
+        }
+
+        fn all_outputs() -> Cow<'static, [u8]> {
+            Cow::Borrowed(&[1, 3])
+        }
+
+        fn all_states() -> &'static [Latch] {
+            &[Latch::Off, Latch::On]
+        }
+
+        fn all_phis() -> &'static [Latch] {
+            &[Latch::Off, Latch::On]
+        }
+
+        fn get_phi_for_input(_state: Latch, input: &Command) -> Option<Latch> {
+            Some(match input {
+                Command::Set => Latch::On,
+                Command::Reset => Latch::Off,
+            })
+        }
+    }
+
+    #[test]
+    fn output_completeness_reports_both_directions() {
+        let report = output_completeness::<Chime>(1);
+        assert_eq!(report.never_produced, [3]);
+        assert_eq!(report.undeclared, [2]);
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn output_completeness_is_bounded_by_depth() {
+        use crate::examples::traffic_light::{LightOutput, TrafficLight};
+
+        assert!(output_completeness::<TrafficLight>(3).is_complete());
+        let shallow = output_completeness::<TrafficLight>(0);
+        assert_eq!(shallow.never_produced, [LightOutput::ShowYellow]);
+        assert!(shallow.undeclared.is_empty());
+    }
+
+    #[test]
+    fn digits_lead_into_accepting() {
+        use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet::*, DigicodePhi, DigicodeState::*};
+
+        let digits: Vec<_> = (0..10).map(Digit).collect();
+        let into_accepting = predecessors::<Digicode>(Accepting);
+        let expected: Vec<_> = [Ready, Accepting]
+            .into_iter()
+            .flat_map(|from| {
+                digits.iter().map(move |input| Predecessor {
+                    from,
+                    input: input.clone(),
+                    phi: DigicodePhi::InputDigit,
+                })
+            })
+            .collect();
+        assert_eq!(into_accepting, expected);
+        assert_eq!(
+            predecessors::<Digicode>(CodeEntered),
+            [Predecessor { from: Accepting, input: OkEnter, phi: DigicodePhi::Finish }]
+        );
+    }
+
+    #[test]
+    fn paths_to_accepting_are_listed_shortest_first() {
+        use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet::*, DigicodeState::*};
+
+        assert!(all_paths_to::<Digicode>(Accepting, 0).is_empty());
+        let one_digit: Vec<Vec<_>> = (0..10).map(|d| vec![Digit(d)]).collect();
+        assert_eq!(all_paths_to::<Digicode>(Accepting, 1), one_digit);
+
+        let paths = all_paths_to::<Digicode>(Accepting, 2);
+        // Two digits, or a rejected `OkEnter` on the empty code followed by one digit.
+        assert_eq!(paths.len(), 10 + 10 * 10 + 10);
+        assert!(paths.contains(&vec![OkEnter, Digit(7)]));
+        assert_eq!(paths[..10], one_digit[..]);
+        assert!(paths[10..].iter().all(|path| path.len() == 2));
+
+        // The guard on the code is honoured: only the valid code reaches `CodeEntered`.
+        assert!(all_paths_to::<Digicode>(CodeEntered, 3).is_empty());
+        assert_eq!(all_paths_to::<Digicode>(CodeEntered, 4), [vec![Digit(4), Digit(9), Digit(2), OkEnter]]);
+    }
+
+    #[test]
+    fn d_reachability_counts_the_setup_before_each_guard_holds() {
+        use crate::examples::secure_door::{Digicode, DigicodePhi::*, DigicodeState::*};
+
+        let steps = |report: &DReachabilityReportOf<Digicode>| -> Vec<_> {
+            report.entries.iter().map(|e| (e.state, e.phi, e.steps)).collect()
+        };
+        let report = d_reachability::<Digicode>(3);
+        assert_eq!(
+            steps(&report),
+            [
+                (Ready, Reject, Some(0)),
+                (Ready, InputDigit, Some(0)),
+                (Accepting, Reject, None),
+                (Accepting, InputDigit, Some(1)),
+                (Accepting, Ignore, None),
+                (Accepting, Finish, Some(3)),
+                (CodeEntered, Lock, None),
+            ]
+        );
+        assert!(report.to_text::<Digicode>().contains("CodeEntered --Lock--> not within depth 3"));
+
+        let deeper = d_reachability::<Digicode>(4);
+        let unreached: Vec<_> = deeper.unreached().map(|e| (e.state, e.phi)).collect();
+        assert_eq!(unreached, [(Accepting, Reject), (Accepting, Ignore)]);
+    }
+
+    #[test]
+    fn digicode_stats() {
+        use crate::examples::secure_door::Digicode;
+
+        assert_eq!(
+            stats::<Digicode>(),
+            SpecStats {
+                states: 3,
+                inputs: 12,
+                outputs: 14,
+                phis: 5,
+                transitions: 7,
+                branching_factor: 7.0 / 3.0,
+                diameter: 2,
+                data_dependent_transitions: 6,
+                // All three states are reachable: eleven inputs are handled
+                // in `Ready` and `Accepting`, `DoorCloses` alone in `CodeEntered`.
+                suite_sizes: SuiteSizes {
+                    logic: 23,
+                    phi_coverage: 23,
+                    robustness: 13,
+                },
+            }
+        );
+    }
+}
@@ -0,0 +1,336 @@
+use crate::fingerprint::SpecFingerprint;
+use crate::json::{self, Value};
+use crate::mbt::{Expectation, SxMTester, TestCase};
+use crate::suite::{GenerationSeed, TestSuite, TestSuiteOf};
+use crate::XMachine;
+use std::fmt::{self, Debug, Write};
+
+/// Version written by `to_json`. `from_json` reads this version only.
+pub const FORMAT_VERSION: u64 = 1;
+
+const FORMAT: &str = "sxm-suite";
+
+/// A suite read back by `from_json`, with the spec it was generated from.
+#[derive(Debug)]
+pub struct ArchivedSuite<Input, Output, State, Memory> {
+    pub version: u64,
+    pub fingerprint: SpecFingerprint,
+    pub suite: TestSuite<Input, Output, State, Memory>,
+}
+
+/// `ArchivedSuite` specialised to the symbols and memory of machine `T`.
+pub type ArchivedSuiteOf<T> = ArchivedSuite<
+    <T as XMachine>::Input,
+    <T as XMachine>::Output,
+    <T as XMachine>::State,
+    <T as XMachine>::Memory,
+>;
+
+impl<Input, Output, State, Memory> ArchivedSuite<Input, Output, State, Memory> {
+    /// Whether the suite was generated from the current structure of `T`.
+    pub fn is_current<T: XMachine>(&self) -> bool {
+        self.fingerprint == SpecFingerprint::of::<T>()
+    }
+}
+
+/// Why a suite could not be written or read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArchiveError {
+    /// The text is not valid JSON.
+    Parse { line: usize, message: String },
+    /// The document does not follow the suite schema.
+    Schema(String),
+    /// The document was written by another version of the format.
+    UnsupportedVersion(u64),
+    /// A symbol or requirement no longer exists in the spec.
+    Undeclared { kind: &'static str, name: String },
+    /// A case cannot be represented, e.g. it uses an `Expectation::Predicate`.
+    Unserializable(String),
+}
+
+impl fmt::Display for ArchiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchiveError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ArchiveError::Schema(message) | ArchiveError::Unserializable(message) => f.write_str(message),
+            ArchiveError::UnsupportedVersion(version) => {
+                write!(f, "unsupported suite format version {} (expected {})", version, FORMAT_VERSION)
+            }
+            ArchiveError::Undeclared { kind, name } => write!(f, "undeclared {} {:?}", kind, name),
+        }
+    }
+}
+
+impl std::error::Error for ArchiveError {}
+
+/// Serializes `suite` as JSON, tagged with the format version and the
+/// current fingerprint of `T`.
+///
+/// Symbols are written as their `Debug` rendering, which keeps the file
+/// reviewable and lets it load after the alphabets are reordered. Setup
+/// configurations are not stored; `from_json` rebuilds them by replay.
+pub fn to_json<T: XMachine>(suite: &TestSuiteOf<T>) -> Result<String, ArchiveError> {
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "  \"format\": {},", json::quote(FORMAT)).unwrap();
+    writeln!(out, "  \"version\": {},", FORMAT_VERSION).unwrap();
+    writeln!(out, "  \"fingerprint\": \"{}\",", SpecFingerprint::of::<T>()).unwrap();
+    writeln!(out, "  \"seed\": \"{}\",", suite.seed).unwrap();
+    write!(out, "  \"cases\": [").unwrap();
+    for (idx, case) in suite.cases.iter().enumerate() {
+        let expected = match &case.expected_output {
+            Expectation::Exact(output) => format!("{{\"exact\": {}}}", symbol(output)),
+            Expectation::AnyOf(outputs) => format!("{{\"any_of\": {}}}", symbols(outputs)),
+            Expectation::Predicate(label, _) => {
+                return Err(ArchiveError::Unserializable(format!(
+                    "case {:?} uses predicate expectation {:?}",
+                    case.name, label
+                )));
+            }
+            Expectation::None => "null".to_string(),
+        };
+        let requirements: Vec<String> = case.requirements.iter().map(|id| json::quote(id)).collect();
+
+        out.push_str(if idx == 0 { "\n" } else { ",\n" });
+        writeln!(out, "    {{").unwrap();
+        writeln!(out, "      \"name\": {},", json::quote(&case.name)).unwrap();
+        writeln!(out, "      \"setup\": {},", symbols(&case.setup_sequence)).unwrap();
+        writeln!(out, "      \"input\": {},", symbol(&case.test_input)).unwrap();
+        writeln!(out, "      \"expected\": {},", expected).unwrap();
+        writeln!(out, "      \"verify\": {},", symbols(&case.verification_sequence)).unwrap();
+        writeln!(out, "      \"requirements\": [{}]", requirements.join(", ")).unwrap();
+        write!(out, "    }}").unwrap();
+    }
+    if !suite.cases.is_empty() {
+        out.push_str("\n  ");
+    }
+    writeln!(out, "]").unwrap();
+    writeln!(out, "}}").unwrap();
+    Ok(out)
+}
+
+/// Reads a suite written by `to_json`, whatever spec it was generated from.
+/// Use `ArchivedSuite::is_current` to tell whether it is stale.
+pub fn from_json<T: XMachine>(text: &str) -> Result<ArchivedSuiteOf<T>, ArchiveError> {
+    let doc = json::parse(text).map_err(|(line, message)| ArchiveError::Parse { line, message })?;
+    if doc.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(ArchiveError::Schema(format!("missing \"format\": {:?}", FORMAT)));
+    }
+    let version = doc
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| schema("missing numeric \"version\""))?;
+    if version != FORMAT_VERSION {
+        return Err(ArchiveError::UnsupportedVersion(version));
+    }
+    let fingerprint = doc
+        .get("fingerprint")
+        .and_then(Value::as_str)
+        .and_then(SpecFingerprint::from_hex)
+        .ok_or_else(|| schema("missing hexadecimal \"fingerprint\""))?;
+    let seed = doc
+        .get("seed")
+        .and_then(Value::as_str)
+        .and_then(|s| u64::from_str_radix(s.trim_start_matches("0x"), 16).ok())
+        .map(GenerationSeed)
+        .ok_or_else(|| schema("missing hexadecimal \"seed\""))?;
+
+    let inputs = T::all_inputs();
+    let outputs = T::all_outputs();
+    let declared = SxMTester::declared_requirements::<T>();
+    let mut cases = Vec::new();
+    for case in array(&doc, "cases")? {
+        let name = string(case, "name")?.to_string();
+        let setup_sequence = decode_symbols(array(case, "setup")?, &inputs, "input")?;
+        let test_input = decode_symbol(case.get("input"), &inputs, "input")?;
+        let expected_output = match case.get("expected") {
+            Some(Value::Null) => Expectation::None,
+            Some(expected) => match (expected.get("exact"), expected.get("any_of")) {
+                (Some(output), _) => Expectation::Exact(decode_symbol(Some(output), &outputs, "output")?),
+                (None, Some(Value::Array(any))) => Expectation::AnyOf(decode_symbols(any, &outputs, "output")?),
+                _ => return Err(schema("\"expected\" must be null, {\"exact\": ..} or {\"any_of\": [..]}")),
+            },
+            None => return Err(schema("case without \"expected\"")),
+        };
+        let verification_sequence = decode_symbols(array(case, "verify")?, &inputs, "input")?;
+        let requirements = array(case, "requirements")?
+            .iter()
+            .map(|id| {
+                let id = id.as_str().ok_or_else(|| schema("requirement ids must be strings"))?;
+                declared
+                    .iter()
+                    .copied()
+                    .find(|&known| known == id)
+                    .ok_or_else(|| ArchiveError::Undeclared {
+                        kind: "requirement",
+                        name: id.to_string(),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let setup_configuration = SxMTester::replay_setup::<T>(&setup_sequence);
+
+        cases.push(TestCase {
+            name,
+            setup_sequence,
+            test_input,
+            expected_output,
+            verification_sequence,
+            requirements,
+            setup_configuration,
+        });
+    }
+
+    Ok(ArchivedSuite {
+        version,
+        fingerprint,
+        suite: TestSuite { seed, cases },
+    })
+}
+
+/// Cases added, removed and changed between two suites, matched by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SuiteDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    /// Cases present in both whose inputs, expectation or requirements differ.
+    pub changed: Vec<String>,
+}
+
+impl SuiteDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// One line per case: `+` added, `-` removed, `~` changed.
+impl fmt::Display for SuiteDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in &self.added {
+            writeln!(f, "+ {}", name)?;
+        }
+        for name in &self.removed {
+            writeln!(f, "- {}", name)?;
+        }
+        for name in &self.changed {
+            writeln!(f, "~ {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two suites, e.g. an archived one and its regeneration after a spec change.
+/// Setup configurations are ignored, as they are not archived.
+pub fn suite_diff<Input, Output, State, Memory>(
+    old: &TestSuite<Input, Output, State, Memory>,
+    new: &TestSuite<Input, Output, State, Memory>,
+) -> SuiteDiff
+where
+    Input: PartialEq,
+    Output: PartialEq,
+{
+    let mut diff = SuiteDiff::default();
+    for case in &new.cases {
+        match old.cases.iter().find(|c| c.name == case.name) {
+            None => diff.added.push(case.name.clone()),
+            Some(previous) => {
+                if previous.setup_sequence != case.setup_sequence
+                    || previous.test_input != case.test_input
+                    || previous.expected_output != case.expected_output
+                    || previous.verification_sequence != case.verification_sequence
+                    || previous.requirements != case.requirements
+                {
+                    diff.changed.push(case.name.clone());
+                }
+            }
+        }
+    }
+    for case in &old.cases {
+        if !new.cases.iter().any(|c| c.name == case.name) {
+            diff.removed.push(case.name.clone());
+        }
+    }
+    diff
+}
+
+fn symbol<S: Debug>(symbol: &S) -> String {
+    json::quote(&format!("{:?}", symbol))
+}
+
+fn symbols<S: Debug>(symbols: &[S]) -> String {
+    let rendered: Vec<String> = symbols.iter().map(symbol).collect();
+    format!("[{}]", rendered.join(", "))
+}
+
+fn decode_symbol<S: Debug + Clone>(
+    value: Option<&Value>,
+    alphabet: &[S],
+    kind: &'static str,
+) -> Result<S, ArchiveError> {
+    let name = value
+        .and_then(Value::as_str)
+        .ok_or_else(|| schema(&format!("{} symbols must be strings", kind)))?;
+    alphabet
+        .iter()
+        .find(|s| format!("{:?}", s) == name)
+        .cloned()
+        .ok_or_else(|| ArchiveError::Undeclared {
+            kind,
+            name: name.to_string(),
+        })
+}
+
+fn decode_symbols<S: Debug + Clone>(
+    values: &[Value],
+    alphabet: &[S],
+    kind: &'static str,
+) -> Result<Vec<S>, ArchiveError> {
+    values.iter().map(|v| decode_symbol(Some(v), alphabet, kind)).collect()
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> Result<&'a [Value], ArchiveError> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .ok_or_else(|| schema(&format!("missing array {:?}", key)))
+}
+
+fn string<'a>(value: &'a Value, key: &str) -> Result<&'a str, ArchiveError> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| schema(&format!("missing string {:?}", key)))
+}
+
+fn schema(message: &str) -> ArchiveError {
+    ArchiveError::Schema(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Digicode;
+    use crate::suite::TestSuiteBuilder;
+
+    #[test]
+    fn an_archived_suite_reads_back_unchanged() {
+        let suite = TestSuiteBuilder::<Digicode>::new().seed(7).robustness().random_walks(3, 4).build();
+        let archived = from_json::<Digicode>(&to_json::<Digicode>(&suite).unwrap()).unwrap();
+        assert_eq!(archived.version, FORMAT_VERSION);
+        assert!(archived.is_current::<Digicode>());
+        assert_eq!(archived.suite.seed, suite.seed);
+        assert_eq!(suite_diff(&suite, &archived.suite), SuiteDiff::default());
+    }
+
+    #[test]
+    fn renamed_and_edited_cases_show_in_the_diff() {
+        let old = TestSuiteBuilder::<Digicode>::new().robustness().build();
+        let mut new = TestSuiteBuilder::<Digicode>::new().robustness().build();
+        new.cases[0].name.push_str(" (renamed)");
+        new.cases[1].requirements.push("REQ-NEW");
+        let diff = suite_diff(&old, &new);
+        assert_eq!(diff.added, [new.cases[0].name.clone()]);
+        assert_eq!(diff.removed, [old.cases[0].name.clone()]);
+        assert_eq!(diff.changed, [new.cases[1].name.clone()]);
+        assert_eq!(diff.to_string().lines().count(), 3);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::archive::{self, ArchiveError};
+use crate::docgen;
+use crate::dynamic::DynMachine;
+use crate::fingerprint::SpecFingerprint;
+use crate::graphviz;
+use crate::store::Store;
+use crate::json;
+use crate::suite::TestSuiteBuilder;
+use crate::XMachine;
+use std::fmt::{self, Write};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file written by `emit_all`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// Graphviz diagram, as `graphviz::generate_dot`.
+    Dot,
+    /// Mermaid state diagram, as `docgen::mermaid`.
+    Mermaid,
+    /// Specification document, as `docgen::markdown`.
+    Markdown,
+    /// Structure of the machine, as `DynMachine::to_json`.
+    Model,
+    /// Generated test suite, as `archive::to_json`.
+    Suite,
+}
+
+impl ArtifactKind {
+    fn extension(self) -> &'static str {
+        match self {
+            ArtifactKind::Dot => "dot",
+            ArtifactKind::Mermaid => "mmd",
+            ArtifactKind::Markdown => "md",
+            ArtifactKind::Model => "model.json",
+            ArtifactKind::Suite => "suite.json",
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ArtifactKind::Dot => "dot",
+            ArtifactKind::Mermaid => "mermaid",
+            ArtifactKind::Markdown => "markdown",
+            ArtifactKind::Model => "model",
+            ArtifactKind::Suite => "suite",
+        }
+    }
+}
+
+/// What `emit_all` writes besides the diagrams, docs and model.
+pub struct ArtifactOptions<'a, T: XMachine> {
+    machine_name: String,
+    suite: Option<TestSuiteBuilder<'a, T>>,
+}
+
+impl<'a, T: XMachine> ArtifactOptions<'a, T> {
+    /// Names the machine in the artifacts and their file names.
+    pub fn new(machine_name: &str) -> Self {
+        Self {
+            machine_name: machine_name.to_string(),
+            suite: None,
+        }
+    }
+
+    /// Also writes the suite `builder` generates.
+    pub fn with_suite(mut self, builder: TestSuiteBuilder<'a, T>) -> Self {
+        self.suite = Some(builder);
+        self
+    }
+}
+
+/// The files written by `emit_all`, as recorded in `manifest.json`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub machine_name: String,
+    /// Fingerprint of the spec the artifacts were generated from.
+    pub fingerprint: SpecFingerprint,
+    /// Every artifact with its path, in writing order.
+    pub artifacts: Vec<(ArtifactKind, PathBuf)>,
+}
+
+impl Manifest {
+    /// Renders the manifest, with paths relative to its directory.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{{").unwrap();
+        writeln!(out, "  \"machine\": {},", json::quote(&self.machine_name)).unwrap();
+        writeln!(out, "  \"fingerprint\": \"{}\",", self.fingerprint).unwrap();
+        write!(out, "  \"artifacts\": [").unwrap();
+        for (idx, (kind, path)) in self.artifacts.iter().enumerate() {
+            let file = path.file_name().map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy());
+            out.push_str(if idx == 0 { "\n" } else { ",\n" });
+            write!(out, "    {{\"kind\": \"{}\", \"path\": {}}}", kind.name(), json::quote(&file)).unwrap();
+        }
+        if !self.artifacts.is_empty() {
+            out.push_str("\n  ");
+        }
+        writeln!(out, "]").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+/// Why `emit_all` stopped.
+#[derive(Debug)]
+pub enum ArtifactError {
+    /// Creating the directory or writing a file failed.
+    Io { path: PathBuf, error: io::Error },
+    /// The generated suite cannot be archived.
+    Archive(ArchiveError),
+}
+
+impl fmt::Display for ArtifactError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArtifactError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ArtifactError::Archive(error) => write!(f, "suite: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ArtifactError {}
+
+/// Writes every artifact of `T` into `dir`, creating it if needed: the DOT
+/// and Mermaid diagrams, the Markdown document, the JSON model, the suite
+/// when configured, and a `manifest.json` listing them.
+///
+/// Files are named after the machine, e.g. `Digicode.dot`, and overwritten.
+/// Characters of the name other than ASCII letters, digits, `-` and `_` are
+/// replaced by `_` in file names, so the name cannot leave `dir`.
+/// Nothing is written if the suite cannot be archived.
+pub fn emit_all<T: XMachine>(
+    dir: impl AsRef<Path>,
+    options: &ArtifactOptions<'_, T>,
+) -> Result<Manifest, ArtifactError>
+where
+    T::Memory: Store,
+{
+    let dir = dir.as_ref();
+    let name = &options.machine_name;
+
+    let mut contents = vec![
+        (ArtifactKind::Dot, graphviz::generate_dot::<T>(name)),
+        (ArtifactKind::Mermaid, docgen::mermaid::<T>()),
+        (ArtifactKind::Markdown, docgen::markdown::<T>(name)),
+        (ArtifactKind::Model, DynMachine::from_machine::<T>(name).to_json()),
+    ];
+    if let Some(builder) = &options.suite {
+        let suite = archive::to_json::<T>(&builder.build()).map_err(ArtifactError::Archive)?;
+        contents.push((ArtifactKind::Suite, suite));
+    }
+
+    let io_error = |path: &Path| {
+        let path = path.to_path_buf();
+        move |error| ArtifactError::Io { path, error }
+    };
+    fs::create_dir_all(dir).map_err(io_error(dir))?;
+
+    let mut manifest = Manifest {
+        machine_name: name.clone(),
+        fingerprint: SpecFingerprint::of::<T>(),
+        artifacts: Vec::new(),
+    };
+    let stem = file_stem(name);
+    for (kind, text) in contents {
+        let path = dir.join(format!("{}.{}", stem, kind.extension()));
+        fs::write(&path, text).map_err(io_error(&path))?;
+        manifest.artifacts.push((kind, path));
+    }
+
+    let path = dir.join("manifest.json");
+    fs::write(&path, manifest.to_json()).map_err(io_error(&path))?;
+    Ok(manifest)
+}
+
+/// `machine_name` made safe as a file name: path separators, dots and the
+/// like become `_`, and an empty name becomes `machine`.
+fn file_stem(machine_name: &str) -> String {
+    if machine_name.is_empty() {
+        return "machine".to_string();
+    }
+    machine_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::TrafficLight;
+
+    #[test]
+    fn file_names_stay_inside_the_directory() {
+        let dir = std::env::temp_dir().join(format!("sxm-artifacts-{}", std::process::id()));
+        let manifest = emit_all::<TrafficLight>(&dir, &ArtifactOptions::new("../Traffic Light")).unwrap();
+        for (_, path) in &manifest.artifacts {
+            assert_eq!(path.parent(), Some(dir.as_path()));
+            assert!(path.file_name().unwrap().to_string_lossy().starts_with("___Traffic_Light."));
+        }
+        assert_eq!(manifest.machine_name, "../Traffic Light");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
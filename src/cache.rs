@@ -0,0 +1,278 @@
+use crate::fingerprint::SpecFingerprint;
+use crate::mbt::{Expectation, SxMTester, TestCase, TestCaseOf};
+use crate::{InputIndex, XMachine};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "sxm-test-cache v1";
+
+type Cases<T> = Vec<TestCaseOf<T>>;
+
+/// On-disk store of generated suites, keyed by name and `SpecFingerprint`.
+///
+/// Symbols are written as their ordinals in `all_inputs()` / `all_outputs()`,
+/// so no serialization support is required from the alphabets. Setup
+/// configurations are not stored; they are rebuilt by replaying the setup
+/// sequence on load. A stored suite is only returned while the fingerprint of
+/// the machine still matches.
+pub struct TestCache {
+    dir: PathBuf,
+}
+
+impl TestCache {
+    /// Creates a cache rooted at `dir`. The directory is created on first store.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Path of the file backing the suite stored under `key`.
+    /// Fails with `InvalidInput` if `key` is empty or contains `..`, a path
+    /// separator or a drive prefix, so every entry stays inside the cache
+    /// directory.
+    pub fn path_for(&self, key: &str) -> io::Result<PathBuf> {
+        if key.is_empty() || key.contains("..") || key.contains(['/', '\\', ':', '\0']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid cache key {:?}", key),
+            ));
+        }
+        Ok(self.dir.join(format!("{}.sxmcache", key)))
+    }
+
+    /// Returns the suite stored under `key`, or runs `generate` and stores
+    /// its result when the entry is missing or was built from another spec.
+    pub fn get_or_generate<T: XMachine>(
+        &self,
+        key: &str,
+        generate: impl FnOnce() -> Cases<T>,
+    ) -> io::Result<Cases<T>> {
+        if let Some(tests) = self.load::<T>(key)? {
+            return Ok(tests);
+        }
+        let tests = generate();
+        self.store::<T>(key, &tests)?;
+        Ok(tests)
+    }
+
+    /// Loads the suite stored under `key`.
+    /// Returns `Ok(None)` if it is missing, stale or unreadable.
+    pub fn load<T: XMachine>(&self, key: &str) -> io::Result<Option<Cases<T>>> {
+        let contents = match fs::read_to_string(self.path_for(key)?) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        Ok(decode::<T>(&contents, SpecFingerprint::of::<T>()))
+    }
+
+    /// Stores `tests` under `key`, tagged with the current fingerprint of `T`.
+    /// Fails with `InvalidData` if a symbol is missing from the declared alphabets
+    /// or a case uses an `Expectation::Predicate`, which cannot be serialized.
+    pub fn store<T: XMachine>(&self, key: &str, tests: &[TestCaseOf<T>]) -> io::Result<()> {
+        let contents = encode::<T>(tests, SpecFingerprint::of::<T>())?;
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path_for(key)?;
+        let tmp = path.with_extension("sxmcache.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// Removes the entry stored under `key`, if any.
+    pub fn invalidate(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path_for(key)?) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+fn encode<T: XMachine>(tests: &[TestCaseOf<T>], fingerprint: SpecFingerprint) -> io::Result<String> {
+    let mut out = String::new();
+    out.push_str(HEADER);
+    out.push('\n');
+    out.push_str(&format!("fingerprint {}\n", fingerprint));
+    out.push_str(&format!("count {}\n", tests.len()));
+
+    for test in tests {
+        out.push_str(&format!("name {}\n", escape(&test.name)));
+        out.push_str(&format!("setup{}\n", encode_inputs::<T>(&test.setup_sequence)?));
+        out.push_str(&format!("input {}\n", encode_input::<T>(&test.test_input)?));
+        match &test.expected_output {
+            Expectation::Exact(output) => {
+                out.push_str(&format!("output {}\n", encode_output::<T>(output)?));
+            }
+            Expectation::AnyOf(outputs) => {
+                out.push_str("output any");
+                for output in outputs {
+                    out.push_str(&format!(" {}", encode_output::<T>(output)?));
+                }
+                out.push('\n');
+            }
+            Expectation::Predicate(label, _) => {
+                return Err(invalid(format!("predicate expectation {:?} cannot be cached", label)));
+            }
+            Expectation::None => out.push_str("output -\n"),
+        }
+        out.push_str(&format!("verify{}\n", encode_inputs::<T>(&test.verification_sequence)?));
+        out.push_str("requirements");
+        for id in &test.requirements {
+            out.push_str(&format!(" {}", escape_token(id)));
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn encode_output<T: XMachine>(output: &T::Output) -> io::Result<usize> {
+    T::all_outputs()
+        .iter()
+        .position(|o| o == output)
+        .ok_or_else(|| invalid(format!("output {:?} is not declared in all_outputs()", output)))
+}
+
+fn decode_output<T: XMachine>(token: &str) -> Option<T::Output> {
+    T::all_outputs().get(token.parse::<usize>().ok()?).cloned()
+}
+
+fn encode_input<T: XMachine>(input: &T::Input) -> io::Result<usize> {
+    T::input_index(input)
+        .map(|idx| idx.0)
+        .ok_or_else(|| invalid(format!("input {:?} is not declared in all_inputs()", input)))
+}
+
+fn encode_inputs<T: XMachine>(inputs: &[T::Input]) -> io::Result<String> {
+    let mut out = String::new();
+    for input in inputs {
+        out.push_str(&format!(" {}", encode_input::<T>(input)?));
+    }
+    Ok(out)
+}
+
+fn decode<T: XMachine>(contents: &str, fingerprint: SpecFingerprint) -> Option<Cases<T>> {
+    let mut lines = contents.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+    if SpecFingerprint::from_hex(field(lines.next()?, "fingerprint")?)? != fingerprint {
+        return None;
+    }
+    let count: usize = field(lines.next()?, "count")?.parse().ok()?;
+    let declared = SxMTester::declared_requirements::<T>();
+
+    let mut tests = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = unescape(field(lines.next()?, "name")?);
+        let setup_sequence = decode_inputs::<T>(field(lines.next()?, "setup")?)?;
+        let test_input = decode_input::<T>(field(lines.next()?, "input")?)?;
+        let expected_output = match field(lines.next()?, "output")? {
+            "-" => Expectation::None,
+            rest => match field(rest, "any") {
+                Some(indices) => Expectation::AnyOf(
+                    indices
+                        .split_whitespace()
+                        .map(decode_output::<T>)
+                        .collect::<Option<Vec<_>>>()?,
+                ),
+                None => Expectation::Exact(decode_output::<T>(rest)?),
+            },
+        };
+        let verification_sequence = decode_inputs::<T>(field(lines.next()?, "verify")?)?;
+        let requirements = field(lines.next()?, "requirements")?
+            .split_whitespace()
+            .map(|token| {
+                let id = unescape(token);
+                declared.iter().copied().find(|&known| known == id)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        let setup_configuration = SxMTester::replay_setup::<T>(&setup_sequence);
+
+        tests.push(TestCase {
+            name,
+            setup_sequence,
+            test_input,
+            expected_output,
+            verification_sequence,
+            requirements,
+            setup_configuration,
+        });
+    }
+    Some(tests)
+}
+
+fn decode_input<T: XMachine>(token: &str) -> Option<T::Input> {
+    T::input_from_index(InputIndex(token.parse().ok()?))
+}
+
+fn decode_inputs<T: XMachine>(tokens: &str) -> Option<Vec<T::Input>> {
+    tokens.split_whitespace().map(decode_input::<T>).collect()
+}
+
+/// Strips `key` from a `key value` line. A bare `key` yields an empty value.
+fn field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(key)?;
+    if rest.is_empty() {
+        Some(rest)
+    } else {
+        rest.strip_prefix(' ')
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Like `escape`, but also keeps whitespace-separated lists splittable.
+fn escape_token(s: &str) -> String {
+    escape(s).replace(' ', "\\s")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('s') => out.push(' '),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::suite_diff;
+    use crate::examples::secure_door::Digicode;
+    use crate::suite::{TestSuite, TestSuiteBuilder};
+
+    #[test]
+    fn stored_suites_are_loaded_instead_of_regenerated() {
+        let cache = TestCache::new(std::env::temp_dir().join(format!("sxm-cache-{}", std::process::id())));
+        let suite = TestSuiteBuilder::<Digicode>::new().robustness().build();
+        let stored = cache.get_or_generate::<Digicode>("digicode", || suite.cases).unwrap();
+        let loaded = cache.get_or_generate::<Digicode>("digicode", || unreachable!()).unwrap();
+        let suite = |cases| TestSuite { seed: 0.into(), cases };
+        assert!(suite_diff(&suite(stored), &suite(loaded)).is_empty());
+
+        cache.invalidate("digicode").unwrap();
+        assert!(cache.load::<Digicode>("digicode").unwrap().is_none());
+        assert!(cache.path_for("../escape").is_err());
+        fs::remove_dir_all(cache.dir()).unwrap();
+    }
+}
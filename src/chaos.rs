@@ -0,0 +1,227 @@
+use crate::rng::SplitMix64;
+use crate::runner::{MachineRunner, StepError, StepResultOf, Transition};
+use crate::suite::GenerationSeed;
+use crate::sut::SutAdapter;
+use crate::{OutputKind, XMachine};
+
+/// A fault `FaultyRunner` can inject into a step the spec accepts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FaultKind {
+    /// The guard rejects the input: the configuration is left untouched.
+    GuardFailure,
+    /// The transition is taken but emits another output from the alphabet,
+    /// or none if the alphabet has no other output.
+    OutputCorruption,
+}
+
+/// Which steps get a fault, either at fixed step indices or drawn with
+/// seeded probabilities. Fixed faults take precedence over drawn ones.
+#[derive(Clone, Debug)]
+pub struct FaultSchedule {
+    fixed: Vec<(usize, FaultKind)>,
+    guard_failure: f64,
+    output_corruption: f64,
+    rng: SplitMix64,
+}
+
+impl FaultSchedule {
+    /// A schedule injecting nothing until faults are added.
+    pub fn new(seed: impl Into<GenerationSeed>) -> Self {
+        Self {
+            fixed: Vec::new(),
+            guard_failure: 0.0,
+            output_corruption: 0.0,
+            rng: SplitMix64::new(seed.into().0),
+        }
+    }
+
+    /// Injects `kind` at the zero-based step `step`.
+    pub fn at(mut self, step: usize, kind: FaultKind) -> Self {
+        self.fixed.push((step, kind));
+        self
+    }
+
+    pub fn with_guard_failure(mut self, probability: f64) -> Self {
+        self.guard_failure = probability;
+        self
+    }
+
+    pub fn with_output_corruption(mut self, probability: f64) -> Self {
+        self.output_corruption = probability;
+        self
+    }
+
+    /// The fault for `step`, if any. Draws once per call.
+    fn fault(&mut self, step: usize) -> Option<FaultKind> {
+        let roll = self.rng.next_f64();
+        if let Some(&(_, kind)) = self.fixed.iter().find(|(s, _)| *s == step) {
+            return Some(kind);
+        }
+        if roll < self.guard_failure {
+            Some(FaultKind::GuardFailure)
+        } else if roll < self.guard_failure + self.output_corruption {
+            Some(FaultKind::OutputCorruption)
+        } else {
+            None
+        }
+    }
+}
+
+/// A fault that was injected, with what the uncorrupted spec would have done.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InjectedFault<State, Phi, Output> {
+    /// Zero-based index of the step in the runner's input stream.
+    pub step: usize,
+    pub kind: FaultKind,
+    /// The transition the spec takes from the configuration the fault hit.
+    pub nominal: Transition<State, Phi, Output>,
+    /// What was emitted instead, for `OutputCorruption`.
+    pub injected_output: Option<Output>,
+}
+
+/// `InjectedFault` specialised to the symbols of machine `T`.
+pub type InjectedFaultOf<T> = InjectedFault<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Output>;
+
+/// Runs the spec while a `FaultSchedule` forces guard failures or corrupts
+/// outputs, for chaos testing.
+///
+/// It implements `SutAdapter`, so it can stand in for a faulty implementation
+/// under a `ShadowRunner` or an `Executor` run, while `faults` tells which of
+/// the reported divergences and violations were injected. Faults only hit
+/// inputs the spec accepts, and after a guard failure the run continues from
+/// the configuration the fault left. The wrapped runner keeps its queue,
+/// deferred inputs, policy and panic isolation across faults, and its metrics
+/// count a forced guard failure as a rejection.
+pub struct FaultyRunner<T: XMachine> {
+    runner: MachineRunner<T>,
+    schedule: FaultSchedule,
+    steps: usize,
+    faults: Vec<InjectedFaultOf<T>>,
+}
+
+impl<T: XMachine> FaultyRunner<T> {
+    /// Starts the spec in its initial configuration.
+    pub fn new(schedule: FaultSchedule) -> Self {
+        Self::with_runner(MachineRunner::new(), schedule)
+    }
+
+    pub fn with_runner(runner: MachineRunner<T>, schedule: FaultSchedule) -> Self {
+        Self {
+            runner,
+            schedule,
+            steps: 0,
+            faults: Vec::new(),
+        }
+    }
+
+    /// Processes one input, injecting the scheduled fault if the spec accepts it.
+    pub fn step(&mut self, input: &T::Input) -> StepResultOf<T> {
+        let step = self.steps;
+        self.steps += 1;
+        let fault = self.schedule.fault(step);
+        let config = self.runner.configuration().clone();
+        let mut transition = self.runner.step(input)?;
+        let Some(kind) = fault else {
+            return Ok(transition);
+        };
+
+        let nominal = transition.clone();
+        let (result, injected_output) = match kind {
+            FaultKind::GuardFailure => {
+                self.runner.set_configuration(config);
+                if let Some(metrics) = self.runner.metrics_mut() {
+                    let error = nominal.output.as_ref().is_some_and(|o| T::output_kind(o) == OutputKind::Error);
+                    metrics.reject_accepted(nominal.phi, error, T::phi_kind);
+                }
+                let error = StepError::GuardRejected {
+                    state: nominal.from,
+                    phi: nominal.phi,
+                };
+                (Err(error), None)
+            }
+            FaultKind::OutputCorruption => {
+                transition.output = self.corrupt(nominal.output.as_ref());
+                let output = transition.output.clone();
+                (Ok(transition), output)
+            }
+        };
+        self.faults.push(InjectedFault {
+            step,
+            kind,
+            nominal,
+            injected_output,
+        });
+        result
+    }
+
+    /// Faults injected so far, in stream order.
+    pub fn faults(&self) -> &[InjectedFaultOf<T>] {
+        &self.faults
+    }
+
+    pub fn runner(&self) -> &MachineRunner<T> {
+        &self.runner
+    }
+
+    /// A uniformly drawn output other than `nominal`, or `None` if there is none.
+    fn corrupt(&mut self, nominal: Option<&T::Output>) -> Option<T::Output> {
+        let others: Vec<T::Output> = T::all_outputs()
+            .iter()
+            .filter(|output| Some(*output) != nominal)
+            .cloned()
+            .collect();
+        if others.is_empty() {
+            return None;
+        }
+        let idx = (self.schedule.rng.next_u64() % others.len() as u64) as usize;
+        others.into_iter().nth(idx)
+    }
+}
+
+impl<T: XMachine> SutAdapter<T> for FaultyRunner<T> {
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output> {
+        self.step(input).ok().and_then(|transition| transition.output)
+    }
+
+    fn observe_state(&self) -> Option<T::State> {
+        Some(self.runner.state())
+    }
+
+    fn observe_store(&self) -> Option<T::Memory> {
+        Some(self.runner.store().clone())
+    }
+
+    /// Resets the spec; the schedule keeps counting steps.
+    fn reset(&mut self) -> bool {
+        self.runner.reset();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::{LightInput, LightPhi, LightState, TrafficLight};
+    use crate::runner::DeferPolicy;
+
+    #[test]
+    fn guard_failure_keeps_the_runner_settings_and_counts_a_rejection() {
+        let runner = MachineRunner::<TrafficLight>::new()
+            .with_metrics()
+            .with_panic_isolation()
+            .with_defer_policy(DeferPolicy::Retry);
+        let mut faulty = FaultyRunner::with_runner(runner, FaultSchedule::new(7).at(0, FaultKind::GuardFailure));
+
+        let error = faulty.step(&LightInput::Tick).unwrap_err();
+        assert!(matches!(error, StepError::GuardRejected { phi: LightPhi::ToGreen, .. }));
+        assert_eq!(faulty.runner().state(), LightState::Red);
+        assert!(faulty.runner().panic_isolation());
+        assert_eq!(faulty.runner().defer_policy(), DeferPolicy::Retry);
+        let metrics = faulty.runner().metrics().expect("metrics kept").phi(LightPhi::ToGreen).unwrap();
+        assert_eq!((metrics.invocations, metrics.guard_rejections), (0, 1));
+
+        faulty.step(&LightInput::Tick).unwrap();
+        assert_eq!(faulty.runner().state(), LightState::Green);
+        assert_eq!(faulty.faults().len(), 1);
+    }
+}
@@ -0,0 +1,189 @@
+//! Closed-loop simulation of a spec against a model of its environment.
+//!
+//! The environment model is an ordinary machine, e.g. a user entering
+//! digits, wired to the system as component `A` of a `MachineNetwork`: its
+//! outputs reach the system and the system's outputs come back to it. On
+//! each step the model takes one action, an input drawn with seeded weights
+//! among those it accepts in its current configuration, and the network
+//! settles. Inputs the system's outputs are routed back as are feedback,
+//! not actions, and are never drawn.
+//!
+//! The report gathers what emerges from the loop: how often the system sits
+//! in each state, which outputs it emits, and how often it rejects what the
+//! environment sends.
+
+use crate::network::{Adapters, MachineNetwork, Tagged, Wiring};
+use crate::rng::SplitMix64;
+use crate::suite::{GenerationSeed, Profile};
+use crate::XMachine;
+
+/// What emerged from a closed-loop run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClosedLoopReport<State, Output> {
+    /// Actions the environment model took.
+    pub steps: usize,
+    /// Each system state with the number of steps that ended in it, in
+    /// `all_states()` order.
+    pub occupancy: Vec<(State, usize)>,
+    /// Each output the system emitted with its count, in order of first emission.
+    pub outputs: Vec<(Output, usize)>,
+    /// Inputs the system rejected.
+    pub rejected: usize,
+    /// Steps after which messages were left pending when the step budget ran out.
+    pub unstable: usize,
+    /// `true` if the run stopped early because the model had no action left.
+    pub deadlocked: bool,
+}
+
+/// `ClosedLoopReport` specialised to the symbols of system `S`.
+pub type ClosedLoopReportOf<S> = ClosedLoopReport<<S as XMachine>::State, <S as XMachine>::Output>;
+
+impl<State: PartialEq, Output> ClosedLoopReport<State, Output> {
+    /// Fraction of the steps that ended in `state`.
+    pub fn occupancy_of(&self, state: State) -> f64 {
+        let count = self.occupancy.iter().find(|(s, _)| *s == state).map_or(0, |(_, count)| *count);
+        if self.steps == 0 {
+            0.0
+        } else {
+            count as f64 / self.steps as f64
+        }
+    }
+}
+
+/// Runs system `S` in a loop with environment model `E`, wired by `W`.
+pub struct ClosedLoop<'a, E: XMachine, S: XMachine, W = Adapters> {
+    network: MachineNetwork<E, S, W>,
+    profile: Option<Profile<'a, E>>,
+    rng: SplitMix64,
+}
+
+impl<'a, E, S, W> ClosedLoop<'a, E, S, W>
+where
+    E: XMachine,
+    S: XMachine,
+    W: Wiring<E, S>,
+{
+    /// Both machines start in their initial configurations; actions are
+    /// drawn uniformly.
+    pub fn new(seed: impl Into<GenerationSeed>) -> Self {
+        Self::from_network(MachineNetwork::new(), seed)
+    }
+
+    /// Runs the loop on `network`, e.g. one with channel policies or started
+    /// from chosen configurations.
+    pub fn from_network(network: MachineNetwork<E, S, W>, seed: impl Into<GenerationSeed>) -> Self {
+        Self {
+            network,
+            profile: None,
+            rng: SplitMix64::new(seed.into().0),
+        }
+    }
+
+    /// Weighs the actions of the model by `profile`, as an operational profile
+    /// weighs random walks. Actions weighted zero are never taken.
+    pub fn with_profile(mut self, profile: Profile<'a, E>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    pub fn network(&self) -> &MachineNetwork<E, S, W> {
+        &self.network
+    }
+
+    /// Lets the model take up to `steps` actions and reports on the system.
+    pub fn run(&mut self, steps: usize) -> ClosedLoopReportOf<S> {
+        let feedback: Vec<E::Input> = S::all_outputs()
+            .iter()
+            .filter_map(|output| W::b_to_a(output).map(|(input, _)| input))
+            .collect();
+        let mut report = ClosedLoopReport {
+            steps: 0,
+            occupancy: S::all_states().iter().map(|&state| (state, 0)).collect(),
+            outputs: Vec::new(),
+            rejected: 0,
+            unstable: 0,
+            deadlocked: false,
+        };
+
+        for _ in 0..steps {
+            let Some(action) = self.next_action(&feedback) else {
+                report.deadlocked = true;
+                break;
+            };
+            let step = self.network.process_input(Tagged::A(action));
+            report.steps += 1;
+            for micro_step in &step.micro_steps {
+                match &micro_step.result {
+                    Tagged::B(Ok(transition)) => {
+                        if let Some(output) = &transition.output {
+                            match report.outputs.iter_mut().find(|(o, _)| o == output) {
+                                Some((_, count)) => *count += 1,
+                                None => report.outputs.push((output.clone(), 1)),
+                            }
+                        }
+                    }
+                    Tagged::B(Err(_)) => report.rejected += 1,
+                    Tagged::A(_) => {}
+                }
+            }
+            if !step.stable {
+                report.unstable += 1;
+            }
+            let state = self.network.b().state();
+            if let Some((_, count)) = report.occupancy.iter_mut().find(|(s, _)| *s == state) {
+                *count += 1;
+            }
+        }
+        report
+    }
+
+    /// An input the model accepts in its current configuration, drawn by
+    /// weight; `None` if there is none.
+    fn next_action(&mut self, feedback: &[E::Input]) -> Option<E::Input> {
+        let env = self.network.a();
+        let (state, configuration) = (env.state(), env.configuration());
+        let actions: Vec<E::Input> = E::all_inputs()
+            .iter()
+            .filter(|input| !feedback.contains(input) && configuration.step(input).is_ok())
+            .cloned()
+            .collect();
+        let weights: Vec<f64> = match self.profile {
+            Some(profile) => actions.iter().map(|input| profile(state, input)).collect(),
+            None => vec![1.0; actions.len()],
+        };
+        let idx = self.rng.weighted_index(&weights)?;
+        actions.into_iter().nth(idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet, DigicodeState, Door, DoorOutputAlphabet, DoorState,
+    };
+
+    /// A visitor who only presses the keys of the code, in any order.
+    fn visitor(_state: DigicodeState, input: &DigicodeInputAlphabet) -> f64 {
+        match input {
+            DigicodeInputAlphabet::Digit(4 | 9 | 2) | DigicodeInputAlphabet::OkEnter => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    fn run(seed: u64) -> ClosedLoopReportOf<Door> {
+        ClosedLoop::<Digicode, Door>::new(seed).with_profile(&visitor).run(50)
+    }
+
+    #[test]
+    fn the_loop_stops_once_the_visitor_has_no_key_left() {
+        let runs: Vec<_> = (0..32).map(run).collect();
+        for report in &runs {
+            assert!(report.deadlocked);
+            assert_eq!(report.occupancy.iter().map(|(_, count)| count).sum::<usize>(), report.steps);
+        }
+        let opened = runs.iter().find(|report| report.occupancy_of(DoorState::Opened) > 0.0).unwrap();
+        assert_eq!(opened.outputs, [(DoorOutputAlphabet::DoorOpens, 1)]);
+        assert_eq!(runs[5], run(5));
+    }
+}
@@ -0,0 +1,286 @@
+//! Byte-level protocol layer, so a spec can sit directly on a socket or a
+//! serial port as a prototype implementation or a test rig peer.
+//!
+//! A `Codec` frames a byte stream into input symbols and renders output
+//! symbols as bytes. `CodecRunner` puts one in front of a `MachineRunner`:
+//! bytes written to it are decoded and stepped through the spec, and the
+//! encoded replies are read back from it. `serve` and `serve_split` pump a
+//! blocking stream until it closes.
+
+use crate::labels;
+use crate::parse::ParseInput;
+use crate::runner::{MachineRunner, StepErrorOf, StepResultOf};
+use crate::XMachine;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// A malformed frame: `consumed` bytes are dropped before decoding resumes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodeError {
+    pub consumed: usize,
+    pub message: String,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "malformed frame of {} byte(s): {}", self.consumed, self.message)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Maps a byte stream to the input alphabet of `T` and outputs back to bytes.
+pub trait Codec<T: XMachine> {
+    /// Decodes the first frame of `buffer`, returning the input and the
+    /// number of bytes it took, or `None` if the frame is not complete yet.
+    fn decode(&mut self, buffer: &[u8]) -> Result<Option<(T::Input, usize)>, DecodeError>;
+
+    /// Appends the bytes of `output` to `out`.
+    fn encode(&mut self, output: &T::Output, out: &mut Vec<u8>);
+
+    /// Appends what the implementation sends when the spec rejects an input,
+    /// e.g. a NAK. Sends nothing by default.
+    fn encode_rejection(&mut self, _error: &StepErrorOf<T>, _out: &mut Vec<u8>) {}
+}
+
+/// A text protocol of one input per line, read with `parse::ParseInput`,
+/// and one output per line, written with its label under `T::labeling()`.
+/// Blank lines are skipped; a rejected input is answered by `rejection`.
+pub struct LineCodec<T: XMachine> {
+    rejection: Option<String>,
+    machine: PhantomData<T>,
+}
+
+impl<T: XMachine> LineCodec<T> {
+    pub fn new() -> Self {
+        Self {
+            rejection: None,
+            machine: PhantomData,
+        }
+    }
+
+    /// Answers rejected inputs with the line `reply`, e.g. `"ERR"`.
+    pub fn with_rejection(mut self, reply: &str) -> Self {
+        self.rejection = Some(reply.to_string());
+        self
+    }
+}
+
+impl<T: XMachine> Default for LineCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: XMachine> Codec<T> for LineCodec<T>
+where
+    T::Input: ParseInput,
+{
+    fn decode(&mut self, buffer: &[u8]) -> Result<Option<(T::Input, usize)>, DecodeError> {
+        let mut start = 0;
+        while let Some(len) = buffer[start..].iter().position(|&b| b == b'\n') {
+            let consumed = start + len + 1;
+            let line = String::from_utf8_lossy(&buffer[start..start + len]);
+            let line = line.trim();
+            if line.is_empty() {
+                start = consumed;
+                continue;
+            }
+            return match T::Input::parse_input(line) {
+                Ok(input) => Ok(Some((input, consumed))),
+                Err(e) => Err(DecodeError {
+                    consumed,
+                    message: e.to_string(),
+                }),
+            };
+        }
+        Ok(None)
+    }
+
+    fn encode(&mut self, output: &T::Output, out: &mut Vec<u8>) {
+        out.extend_from_slice(labels::output::<T>(output).as_bytes());
+        out.push(b'\n');
+    }
+
+    fn encode_rejection(&mut self, _error: &StepErrorOf<T>, out: &mut Vec<u8>) {
+        if let Some(reply) = &self.rejection {
+            out.extend_from_slice(reply.as_bytes());
+            out.push(b'\n');
+        }
+    }
+}
+
+type Observer<T> = Box<dyn FnMut(&<T as XMachine>::Input, &StepResultOf<T>)>;
+
+/// The spec behind a byte interface: written bytes are decoded by `C` and
+/// processed, and the encoded replies are read back.
+///
+/// Malformed frames are skipped and counted by `decode_errors`.
+pub struct CodecRunner<T: XMachine, C> {
+    runner: MachineRunner<T>,
+    codec: C,
+    incoming: Vec<u8>,
+    outgoing: VecDeque<u8>,
+    decode_errors: usize,
+    observer: Option<Observer<T>>,
+}
+
+impl<T: XMachine, C: Codec<T>> CodecRunner<T, C> {
+    /// Starts the spec in its initial configuration.
+    pub fn new(codec: C) -> Self {
+        Self::with_runner(MachineRunner::new(), codec)
+    }
+
+    pub fn with_runner(runner: MachineRunner<T>, codec: C) -> Self {
+        Self {
+            runner,
+            codec,
+            incoming: Vec::new(),
+            outgoing: VecDeque::new(),
+            decode_errors: 0,
+            observer: None,
+        }
+    }
+
+    /// Registers a callback invoked with every decoded input and its step result.
+    pub fn on_step(mut self, observer: impl FnMut(&T::Input, &StepResultOf<T>) + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    pub fn runner(&self) -> &MachineRunner<T> {
+        &self.runner
+    }
+
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Malformed frames skipped so far.
+    pub fn decode_errors(&self) -> usize {
+        self.decode_errors
+    }
+
+    /// Returns the spec to its initial configuration and drops a partial
+    /// frame and unread replies. The decode error count is kept.
+    pub fn reset(&mut self) {
+        self.runner.reset();
+        self.incoming.clear();
+        self.outgoing.clear();
+    }
+
+    /// Processes `bytes` and returns the replies to every complete frame.
+    /// Bytes of an incomplete frame are kept for the next call.
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.incoming.extend_from_slice(bytes);
+        let mut reply = Vec::new();
+        let mut start = 0;
+        loop {
+            match self.codec.decode(&self.incoming[start..]) {
+                Ok(Some((input, consumed))) => {
+                    start += consumed.max(1);
+                    let result = self.runner.step(&input);
+                    match &result {
+                        Ok(transition) => {
+                            if let Some(output) = &transition.output {
+                                self.codec.encode(output, &mut reply);
+                            }
+                        }
+                        Err(error) => self.codec.encode_rejection(error, &mut reply),
+                    }
+                    if let Some(observer) = self.observer.as_mut() {
+                        observer(&input, &result);
+                    }
+                }
+                Ok(None) => break,
+                Err(error) => {
+                    self.decode_errors += 1;
+                    start += error.consumed.max(1);
+                }
+            }
+            if start >= self.incoming.len() {
+                break;
+            }
+        }
+        self.incoming.drain(..start.min(self.incoming.len()));
+        reply
+    }
+
+    /// Pumps `stream` until it reaches end of file, answering every frame on it.
+    pub fn serve<S: Read + Write>(&mut self, stream: &mut S) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+        loop {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            let reply = self.feed(&buffer[..read]);
+            stream.write_all(&reply)?;
+            stream.flush()?;
+        }
+    }
+
+    /// Like `serve`, for interfaces read and written through separate
+    /// handles, e.g. a cloned `TcpStream` or the two ends of a serial port.
+    pub fn serve_split<R: Read, W: Write>(&mut self, reader: &mut R, writer: &mut W) -> io::Result<()> {
+        let mut buffer = [0; 1024];
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            let reply = self.feed(&buffer[..read]);
+            writer.write_all(&reply)?;
+            writer.flush()?;
+        }
+    }
+}
+
+/// Bytes written are processed as by `CodecRunner::feed`; the replies are
+/// buffered for `Read`.
+impl<T: XMachine, C: Codec<T>> Write for CodecRunner<T, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let reply = self.feed(buf);
+        self.outgoing.extend(reply);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reads the buffered replies; returns 0 when none is pending.
+impl<T: XMachine, C: Codec<T>> Read for CodecRunner<T, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.outgoing.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeState};
+
+    #[test]
+    fn lines_are_decoded_stepped_and_answered() {
+        let mut runner = CodecRunner::<Digicode, _>::new(LineCodec::new().with_rejection("ERR"));
+        let mut reply = runner.feed(b"DoorCloses\nBogus\n\nDigit 4\nDigit 9\nDig");
+        reply.extend(runner.feed(b"it 2\nOkEnter\n"));
+        assert_eq!(String::from_utf8(reply).unwrap(), "ERR\nDigit(4)\nDigit(9)\nDigit(2)\nOpen\n");
+        assert_eq!(runner.decode_errors(), 1);
+        assert_eq!(runner.runner().state(), DigicodeState::CodeEntered);
+    }
+
+    #[test]
+    fn written_frames_are_answered_through_read() {
+        let mut runner = CodecRunner::<Digicode, _>::new(LineCodec::new());
+        runner.write_all(b"Digit 4\nDoorCloses\n").unwrap();
+        let mut reply = String::new();
+        runner.read_to_string(&mut reply).unwrap();
+        assert_eq!(reply, "Digit(4)\n");
+        assert_eq!(runner.runner().state(), DigicodeState::Accepting);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::configuration::Configuration;
+use crate::labels;
+use crate::mbt::{PathConstraints, TestCase, TestCaseOf};
+use crate::network::{route, Wiring};
+use crate::search::{self, Goal};
+use crate::store::Store;
+use crate::{Visibility, XMachine};
+use std::convert::TryFrom;
+use std::fmt;
+
+/// How the outputs of one machine convert into inputs of its peer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdapterReport<Output, PeerInput> {
+    /// Outputs that convert, with the peer input they become.
+    pub connected: Vec<(Output, PeerInput)>,
+    /// Outputs for which the conversion fails.
+    pub unconnected: Vec<Output>,
+    /// Peer inputs reached from several outputs: the peer cannot tell them apart.
+    pub ambiguous: Vec<(PeerInput, Vec<Output>)>,
+    /// Conversions producing an input missing from the peer's `all_inputs()`.
+    pub undeclared: Vec<(Output, PeerInput)>,
+    /// Outputs marked `Visibility::Internal` or `Visibility::Shared` that do not convert.
+    pub missing_internal: Vec<Output>,
+}
+
+impl<Output, PeerInput> AdapterReport<Output, PeerInput> {
+    /// `true` if no conversion is ambiguous, undeclared or missing.
+    pub fn is_sound(&self) -> bool {
+        self.ambiguous.is_empty() && self.undeclared.is_empty() && self.missing_internal.is_empty()
+    }
+}
+
+/// `AdapterReport` for outputs of `M` converted into `PeerInput`.
+pub type AdapterReportOf<M, PeerInput> = AdapterReport<<M as XMachine>::Output, PeerInput>;
+
+/// Adapter reports for both directions of a composition of `A` and `B`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdapterCheck<OutputA, InputB, OutputB, InputA> {
+    pub a_to_b: AdapterReport<OutputA, InputB>,
+    pub b_to_a: AdapterReport<OutputB, InputA>,
+}
+
+impl<OutputA, InputB, OutputB, InputA> AdapterCheck<OutputA, InputB, OutputB, InputA> {
+    pub fn is_sound(&self) -> bool {
+        self.a_to_b.is_sound() && self.b_to_a.is_sound()
+    }
+}
+
+/// `AdapterCheck` specialised to the machines `A` and `B`.
+pub type AdapterCheckOf<A, B> = AdapterCheck<
+    <A as XMachine>::Output,
+    <B as XMachine>::Input,
+    <B as XMachine>::Output,
+    <A as XMachine>::Input,
+>;
+
+/// Checks the `TryFrom` adapters of a composition of `A` and `B` in both directions.
+pub fn check_adapters<A, B>() -> AdapterCheckOf<A, B>
+where
+    A: XMachine,
+    B: XMachine,
+    B::Input: TryFrom<A::Output>,
+    A::Input: TryFrom<B::Output>,
+{
+    AdapterCheck {
+        a_to_b: check_direction::<A, B>(),
+        b_to_a: check_direction::<B, A>(),
+    }
+}
+
+/// Converts every declared output of `Sender` into an input of `Receiver`.
+pub fn check_direction<Sender, Receiver>() -> AdapterReportOf<Sender, Receiver::Input>
+where
+    Sender: XMachine,
+    Receiver: XMachine,
+    Receiver::Input: TryFrom<Sender::Output>,
+{
+    let mut report = AdapterReport {
+        connected: Vec::new(),
+        unconnected: Vec::new(),
+        ambiguous: Vec::new(),
+        undeclared: Vec::new(),
+        missing_internal: Vec::new(),
+    };
+
+    for output in Sender::all_outputs().iter() {
+        let Ok(input) = Receiver::Input::try_from(output.clone()) else {
+            if matches!(
+                Sender::output_visibility(output),
+                Some(Visibility::Internal) | Some(Visibility::Shared)
+            ) {
+                report.missing_internal.push(output.clone());
+            }
+            report.unconnected.push(output.clone());
+            continue;
+        };
+        if !Receiver::all_inputs().contains(&input) {
+            report.undeclared.push((output.clone(), input.clone()));
+        }
+        report.connected.push((output.clone(), input));
+    }
+
+    for (idx, (_, input)) in report.connected.iter().enumerate() {
+        let sources: Vec<Sender::Output> = report
+            .connected
+            .iter()
+            .filter(|(_, other)| other == input)
+            .map(|(o, _)| o.clone())
+            .collect();
+        let first = report.connected.iter().position(|(_, other)| other == input);
+        if sources.len() > 1 && first == Some(idx) {
+            report.ambiguous.push((input.clone(), sources));
+        }
+    }
+    report
+}
+
+/// Contract tests for the messages `Sender` routes to `Receiver`, from `contract_tests`.
+pub struct Contract<Sender: XMachine, Receiver: XMachine> {
+    /// One case of `Receiver` per routed output: the converted input applied in
+    /// the cheapest reachable configuration accepting it.
+    pub tests: Vec<TestCaseOf<Receiver>>,
+    /// Routed outputs whose converted input no configuration of `Receiver`
+    /// reachable within the bound accepts: the message is always dropped.
+    pub droppable: Vec<(Sender::Output, Receiver::Input)>,
+}
+
+/// Generates the contract tests of the composition of `A` and `B` under
+/// wiring `W`, in both directions, exploring configurations reachable within
+/// `depth` inputs.
+pub fn contract_tests<A, B, W>(depth: usize) -> (Contract<A, B>, Contract<B, A>)
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+    A::Memory: Store,
+    B::Memory: Store,
+{
+    (
+        contract_direction::<A, B>(W::a_to_b, depth),
+        contract_direction::<B, A>(W::b_to_a, depth),
+    )
+}
+
+/// A direction of a `Wiring`, e.g. `Wiring::a_to_b`.
+pub type Wire<Sender, Receiver> =
+    fn(&<Sender as XMachine>::Output) -> Option<(<Receiver as XMachine>::Input, Option<&'static str>)>;
+
+/// Contract tests for the outputs of `Sender` that `wire` routes to `Receiver`.
+/// Outputs marked `Visibility::External` are not routed and get no test.
+pub fn contract_direction<Sender, Receiver>(
+    wire: Wire<Sender, Receiver>,
+    depth: usize,
+) -> Contract<Sender, Receiver>
+where
+    Sender: XMachine,
+    Receiver: XMachine,
+    Receiver::Memory: Store,
+{
+    let mut contract = Contract {
+        tests: Vec::new(),
+        droppable: Vec::new(),
+    };
+    for output in Sender::all_outputs().iter() {
+        let (Some((input, _)), _) = route::<Sender, _>(output, wire) else {
+            continue;
+        };
+        match accepting_configuration::<Receiver>(&input, depth) {
+            Some((setup_sequence, config)) => {
+                let (transition, _) = config.step(&input).expect("configuration accepts the input");
+                contract.tests.push(TestCase {
+                    name: format!(
+                        "Contract: {} as {} accepted in {}",
+                        labels::output::<Sender>(output),
+                        labels::input::<Receiver>(&input),
+                        labels::state::<Receiver>(config.state)
+                    ),
+                    setup_sequence,
+                    test_input: input,
+                    expected_output: transition.output.into(),
+                    verification_sequence: Vec::new(),
+                    requirements: Receiver::requirement_ids(transition.from, transition.phi).to_vec(),
+                    setup_configuration: Some(config.into_parts()),
+                });
+            }
+            None => contract.droppable.push((output.clone(), input)),
+        }
+    }
+    contract
+}
+
+/// The cheapest setup, found by `search::search`, after which `T` accepts
+/// `input` within `depth` inputs.
+fn accepting_configuration<T: XMachine>(input: &T::Input, depth: usize) -> Option<(Vec<T::Input>, Configuration<T>)> {
+    let accepts = |config: &Configuration<T>| config.step(input).is_ok();
+    let found = search::search::<T>(&Goal::Configuration(&accepts), &PathConstraints::new().max_len(depth))?;
+    Some((found.path, found.configuration))
+}
+
+impl<Sender: XMachine, Receiver: XMachine> fmt::Debug for Contract<Sender, Receiver>
+where
+    Receiver::Memory: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Contract")
+            .field("tests", &self.tests)
+            .field("droppable", &self.droppable)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, Door, DoorInputAlphabet, DoorOutputAlphabet,
+    };
+    use crate::network::Adapters;
+    use std::borrow::Cow;
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum PanelInput {
+        Poll,
+        Refresh,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum PanelStatus {
+        Opened,
+        Opening,
+        Fault,
+    }
+
+    /// A status panel driving the door from what it reports.
+    struct Panel;
+
+    impl XMachine for Panel {
+        type Input = PanelInput;
+        type Output = PanelStatus;
+        type State = ();
+        type Memory = ();
+        type Phi = ();
+
+        fn next_state(_state: (), _phi: ()) -> Option<()> {
+            Some(())
+        }
+
+        fn initial_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn final_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(_phi: (), _store: &mut (), _input: &PanelInput) -> Result<Option<PanelStatus>, ()> {
+            Ok(Some(PanelStatus::Opened))
+        }
+
+        /// `Refresh` is missing, although the door's adapter produces it.
+        fn all_inputs() -> Cow<'static, [PanelInput]> {
+            Cow::Borrowed(&[PanelInput::Poll])
+        }
+
+        fn all_outputs() -> Cow<'static, [PanelStatus]> {
+            Cow::Borrowed(&[PanelStatus::Opened, PanelStatus::Opening, PanelStatus::Fault])
+        }
+
+        fn all_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn all_phis() -> &'static [()] {
+            &[()]
+        }
+
+        fn get_phi_for_input(_state: (), _input: &PanelInput) -> Option<()> {
+            Some(())
+        }
+
+        fn output_visibility(output: &PanelStatus) -> Option<Visibility> {
+            (*output == PanelStatus::Fault).then_some(Visibility::Internal)
+        }
+    }
+
+    /// Misreports a door still opening as opened, and drops faults meant for the door.
+    impl TryFrom<PanelStatus> for DoorInputAlphabet {
+        type Error = ();
+
+        fn try_from(status: PanelStatus) -> Result<Self, ()> {
+            match status {
+                PanelStatus::Opened | PanelStatus::Opening => Ok(DoorInputAlphabet::Open),
+                PanelStatus::Fault => Err(()),
+            }
+        }
+    }
+
+    impl TryFrom<DoorOutputAlphabet> for PanelInput {
+        type Error = ();
+
+        fn try_from(output: DoorOutputAlphabet) -> Result<Self, ()> {
+            match output {
+                DoorOutputAlphabet::DoorOpens => Ok(PanelInput::Refresh),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn the_secure_door_adapters_are_sound() {
+        let check = check_adapters::<Digicode, Door>();
+        assert!(check.is_sound());
+        assert_eq!(check.a_to_b.connected, [(DigicodeOutputAlphabet::Open, DoorInputAlphabet::Open)]);
+        assert_eq!(
+            check.b_to_a.connected,
+            [(DoorOutputAlphabet::DoorCloses, DigicodeInputAlphabet::DoorCloses)]
+        );
+    }
+
+    #[test]
+    fn a_misreporting_adapter_is_flagged() {
+        let check = check_adapters::<Panel, Door>();
+        assert!(!check.is_sound());
+        assert_eq!(
+            check.a_to_b.ambiguous,
+            [(DoorInputAlphabet::Open, vec![PanelStatus::Opened, PanelStatus::Opening])]
+        );
+        assert_eq!(check.a_to_b.missing_internal, [PanelStatus::Fault]);
+        assert_eq!(check.b_to_a.undeclared, [(DoorOutputAlphabet::DoorOpens, PanelInput::Refresh)]);
+    }
+
+    #[test]
+    fn contract_tests_apply_routed_messages_where_the_peer_accepts_them() {
+        let (digicode_to_door, door_to_digicode) = contract_tests::<Digicode, Door, Adapters>(4);
+        assert!(digicode_to_door.droppable.is_empty());
+        assert!(!digicode_to_door.tests.is_empty());
+        for case in digicode_to_door.tests.iter() {
+            let (state, store) = case.setup_configuration.expect("contract cases record their setup");
+            assert!(Configuration::<Door>::new(state, store).step(&case.test_input).is_ok());
+        }
+        assert!(door_to_digicode.tests.iter().all(|case| case.setup_sequence.len() <= 4));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::isolation;
+use crate::runner::{StepError, StepErrorOf, Transition, TransitionOf};
+use crate::XMachine;
+use std::fmt;
+
+/// A configuration of machine `T`: its control state together with its memory.
+pub struct Configuration<T: XMachine> {
+    pub state: T::State,
+    pub store: T::Memory,
+}
+
+impl<T: XMachine> Configuration<T> {
+    pub fn new(state: T::State, store: T::Memory) -> Self {
+        Self { state, store }
+    }
+
+    /// Every initial state paired with `initial_store()`.
+    pub fn initial() -> Vec<Self> {
+        T::initial_states()
+            .iter()
+            .map(|&state| Self::new(state, T::initial_store()))
+            .collect()
+    }
+
+    pub fn into_parts(self) -> (T::State, T::Memory) {
+        (self.state, self.store)
+    }
+
+    /// The transition taken on `input` and the configuration it leads to.
+    /// `self` is left untouched, also when the input is rejected.
+    pub fn step(&self, input: &T::Input) -> Result<(TransitionOf<T>, Self), StepErrorOf<T>> {
+        let state = self.state;
+        let phi = T::get_phi_for_input(state, input).ok_or(StepError::NoPhi { state })?;
+        let to = T::next_state(state, phi).ok_or(StepError::NoNextState { state, phi })?;
+
+        let mut store = self.store.clone();
+        let output = isolation::execute_phi::<T>(state, phi, &mut store, input)
+            .map_err(|_| StepError::GuardRejected { state, phi })?;
+
+        Ok((Transition { from: state, phi, to, output }, Self::new(to, store)))
+    }
+}
+
+impl<T: XMachine> From<(T::State, T::Memory)> for Configuration<T> {
+    fn from((state, store): (T::State, T::Memory)) -> Self {
+        Self::new(state, store)
+    }
+}
+
+impl<T: XMachine> Clone for Configuration<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.state, self.store.clone())
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Configuration<T>
+where
+    T::Memory: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Configuration")
+            .field("state", &self.state)
+            .field("store", &self.store)
+            .finish()
+    }
+}
+
+impl<T: XMachine> PartialEq for Configuration<T>
+where
+    T::Memory: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.state == other.state && self.store == other.store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, DigicodeState};
+
+    #[test]
+    fn stepping_returns_the_next_configuration_and_keeps_the_current_one() {
+        let ready = Configuration::<Digicode>::initial().remove(0);
+        let (transition, accepting) = ready.step(&DigicodeInputAlphabet::Digit(4)).unwrap();
+        assert_eq!(transition.output, Some(DigicodeOutputAlphabet::Digit(4)));
+        assert_eq!((ready.state, accepting.state), (DigicodeState::Ready, DigicodeState::Accepting));
+        assert_ne!(ready, accepting);
+        assert_eq!(
+            ready.step(&DigicodeInputAlphabet::DoorCloses).map(|_| ()),
+            Err(StepError::NoPhi { state: DigicodeState::Ready })
+        );
+    }
+}
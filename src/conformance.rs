@@ -0,0 +1,186 @@
+//! Post-hoc conformance checking of logged runs, for systems that cannot be
+//! shadowed live.
+//!
+//! Production logs parsed into `(input, observed output)` events, e.g. with
+//! `parse::ParseInput`, are replayed through the spec. The first event whose
+//! output differs from the one the spec predicts is reported together with
+//! the spec's configuration and the events leading up to it. As in the
+//! executor, outputs marked `Visibility::Internal` are not expected in
+//! the log, and a rejected input counts as no output.
+
+use crate::runner::{MachineRunner, StepResultOf};
+use crate::{Visibility, XMachine};
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Events kept before a divergence by `check_log`.
+pub const DEFAULT_CONTEXT: usize = 8;
+
+/// The first logged event the spec disagrees with.
+pub struct LogDivergence<T: XMachine> {
+    /// Zero-based index of the event in the log.
+    pub index: usize,
+    pub input: T::Input,
+    /// Observable output the spec predicts.
+    pub expected_output: Option<T::Output>,
+    pub observed_output: Option<T::Output>,
+    /// The spec's state when the event occurred.
+    pub state: T::State,
+    /// The spec's memory when the event occurred.
+    pub store: T::Memory,
+    /// What the spec did with the input.
+    pub spec: StepResultOf<T>,
+    /// The events preceding this one, oldest first.
+    pub context: Vec<(T::Input, Option<T::Output>)>,
+}
+
+/// How far a log conformed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogSummary<State> {
+    pub events: usize,
+    /// Events whose input the spec rejected, with no output logged.
+    pub rejected: usize,
+    /// The spec's state after the last event.
+    pub final_state: State,
+}
+
+/// `LogSummary` specialised to the states of machine `T`.
+pub type LogSummaryOf<T> = LogSummary<<T as XMachine>::State>;
+
+/// Replays `events` from an initial configuration and stops at the first
+/// divergence, keeping `DEFAULT_CONTEXT` events of context.
+pub fn check_log<T, I>(events: I) -> Result<LogSummaryOf<T>, LogDivergence<T>>
+where
+    T: XMachine,
+    I: IntoIterator<Item = (T::Input, Option<T::Output>)>,
+{
+    check_log_from(MachineRunner::new(), events, DEFAULT_CONTEXT)
+}
+
+/// Replays `events` from the configuration of `runner`, e.g. the state the
+/// system was known to be in when the log starts, keeping `context` events
+/// before a divergence.
+pub fn check_log_from<T, I>(
+    mut runner: MachineRunner<T>,
+    events: I,
+    context: usize,
+) -> Result<LogSummaryOf<T>, LogDivergence<T>>
+where
+    T: XMachine,
+    I: IntoIterator<Item = (T::Input, Option<T::Output>)>,
+{
+    let mut recent = VecDeque::with_capacity(context);
+    let mut rejected = 0;
+    let mut count = 0;
+    for (index, (input, observed_output)) in events.into_iter().enumerate() {
+        count = index + 1;
+        let (state, store) = (runner.state(), runner.store().clone());
+        let spec = runner.step(&input);
+        let expected_output = spec
+            .as_ref()
+            .ok()
+            .and_then(|transition| transition.output.clone())
+            .filter(|output| T::output_visibility(output) != Some(Visibility::Internal));
+        if expected_output != observed_output {
+            return Err(LogDivergence {
+                index,
+                input,
+                expected_output,
+                observed_output,
+                state,
+                store,
+                spec,
+                context: recent.into_iter().collect(),
+            });
+        }
+        if spec.is_err() {
+            rejected += 1;
+        }
+        if context > 0 {
+            if recent.len() == context {
+                recent.pop_front();
+            }
+            recent.push_back((input, observed_output));
+        }
+    }
+    Ok(LogSummary {
+        events: count,
+        rejected,
+        final_state: runner.state(),
+    })
+}
+
+impl<T: XMachine> Clone for LogDivergence<T> {
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index,
+            input: self.input.clone(),
+            expected_output: self.expected_output.clone(),
+            observed_output: self.observed_output.clone(),
+            state: self.state,
+            store: self.store.clone(),
+            spec: self.spec.clone(),
+            context: self.context.clone(),
+        }
+    }
+}
+
+impl<T: XMachine> fmt::Debug for LogDivergence<T>
+where
+    T::Memory: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LogDivergence")
+            .field("index", &self.index)
+            .field("input", &self.input)
+            .field("expected_output", &self.expected_output)
+            .field("observed_output", &self.observed_output)
+            .field("state", &self.state)
+            .field("store", &self.store)
+            .field("spec", &self.spec)
+            .field("context", &self.context)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet as In, DigicodeOutputAlphabet as Out, DigicodeState,
+    };
+
+    fn unlock() -> Vec<(In, Option<Out>)> {
+        vec![
+            (In::DoorCloses, None),
+            (In::Digit(4), Some(Out::Digit(4))),
+            (In::Digit(9), Some(Out::Digit(9))),
+            (In::Digit(2), Some(Out::Digit(2))),
+            (In::OkEnter, Some(Out::Open)),
+        ]
+    }
+
+    #[test]
+    fn a_conforming_log_is_summarized() {
+        let summary = check_log::<Digicode, _>(unlock()).unwrap();
+        assert_eq!(
+            summary,
+            LogSummary {
+                events: 5,
+                rejected: 1,
+                final_state: DigicodeState::CodeEntered,
+            }
+        );
+    }
+
+    #[test]
+    fn the_first_divergence_is_reported_with_its_context() {
+        let mut log = unlock();
+        log[3].1 = Some(Out::Digit(3));
+        let divergence = check_log_from::<Digicode, _>(MachineRunner::new(), log.clone(), 2).unwrap_err();
+        assert_eq!(divergence.index, 3);
+        assert_eq!(divergence.expected_output, Some(Out::Digit(2)));
+        assert_eq!(divergence.state, DigicodeState::Accepting);
+        assert_eq!(divergence.context, log[1..3]);
+    }
+}
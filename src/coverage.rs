@@ -0,0 +1,281 @@
+//! Spec coverage of an executed suite, with a gate for CI.
+//!
+//! A `Coverage` records the states, transitions and phis of the spec that a
+//! run exercised, either transition by transition or by replaying test cases
+//! on the spec. Called from a `#[test]` after the suite has run,
+//! `assert_at_least` fails the build when coverage drops below the targets,
+//! listing what is left uncovered.
+
+use crate::labels;
+use crate::mbt::TestCaseOf;
+use crate::runner::{MachineRunner, TransitionOf};
+use crate::{IndexSet, XMachine};
+use std::fmt::{self, Write};
+use std::marker::PhantomData;
+
+/// Minimum ratios, between 0.0 and 1.0, that `Coverage::assert_at_least`
+/// enforces. The default requires full coverage.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CoverageTargets {
+    pub states: f64,
+    /// Defined (state, phi) pairs of the associated automaton.
+    pub transitions: f64,
+    pub phis: f64,
+}
+
+impl Default for CoverageTargets {
+    fn default() -> Self {
+        Self {
+            states: 1.0,
+            transitions: 1.0,
+            phis: 1.0,
+        }
+    }
+}
+
+/// The states, transitions and phis of spec `T` a run has covered.
+///
+/// The transitions to cover are the (state, phi) pairs some input selects in
+/// the state and for which `next_state` is defined, reachable or not.
+pub struct Coverage<T: XMachine> {
+    states: IndexSet,
+    transitions: IndexSet,
+    phis: IndexSet,
+    machine: PhantomData<T>,
+}
+
+impl<T: XMachine> Coverage<T> {
+    /// Nothing covered yet.
+    pub fn new() -> Self {
+        let (states, phis) = (T::all_states().len(), T::all_phis().len());
+        Self {
+            states: IndexSet::new(states),
+            transitions: IndexSet::new(states * phis),
+            phis: IndexSet::new(phis),
+            machine: PhantomData,
+        }
+    }
+
+    /// What the spec covers replaying each case's setup sequence and test
+    /// input from an initial configuration, up to the first rejected input.
+    pub fn of_cases(cases: &[TestCaseOf<T>]) -> Self {
+        let mut coverage = Self::new();
+        for case in cases {
+            let mut runner = MachineRunner::<T>::new();
+            coverage.record_state(runner.state());
+            for input in case.setup_sequence.iter().chain(std::iter::once(&case.test_input)) {
+                match runner.step(input) {
+                    Ok(transition) => coverage.record(&transition),
+                    Err(_) => break,
+                }
+            }
+        }
+        coverage
+    }
+
+    /// Marks `state` as visited, e.g. the initial state of a run.
+    pub fn record_state(&mut self, state: T::State) {
+        if let Some(idx) = T::state_index(state) {
+            self.states.insert(idx.0);
+        }
+    }
+
+    /// Marks a transition taken, with its source and target states and its phi.
+    pub fn record(&mut self, transition: &TransitionOf<T>) {
+        self.record_state(transition.from);
+        self.record_state(transition.to);
+        if let Some(phi) = T::phi_index(transition.phi) {
+            self.phis.insert(phi.0);
+            if let Some(state) = T::state_index(transition.from) {
+                self.transitions.insert(state.0 * T::all_phis().len() + phi.0);
+            }
+        }
+    }
+
+    /// Adds what `other` covers.
+    pub fn merge(&mut self, other: &Self) {
+        for idx in other.states.iter() {
+            self.states.insert(idx);
+        }
+        for idx in other.transitions.iter() {
+            self.transitions.insert(idx);
+        }
+        for idx in other.phis.iter() {
+            self.phis.insert(idx);
+        }
+    }
+
+    /// Fraction of `all_states()` visited; 1.0 if there are none.
+    pub fn states(&self) -> f64 {
+        ratio(self.states.count(), T::all_states().len())
+    }
+
+    /// Fraction of the defined transitions taken; 1.0 if there are none.
+    pub fn transitions(&self) -> f64 {
+        let defined = defined_transitions::<T>();
+        let covered = defined.iter().filter(|&&(state, phi)| self.covers(state, phi)).count();
+        ratio(covered, defined.len())
+    }
+
+    /// Fraction of `all_phis()` applied; 1.0 if there are none.
+    pub fn phis(&self) -> f64 {
+        ratio(self.phis.count(), T::all_phis().len())
+    }
+
+    pub fn uncovered_states(&self) -> Vec<T::State> {
+        T::all_states()
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !self.states.contains(*idx))
+            .map(|(_, &state)| state)
+            .collect()
+    }
+
+    /// Defined transitions not taken, in `all_states()` then `all_phis()` order.
+    pub fn uncovered_transitions(&self) -> Vec<(T::State, T::Phi)> {
+        defined_transitions::<T>()
+            .into_iter()
+            .filter(|&(state, phi)| !self.covers(state, phi))
+            .collect()
+    }
+
+    pub fn uncovered_phis(&self) -> Vec<T::Phi> {
+        T::all_phis()
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !self.phis.contains(*idx))
+            .map(|(_, &phi)| phi)
+            .collect()
+    }
+
+    /// `true` if every ratio reaches its target.
+    pub fn meets(&self, targets: CoverageTargets) -> bool {
+        self.states() >= targets.states && self.transitions() >= targets.transitions && self.phis() >= targets.phis
+    }
+
+    /// Panics with `to_text` unless every ratio reaches its target.
+    pub fn assert_at_least(&self, targets: CoverageTargets) {
+        if !self.meets(targets) {
+            panic!("spec coverage below target\n{}", self.to_text(Some(targets)));
+        }
+    }
+
+    /// Ratios, compared with `targets` if given, followed by every uncovered element.
+    pub fn to_text(&self, targets: Option<CoverageTargets>) -> String {
+        let mut out = String::new();
+        let mut line = |name: &str, ratio: f64, target: Option<f64>| {
+            write!(out, "{:<12}{:>6.1}%", name, ratio * 100.0).unwrap();
+            if let Some(target) = target {
+                let verdict = if ratio >= target { "ok" } else { "BELOW" };
+                write!(out, " (target {:.1}%, {})", target * 100.0, verdict).unwrap();
+            }
+            out.push('\n');
+        };
+        line("states", self.states(), targets.map(|t| t.states));
+        line("transitions", self.transitions(), targets.map(|t| t.transitions));
+        line("phis", self.phis(), targets.map(|t| t.phis));
+
+        for state in self.uncovered_states() {
+            writeln!(out, "uncovered state {}", labels::state::<T>(state)).unwrap();
+        }
+        for (state, phi) in self.uncovered_transitions() {
+            writeln!(out, "uncovered transition {} in {}", labels::phi::<T>(phi), labels::state::<T>(state)).unwrap();
+        }
+        for phi in self.uncovered_phis() {
+            writeln!(out, "uncovered phi {}", labels::phi::<T>(phi)).unwrap();
+        }
+        out
+    }
+
+    fn covers(&self, state: T::State, phi: T::Phi) -> bool {
+        match (T::state_index(state), T::phi_index(phi)) {
+            (Some(state), Some(phi)) => self.transitions.contains(state.0 * T::all_phis().len() + phi.0),
+            _ => false,
+        }
+    }
+}
+
+/// The (state, phi) pairs some input selects with a next state defined.
+pub(crate) fn defined_transitions<T: XMachine>() -> Vec<(T::State, T::Phi)> {
+    let inputs = T::all_inputs();
+    let mut transitions = Vec::new();
+    for &state in T::all_states() {
+        for &phi in T::all_phis() {
+            let selected = inputs.iter().any(|input| T::get_phi_for_input(state, input).map(T::phi_kind) == Some(phi));
+            if selected && T::next_state(state, phi).is_some() {
+                transitions.push((state, phi));
+            }
+        }
+    }
+    transitions
+}
+
+fn ratio(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        covered as f64 / total as f64
+    }
+}
+
+impl<T: XMachine> Default for Coverage<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: XMachine> Clone for Coverage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            states: self.states.clone(),
+            transitions: self.transitions.clone(),
+            phis: self.phis.clone(),
+            machine: PhantomData,
+        }
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Coverage<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coverage")
+            .field("states", &self.states())
+            .field("transitions", &self.transitions())
+            .field("phis", &self.phis())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::{LightInput, LightState, TrafficLight};
+    use crate::suite::TestSuiteBuilder;
+
+    #[test]
+    fn a_logic_suite_covers_the_whole_spec() {
+        let distinguishing = |_| vec![LightInput::Tick];
+        let cases = TestSuiteBuilder::<TrafficLight>::new().logic(&distinguishing).build().cases;
+        let coverage = Coverage::<TrafficLight>::of_cases(&cases);
+        coverage.assert_at_least(CoverageTargets::default());
+        assert!(coverage.uncovered_transitions().is_empty());
+    }
+
+    #[test]
+    fn merged_runs_add_up() {
+        let mut red = Coverage::<TrafficLight>::new();
+        red.record(&MachineRunner::<TrafficLight>::new().step(&LightInput::Tick).unwrap());
+        let mut green = Coverage::<TrafficLight>::new();
+        green.record(&MachineRunner::<TrafficLight>::from_parts(LightState::Green, 0).step(&LightInput::Tick).unwrap());
+        assert!(!red.meets(CoverageTargets::default()));
+        let transitions = red.transitions();
+        red.merge(&green);
+        assert!(red.transitions() > transitions);
+        assert!(red.to_text(None).contains("uncovered"));
+    }
+
+    #[test]
+    #[should_panic(expected = "spec coverage below target")]
+    fn the_gate_fails_below_target() {
+        Coverage::<TrafficLight>::new().assert_at_least(CoverageTargets::default());
+    }
+}
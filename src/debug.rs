@@ -0,0 +1,375 @@
+use crate::network::{Adapters, MachineNetwork, StepReport, Tagged, Wiring};
+use crate::runner::{MachineRunner, StepErrorOf, TransitionOf};
+use crate::XMachine;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Condition on a transition at which a `DebugRunner` pauses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Breakpoint<State, Phi> {
+    /// Pause after a transition entering `State`.
+    EnterState(State),
+    /// Pause after `Phi` fires.
+    Phi(Phi),
+}
+
+/// `Breakpoint` specialised to the symbols of machine `T`.
+pub type BreakpointOf<T> = Breakpoint<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+impl<State: PartialEq, Phi: PartialEq + Copy> Breakpoint<State, Phi> {
+    /// `true` if `transition` of machine `T` triggers this breakpoint. Also
+    /// applies to the component transitions of `MachineNetwork::feed` and
+    /// `deliver_next`. Phis are compared by `T::phi_kind`, so a breakpoint on
+    /// a kind's representative pauses on every instance.
+    pub fn matches<T: XMachine<State = State, Phi = Phi>>(&self, transition: &TransitionOf<T>) -> bool {
+        match self {
+            Breakpoint::EnterState(state) => transition.to == *state,
+            Breakpoint::Phi(phi) => T::phi_kind(transition.phi) == T::phi_kind(*phi),
+        }
+    }
+}
+
+/// Why `DebugRunner::step` or `DebugRunner::resume` returned.
+pub enum Pause<T: XMachine> {
+    /// A single step completed without hitting a breakpoint.
+    Stepped { transition: TransitionOf<T> },
+    /// A transition hit at least one breakpoint; the listed breakpoints matched.
+    Breakpoint {
+        transition: TransitionOf<T>,
+        hits: Vec<BreakpointOf<T>>,
+    },
+    /// The machine rejected an input. The configuration is unchanged.
+    Rejected {
+        input: T::Input,
+        error: StepErrorOf<T>,
+    },
+    /// Every queued input was processed.
+    Idle,
+}
+
+/// A `MachineRunner` that processes queued inputs and pauses on breakpoints,
+/// so the configuration can be inspected or edited between steps.
+pub struct DebugRunner<T: XMachine> {
+    runner: MachineRunner<T>,
+    breakpoints: Vec<BreakpointOf<T>>,
+    queue: VecDeque<T::Input>,
+}
+
+impl<T: XMachine> DebugRunner<T> {
+    pub fn new() -> Self {
+        Self::from_runner(MachineRunner::new())
+    }
+
+    pub fn from_runner(runner: MachineRunner<T>) -> Self {
+        Self {
+            runner,
+            breakpoints: Vec::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: BreakpointOf<T>) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Returns `false` if `breakpoint` was not set.
+    pub fn remove_breakpoint(&mut self, breakpoint: BreakpointOf<T>) -> bool {
+        let before = self.breakpoints.len();
+        self.breakpoints.retain(|b| *b != breakpoint);
+        self.breakpoints.len() != before
+    }
+
+    pub fn breakpoints(&self) -> &[BreakpointOf<T>] {
+        &self.breakpoints
+    }
+
+    /// Appends inputs to process.
+    pub fn feed<'a>(&mut self, inputs: impl IntoIterator<Item = &'a T::Input>) {
+        self.queue.extend(inputs.into_iter().cloned());
+    }
+
+    /// Inputs still waiting to be processed.
+    pub fn queued(&self) -> impl Iterator<Item = &T::Input> {
+        self.queue.iter()
+    }
+
+    pub fn runner(&self) -> &MachineRunner<T> {
+        &self.runner
+    }
+
+    /// Edits the configuration at a pause point.
+    pub fn runner_mut(&mut self) -> &mut MachineRunner<T> {
+        &mut self.runner
+    }
+
+    /// Processes exactly one queued input.
+    /// Returns `Pause::Idle` if the queue is empty.
+    pub fn step(&mut self) -> Pause<T> {
+        let Some(input) = self.queue.pop_front() else {
+            return Pause::Idle;
+        };
+        match self.runner.step(&input) {
+            Ok(transition) => {
+                let hits = self.hits(&transition);
+                if hits.is_empty() {
+                    Pause::Stepped { transition }
+                } else {
+                    Pause::Breakpoint { transition, hits }
+                }
+            }
+            Err(error) => Pause::Rejected { input, error },
+        }
+    }
+
+    /// Processes queued inputs until a breakpoint hits, an input is rejected,
+    /// or the queue is empty.
+    pub fn resume(&mut self) -> Pause<T> {
+        loop {
+            match self.step() {
+                Pause::Stepped { .. } => continue,
+                pause => return pause,
+            }
+        }
+    }
+
+    fn hits(&self, transition: &TransitionOf<T>) -> Vec<BreakpointOf<T>> {
+        self.breakpoints
+            .iter()
+            .copied()
+            .filter(|b| b.matches::<T>(transition))
+            .collect()
+    }
+}
+
+impl<T: XMachine> Default for DebugRunner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Pause<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pause::Stepped { transition } => f
+                .debug_struct("Stepped")
+                .field("transition", transition)
+                .finish(),
+            Pause::Breakpoint { transition, hits } => f
+                .debug_struct("Breakpoint")
+                .field("transition", transition)
+                .field("hits", hits)
+                .finish(),
+            Pause::Rejected { input, error } => f
+                .debug_struct("Rejected")
+                .field("input", input)
+                .field("error", error)
+                .finish(),
+            Pause::Idle => f.write_str("Idle"),
+        }
+    }
+}
+
+/// A breakpoint on one component of a `MachineNetwork`.
+pub type NetworkBreakpointOf<A, B> = Tagged<BreakpointOf<A>, BreakpointOf<B>>;
+
+/// Why `NetworkDebugger::step` or `NetworkDebugger::resume` returned. Each
+/// report holds the single micro-step taken.
+pub enum NetworkPause<A: XMachine, B: XMachine> {
+    /// A micro-step completed without hitting a breakpoint.
+    Stepped { report: StepReport<A, B> },
+    /// A component transition hit at least one breakpoint; the listed breakpoints matched.
+    Breakpoint {
+        report: StepReport<A, B>,
+        hits: Vec<NetworkBreakpointOf<A, B>>,
+    },
+    /// A component rejected its input. Its configuration is unchanged.
+    Rejected { report: StepReport<A, B> },
+    /// No routed message is pending and every queued environment input was processed.
+    Idle,
+}
+
+/// A `MachineNetwork` stepped one micro-step at a time, pausing on
+/// breakpoints of either component, so both configurations and the pending
+/// messages can be inspected or edited between micro-steps.
+///
+/// Routed messages are delivered before the next environment input is taken,
+/// so stepping follows `MachineNetwork::process_input`.
+pub struct NetworkDebugger<A: XMachine, B: XMachine, W = Adapters> {
+    network: MachineNetwork<A, B, W>,
+    breakpoints: Vec<NetworkBreakpointOf<A, B>>,
+    queue: VecDeque<Tagged<A::Input, B::Input>>,
+}
+
+impl<A, B, W> NetworkDebugger<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    pub fn new() -> Self {
+        Self::from_network(MachineNetwork::new())
+    }
+
+    pub fn from_network(network: MachineNetwork<A, B, W>) -> Self {
+        Self {
+            network,
+            breakpoints: Vec::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: NetworkBreakpointOf<A, B>) {
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+    }
+
+    /// Returns `false` if `breakpoint` was not set.
+    pub fn remove_breakpoint(&mut self, breakpoint: &NetworkBreakpointOf<A, B>) -> bool {
+        let before = self.breakpoints.len();
+        self.breakpoints.retain(|b| b != breakpoint);
+        self.breakpoints.len() != before
+    }
+
+    pub fn breakpoints(&self) -> &[NetworkBreakpointOf<A, B>] {
+        &self.breakpoints
+    }
+
+    /// Appends environment inputs to process.
+    pub fn feed<'a>(&mut self, inputs: impl IntoIterator<Item = &'a Tagged<A::Input, B::Input>>) {
+        self.queue.extend(inputs.into_iter().cloned());
+    }
+
+    /// Environment inputs still waiting to be processed.
+    pub fn queued(&self) -> impl Iterator<Item = &Tagged<A::Input, B::Input>> {
+        self.queue.iter()
+    }
+
+    pub fn network(&self) -> &MachineNetwork<A, B, W> {
+        &self.network
+    }
+
+    /// Edits the components' configurations at a pause point.
+    pub fn network_mut(&mut self) -> &mut MachineNetwork<A, B, W> {
+        &mut self.network
+    }
+
+    /// Takes exactly one micro-step: delivers the next pending routed
+    /// message, or else consumes the next queued environment input.
+    /// Returns `NetworkPause::Idle` if there is neither.
+    pub fn step(&mut self) -> NetworkPause<A, B> {
+        let report = match self.network.deliver_next() {
+            Some(report) => report,
+            None => match self.queue.pop_front() {
+                Some(input) => self.network.feed(input),
+                None => return NetworkPause::Idle,
+            },
+        };
+        let hits = match report.micro_steps.first().map(|step| &step.result) {
+            Some(Tagged::A(Ok(transition))) => self.hits_a(transition),
+            Some(Tagged::B(Ok(transition))) => self.hits_b(transition),
+            _ => return NetworkPause::Rejected { report },
+        };
+        if hits.is_empty() {
+            NetworkPause::Stepped { report }
+        } else {
+            NetworkPause::Breakpoint { report, hits }
+        }
+    }
+
+    /// Takes micro-steps until a breakpoint hits, a component rejects its
+    /// input, or the network is stable with no environment input left.
+    pub fn resume(&mut self) -> NetworkPause<A, B> {
+        loop {
+            match self.step() {
+                NetworkPause::Stepped { .. } => continue,
+                pause => return pause,
+            }
+        }
+    }
+
+    fn hits_a(&self, transition: &TransitionOf<A>) -> Vec<NetworkBreakpointOf<A, B>> {
+        self.breakpoints
+            .iter()
+            .filter(|b| matches!(b, Tagged::A(b) if b.matches::<A>(transition)))
+            .cloned()
+            .collect()
+    }
+
+    fn hits_b(&self, transition: &TransitionOf<B>) -> Vec<NetworkBreakpointOf<A, B>> {
+        self.breakpoints
+            .iter()
+            .filter(|b| matches!(b, Tagged::B(b) if b.matches::<B>(transition)))
+            .cloned()
+            .collect()
+    }
+}
+
+impl<A, B, W> Default for NetworkDebugger<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: XMachine, B: XMachine> fmt::Debug for NetworkPause<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkPause::Stepped { report } => f.debug_struct("Stepped").field("report", report).finish(),
+            NetworkPause::Breakpoint { report, hits } => f
+                .debug_struct("Breakpoint")
+                .field("report", report)
+                .field("hits", hits)
+                .finish(),
+            NetworkPause::Rejected { report } => f.debug_struct("Rejected").field("report", report).finish(),
+            NetworkPause::Idle => f.write_str("Idle"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{DigicodeInputAlphabet, DoorState, SecureDoor};
+    use DigicodeInputAlphabet::*;
+
+    #[test]
+    fn network_breakpoints_pause_at_the_component_micro_step() {
+        let mut debugger = NetworkDebugger::from_network(SecureDoor::new());
+        debugger.add_breakpoint(Tagged::B(Breakpoint::EnterState(DoorState::Opened)));
+        let inputs = [Digit(4), Digit(9), Digit(2), OkEnter].map(Tagged::A);
+        debugger.feed(&inputs);
+
+        match debugger.resume() {
+            NetworkPause::Breakpoint { report, hits } => {
+                assert_eq!(hits, [Tagged::B(Breakpoint::EnterState(DoorState::Opened))]);
+                assert!(matches!(report.micro_steps[0].result, Tagged::B(Ok(_))));
+            }
+            pause => panic!("expected a breakpoint, got {pause:?}"),
+        }
+        assert_eq!(debugger.network().b().state(), DoorState::Opened);
+        assert!(matches!(debugger.resume(), NetworkPause::Idle));
+    }
+
+    #[test]
+    fn network_steps_deliver_routed_messages_before_the_next_input() {
+        let mut debugger = NetworkDebugger::from_network(SecureDoor::new());
+        let inputs = [Digit(4), Digit(9), Digit(2), OkEnter, Digit(1)].map(Tagged::A);
+        debugger.feed(&inputs);
+        for _ in 0..4 {
+            assert!(matches!(debugger.step(), NetworkPause::Stepped { .. }));
+        }
+        assert!(!debugger.network().is_stable());
+        match debugger.step() {
+            NetworkPause::Stepped { report } => assert!(matches!(report.micro_steps[0].result, Tagged::B(_))),
+            pause => panic!("expected the routed message, got {pause:?}"),
+        }
+        assert_eq!(debugger.queued().count(), 1);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::labels;
+use crate::store::Store;
+use crate::{TransitionSpec, XMachine};
+use std::fmt::Write;
+
+/// Renders a Markdown specification document for `T`: states, alphabets,
+/// memory fields, transition table, phi catalog and a Mermaid state diagram.
+/// Ghost fields are marked, since implementations need not hold them.
+///
+/// Everything is derived from the `XMachine` implementation and its
+/// documentation hooks (`state_doc`, `state_invariants`, `phi_guard_doc`,
+/// `requirement_ids`), so the document cannot drift from the code.
+pub fn markdown<T: XMachine>(machine_name: &str) -> String
+where
+    T::Memory: Store,
+{
+    let mut output = String::new();
+    writeln!(output, "# {}", machine_name).unwrap();
+
+    writeln!(output, "\n## States\n").unwrap();
+    writeln!(output, "| State | Initial | Final | Description | Invariants |").unwrap();
+    writeln!(output, "|---|---|---|---|---|").unwrap();
+    for &state in T::all_states() {
+        let invariants: Vec<&str> = T::state_invariants(state).iter().map(|i| i.doc).collect();
+        writeln!(
+            output,
+            "| {} | {} | {} | {} | {} |",
+            cell(&code::<T>(labels::state::<T>(state))),
+            mark(T::initial_states().contains(&state)),
+            mark(T::final_states().contains(&state)),
+            cell(T::state_doc(state).unwrap_or("")),
+            cell(&invariants.join("; ")),
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "\n## Inputs\n").unwrap();
+    for input in T::all_inputs().iter() {
+        writeln!(output, "- {}", code::<T>(labels::input::<T>(input))).unwrap();
+    }
+    writeln!(output, "\n## Outputs\n").unwrap();
+    for out in T::all_outputs().iter() {
+        writeln!(output, "- {}", code::<T>(labels::output::<T>(out))).unwrap();
+    }
+
+    writeln!(output, "\n## Memory\n").unwrap();
+    let ghosts = T::Memory::ghost_fields();
+    for (field, _) in T::initial_store().abstraction() {
+        let ghost = if ghosts.contains(&field) { " (ghost)" } else { "" };
+        writeln!(output, "- `{}`{}", field, ghost).unwrap();
+    }
+
+    writeln!(output, "\n## Transitions\n").unwrap();
+    writeln!(output, "| From | Input | Phi | To |").unwrap();
+    writeln!(output, "|---|---|---|---|").unwrap();
+    let inputs = T::all_inputs();
+    for &state in T::all_states() {
+        for input in inputs.iter() {
+            let (phi, target) = match T::transition_spec(state, input) {
+                TransitionSpec::Defined(phi) => (
+                    code::<T>(labels::phi::<T>(phi)),
+                    T::next_state(state, phi).map(|s| code::<T>(labels::state::<T>(s))).unwrap_or_default(),
+                ),
+                TransitionSpec::Forbidden => continue,
+                TransitionSpec::Unspecified => ("*unspecified*".to_string(), String::new()),
+            };
+            let state = code::<T>(labels::state::<T>(state));
+            let input = code::<T>(labels::input::<T>(input));
+            writeln!(output, "| {} | {} | {} | {} |", cell(&state), cell(&input), cell(&phi), cell(&target)).unwrap();
+        }
+    }
+
+    writeln!(output, "\n## Processing functions\n").unwrap();
+    writeln!(output, "| Phi | Guard | Requirements |").unwrap();
+    writeln!(output, "|---|---|---|").unwrap();
+    for &phi in T::all_phis() {
+        let mut requirements: Vec<&str> = Vec::new();
+        for &state in T::all_states() {
+            for &id in T::requirement_ids(state, phi) {
+                if !requirements.contains(&id) {
+                    requirements.push(id);
+                }
+            }
+        }
+        writeln!(
+            output,
+            "| {} | {} | {} |",
+            cell(&code::<T>(labels::phi::<T>(phi))),
+            cell(T::phi_guard_doc(phi).unwrap_or("")),
+            cell(&requirements.join(", ")),
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "\n## Diagram\n").unwrap();
+    writeln!(output, "```mermaid").unwrap();
+    output.push_str(&mermaid::<T>());
+    writeln!(output, "```").unwrap();
+    output
+}
+
+/// Renders the Mermaid state diagram embedded by `markdown`, without the
+/// code fence, e.g. for a standalone `.mmd` file.
+///
+/// A state whose `Debug` rendering is a plain identifier, as for unit
+/// variants, is its own node id; others, e.g. the tuples of `sxm_regions`,
+/// get `s` and their position, with the rendering as label.
+pub fn mermaid<T: XMachine>() -> String {
+    let mut states: Vec<T::State> = T::all_states().to_vec();
+    for &source in T::all_states() {
+        for &phi in T::all_phis() {
+            if let Some(target) = T::next_state(source, phi) {
+                if !states.contains(&target) {
+                    states.push(target);
+                }
+            }
+        }
+    }
+    let id = |state: &T::State| {
+        let debug = format!("{:?}", state);
+        if is_identifier(&debug) {
+            debug
+        } else {
+            format!("s{}", states.iter().position(|s| s == state).unwrap_or(states.len()))
+        }
+    };
+
+    let mut output = String::new();
+    writeln!(output, "stateDiagram-v2").unwrap();
+    for state in &states {
+        let label = labels::state::<T>(*state);
+        if label != id(state) {
+            writeln!(output, "    state \"{}\" as {}", mermaid_text(&label), id(state)).unwrap();
+        }
+    }
+    for state in T::initial_states() {
+        writeln!(output, "    [*] --> {}", id(state)).unwrap();
+    }
+    for &source in T::all_states() {
+        for &phi in T::all_phis() {
+            if let Some(target) = T::next_state(source, phi) {
+                writeln!(output, "    {} --> {} : {}", id(&source), id(&target), mermaid_text(&labels::phi::<T>(phi)))
+                    .unwrap();
+            }
+        }
+    }
+    for state in T::final_states() {
+        writeln!(output, "    {} --> [*]", id(state)).unwrap();
+    }
+    output
+}
+
+/// `true` if `text` can be a Mermaid node id as is.
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Escapes a Mermaid label: quotes would end it, newlines the statement.
+fn mermaid_text(label: &str) -> String {
+    label.replace('"', "#quot;").replace('\n', " ")
+}
+
+/// Sets `label` in a code span when it is Rust syntax, i.e. under `Labeling::Debug`.
+fn code<T: XMachine>(label: String) -> String {
+    if T::labeling().is_debug() {
+        format!("`{}`", label)
+    } else {
+        label
+    }
+}
+
+fn mark(flag: bool) -> &'static str {
+    if flag {
+        "yes"
+    } else {
+        ""
+    }
+}
+
+/// Escapes a table cell: pipes would end the cell, newlines the row.
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::TrafficLight;
+
+    crate::sxm_regions! {
+        struct Crossing(TrafficLight, TrafficLight);
+    }
+
+    #[test]
+    fn mermaid_node_ids_are_identifiers() {
+        let diagram = mermaid::<Crossing>();
+        assert!(diagram.contains("    state \"(Red, Red)\" as s0\n"), "{}", diagram);
+        for line in diagram.lines().skip(1).filter(|line| line.contains("-->")) {
+            let (from, rest) = line.trim().split_once(" --> ").unwrap();
+            let to = rest.split(" : ").next().unwrap();
+            assert!([from, to].iter().all(|id| *id == "[*]" || is_identifier(id)), "{}", line);
+        }
+    }
+}
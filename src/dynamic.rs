@@ -0,0 +1,712 @@
+use crate::json;
+use crate::yaml::{self, Value};
+use crate::XMachine;
+use std::fmt;
+use std::fmt::Write;
+
+/// A processing function of a `DynMachine`, with its guard named in prose.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynPhi {
+    pub name: String,
+    /// Data condition under which the phi applies, as in `XMachine::phi_guard_doc`.
+    pub guard: Option<String>,
+}
+
+/// One arc of the associated automaton: `input` in `from` selects `phi`,
+/// which leads to `to`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DynTransition {
+    pub from: String,
+    pub input: String,
+    pub phi: String,
+    pub to: String,
+    /// Output emitted, when it does not depend on memory.
+    pub output: Option<String>,
+}
+
+/// A machine described at runtime, with every symbol named by a string.
+///
+/// It carries the finite structure of a spec (alphabets, states, transitions
+/// and guard names) but no memory, so it is a review and exchange format
+/// rather than an executable `XMachine`. Symbols written from an `XMachine`
+/// are named by their `Debug` form, so comparing `from_machine` with a loaded
+/// description shows where a typed spec and its YAML have drifted apart.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynMachine {
+    pub name: String,
+    pub states: Vec<String>,
+    pub initial_states: Vec<String>,
+    pub final_states: Vec<String>,
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub phis: Vec<DynPhi>,
+    pub transitions: Vec<DynTransition>,
+}
+
+/// Transitions that differ between two versions of a machine, matched by
+/// their source state and input.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpecDiff {
+    /// Only in the newer version.
+    pub added: Vec<DynTransition>,
+    /// Only in the older version.
+    pub removed: Vec<DynTransition>,
+    /// In both, with another phi, target or output, or a phi whose guard
+    /// changed. Holds the newer version.
+    pub changed: Vec<DynTransition>,
+}
+
+impl SpecDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether the transition of `input` in `state` was added, removed or changed.
+    pub fn touches(&self, state: &str, input: &str) -> bool {
+        self.added
+            .iter()
+            .chain(&self.removed)
+            .chain(&self.changed)
+            .any(|t| t.from == state && t.input == input)
+    }
+}
+
+/// Why a machine description could not be loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DynMachineError {
+    /// The text is not in the supported YAML subset.
+    Parse { line: usize, message: String },
+    /// The document does not follow the machine schema.
+    Schema(String),
+    /// A transition or state list refers to an undeclared name.
+    Undeclared { kind: &'static str, name: String },
+    /// A name is declared twice.
+    Duplicate { kind: &'static str, name: String },
+}
+
+/// The declarations of a `DynMachine` that can be removed or renamed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SymbolKind {
+    State,
+    Input,
+    Output,
+    Phi,
+}
+
+impl SymbolKind {
+    /// The kind as named in `DynMachineError`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SymbolKind::State => "state",
+            SymbolKind::Input => "input",
+            SymbolKind::Output => "output",
+            SymbolKind::Phi => "phi",
+        }
+    }
+}
+
+impl fmt::Display for DynMachineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DynMachineError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            DynMachineError::Schema(message) => f.write_str(message),
+            DynMachineError::Undeclared { kind, name } => write!(f, "undeclared {} {:?}", kind, name),
+            DynMachineError::Duplicate { kind, name } => write!(f, "duplicate {} {:?}", kind, name),
+        }
+    }
+}
+
+impl std::error::Error for DynMachineError {}
+
+impl DynMachine {
+    /// Describes `T`: one transition per (state, input) pair with a phi and a target.
+    /// Outputs depend on memory, so transitions are written without one.
+    pub fn from_machine<T: XMachine>(name: &str) -> Self {
+        let mut transitions = Vec::new();
+        let inputs = T::all_inputs();
+        for &state in T::all_states() {
+            for input in inputs.iter() {
+                let Some(phi) = T::get_phi_for_input(state, input) else {
+                    continue;
+                };
+                if let Some(to) = T::next_state(state, phi) {
+                    transitions.push(DynTransition {
+                        from: format!("{:?}", state),
+                        input: format!("{:?}", input),
+                        phi: format!("{:?}", T::phi_kind(phi)),
+                        to: format!("{:?}", to),
+                        output: None,
+                    });
+                }
+            }
+        }
+
+        DynMachine {
+            name: name.to_string(),
+            states: debug_names(T::all_states()),
+            initial_states: debug_names(T::initial_states()),
+            final_states: debug_names(T::final_states()),
+            inputs: debug_names(&inputs),
+            outputs: debug_names(&T::all_outputs()),
+            phis: T::all_phis()
+                .iter()
+                .map(|&phi| DynPhi {
+                    name: format!("{:?}", phi),
+                    guard: T::phi_guard_doc(phi).map(str::to_string),
+                })
+                .collect(),
+            transitions,
+        }
+    }
+
+    /// The transition selected by `input` in `state`, if any.
+    pub fn transition(&self, state: &str, input: &str) -> Option<&DynTransition> {
+        self.transitions.iter().find(|t| t.from == state && t.input == input)
+    }
+
+    /// What changed from `self` to `newer`, e.g. from the description saved
+    /// with the last release to `from_machine` of the current spec.
+    pub fn diff(&self, newer: &DynMachine) -> SpecDiff {
+        let guard = |machine: &DynMachine, phi: &str| {
+            machine.phis.iter().find(|p| p.name == phi).and_then(|p| p.guard.clone())
+        };
+        let mut diff = SpecDiff::default();
+        for t in &newer.transitions {
+            match self.transition(&t.from, &t.input) {
+                None => diff.added.push(t.clone()),
+                Some(old) if old != t || guard(self, &old.phi) != guard(newer, &t.phi) => diff.changed.push(t.clone()),
+                Some(_) => {}
+            }
+        }
+        for t in &self.transitions {
+            if newer.transition(&t.from, &t.input).is_none() {
+                diff.removed.push(t.clone());
+            }
+        }
+        diff
+    }
+
+    /// Checks that every referenced state, input, output and phi is declared,
+    /// that none is declared twice, that no (state, input) pair has two
+    /// transitions, and that a phi leads from a state to a single state.
+    pub fn validate(&self) -> Result<(), DynMachineError> {
+        let undeclared = |kind: &'static str, name: &str| DynMachineError::Undeclared {
+            kind,
+            name: name.to_string(),
+        };
+        let phis: Vec<String> = self.phis.iter().map(|p| p.name.clone()).collect();
+        for (kind, names) in [
+            ("state", &self.states),
+            ("input", &self.inputs),
+            ("output", &self.outputs),
+            ("phi", &phis),
+        ] {
+            for (idx, name) in names.iter().enumerate() {
+                if names[..idx].contains(name) {
+                    return Err(DynMachineError::Duplicate {
+                        kind,
+                        name: name.clone(),
+                    });
+                }
+            }
+        }
+        for state in self.initial_states.iter().chain(&self.final_states) {
+            if !self.states.contains(state) {
+                return Err(undeclared("state", state));
+            }
+        }
+        for (idx, t) in self.transitions.iter().enumerate() {
+            for state in [&t.from, &t.to] {
+                if !self.states.contains(state) {
+                    return Err(undeclared("state", state));
+                }
+            }
+            if !self.inputs.contains(&t.input) {
+                return Err(undeclared("input", &t.input));
+            }
+            if !self.phis.iter().any(|p| p.name == t.phi) {
+                return Err(undeclared("phi", &t.phi));
+            }
+            if let Some(output) = &t.output {
+                if !self.outputs.contains(output) {
+                    return Err(undeclared("output", output));
+                }
+            }
+            if self.transitions[..idx].iter().any(|o| o.from == t.from && o.input == t.input) {
+                return Err(DynMachineError::Schema(format!(
+                    "state {:?} has several transitions on input {:?}",
+                    t.from, t.input
+                )));
+            }
+            if self.transitions[..idx].iter().any(|o| o.from == t.from && o.phi == t.phi && o.to != t.to) {
+                return Err(DynMachineError::Schema(format!(
+                    "phi {:?} leads from state {:?} to several states",
+                    t.phi, t.from
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Declares a new state.
+    pub fn add_state(&mut self, name: &str) -> Result<(), DynMachineError> {
+        self.edit(|m| m.states.push(name.to_string()))
+    }
+
+    pub fn add_input(&mut self, name: &str) -> Result<(), DynMachineError> {
+        self.edit(|m| m.inputs.push(name.to_string()))
+    }
+
+    pub fn add_output(&mut self, name: &str) -> Result<(), DynMachineError> {
+        self.edit(|m| m.outputs.push(name.to_string()))
+    }
+
+    pub fn add_phi(&mut self, name: &str, guard: Option<&str>) -> Result<(), DynMachineError> {
+        self.edit(|m| {
+            m.phis.push(DynPhi {
+                name: name.to_string(),
+                guard: guard.map(str::to_string),
+            })
+        })
+    }
+
+    /// Marks the declared `state` as initial or not.
+    pub fn set_initial(&mut self, state: &str, initial: bool) -> Result<(), DynMachineError> {
+        self.edit(|m| toggle(&mut m.initial_states, state, initial))
+    }
+
+    /// Marks the declared `state` as final or not.
+    pub fn set_final(&mut self, state: &str, is_final: bool) -> Result<(), DynMachineError> {
+        self.edit(|m| toggle(&mut m.final_states, state, is_final))
+    }
+
+    /// Adds an arc between declared states, on a declared input and phi;
+    /// fails if its state already has a transition on the input.
+    pub fn add_transition(&mut self, transition: DynTransition) -> Result<(), DynMachineError> {
+        self.edit(|m| m.transitions.push(transition))
+    }
+
+    /// Removes the transition selected by `input` in `state`.
+    pub fn remove_transition(&mut self, state: &str, input: &str) -> Result<(), DynMachineError> {
+        if self.transition(state, input).is_none() {
+            return Err(DynMachineError::Undeclared {
+                kind: "transition",
+                name: format!("{} on {}", state, input),
+            });
+        }
+        self.edit(|m| m.transitions.retain(|t| t.from != state || t.input != input))
+    }
+
+    /// Removes a declaration with what depends on it: the transitions from,
+    /// to or on a removed state, input or phi, and the output of transitions
+    /// emitting a removed output.
+    pub fn remove(&mut self, kind: SymbolKind, name: &str) -> Result<(), DynMachineError> {
+        self.declared(kind, name)?;
+        self.edit(|m| match kind {
+            SymbolKind::State => {
+                for states in [&mut m.states, &mut m.initial_states, &mut m.final_states] {
+                    states.retain(|s| s != name);
+                }
+                m.transitions.retain(|t| t.from != name && t.to != name);
+            }
+            SymbolKind::Input => {
+                m.inputs.retain(|i| i != name);
+                m.transitions.retain(|t| t.input != name);
+            }
+            SymbolKind::Output => {
+                m.outputs.retain(|o| o != name);
+                for t in &mut m.transitions {
+                    if t.output.as_deref() == Some(name) {
+                        t.output = None;
+                    }
+                }
+            }
+            SymbolKind::Phi => {
+                m.phis.retain(|p| p.name != name);
+                m.transitions.retain(|t| t.phi != name);
+            }
+        })
+    }
+
+    /// Renames a declaration and every reference to it; fails if `new_name`
+    /// is already declared.
+    pub fn rename(&mut self, kind: SymbolKind, name: &str, new_name: &str) -> Result<(), DynMachineError> {
+        self.declared(kind, name)?;
+        let rename = |s: &mut String| {
+            if s == name {
+                *s = new_name.to_string();
+            }
+        };
+        self.edit(|m| match kind {
+            SymbolKind::State => {
+                m.states.iter_mut().chain(&mut m.initial_states).chain(&mut m.final_states).for_each(rename);
+                m.transitions.iter_mut().for_each(|t| {
+                    rename(&mut t.from);
+                    rename(&mut t.to);
+                });
+            }
+            SymbolKind::Input => {
+                m.inputs.iter_mut().for_each(rename);
+                m.transitions.iter_mut().for_each(|t| rename(&mut t.input));
+            }
+            SymbolKind::Output => {
+                m.outputs.iter_mut().for_each(rename);
+                m.transitions.iter_mut().filter_map(|t| t.output.as_mut()).for_each(rename);
+            }
+            SymbolKind::Phi => {
+                m.phis.iter_mut().for_each(|p| rename(&mut p.name));
+                m.transitions.iter_mut().for_each(|t| rename(&mut t.phi));
+            }
+        })
+    }
+
+    /// Applies `edit` to a copy and keeps it only if it validates, so a
+    /// failed edit leaves the machine unchanged.
+    fn edit(&mut self, edit: impl FnOnce(&mut DynMachine)) -> Result<(), DynMachineError> {
+        let mut edited = self.clone();
+        edit(&mut edited);
+        edited.validate()?;
+        *self = edited;
+        Ok(())
+    }
+
+    fn declared(&self, kind: SymbolKind, name: &str) -> Result<(), DynMachineError> {
+        let declared = match kind {
+            SymbolKind::State => self.states.iter().any(|s| s == name),
+            SymbolKind::Input => self.inputs.iter().any(|i| i == name),
+            SymbolKind::Output => self.outputs.iter().any(|o| o == name),
+            SymbolKind::Phi => self.phis.iter().any(|p| p.name == name),
+        };
+        if declared {
+            Ok(())
+        } else {
+            Err(DynMachineError::Undeclared {
+                kind: kind.name(),
+                name: name.to_string(),
+            })
+        }
+    }
+
+    /// Renders the machine in the YAML schema read by `from_yaml`.
+    pub fn to_yaml(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "name: {}", yaml::quote(&self.name)).unwrap();
+        for (key, items) in [
+            ("states", &self.states),
+            ("initial_states", &self.initial_states),
+            ("final_states", &self.final_states),
+            ("inputs", &self.inputs),
+            ("outputs", &self.outputs),
+        ] {
+            write_list(&mut out, key, items);
+        }
+
+        writeln!(out, "phis:").unwrap();
+        for phi in &self.phis {
+            writeln!(out, "  - name: {}", yaml::quote(&phi.name)).unwrap();
+            if let Some(guard) = &phi.guard {
+                writeln!(out, "    guard: {}", yaml::quote(guard)).unwrap();
+            }
+        }
+
+        writeln!(out, "transitions:").unwrap();
+        for t in &self.transitions {
+            writeln!(out, "  - from: {}", yaml::quote(&t.from)).unwrap();
+            writeln!(out, "    input: {}", yaml::quote(&t.input)).unwrap();
+            writeln!(out, "    phi: {}", yaml::quote(&t.phi)).unwrap();
+            writeln!(out, "    to: {}", yaml::quote(&t.to)).unwrap();
+            if let Some(output) = &t.output {
+                writeln!(out, "    output: {}", yaml::quote(output)).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Renders the machine as JSON, with the keys of the `to_yaml` schema.
+    pub fn to_json(&self) -> String {
+        let list = |items: &[String]| {
+            let items: Vec<String> = items.iter().map(|item| json::quote(item)).collect();
+            format!("[{}]", items.join(", "))
+        };
+        let optional = |value: &Option<String>| value.as_deref().map_or("null".to_string(), json::quote);
+
+        let mut out = String::new();
+        writeln!(out, "{{").unwrap();
+        writeln!(out, "  \"name\": {},", json::quote(&self.name)).unwrap();
+        for (key, items) in [
+            ("states", &self.states),
+            ("initial_states", &self.initial_states),
+            ("final_states", &self.final_states),
+            ("inputs", &self.inputs),
+            ("outputs", &self.outputs),
+        ] {
+            writeln!(out, "  \"{}\": {},", key, list(items)).unwrap();
+        }
+
+        let phis: Vec<String> = self
+            .phis
+            .iter()
+            .map(|phi| format!("{{\"name\": {}, \"guard\": {}}}", json::quote(&phi.name), optional(&phi.guard)))
+            .collect();
+        writeln!(out, "  \"phis\": [{}],", phis.join(", ")).unwrap();
+
+        write!(out, "  \"transitions\": [").unwrap();
+        for (idx, t) in self.transitions.iter().enumerate() {
+            out.push_str(if idx == 0 { "\n" } else { ",\n" });
+            write!(
+                out,
+                "    {{\"from\": {}, \"input\": {}, \"phi\": {}, \"to\": {}, \"output\": {}}}",
+                json::quote(&t.from),
+                json::quote(&t.input),
+                json::quote(&t.phi),
+                json::quote(&t.to),
+                optional(&t.output)
+            )
+            .unwrap();
+        }
+        if !self.transitions.is_empty() {
+            out.push_str("\n  ");
+        }
+        writeln!(out, "]").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    /// Loads and validates a machine written in the schema of `to_yaml`.
+    ///
+    /// Hand-written files may also use flow lists (`states: [A, B]`), plain
+    /// scalars and `#` comments. Empty lists may be omitted.
+    pub fn from_yaml(text: &str) -> Result<Self, DynMachineError> {
+        let doc = yaml::parse(text).map_err(|(line, message)| DynMachineError::Parse { line, message })?;
+        Self::from_document(&doc)
+    }
+
+    /// Loads and validates a machine written by `to_json`, e.g. a baseline
+    /// committed next to the spec.
+    pub fn from_json(text: &str) -> Result<Self, DynMachineError> {
+        let doc = json::parse(text).map_err(|(line, message)| DynMachineError::Parse { line, message })?;
+        Self::from_document(&from_json_value(doc))
+    }
+
+    fn from_document(doc: &Value) -> Result<Self, DynMachineError> {
+        let machine = DynMachine {
+            name: scalar(doc, "name")?.unwrap_or_default(),
+            states: scalars(doc, "states")?,
+            initial_states: scalars(doc, "initial_states")?,
+            final_states: scalars(doc, "final_states")?,
+            inputs: scalars(doc, "inputs")?,
+            outputs: scalars(doc, "outputs")?,
+            phis: maps(doc, "phis")?
+                .iter()
+                .map(|phi| {
+                    Ok(DynPhi {
+                        name: required(phi, "name")?,
+                        guard: scalar(phi, "guard")?,
+                    })
+                })
+                .collect::<Result<_, DynMachineError>>()?,
+            transitions: maps(doc, "transitions")?
+                .iter()
+                .map(|t| {
+                    Ok(DynTransition {
+                        from: required(t, "from")?,
+                        input: required(t, "input")?,
+                        phi: required(t, "phi")?,
+                        to: required(t, "to")?,
+                        output: scalar(t, "output")?,
+                    })
+                })
+                .collect::<Result<_, DynMachineError>>()?,
+        };
+        machine.validate()?;
+        Ok(machine)
+    }
+}
+
+/// A valid `DynMachine` under edit, with undo and redo, as the backend of an
+/// interactive model editor.
+///
+/// Every edit is validated as a whole: one that fails leaves the machine and
+/// the history unchanged, and one that changes nothing is not recorded.
+#[derive(Clone, Debug)]
+pub struct DynMachineEditor {
+    machine: DynMachine,
+    undo: Vec<DynMachine>,
+    redo: Vec<DynMachine>,
+}
+
+impl DynMachineEditor {
+    /// Starts editing `machine`, which must validate.
+    pub fn new(machine: DynMachine) -> Result<Self, DynMachineError> {
+        machine.validate()?;
+        Ok(Self {
+            machine,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        })
+    }
+
+    pub fn machine(&self) -> &DynMachine {
+        &self.machine
+    }
+
+    pub fn into_machine(self) -> DynMachine {
+        self.machine
+    }
+
+    /// Applies `edit`, e.g. `|m| m.add_state("Locked")`, and records it for
+    /// `undo`. Direct changes to the fields are validated too.
+    pub fn edit(
+        &mut self,
+        edit: impl FnOnce(&mut DynMachine) -> Result<(), DynMachineError>,
+    ) -> Result<(), DynMachineError> {
+        let mut edited = self.machine.clone();
+        edit(&mut edited)?;
+        edited.validate()?;
+        if edited != self.machine {
+            self.undo.push(std::mem::replace(&mut self.machine, edited));
+            self.redo.clear();
+        }
+        Ok(())
+    }
+
+    /// Reverts the last edit; `false` if there is none.
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(previous) => {
+                self.redo.push(std::mem::replace(&mut self.machine, previous));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Reapplies the last undone edit; `false` if there is none. Any new
+    /// edit discards the undone ones.
+    pub fn redo(&mut self) -> bool {
+        match self.redo.pop() {
+            Some(next) => {
+                self.undo.push(std::mem::replace(&mut self.machine, next));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+fn toggle(names: &mut Vec<String>, name: &str, present: bool) {
+    let listed = names.iter().any(|n| n == name);
+    if present && !listed {
+        names.push(name.to_string());
+    } else if !present {
+        names.retain(|n| n != name);
+    }
+}
+
+fn debug_names<X: fmt::Debug>(items: &[X]) -> Vec<String> {
+    items.iter().map(|item| format!("{:?}", item)).collect()
+}
+
+fn write_list(out: &mut String, key: &str, items: &[String]) {
+    if items.is_empty() {
+        writeln!(out, "{}: []", key).unwrap();
+        return;
+    }
+    writeln!(out, "{}:", key).unwrap();
+    for item in items {
+        writeln!(out, "  - {}", yaml::quote(item)).unwrap();
+    }
+}
+
+/// The YAML shape of a JSON document: `null` reads as an empty scalar, which
+/// the schema treats as absent.
+fn from_json_value(value: json::Value) -> Value {
+    match value {
+        json::Value::Null => Value::Scalar(String::new()),
+        json::Value::Bool(b) => Value::Scalar(b.to_string()),
+        json::Value::Number(n) | json::Value::String(n) => Value::Scalar(n),
+        json::Value::Array(items) => Value::List(items.into_iter().map(from_json_value).collect()),
+        json::Value::Object(members) => Value::Map(members.into_iter().map(|(k, v)| (k, from_json_value(v))).collect()),
+    }
+}
+
+fn schema(message: String) -> DynMachineError {
+    DynMachineError::Schema(message)
+}
+
+/// Optional scalar field; an empty value counts as absent.
+fn scalar(map: &Value, key: &str) -> Result<Option<String>, DynMachineError> {
+    match map.get(key) {
+        None => Ok(None),
+        Some(Value::Scalar(s)) if s.is_empty() => Ok(None),
+        Some(Value::Scalar(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(schema(format!("`{}` must be a scalar", key))),
+    }
+}
+
+fn required(map: &Value, key: &str) -> Result<String, DynMachineError> {
+    scalar(map, key)?.ok_or_else(|| schema(format!("missing `{}`", key)))
+}
+
+fn list<'a>(doc: &'a Value, key: &str) -> Result<&'a [Value], DynMachineError> {
+    match doc.get(key) {
+        None => Ok(&[]),
+        Some(Value::Scalar(s)) if s.is_empty() => Ok(&[]),
+        Some(value) => value.as_list().ok_or_else(|| schema(format!("`{}` must be a list", key))),
+    }
+}
+
+fn scalars(doc: &Value, key: &str) -> Result<Vec<String>, DynMachineError> {
+    list(doc, key)?
+        .iter()
+        .map(|item| {
+            item.as_scalar()
+                .map(str::to_string)
+                .ok_or_else(|| schema(format!("`{}` must list scalars", key)))
+        })
+        .collect()
+}
+
+fn maps<'a>(doc: &'a Value, key: &str) -> Result<Vec<&'a Value>, DynMachineError> {
+    list(doc, key)?
+        .iter()
+        .map(|item| match item {
+            Value::Map(_) => Ok(item),
+            _ => Err(schema(format!("`{}` must list maps", key))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Digicode;
+
+    #[test]
+    fn a_phi_leading_to_several_states_is_rejected() {
+        let mut machine = DynMachine::from_machine::<Digicode>("digicode");
+        assert_eq!(machine.validate(), Ok(()));
+        let ready = machine.transitions.iter().position(|t| t.from == "Ready" && t.phi == "InputDigit").unwrap();
+        let mut looping = machine.transitions[ready].clone();
+        looping.input = "OkEnter".to_string();
+        looping.to = "Ready".to_string();
+        machine.transitions.retain(|t| !(t.from == "Ready" && t.input == "OkEnter"));
+        machine.transitions.push(looping);
+        assert!(matches!(machine.validate(), Err(DynMachineError::Schema(_))));
+    }
+
+    #[test]
+    fn descriptions_round_trip_through_yaml_and_json() {
+        let machine = DynMachine::from_machine::<Digicode>("digicode");
+        assert_eq!(DynMachine::from_yaml(&machine.to_yaml()), Ok(machine.clone()));
+        assert_eq!(DynMachine::from_json(&machine.to_json()), Ok(machine));
+    }
+}
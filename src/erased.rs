@@ -0,0 +1,203 @@
+//! Runners of different machines behind one object-safe interface.
+//!
+//! `ErasedRunner` hides the symbol types of a spec: inputs go in and outputs
+//! come out as bytes through the runner's `Codec`, and states are reported by
+//! their label under the spec's `labeling()`. Any `CodecRunner` implements it, so runners of
+//! unrelated protocol machines can be kept in one collection, e.g. by a
+//! device manager, as `AnyMachineRunner`s and still be downcast to their
+//! concrete type when needed.
+
+use crate::codec::{Codec, CodecRunner, LineCodec};
+use crate::labels;
+use crate::parse::ParseInput;
+use crate::XMachine;
+use std::any::{self, Any};
+use std::fmt;
+
+/// A runner whose spec is only known through its byte interface.
+pub trait ErasedRunner {
+    /// Type name of the spec.
+    fn machine(&self) -> &'static str;
+
+    /// The current state, named by the spec's `labeling()`.
+    fn state(&self) -> String;
+
+    /// `true` if the current state is final.
+    fn is_final(&self) -> bool;
+
+    /// Processes `bytes` and returns the encoded replies, as `CodecRunner::feed`.
+    fn feed(&mut self, bytes: &[u8]) -> Vec<u8>;
+
+    /// Malformed frames skipped so far.
+    fn decode_errors(&self) -> usize;
+
+    /// Returns to the initial configuration.
+    fn reset(&mut self);
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Feeds `text` and returns the replies as text, for line protocols.
+    fn feed_str(&mut self, text: &str) -> String {
+        String::from_utf8_lossy(&self.feed(text.as_bytes())).into_owned()
+    }
+}
+
+impl<T, C> ErasedRunner for CodecRunner<T, C>
+where
+    T: XMachine + 'static,
+    C: Codec<T> + 'static,
+{
+    fn machine(&self) -> &'static str {
+        any::type_name::<T>()
+    }
+
+    fn state(&self) -> String {
+        labels::state::<T>(self.runner().state())
+    }
+
+    fn is_final(&self) -> bool {
+        T::final_states().contains(&self.runner().state())
+    }
+
+    fn feed(&mut self, bytes: &[u8]) -> Vec<u8> {
+        CodecRunner::feed(self, bytes)
+    }
+
+    fn decode_errors(&self) -> usize {
+        CodecRunner::decode_errors(self)
+    }
+
+    fn reset(&mut self) {
+        CodecRunner::reset(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// A named, type-erased runner.
+pub struct AnyMachineRunner {
+    name: String,
+    runner: Box<dyn ErasedRunner>,
+}
+
+impl AnyMachineRunner {
+    pub fn new<T, C>(name: &str, runner: CodecRunner<T, C>) -> Self
+    where
+        T: XMachine + 'static,
+        C: Codec<T> + 'static,
+    {
+        Self::from_boxed(name, Box::new(runner))
+    }
+
+    /// Wraps any other implementation of `ErasedRunner`.
+    pub fn from_boxed(name: &str, runner: Box<dyn ErasedRunner>) -> Self {
+        Self {
+            name: name.to_string(),
+            runner,
+        }
+    }
+
+    /// Spec `T` in its initial configuration behind a `LineCodec`.
+    pub fn lines<T>(name: &str) -> Self
+    where
+        T: XMachine + 'static,
+        T::Input: ParseInput,
+    {
+        Self::new(name, CodecRunner::<T, _>::new(LineCodec::new()))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn runner(&self) -> &dyn ErasedRunner {
+        self.runner.as_ref()
+    }
+
+    pub fn runner_mut(&mut self) -> &mut dyn ErasedRunner {
+        self.runner.as_mut()
+    }
+
+    /// The concrete runner, if it runs spec `T` behind codec `C`.
+    pub fn downcast_ref<T, C>(&self) -> Option<&CodecRunner<T, C>>
+    where
+        T: XMachine + 'static,
+        C: Codec<T> + 'static,
+    {
+        self.runner.as_any().downcast_ref()
+    }
+
+    pub fn downcast_mut<T, C>(&mut self) -> Option<&mut CodecRunner<T, C>>
+    where
+        T: XMachine + 'static,
+        C: Codec<T> + 'static,
+    {
+        self.runner.as_any_mut().downcast_mut()
+    }
+
+    pub fn machine(&self) -> &'static str {
+        self.runner.machine()
+    }
+
+    pub fn state(&self) -> String {
+        self.runner.state()
+    }
+
+    pub fn is_final(&self) -> bool {
+        self.runner.is_final()
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<u8> {
+        self.runner.feed(bytes)
+    }
+
+    pub fn feed_str(&mut self, text: &str) -> String {
+        self.runner.feed_str(text)
+    }
+
+    pub fn decode_errors(&self) -> usize {
+        self.runner.decode_errors()
+    }
+
+    pub fn reset(&mut self) {
+        self.runner.reset()
+    }
+}
+
+impl fmt::Debug for AnyMachineRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AnyMachineRunner")
+            .field("name", &self.name)
+            .field("machine", &self.machine())
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, Door};
+
+    #[test]
+    fn runners_of_different_specs_share_a_collection() {
+        let mut devices = [AnyMachineRunner::lines::<Digicode>("keypad"), AnyMachineRunner::lines::<Door>("door")];
+        assert_eq!(devices[0].feed_str("Digit 4\nDigit 9\nDigit 2\nOkEnter\n"), "Digit(4)\nDigit(9)\nDigit(2)\nOpen\n");
+        assert_eq!(devices[1].feed_str("Open\n"), "DoorOpens\n");
+        assert_eq!(devices.iter().map(AnyMachineRunner::state).collect::<Vec<_>>(), ["CodeEntered", "Opened"]);
+
+        let door = devices[1].downcast_ref::<Door, LineCodec<Door>>().unwrap();
+        assert_eq!(door.runner().store(), &1);
+        assert!(devices[1].downcast_ref::<Digicode, LineCodec<Digicode>>().is_none());
+        devices[0].reset();
+        assert_eq!(devices[0].state(), "Ready");
+    }
+}
@@ -0,0 +1,8 @@
+//! Canonical machines implementing `XMachine`, for learning the API and as
+//! test fixtures. Enabled with the `examples` feature.
+
+pub mod admin_digicode;
+pub mod handshake;
+pub mod secure_door;
+pub mod traffic_light;
+pub mod vending_machine;
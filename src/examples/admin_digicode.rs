@@ -0,0 +1,139 @@
+//! A `Digicode` variant with an admin key, defined as an extension of the
+//! base spec: the key opens the door without the code and, once the door is
+//! open, re-arms the keypad. Every other transition is inherited.
+
+use crate::examples::secure_door::{Digicode, DigicodeOutputAlphabet, DigicodeState};
+use crate::extend::{Ext, ExtInputOf, ExtMemoryOf, ExtOutputOf, ExtPhiOf, ExtStateOf, Extended, Extension, Override};
+use std::borrow::Cow;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdminInput {
+    AdminKey,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AdminState {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AdminOutput {}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AdminPhi {
+    Override,
+    Rearm,
+}
+
+pub struct AdminOverride;
+
+/// The digicode with admin override.
+pub type AdminDigicode = Extended<AdminOverride>;
+
+impl Extension for AdminOverride {
+    type Base = Digicode;
+    type State = AdminState;
+    type Input = AdminInput;
+    type Output = AdminOutput;
+    type Phi = AdminPhi;
+    /// Number of admin overrides, for auditing.
+    type Memory = u32;
+
+    fn initial_store() -> Self::Memory {
+        0
+    }
+
+    fn states() -> &'static [Self::State] {
+        &[]
+    }
+
+    fn inputs() -> Cow<'static, [Self::Input]> {
+        Cow::Borrowed(&[AdminInput::AdminKey])
+    }
+
+    fn outputs() -> Cow<'static, [Self::Output]> {
+        Cow::Borrowed(&[])
+    }
+
+    fn phis() -> &'static [Self::Phi] {
+        &[AdminPhi::Override, AdminPhi::Rearm]
+    }
+
+    fn get_phi_for_input(state: ExtStateOf<Self>, input: &ExtInputOf<Self>) -> Override<ExtPhiOf<Self>> {
+        use DigicodeState::*;
+
+        match (state, input) {
+            (Ext::Base(Ready | Accepting), Ext::Own(AdminInput::AdminKey)) => {
+                Override::Replace(Some(Ext::Own(AdminPhi::Override)))
+            }
+            (Ext::Base(CodeEntered), Ext::Own(AdminInput::AdminKey)) => {
+                Override::Replace(Some(Ext::Own(AdminPhi::Rearm)))
+            }
+            _ => Override::Inherit,
+        }
+    }
+
+    fn next_state(state: ExtStateOf<Self>, phi: ExtPhiOf<Self>) -> Override<ExtStateOf<Self>> {
+        use DigicodeState::*;
+
+        match (state, phi) {
+            (Ext::Base(Ready | Accepting), Ext::Own(AdminPhi::Override)) => {
+                Override::Replace(Some(Ext::Base(CodeEntered)))
+            }
+            (Ext::Base(CodeEntered), Ext::Own(AdminPhi::Rearm)) => Override::Replace(Some(Ext::Base(Ready))),
+            _ => Override::Inherit,
+        }
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut ExtMemoryOf<Self>,
+        input: &ExtInputOf<Self>,
+    ) -> Result<Option<ExtOutputOf<Self>>, ()> {
+        let (digicode, overrides) = store;
+
+        match (phi, input) {
+            (AdminPhi::Override, Ext::Own(AdminInput::AdminKey)) => {
+                *overrides += 1;
+                Ok(Some(Ext::Base(DigicodeOutputAlphabet::Open)))
+            }
+            (AdminPhi::Rearm, Ext::Own(AdminInput::AdminKey)) => {
+                digicode.current_sequence.clear();
+                Ok(Some(Ext::Base(DigicodeOutputAlphabet::Initialise)))
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {
+        match phi {
+            AdminPhi::Override => Some("admin key presented"),
+            AdminPhi::Rearm => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputKind, XMachine};
+
+    use crate::analysis::SpecIssue;
+    use crate::examples::secure_door::DigicodePhi::{Ignore, Reject};
+    use crate::examples::secure_door::DigicodeState::Accepting;
+
+    // The undispatched `Accepting` transitions inherited from `Digicode`.
+    crate::sxm_well_formed_test!(
+        admin_digicode_is_well_formed,
+        super::AdminDigicode,
+        allow [
+            SpecIssue::UnreachablePhi { state: Ext::Base(Accepting), phi: Ext::Base(Reject) },
+            SpecIssue::UnreachablePhi { state: Ext::Base(Accepting), phi: Ext::Base(Ignore) },
+        ]
+    );
+
+    #[test]
+    fn base_outputs_keep_their_kind() {
+        let reject = Ext::Base(DigicodeOutputAlphabet::RejectInput);
+        assert_eq!(AdminDigicode::output_kind(&reject), OutputKind::Error);
+        assert_eq!(AdminDigicode::output_kind(&Ext::Base(DigicodeOutputAlphabet::Open)), OutputKind::Regular);
+    }
+}
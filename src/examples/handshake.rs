@@ -0,0 +1,174 @@
+//! A TCP-like connection: a three-way handshake, data segments limited by a
+//! send window, and an orderly close once everything is acknowledged.
+
+use crate::XMachine;
+use std::borrow::Cow;
+
+/// Maximum number of unacknowledged segments.
+pub const WINDOW: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HandshakeInput {
+    Connect,
+    SynAck,
+    Send,
+    Ack,
+    Close,
+    Reset,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HandshakeOutput {
+    Syn,
+    Ack,
+    Data,
+    Fin,
+}
+
+crate::sxm_indexed! {
+    pub enum HandshakeState {
+        Closed,
+        SynSent,
+        Established,
+        FinWait,
+    }
+}
+
+crate::sxm_store! {
+    /// `next_seq` is the sequence number of the next data segment, `unacked`
+    /// the number of segments sent but not yet acknowledged.
+    #[derive(Debug)]
+    pub struct HandshakeMemory {
+        pub next_seq: u32,
+        pub unacked: u32,
+    }
+}
+
+crate::sxm_indexed! {
+    pub enum HandshakePhi {
+        SendSyn,
+        CompleteHandshake,
+        Transmit,
+        Acknowledge,
+        StartClose,
+        FinishClose,
+        Abort,
+    }
+}
+
+pub struct Handshake;
+
+impl XMachine for Handshake {
+    type Input = HandshakeInput;
+    type Output = HandshakeOutput;
+    type State = HandshakeState;
+    type Memory = HandshakeMemory;
+    type Phi = HandshakePhi;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use HandshakePhi::*;
+        use HandshakeState::*;
+
+        match (state, phi) {
+            (Closed, SendSyn) => Some(SynSent),
+            (SynSent, CompleteHandshake) => Some(Established),
+            (Established, Transmit) => Some(Established),
+            (Established, Acknowledge) => Some(Established),
+            (Established, StartClose) => Some(FinWait),
+            (FinWait, FinishClose) => Some(Closed),
+            (_, Abort) => Some(Closed),
+            _ => None,
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        &[HandshakeState::Closed]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        &[HandshakeState::Closed]
+    }
+
+    fn initial_store() -> Self::Memory {
+        HandshakeMemory {
+            next_seq: 0,
+            unacked: 0,
+        }
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut Self::Memory,
+        input: &Self::Input,
+    ) -> Result<Option<Self::Output>, ()> {
+        use HandshakeInput as In;
+        use HandshakeOutput as Out;
+        use HandshakePhi::*;
+
+        match (phi, input) {
+            (SendSyn, In::Connect) => Ok(Some(Out::Syn)),
+            (CompleteHandshake, In::SynAck) => Ok(Some(Out::Ack)),
+            (Transmit, In::Send) if store.unacked < WINDOW => {
+                store.next_seq += 1;
+                store.unacked += 1;
+                Ok(Some(Out::Data))
+            }
+            (Acknowledge, In::Ack) if store.unacked > 0 => {
+                store.unacked -= 1;
+                Ok(None)
+            }
+            (StartClose, In::Close) if store.unacked == 0 => Ok(Some(Out::Fin)),
+            (FinishClose, In::Ack) => Ok(None),
+            (Abort, In::Reset) => {
+                *store = Self::initial_store();
+                Ok(None)
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        use HandshakeInput::*;
+        Cow::Borrowed(&[Connect, SynAck, Send, Ack, Close, Reset])
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        use HandshakeOutput::*;
+        Cow::Borrowed(&[Syn, Ack, Data, Fin])
+    }
+
+    crate::sxm_index_tables!(states, phis);
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        use HandshakeInput::*;
+        use HandshakePhi::*;
+        use HandshakeState::*;
+
+        match (state, input) {
+            (Closed, Connect) => Some(SendSyn),
+            (SynSent, SynAck) => Some(CompleteHandshake),
+            (Established, Send) => Some(Transmit),
+            (Established, Ack) => Some(Acknowledge),
+            (Established, Close) => Some(StartClose),
+            (FinWait, Ack) => Some(FinishClose),
+            (_, Reset) => Some(Abort),
+            _ => None,
+        }
+    }
+
+    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {
+        use HandshakePhi::*;
+
+        match phi {
+            Transmit => Some("send window not full"),
+            Acknowledge => Some("a segment is unacknowledged"),
+            StartClose => Some("every segment acknowledged"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    crate::sxm_well_formed_test!(handshake_is_well_formed, super::Handshake);
+}
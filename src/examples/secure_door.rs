@@ -0,0 +1,451 @@
+//! The keypad and door pair: a `Digicode` that opens a `Door` once the valid
+//! code <4, 9, 2> is entered, and re-arms when the door closes.
+
+use crate::network::MachineNetwork;
+use crate::{OutputKind, StateInvariant, XMachine};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/// Adapter: Digicode Output -> Door Input
+impl TryFrom<DigicodeOutputAlphabet> for DoorInputAlphabet {
+    type Error = ();
+
+    fn try_from(output: DigicodeOutputAlphabet) -> Result<Self, Self::Error> {
+        match output {
+            DigicodeOutputAlphabet::Open => Ok(DoorInputAlphabet::Open),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Adapter: Door Output -> Digicode Input
+impl TryFrom<DoorOutputAlphabet> for DigicodeInputAlphabet {
+    type Error = ();
+
+    fn try_from(output: DoorOutputAlphabet) -> Result<Self, Self::Error> {
+        match output {
+            DoorOutputAlphabet::DoorCloses => Ok(DigicodeInputAlphabet::DoorCloses),
+            _ => Err(()),
+        }
+    }
+}
+
+crate::sxm_input! {
+    /// Input Alphabet (Σ)
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum DigicodeInputAlphabet {
+        OkEnter,
+        DoorCloses,
+        Digit(u8),
+    }
+}
+
+/// Output Alphabet (Γ)
+#[derive(Clone, Debug, PartialEq)]
+pub enum DigicodeOutputAlphabet {
+    Digit(u8),
+    Open,
+    Initialise,
+    IgnoreDigit,
+    RejectInput,
+    None,
+}
+
+crate::sxm_indexed! {
+    /// States (Q)
+    pub enum DigicodeState {
+        Ready,
+        Accepting,
+        CodeEntered,
+    }
+}
+
+crate::sxm_store! {
+    /// Memory (M)
+    #[derive(Debug)]
+    pub struct DigicodeMemory {
+        pub current_sequence: Vec<u8> => |digits| digits.len(),
+        pub valid_code: Vec<u8>,
+    }
+}
+
+crate::sxm_indexed! {
+    /// Phi (Φ)
+    pub enum DigicodePhi {
+        Reject,
+        InputDigit,
+        Ignore,
+        Finish,
+        Lock,
+    }
+}
+
+pub struct Digicode;
+
+impl XMachine for Digicode {
+    type Input = DigicodeInputAlphabet;
+    type Output = DigicodeOutputAlphabet;
+    type State = DigicodeState;
+    type Memory = DigicodeMemory;
+    type Phi = DigicodePhi;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use DigicodeState::*;
+        use DigicodePhi::*;
+
+        match (state, phi) {
+            (Ready, InputDigit) => Some(Accepting),
+            (Ready, Reject) => Some(Ready),
+            (Accepting, InputDigit) => Some(Accepting),
+            (Accepting, Finish) => Some(CodeEntered),
+            (Accepting, Reject) => Some(Ready),
+            (Accepting, Ignore) => Some(Accepting),
+            (CodeEntered, Lock) => Some(Ready),
+            _ => None,
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        &[DigicodeState::Ready]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        use DigicodeState::*;
+        &[Ready, Accepting, CodeEntered]
+    }
+
+    fn initial_store() -> Self::Memory {
+        DigicodeMemory {
+            current_sequence: Vec::new(),
+            valid_code: vec![4, 9, 2],
+        }
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut Self::Memory,
+        input: &Self::Input,
+    ) -> Result<Option<Self::Output>, ()> {
+        use DigicodePhi::*;
+        use DigicodeInputAlphabet as In;
+        use DigicodeOutputAlphabet as Out;
+
+        match (phi, input) {
+            (Reject, In::OkEnter) => {
+                if store.current_sequence != store.valid_code {
+                    store.current_sequence.clear();
+                    Ok(Some(Out::RejectInput))
+                } else {
+                    Err(())
+                }
+            }
+            (InputDigit, In::Digit(d)) => {
+                if store.current_sequence.len() < store.valid_code.len() {
+                    store.current_sequence.push(*d);
+                    Ok(Some(Out::Digit(*d)))
+                } else {
+                    Err(())
+                }
+            }
+            (Ignore, In::Digit(_)) => {
+                if store.current_sequence.len() == store.valid_code.len() {
+                    Ok(Some(Out::IgnoreDigit))
+                } else {
+                    Err(())
+                }
+            }
+            (Finish, In::OkEnter) => {
+                if store.current_sequence == store.valid_code {
+                    Ok(Some(Out::Open))
+                } else {
+                    Err(())
+                }
+            }
+            (Lock, In::DoorCloses) => {
+                store.current_sequence.clear();
+                Ok(Some(Out::Initialise))
+            }
+            _ => Err(()),
+        }
+    }
+
+    crate::sxm_index_tables!(states, phis);
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        use DigicodeInputAlphabet::*;
+        Cow::Borrowed(&[
+            OkEnter,
+            DoorCloses,
+            Digit(0),
+            Digit(1),
+            Digit(2),
+            Digit(3),
+            Digit(4),
+            Digit(5),
+            Digit(6),
+            Digit(7),
+            Digit(8),
+            Digit(9)
+        ])
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        use DigicodeOutputAlphabet::*;
+        Cow::Borrowed(&[
+            Digit(0),
+            Digit(1),
+            Digit(2),
+            Digit(3),
+            Digit(4),
+            Digit(5),
+            Digit(6),
+            Digit(7),
+            Digit(8),
+            Digit(9),
+            Open,
+            Initialise,
+            IgnoreDigit,
+            RejectInput,
+        ])
+    }
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        use DigicodeState::*;
+        use DigicodePhi::*;
+        use DigicodeInputAlphabet::*;
+
+        match (state, input) {
+            (Ready, Digit(_)) => Some(InputDigit),
+            (Ready, OkEnter) => Some(Reject),
+            (Accepting, Digit(_)) => Some(InputDigit),
+            (Accepting, OkEnter) => Some(Finish),
+            (CodeEntered, DoorCloses) => Some(Lock),
+            _ => None,
+        }
+    }
+
+    fn state_doc(state: Self::State) -> Option<&'static str> {
+        Some(match state {
+            DigicodeState::Ready => "waiting for the first digit",
+            DigicodeState::Accepting => "collecting digits",
+            DigicodeState::CodeEntered => "valid code entered, door unlocked",
+        })
+    }
+
+    fn state_invariants(state: Self::State) -> Vec<StateInvariant<Self::Memory>> {
+        match state {
+            DigicodeState::Ready => vec![StateInvariant {
+                doc: "no digits entered",
+                check: |store| store.current_sequence.is_empty(),
+            }],
+            DigicodeState::Accepting => vec![StateInvariant {
+                doc: "at most the code length entered",
+                check: |store| store.current_sequence.len() <= store.valid_code.len(),
+            }],
+            DigicodeState::CodeEntered => vec![StateInvariant {
+                doc: "entered sequence equals the valid code",
+                check: |store| store.current_sequence == store.valid_code,
+            }],
+        }
+    }
+
+    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {
+        use DigicodePhi::*;
+
+        match phi {
+            Reject => Some("entered sequence differs from the valid code"),
+            InputDigit => Some("fewer digits entered than the code length"),
+            Ignore => Some("code length already reached"),
+            Finish => Some("entered sequence equals the valid code"),
+            Lock => None,
+        }
+    }
+
+    fn output_kind(output: &Self::Output) -> OutputKind {
+        match output {
+            DigicodeOutputAlphabet::RejectInput => OutputKind::Error,
+            _ => OutputKind::Regular,
+        }
+    }
+}
+
+crate::sxm_scenarios! {
+    /// Key flows of the digicode, checked against the spec with `Scenario::check`.
+    pub fn digicode_scenarios() for Digicode {
+        "happy path unlock" {
+            DigicodeInputAlphabet::Digit(4) => DigicodeOutputAlphabet::Digit(4), DigicodeState::Accepting;
+            DigicodeInputAlphabet::Digit(9) => DigicodeOutputAlphabet::Digit(9);
+            DigicodeInputAlphabet::Digit(2) => DigicodeOutputAlphabet::Digit(2);
+            DigicodeInputAlphabet::OkEnter => DigicodeOutputAlphabet::Open, DigicodeState::CodeEntered;
+        }
+        "wrong code is refused" {
+            DigicodeInputAlphabet::Digit(1) => DigicodeOutputAlphabet::Digit(1);
+            DigicodeInputAlphabet::Digit(2) => DigicodeOutputAlphabet::Digit(2);
+            DigicodeInputAlphabet::Digit(3) => DigicodeOutputAlphabet::Digit(3);
+            DigicodeInputAlphabet::OkEnter => !, DigicodeState::Accepting;
+        }
+    }
+}
+
+crate::sxm_input! {
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum DoorInputAlphabet {
+        Open,
+        Close,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DoorOutputAlphabet {
+    DoorOpens,
+    DoorCloses,
+    OpenIgnored,
+    CloseIgnored,
+}
+
+crate::sxm_indexed! {
+    pub enum DoorState {
+        Closed,
+        Opened,
+    }
+}
+
+pub type DoorMemory = u32;
+
+crate::sxm_indexed! {
+    pub enum DoorPhi {
+        OpenDoor,
+        CloseDoor,
+        IgnoreOpen,
+        IgnoreClose,
+    }
+}
+
+pub struct Door;
+
+impl XMachine for Door {
+    type Input = DoorInputAlphabet;
+    type Output = DoorOutputAlphabet;
+    type State = DoorState;
+    type Memory = DoorMemory;
+    type Phi = DoorPhi;
+
+    fn initial_states() -> &'static [Self::State] {
+        &[DoorState::Closed]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        use DoorState::*;
+        &[Closed, Opened]
+    }
+
+    fn initial_store() -> Self::Memory {
+        0
+    }
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use DoorState::*;
+        use DoorPhi::*;
+
+        match (state, phi) {
+            (Closed, OpenDoor) => Some(Opened),
+            (Closed, IgnoreClose) => Some(Closed),
+            (Opened, CloseDoor) => Some(Closed),
+            (Opened, IgnoreOpen) => Some(Opened),
+            _ => None,
+        }
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut Self::Memory,
+        input: &Self::Input,
+    ) -> Result<Option<Self::Output>, ()> {
+        use DoorPhi::*;
+        use DoorInputAlphabet as In;
+        use DoorOutputAlphabet as Out;
+
+        match (phi, input) {
+            (OpenDoor, In::Open) => {
+                *store += 1;
+                Ok(Some(Out::DoorOpens))
+            }
+            (CloseDoor, In::Close) => Ok(Some(Out::DoorCloses)),
+            (IgnoreOpen, In::Open) => Ok(Some(Out::OpenIgnored)),
+            (IgnoreClose, In::Close) => Ok(Some(Out::CloseIgnored)),
+            _ => Err(()),
+        }
+    }
+
+    crate::sxm_index_tables!(states, phis);
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        use DoorInputAlphabet::*;
+        Cow::Borrowed(&[
+            Open,
+            Close
+        ])
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        use DoorOutputAlphabet::*;
+        Cow::Borrowed(&[
+            DoorOpens,
+            DoorCloses,
+            OpenIgnored,
+            CloseIgnored,
+        ])
+    }
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        use DoorState::*;
+        use DoorInputAlphabet::*;
+        use DoorPhi::*;
+
+        match (state, input) {
+            (Closed, Open) => Some(OpenDoor),
+            (Closed, Close) => Some(IgnoreClose),
+            (Opened, Close) => Some(CloseDoor),
+            (Opened, Open) => Some(IgnoreOpen),
+        }
+    }
+
+    fn ignore_output(phi: Self::Phi) -> Option<Self::Output> {
+        match phi {
+            DoorPhi::IgnoreOpen => Some(DoorOutputAlphabet::OpenIgnored),
+            DoorPhi::IgnoreClose => Some(DoorOutputAlphabet::CloseIgnored),
+            _ => None,
+        }
+    }
+
+    /// Moving the door takes seconds on the rig; setup sequences avoid it
+    /// when they can.
+    fn transition_cost(_state: Self::State, phi: Self::Phi) -> u32 {
+        match phi {
+            DoorPhi::OpenDoor | DoorPhi::CloseDoor => 10,
+            DoorPhi::IgnoreOpen | DoorPhi::IgnoreClose => 1,
+        }
+    }
+}
+
+/// The two machines connected through their `TryFrom` adapters.
+pub type SecureDoor = MachineNetwork<Digicode, Door>;
+
+#[cfg(test)]
+mod tests {
+    use super::{DigicodePhi, DigicodeState};
+    use crate::analysis::SpecIssue;
+
+    // Rejecting a wrong code and ignoring extra digits once `Accepting`
+    // depend on the store, which `get_phi_for_input` cannot see, so no input
+    // dispatches them.
+    crate::sxm_well_formed_test!(
+        digicode_is_well_formed,
+        super::Digicode,
+        allow [
+            SpecIssue::UnreachablePhi { state: DigicodeState::Accepting, phi: DigicodePhi::Reject },
+            SpecIssue::UnreachablePhi { state: DigicodeState::Accepting, phi: DigicodePhi::Ignore },
+        ]
+    );
+    crate::sxm_well_formed_test!(door_is_well_formed, super::Door);
+}
@@ -0,0 +1,127 @@
+//! A traffic light cycling Red -> Green -> Yellow -> Red on every tick, with a
+//! fault input that forces it to Red and counts the faults.
+
+use crate::XMachine;
+use std::borrow::Cow;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LightInput {
+    Tick,
+    Fault,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LightOutput {
+    ShowRed,
+    ShowGreen,
+    ShowYellow,
+}
+
+crate::sxm_indexed! {
+    pub enum LightState {
+        Red,
+        Green,
+        Yellow,
+    }
+}
+
+/// Number of faults seen so far.
+pub type LightMemory = u32;
+
+crate::sxm_indexed! {
+    pub enum LightPhi {
+        ToGreen,
+        ToYellow,
+        ToRed,
+        FailSafe,
+    }
+}
+
+pub struct TrafficLight;
+
+impl XMachine for TrafficLight {
+    type Input = LightInput;
+    type Output = LightOutput;
+    type State = LightState;
+    type Memory = LightMemory;
+    type Phi = LightPhi;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use LightPhi::*;
+        use LightState::*;
+
+        match (state, phi) {
+            (Red, ToGreen) => Some(Green),
+            (Green, ToYellow) => Some(Yellow),
+            (Yellow, ToRed) => Some(Red),
+            (_, FailSafe) => Some(Red),
+            _ => None,
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        &[LightState::Red]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        &[LightState::Red]
+    }
+
+    fn initial_store() -> Self::Memory {
+        0
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut Self::Memory,
+        input: &Self::Input,
+    ) -> Result<Option<Self::Output>, ()> {
+        use LightInput as In;
+        use LightOutput as Out;
+        use LightPhi::*;
+
+        match (phi, input) {
+            (ToGreen, In::Tick) => Ok(Some(Out::ShowGreen)),
+            (ToYellow, In::Tick) => Ok(Some(Out::ShowYellow)),
+            (ToRed, In::Tick) => Ok(Some(Out::ShowRed)),
+            (FailSafe, In::Fault) => {
+                *store += 1;
+                Ok(Some(Out::ShowRed))
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        Cow::Borrowed(&[LightInput::Tick, LightInput::Fault])
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        use LightOutput::*;
+        Cow::Borrowed(&[ShowRed, ShowGreen, ShowYellow])
+    }
+
+    crate::sxm_index_tables!(states, phis);
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        use LightInput::*;
+        use LightPhi::*;
+        use LightState::*;
+
+        match (state, input) {
+            (Red, Tick) => Some(ToGreen),
+            (Green, Tick) => Some(ToYellow),
+            (Yellow, Tick) => Some(ToRed),
+            (_, Fault) => Some(FailSafe),
+        }
+    }
+
+    fn tick_input() -> Option<Self::Input> {
+        Some(LightInput::Tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    crate::sxm_well_formed_test!(traffic_light_is_well_formed, super::TrafficLight);
+}
@@ -0,0 +1,205 @@
+//! A vending machine that accepts coins until the price is covered, dispenses
+//! a product with change on selection, and refunds the credit on request.
+//!
+//! `InsertCoin` binds the value of the coin it was selected by, so it is one
+//! kind of phi with an instance per coin: `phi_kind` maps every instance to
+//! `InsertCoin(0)`, the representative listed by `all_phis`.
+
+use crate::XMachine;
+use std::borrow::Cow;
+
+/// Price of every product, in cents.
+pub const PRICE: u32 = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VendingInput {
+    /// A coin worth the given number of cents.
+    Coin(u32),
+    Select,
+    Refund,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VendingOutput {
+    /// Credit after a coin was accepted.
+    Credit(u32),
+    Dispense,
+    /// Cents returned to the customer.
+    Change(u32),
+}
+
+crate::sxm_indexed! {
+    pub enum VendingState {
+        Idle,
+        HasCredit,
+    }
+}
+
+/// Credit inserted, in cents.
+pub type VendingMemory = u32;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum VendingPhi {
+    /// Accepts a coin worth the given number of cents.
+    InsertCoin(u32),
+    Vend,
+    ReturnCredit,
+}
+
+pub struct VendingMachine;
+
+impl XMachine for VendingMachine {
+    type Input = VendingInput;
+    type Output = VendingOutput;
+    type State = VendingState;
+    type Memory = VendingMemory;
+    type Phi = VendingPhi;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use VendingPhi::*;
+        use VendingState::*;
+
+        match (state, phi) {
+            (_, InsertCoin(_)) => Some(HasCredit),
+            (HasCredit, Vend) => Some(Idle),
+            (HasCredit, ReturnCredit) => Some(Idle),
+            _ => None,
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        &[VendingState::Idle]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        &[VendingState::Idle]
+    }
+
+    fn initial_store() -> Self::Memory {
+        0
+    }
+
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut Self::Memory,
+        input: &Self::Input,
+    ) -> Result<Option<Self::Output>, ()> {
+        use VendingInput as In;
+        use VendingOutput as Out;
+        use VendingPhi::*;
+
+        match (phi, input) {
+            (InsertCoin(cents), In::Coin(_)) if *store < PRICE => {
+                *store += cents;
+                Ok(Some(Out::Credit(*store)))
+            }
+            (Vend, In::Select) if *store >= PRICE => {
+                let change = *store - PRICE;
+                *store = 0;
+                Ok(Some(if change > 0 { Out::Change(change) } else { Out::Dispense }))
+            }
+            (ReturnCredit, In::Refund) => {
+                let change = *store;
+                *store = 0;
+                Ok(Some(Out::Change(change)))
+            }
+            _ => Err(()),
+        }
+    }
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        use VendingInput::*;
+        Cow::Borrowed(&[Coin(25), Coin(50), Coin(100), Select, Refund])
+    }
+
+    /// Credit and change amounts depend on the coins inserted, so the output
+    /// alphabet is built at runtime. Coins are refused once the price is
+    /// covered, which bounds every amount by `PRICE + 75`.
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        use VendingOutput::*;
+        let mut outputs = vec![Dispense];
+        for cents in (25..=PRICE + 75).step_by(25) {
+            outputs.push(Credit(cents));
+            outputs.push(Change(cents));
+        }
+        Cow::Owned(outputs)
+    }
+
+    crate::sxm_index_tables!(states);
+
+    fn all_phis() -> &'static [Self::Phi] {
+        use VendingPhi::*;
+        &[InsertCoin(0), Vend, ReturnCredit]
+    }
+
+    fn phi_kind(phi: Self::Phi) -> Self::Phi {
+        match phi {
+            VendingPhi::InsertCoin(_) => VendingPhi::InsertCoin(0),
+            phi => phi,
+        }
+    }
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        use VendingInput::*;
+        use VendingPhi::*;
+        use VendingState::*;
+
+        match (state, input) {
+            (_, Coin(cents)) => Some(InsertCoin(*cents)),
+            (HasCredit, Select) => Some(Vend),
+            (HasCredit, Refund) => Some(ReturnCredit),
+            _ => None,
+        }
+    }
+
+    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {
+        use VendingPhi::*;
+
+        match phi {
+            InsertCoin(_) => Some("credit below the price"),
+            Vend => Some("credit covers the price"),
+            ReturnCredit => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug::{Breakpoint, DebugRunner, Pause};
+    use crate::mbt::PathConstraints;
+    use crate::search::path_to_state;
+
+    crate::sxm_well_formed_test!(vending_machine_is_well_formed, super::VendingMachine);
+
+    #[test]
+    fn phis_of_one_kind_share_an_index() {
+        let kind = VendingMachine::phi_index(VendingPhi::InsertCoin(0));
+        assert_eq!(VendingMachine::phi_index(VendingPhi::InsertCoin(50)), kind);
+        assert_ne!(VendingMachine::phi_index(VendingPhi::Vend), kind);
+    }
+
+    #[test]
+    fn cycles_contain_every_instance_of_their_kinds() {
+        let cycles = crate::analysis::cycles::<VendingMachine>();
+        let coin = VendingPhi::InsertCoin(25);
+        assert!(cycles.iter().any(|cycle| cycle.contains::<VendingMachine>(&VendingState::HasCredit, &coin)));
+    }
+
+    #[test]
+    fn a_breakpoint_on_a_kind_pauses_on_every_instance() {
+        let mut debugger = DebugRunner::<VendingMachine>::new();
+        debugger.add_breakpoint(Breakpoint::Phi(VendingPhi::InsertCoin(0)));
+        debugger.feed(&[VendingInput::Coin(25)]);
+        let Pause::Breakpoint { transition, .. } = debugger.resume() else {
+            panic!("the coin did not hit the breakpoint");
+        };
+        assert_eq!(transition.phi, VendingPhi::InsertCoin(25));
+    }
+
+    #[test]
+    fn forbidding_a_kind_forbids_every_instance() {
+        let constraints = PathConstraints::new().avoid_phi(VendingPhi::InsertCoin(0));
+        assert_eq!(path_to_state::<VendingMachine>(VendingState::HasCredit, &constraints), None);
+    }
+}
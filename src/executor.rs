@@ -0,0 +1,1221 @@
+use crate::adaptive::{AdaptiveTestCaseOf, DistinguishingTree, DistinguishingTreeOf, TestTree};
+use crate::configuration::Configuration;
+use crate::homing::HomingSequenceOf;
+use crate::journal::{Journal, JournalError};
+use crate::mbt::{Expectation, NetworkTestCaseOf, SxMTester, TestCaseOf};
+use crate::network::Tagged;
+use crate::runner::MachineRunner;
+use crate::store::{FieldChange, Store};
+use crate::sut::{NetworkSutAdapter, SutAdapter};
+use crate::{OutputKind, Visibility, XMachine};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Outcome of running one test case against a SUT.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict<Output, State> {
+    Pass,
+    /// The output of the test input does not satisfy the expectation.
+    OutputMismatch {
+        expected: Expectation<Output>,
+        actual: Option<Output>,
+    },
+    /// The SUT signalled an `OutputKind::Error` output the expectation does
+    /// not allow.
+    UnexpectedError {
+        expected: Expectation<Output>,
+        actual: Output,
+    },
+    /// The SUT reported a different state than the spec predicts after the test input.
+    StateMismatch { expected: State, actual: State },
+    /// Adaptive verification observed outputs that identify no spec state.
+    Unidentified { expected: State },
+    /// The SUT reported a memory that differs from the spec's after the test
+    /// input, in `state`, on the listed fields: `before` is the spec's value
+    /// and `after` the SUT's. Ghost fields are not compared.
+    StoreMismatch { state: State, changes: Vec<FieldChange> },
+    /// The SUT does not support `SutAdapter::inject` and the case's setup
+    /// sequence does not reach its setup configuration on the spec, so the
+    /// case could not be run faithfully; see `TestCase::requires_injection`.
+    InjectionRequired,
+    /// Without an observable state, the output of the verification input at
+    /// `index` differs from the one the spec predicts, so the SUT is not in
+    /// the state the spec reaches.
+    VerificationMismatch {
+        index: usize,
+        expected: Option<Output>,
+        actual: Option<Output>,
+    },
+}
+
+/// `Verdict` specialised to the symbols of machine `T`.
+pub type VerdictOf<T> = Verdict<<T as XMachine>::Output, <T as XMachine>::State>;
+
+/// The verdict of one test case.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseResult<Output, State> {
+    pub name: String,
+    pub verdict: Verdict<Output, State>,
+    /// `true` if the setup configuration was injected rather than replayed.
+    pub injected: bool,
+}
+
+/// `CaseResult` specialised to the symbols of machine `T`.
+pub type CaseResultOf<T> = CaseResult<<T as XMachine>::Output, <T as XMachine>::State>;
+
+/// Results of a whole suite, in suite order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SuiteReport<Output, State> {
+    pub results: Vec<CaseResult<Output, State>>,
+}
+
+/// `SuiteReport` specialised to the symbols of machine `T`.
+pub type SuiteReportOf<T> = SuiteReport<<T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Output, State> SuiteReport<Output, State> {
+    pub fn passed(&self) -> usize {
+        self.results.len() - self.failures().count()
+    }
+
+    /// Results whose verdict is not `Verdict::Pass`.
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult<Output, State>> {
+        self.results
+            .iter()
+            .filter(|r| !matches!(r.verdict, Verdict::Pass))
+    }
+
+    pub fn all_passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+}
+
+/// Outcome of running one network test case against a SUT.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkVerdict<OutputA, OutputB> {
+    Pass,
+    /// The network emitted something else than the case expects.
+    OutputMismatch {
+        expected: Vec<Tagged<OutputA, OutputB>>,
+        actual: Vec<Tagged<OutputA, OutputB>>,
+    },
+    /// The SUT reported internal messages still pending after the test input.
+    Unstable,
+    /// A component left its configuration although the test input was
+    /// rejected: `a` and `b` tell which did, by state or non-ghost field.
+    ConfigurationChanged { a: bool, b: bool },
+}
+
+/// `NetworkVerdict` specialised to the outputs of components `A` and `B`.
+pub type NetworkVerdictOf<A, B> = NetworkVerdict<<A as XMachine>::Output, <B as XMachine>::Output>;
+
+/// How `Executor::reset` brought a SUT back to its initial configuration,
+/// i.e. which reliable-reset assumption the results rest on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ResetMethod {
+    /// `SutAdapter::reset`.
+    Adapter,
+    /// `XMachine::reset_input`, whose reliability `SxMTester::generate_reset_tests` checks.
+    Input,
+}
+
+/// How outputs observed over a case are matched against the spec, for SUTs
+/// whose outputs arrive late or out of order.
+///
+/// The looser modes judge the whole window of the test input and the
+/// verification sequence at once: every output the spec predicts there must
+/// be observed, and every observed output must be predicted.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OutputMatching {
+    /// Each input emits the output the spec predicts for it, as in `Executor::run_case`.
+    #[default]
+    Exact,
+    /// A predicted output may arrive up to this many inputs late, but within
+    /// the window, and outputs keep their order.
+    Eventually(usize),
+    /// The outputs observed over the window equal the predicted ones as a
+    /// multiset, in any order.
+    Unordered,
+}
+
+/// Options of `Executor::run_case_with` and `Executor::run_suite_with`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExecutorOptions {
+    pub output_matching: OutputMatching,
+}
+
+/// Runs generated test cases against a `SutAdapter`.
+pub struct Executor;
+
+impl Executor {
+    /// Brings `sut` into the case's setup configuration, applies the test input
+    /// and compares the output, and the state if the SUT exposes it, with the spec.
+    ///
+    /// The setup configuration is injected when both the case carries one and
+    /// the adapter supports `SutAdapter::inject`; otherwise the setup sequence
+    /// is replayed and its outputs are ignored. Internal outputs are not judged.
+    ///
+    /// When the SUT does not expose its state, the state is inferred instead:
+    /// the outputs of the verification sequence must match those the spec
+    /// predicts from the configuration it reaches. When it exposes its
+    /// memory, the memory must be `Store::observably_eq` to the spec's.
+    pub fn run_case<T, S>(sut: &mut S, case: &TestCaseOf<T>) -> CaseResultOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        let injected = Self::setup(sut, case);
+        let actual = sut.apply(&case.test_input);
+        let observed_state = sut.observe_state();
+        let observed_store = sut.observe_store();
+        let observed: Vec<Option<T::Output>> =
+            case.verification_sequence.iter().map(|input| sut.apply(input)).collect();
+
+        let verdict = Self::check_setup::<T>(&case.setup_sequence, case.setup_configuration.as_ref(), injected)
+            .or_else(|| Self::check_output::<T>(case, actual))
+            .or_else(|| match (observed_state, Self::expected_state::<T>(case)) {
+                (Some(actual), Some(expected)) if actual != expected => {
+                    Some(Verdict::StateMismatch { expected, actual })
+                }
+                (Some(_), _) => None,
+                (None, _) => Self::check_verification::<T>(case, observed),
+            })
+            .or_else(|| Self::check_store::<T>(case, observed_store))
+            .unwrap_or(Verdict::Pass);
+
+        CaseResult {
+            name: case.name.clone(),
+            verdict,
+            injected,
+        }
+    }
+
+    /// Like `run_case`, matching outputs as `options` sets.
+    ///
+    /// Under `OutputMatching::Eventually` and `OutputMatching::Unordered` the
+    /// outputs of the verification sequence are always checked, since late
+    /// outputs of the test input land there, and a state the SUT exposes is
+    /// compared afterwards, then a memory it exposes. Outputs are reported where the match failed: an
+    /// output missing or unexpected at the test input as `OutputMismatch`,
+    /// within the verification sequence as `VerificationMismatch`.
+    pub fn run_case_with<T, S>(sut: &mut S, case: &TestCaseOf<T>, options: &ExecutorOptions) -> CaseResultOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        if options.output_matching == OutputMatching::Exact {
+            return Self::run_case::<T, S>(sut, case);
+        }
+        let injected = Self::setup(sut, case);
+        let mut actual = vec![Self::observable::<T>(sut.apply(&case.test_input))];
+        let observed_state = sut.observe_state();
+        let observed_store = sut.observe_store();
+        actual.extend(case.verification_sequence.iter().map(|input| Self::observable::<T>(sut.apply(input))));
+
+        let verdict = Self::check_setup::<T>(&case.setup_sequence, case.setup_configuration.as_ref(), injected)
+            .or_else(|| Self::check_window::<T>(case, &actual, options.output_matching))
+            .or_else(|| match (observed_state, Self::expected_state::<T>(case)) {
+                (Some(actual), Some(expected)) if actual != expected => {
+                    Some(Verdict::StateMismatch { expected, actual })
+                }
+                _ => None,
+            })
+            .or_else(|| Self::check_store::<T>(case, observed_store))
+            .unwrap_or(Verdict::Pass);
+
+        CaseResult {
+            name: case.name.clone(),
+            verdict,
+            injected,
+        }
+    }
+
+    /// Like `run_case`, but verifies the state reached by the test input with
+    /// the adaptive distinguishing `tree` instead of the verification sequence.
+    ///
+    /// Works on black-box SUTs: the state is identified from outputs alone.
+    pub fn run_case_adaptive<T, S>(
+        sut: &mut S,
+        case: &TestCaseOf<T>,
+        tree: &DistinguishingTreeOf<T>,
+    ) -> CaseResultOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        let injected = Self::setup(sut, case);
+        let actual = sut.apply(&case.test_input);
+
+        let verdict = Self::check_setup::<T>(&case.setup_sequence, case.setup_configuration.as_ref(), injected)
+            .or_else(|| Self::check_output::<T>(case, actual))
+            .unwrap_or_else(|| {
+                let Some(expected) = Self::expected_state::<T>(case) else {
+                    return Verdict::Pass;
+                };
+                match Self::identify_state(sut, tree) {
+                    Some(actual) if actual != expected => Verdict::StateMismatch { expected, actual },
+                    Some(_) => Verdict::Pass,
+                    None => Verdict::Unidentified { expected },
+                }
+            });
+
+        CaseResult {
+            name: case.name.clone(),
+            verdict,
+            injected,
+        }
+    }
+
+    /// Walks `tree` on `sut` and returns the state its outputs identify, or
+    /// `None` if an output has no branch or the tree is unresolved there.
+    pub fn identify_state<T, S>(sut: &mut S, tree: &DistinguishingTreeOf<T>) -> Option<T::State>
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+    {
+        let mut node = tree;
+        loop {
+            match node {
+                DistinguishingTree::Leaf(state) => return Some(*state),
+                DistinguishingTree::Unresolved(_) => return None,
+                DistinguishingTree::Node { input, .. } => {
+                    let output = sut.apply(input);
+                    node = node.branch(output.as_ref())?;
+                }
+            }
+        }
+    }
+
+    /// Brings `sut` into the case's setup configuration like `run_case`, then
+    /// walks its test tree, each observed output choosing the next input,
+    /// down to the verdict of the leaf reached.
+    ///
+    /// An output no branch accepts ends the walk early: as an
+    /// `OutputMismatch` at a node with a single branch, such as the test
+    /// input, and as `Unidentified` where the tree distinguishes states.
+    /// Internal outputs count as no output.
+    pub fn run_adaptive_case<T, S>(sut: &mut S, case: &AdaptiveTestCaseOf<T>) -> CaseResultOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        let injected = Self::setup_from::<T, S>(sut, &case.setup_sequence, case.setup_configuration.as_ref());
+        let setup = Self::check_setup::<T>(&case.setup_sequence, case.setup_configuration.as_ref(), injected);
+        let mut node = &case.tree;
+        let verdict = setup.unwrap_or_else(|| loop {
+            let (input, branches) = match node {
+                TestTree::Verdict(verdict) => break verdict.clone(),
+                TestTree::Apply { input, branches } => (input, branches),
+            };
+            let actual = Self::observable::<T>(sut.apply(input));
+            match branches.iter().find(|(expected, _)| Self::satisfies::<T>(expected, actual.as_ref())) {
+                Some((_, next)) => node = next,
+                None => {
+                    break match (branches.as_slice(), case.expected_state) {
+                        ([_, _, ..], Some(expected)) => Verdict::Unidentified { expected },
+                        _ => Self::mismatch::<T>(
+                            branches.first().map_or(Expectation::None, |(e, _)| e.clone()),
+                            actual,
+                        ),
+                    };
+                }
+            }
+        });
+
+        CaseResult {
+            name: case.name.clone(),
+            verdict,
+            injected,
+        }
+    }
+
+    /// Runs every adaptive case on a fresh SUT from `new_sut`.
+    pub fn run_adaptive_suite<T, S>(cases: &[AdaptiveTestCaseOf<T>], mut new_sut: impl FnMut() -> S) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        SuiteReport {
+            results: cases
+                .iter()
+                .map(|case| Self::run_adaptive_case::<T, S>(&mut new_sut(), case))
+                .collect(),
+        }
+    }
+
+    /// Runs every case on a fresh SUT from `new_sut`.
+    pub fn run_suite<T, S>(cases: &[TestCaseOf<T>], mut new_sut: impl FnMut() -> S) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        SuiteReport {
+            results: cases
+                .iter()
+                .map(|case| Self::run_case::<T, S>(&mut new_sut(), case))
+                .collect(),
+        }
+    }
+
+    /// Like `run_suite`, matching outputs as `options` sets.
+    pub fn run_suite_with<T, S>(
+        cases: &[TestCaseOf<T>],
+        mut new_sut: impl FnMut() -> S,
+        options: &ExecutorOptions,
+    ) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        SuiteReport {
+            results: cases
+                .iter()
+                .map(|case| Self::run_case_with::<T, S>(&mut new_sut(), case, options))
+                .collect(),
+        }
+    }
+
+    /// Like `run_suite`, but records each verdict in `journal` as soon as its
+    /// case ends, replacing any earlier journal, so that `resume` can finish
+    /// the run if it is interrupted.
+    pub fn run_suite_journaled<T, S>(
+        cases: &[TestCaseOf<T>],
+        new_sut: impl FnMut() -> S,
+        journal: &Journal,
+    ) -> Result<SuiteReportOf<T>, JournalError>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        Self::run_journaled(cases, new_sut, journal, vec![None; cases.len()])
+    }
+
+    /// Continues the run of `cases` that `journal` records: the cases it
+    /// holds a verdict for are skipped, the others run on a fresh SUT from
+    /// `new_sut` and are journaled in turn. The report merges both, in suite
+    /// order, as if the run had not been interrupted.
+    ///
+    /// Without a journal file the whole suite runs, as `run_suite_journaled`.
+    /// A case cut short by the interruption runs again from its setup.
+    pub fn resume<T, S>(
+        cases: &[TestCaseOf<T>],
+        new_sut: impl FnMut() -> S,
+        journal: &Journal,
+    ) -> Result<SuiteReportOf<T>, JournalError>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        let done = journal.results::<T>(cases)?;
+        Self::run_journaled(cases, new_sut, journal, done)
+    }
+
+    fn run_journaled<T, S>(
+        cases: &[TestCaseOf<T>],
+        mut new_sut: impl FnMut() -> S,
+        journal: &Journal,
+        mut done: Vec<Option<CaseResultOf<T>>>,
+    ) -> Result<SuiteReportOf<T>, JournalError>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        let mut writer = journal.rewrite::<T>(cases.len(), &done)?;
+        for (index, case) in cases.iter().enumerate() {
+            if done[index].is_none() {
+                let result = Self::run_case::<T, S>(&mut new_sut(), case);
+                writer.record::<T>(index, &result)?;
+                done[index] = Some(result);
+            }
+        }
+        Ok(SuiteReport {
+            results: done.into_iter().flatten().collect(),
+        })
+    }
+
+    /// Returns `sut` to its initial configuration with `SutAdapter::reset`, or
+    /// else by applying `XMachine::reset_input`. `None` if neither exists.
+    pub fn reset<T, S>(sut: &mut S) -> Option<ResetMethod>
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+    {
+        if sut.reset() {
+            return Some(ResetMethod::Adapter);
+        }
+        let input = T::reset_input()?;
+        sut.apply(&input);
+        Some(ResetMethod::Input)
+    }
+
+    /// Like `run_suite`, but runs every case on the same `sut`, resetting it
+    /// with `reset` before each one, for SUTs that are costly to recreate.
+    ///
+    /// # Panics
+    /// If `sut` cannot be reset.
+    pub fn run_suite_with_reset<T, S>(cases: &[TestCaseOf<T>], sut: &mut S) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        SuiteReport {
+            results: cases
+                .iter()
+                .map(|case| {
+                    Self::reset::<T, S>(sut).expect("SUT has neither SutAdapter::reset nor XMachine::reset_input");
+                    Self::run_case::<T, S>(sut, case)
+                })
+                .collect(),
+        }
+    }
+
+    /// Brings `sut`, in any of the configurations `homing` was built from, to
+    /// an initial state: applies the homing inputs, then the transfer of the
+    /// outcome their outputs select. Returns the state the homing inputs
+    /// identified, or `None` if the spec cannot produce the outputs observed,
+    /// in which case no transfer is applied.
+    pub fn home<T, S>(sut: &mut S, homing: &HomingSequenceOf<T>) -> Option<T::State>
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+    {
+        let observed: Vec<Option<T::Output>> =
+            homing.inputs().iter().map(|input| Self::observable::<T>(sut.apply(input))).collect();
+        let outcome = homing.outcome(&observed)?;
+        for input in &outcome.transfer {
+            sut.apply(input);
+        }
+        Some(outcome.state)
+    }
+
+    /// Like `run_suite_with_reset`, for SUTs without a reliable reset: every
+    /// case runs on the same `sut` after `home` brings it back to an initial
+    /// state, starting with the first, as the SUT may start anywhere.
+    ///
+    /// A case whose preamble produces outputs the spec cannot is not run and
+    /// is judged `Verdict::Unidentified`, expecting the first initial state.
+    /// A spec without initial states has no state to expect, so its cases
+    /// are run regardless.
+    pub fn run_suite_homed<T, S>(cases: &[TestCaseOf<T>], sut: &mut S, homing: &HomingSequenceOf<T>) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+    {
+        SuiteReport {
+            results: cases
+                .iter()
+                .map(|case| match (Self::home::<T, S>(sut, homing), T::initial_states().first()) {
+                    (None, Some(&expected)) => CaseResult {
+                        name: case.name.clone(),
+                        verdict: Verdict::Unidentified { expected },
+                        injected: false,
+                    },
+                    _ => Self::run_case::<T, S>(sut, case),
+                })
+                .collect(),
+        }
+    }
+
+    /// Like `run_suite_with_reset`, but spreads the cases over `workers`
+    /// threads. Each worker creates one SUT from `new_sut` and runs its cases
+    /// on it one at a time, resetting it with `reset` between cases.
+    ///
+    /// Workers pick the next pending case as they finish one, so slow cases do
+    /// not hold up a whole share of the suite. Results are in suite order
+    /// whatever the scheduling, so the report equals the one of `run_suite`
+    /// for SUTs that behave the same on every run.
+    ///
+    /// # Panics
+    /// If `workers` is 0, a worker panics, or a worker running more than one
+    /// case cannot reset its SUT.
+    pub fn run_suite_parallel<T, S>(
+        cases: &[TestCaseOf<T>],
+        workers: usize,
+        new_sut: impl Fn() -> S + Sync,
+    ) -> SuiteReportOf<T>
+    where
+        T: XMachine,
+        T::Memory: Store,
+        S: SutAdapter<T>,
+        TestCaseOf<T>: Sync,
+        CaseResultOf<T>: Send,
+    {
+        assert!(workers > 0, "run_suite_parallel needs at least one worker");
+        let next = AtomicUsize::new(0);
+        let mut results: Vec<(usize, CaseResultOf<T>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers.min(cases.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut sut = new_sut();
+                        let mut done = Vec::new();
+                        loop {
+                            let idx = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(case) = cases.get(idx) else {
+                                break done;
+                            };
+                            if !done.is_empty() {
+                                Self::reset::<T, S>(&mut sut)
+                                    .expect("SUT has neither SutAdapter::reset nor XMachine::reset_input");
+                            }
+                            done.push((idx, Self::run_case::<T, S>(&mut sut, case)));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("executor worker panicked"))
+                .collect()
+        });
+        results.sort_by_key(|(idx, _)| *idx);
+        SuiteReport {
+            results: results.into_iter().map(|(_, result)| result).collect(),
+        }
+    }
+
+    /// Replays the case's setup sequence on `sut`, applies the test input and
+    /// compares the emitted outputs. If the SUT exposes them, also checks it
+    /// is stable and both components kept their configuration.
+    pub fn run_network_case<A, B, S>(sut: &mut S, case: &NetworkTestCaseOf<A, B>) -> NetworkVerdictOf<A, B>
+    where
+        A: XMachine,
+        B: XMachine,
+        S: NetworkSutAdapter<A, B>,
+        A::Memory: Store,
+        B::Memory: Store,
+    {
+        for input in &case.setup_sequence {
+            sut.apply(input);
+        }
+        let before = sut.observe_configurations();
+        let actual = sut.apply(&case.test_input);
+        if actual != case.expected_outputs {
+            return NetworkVerdict::OutputMismatch {
+                expected: case.expected_outputs.clone(),
+                actual,
+            };
+        }
+        if sut.is_stable() == Some(false) {
+            return NetworkVerdict::Unstable;
+        }
+        if let (Some((a0, b0)), Some((a1, b1))) = (before, sut.observe_configurations()) {
+            let (a, b) = (!Self::kept(&a0, &a1), !Self::kept(&b0, &b1));
+            if a || b {
+                return NetworkVerdict::ConfigurationChanged { a, b };
+            }
+        }
+        NetworkVerdict::Pass
+    }
+
+    /// `true` if `after` has the state of `before` and a memory that differs on ghost fields only.
+    fn kept<T>(before: &Configuration<T>, after: &Configuration<T>) -> bool
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        before.state == after.state && before.store.observably_eq(&after.store)
+    }
+
+    /// Brings `sut` into the setup configuration; returns `true` if it was injected.
+    fn setup<T, S>(sut: &mut S, case: &TestCaseOf<T>) -> bool
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+    {
+        Self::setup_from::<T, S>(sut, &case.setup_sequence, case.setup_configuration.as_ref())
+    }
+
+    fn setup_from<T, S>(sut: &mut S, sequence: &[T::Input], configuration: Option<&(T::State, T::Memory)>) -> bool
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+    {
+        let injected = match configuration {
+            Some((state, store)) => sut.inject(*state, store),
+            None => false,
+        };
+        if !injected {
+            for input in sequence {
+                sut.apply(input);
+            }
+        }
+        injected
+    }
+
+    /// `Some` failing verdict if `actual` does not satisfy the case's expectation.
+    ///
+    /// An expectation made only of `Visibility::Internal` outputs is not
+    /// checked, and an internal output the SUT reports counts as no output.
+    fn check_output<T: XMachine>(case: &TestCaseOf<T>, actual: Option<T::Output>) -> Option<VerdictOf<T>> {
+        let actual = Self::observable::<T>(actual);
+        if Self::satisfies::<T>(&case.expected_output, actual.as_ref()) {
+            None
+        } else {
+            Some(Self::mismatch::<T>(case.expected_output.clone(), actual))
+        }
+    }
+
+    /// `Verdict::UnexpectedError` if `actual` is an error output, else `Verdict::OutputMismatch`.
+    pub(crate) fn mismatch<T: XMachine>(expected: Expectation<T::Output>, actual: Option<T::Output>) -> VerdictOf<T> {
+        match actual {
+            Some(actual) if T::output_kind(&actual) == OutputKind::Error => {
+                Verdict::UnexpectedError { expected, actual }
+            }
+            actual => Verdict::OutputMismatch { expected, actual },
+        }
+    }
+
+    /// `output`, unless it is `Visibility::Internal`.
+    pub(crate) fn observable<T: XMachine>(output: Option<T::Output>) -> Option<T::Output> {
+        output.filter(|output| T::output_visibility(output) != Some(Visibility::Internal))
+    }
+
+    /// `true` if the observable `actual` satisfies `expected`, or `expected`
+    /// is made only of internal outputs.
+    pub(crate) fn satisfies<T: XMachine>(expected: &Expectation<T::Output>, actual: Option<&T::Output>) -> bool {
+        Self::expects_internal::<T>(expected) || expected.matches(actual)
+    }
+
+    fn expects_internal<T: XMachine>(expected: &Expectation<T::Output>) -> bool {
+        let internal = |output: &T::Output| T::output_visibility(output) == Some(Visibility::Internal);
+        match expected {
+            Expectation::Exact(output) => internal(output),
+            Expectation::AnyOf(outputs) => !outputs.is_empty() && outputs.iter().all(internal),
+            Expectation::Predicate(..) | Expectation::None => false,
+        }
+    }
+
+    /// `Some` failing verdict if the observable outputs `actual` of the test
+    /// input and the verification sequence do not match the spec's under `matching`.
+    ///
+    /// Each step has an expectation, or none when anything goes there: an
+    /// internal-only expectation, or a verification step after a setup the
+    /// spec rejects. Outputs at such steps may still match another step.
+    fn check_window<T: XMachine>(
+        case: &TestCaseOf<T>,
+        actual: &[Option<T::Output>],
+        matching: OutputMatching,
+    ) -> Option<VerdictOf<T>> {
+        let mut expected: Vec<Option<Expectation<T::Output>>> = Vec::with_capacity(actual.len());
+        expected.push(Some(case.expected_output.clone()).filter(|e| !Self::expects_internal::<T>(e)));
+        match Self::spec_after_test::<T>(case) {
+            Some(mut spec) => expected.extend(case.verification_sequence.iter().map(|input| {
+                Some(Self::observable::<T>(spec.step(input).ok().and_then(|t| t.output)).into())
+            })),
+            None => expected.extend(case.verification_sequence.iter().map(|_| None)),
+        }
+
+        let mut matched = vec![false; actual.len()];
+        let wanted = |e: &Option<Expectation<T::Output>>| matches!(e, Some(e) if *e != Expectation::None);
+        match matching {
+            OutputMatching::Exact | OutputMatching::Eventually(_) => {
+                let delay = match matching {
+                    OutputMatching::Eventually(delay) => delay,
+                    _ => 0,
+                };
+                let mut next = 0;
+                for (step, expectation) in expected.iter().enumerate().filter(|(_, e)| wanted(e)) {
+                    let last = (step + delay).min(actual.len() - 1);
+                    let found = (step.max(next)..=last)
+                        .find(|&at| !matched[at] && Self::matches_at::<T>(expectation, &actual[at]));
+                    let Some(at) = found else {
+                        return Some(Self::window_mismatch::<T>(case, &expected, actual, step));
+                    };
+                    matched[at] = true;
+                    next = at + 1;
+                }
+            }
+            OutputMatching::Unordered => {
+                // A maximum bipartite matching of steps to outputs, so a loose
+                // expectation taking an output a stricter one needs is undone.
+                let fits = |step: usize, at: usize| Self::matches_at::<T>(&expected[step], &actual[at]);
+                let mut owner: Vec<Option<usize>> = vec![None; actual.len()];
+                for step in (0..expected.len()).filter(|&step| wanted(&expected[step])) {
+                    if !augment(step, &fits, &mut owner, &mut vec![false; actual.len()]) {
+                        return Some(Self::window_mismatch::<T>(case, &expected, actual, step));
+                    }
+                }
+                // Outputs left over should be those where anything goes, so
+                // the others take their place in the matching if they can.
+                let loose = |at: usize| expected[at].is_none();
+                for at in 0..actual.len() {
+                    if actual[at].is_some() && owner[at].is_none() && !loose(at) {
+                        release(at, &fits, &loose, &mut owner, &mut vec![false; expected.len()]);
+                    }
+                }
+                matched = owner.iter().map(Option::is_some).collect();
+            }
+        }
+        (0..actual.len())
+            .find(|&at| actual[at].is_some() && !matched[at] && expected[at].is_some())
+            .map(|at| Self::window_mismatch::<T>(case, &expected, actual, at))
+    }
+
+    fn matches_at<T: XMachine>(expected: &Option<Expectation<T::Output>>, actual: &Option<T::Output>) -> bool {
+        matches!((expected, actual), (Some(expected), Some(actual)) if expected.matches(Some(actual)))
+    }
+
+    /// The verdict for a match that failed at `step` of the window.
+    fn window_mismatch<T: XMachine>(
+        case: &TestCaseOf<T>,
+        expected: &[Option<Expectation<T::Output>>],
+        actual: &[Option<T::Output>],
+        step: usize,
+    ) -> VerdictOf<T> {
+        if step == 0 {
+            return Self::mismatch::<T>(case.expected_output.clone(), actual[0].clone());
+        }
+        Verdict::VerificationMismatch {
+            index: step - 1,
+            expected: match &expected[step] {
+                Some(Expectation::Exact(output)) => Some(output.clone()),
+                _ => None,
+            },
+            actual: actual[step].clone(),
+        }
+    }
+
+    /// `Some` failing verdict if the outputs `observed` for the verification
+    /// sequence differ from those the spec predicts, internal outputs aside.
+    fn check_verification<T: XMachine>(case: &TestCaseOf<T>, observed: Vec<Option<T::Output>>) -> Option<VerdictOf<T>> {
+        let mut spec = Self::spec_after_test::<T>(case)?;
+        for (index, (input, actual)) in case.verification_sequence.iter().zip(observed).enumerate() {
+            let expected = Self::observable::<T>(spec.step(input).ok().and_then(|t| t.output));
+            let actual = Self::observable::<T>(actual);
+            if expected != actual {
+                return Some(Verdict::VerificationMismatch { index, expected, actual });
+            }
+        }
+        None
+    }
+
+    /// `Some(Verdict::InjectionRequired)` if the setup was replayed rather
+    /// than injected but the replay does not reach `configuration` on the spec.
+    fn check_setup<T>(
+        sequence: &[T::Input],
+        configuration: Option<&(T::State, T::Memory)>,
+        injected: bool,
+    ) -> Option<VerdictOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        (!injected && !SxMTester::reproduces::<T>(sequence, configuration)).then_some(Verdict::InjectionRequired)
+    }
+
+    /// `Some(Verdict::StoreMismatch)` if the memory `observed` after the test
+    /// input differs from the spec's on a non-ghost field.
+    fn check_store<T>(case: &TestCaseOf<T>, observed: Option<T::Memory>) -> Option<VerdictOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        let observed = observed?;
+        let spec = Self::spec_after_test::<T>(case)?;
+        if spec.store().observably_eq(&observed) {
+            return None;
+        }
+        Some(Verdict::StoreMismatch {
+            state: spec.state(),
+            changes: spec.store().diff(&observed),
+        })
+    }
+
+    /// The state the spec is in after the case's test input, which is the
+    /// state before it if the spec rejects the input.
+    pub(crate) fn expected_state<T: XMachine>(case: &TestCaseOf<T>) -> Option<T::State> {
+        Self::spec_after_test::<T>(case).map(|spec| spec.state())
+    }
+
+    /// The spec after the case's test input, still in the configuration
+    /// before it if it rejects the input. `None` if the setup sequence is rejected.
+    fn spec_after_test<T: XMachine>(case: &TestCaseOf<T>) -> Option<MachineRunner<T>> {
+        let mut spec = match &case.setup_configuration {
+            Some((state, store)) => MachineRunner::<T>::from_parts(*state, store.clone()),
+            None => {
+                let mut spec = MachineRunner::<T>::new();
+                spec.run(&case.setup_sequence).ok()?;
+                spec
+            }
+        };
+        let _ = spec.step(&case.test_input);
+        Some(spec)
+    }
+}
+
+
+/// Matches `step` to an output it fits, moving the step holding that output
+/// to another one if needed: one augmenting path of Kuhn's algorithm.
+/// `owner[at]` is the step output `at` is matched to.
+fn augment(step: usize, fits: &dyn Fn(usize, usize) -> bool, owner: &mut [Option<usize>], seen: &mut [bool]) -> bool {
+    for at in 0..owner.len() {
+        if seen[at] || !fits(step, at) {
+            continue;
+        }
+        seen[at] = true;
+        if owner[at].map_or(true, |other| augment(other, fits, owner, seen)) {
+            owner[at] = Some(step);
+            return true;
+        }
+    }
+    false
+}
+
+/// Matches the unmatched output `at` to a step it fits, whose output is in
+/// turn released unless it is `loose`, keeping every step matched. Leaves
+/// the matching as it was if no such chain exists.
+fn release(
+    at: usize,
+    fits: &dyn Fn(usize, usize) -> bool,
+    loose: &dyn Fn(usize) -> bool,
+    owner: &mut [Option<usize>],
+    seen: &mut [bool],
+) -> bool {
+    for step in 0..seen.len() {
+        if seen[step] || !fits(step, at) {
+            continue;
+        }
+        let Some(held) = owner.iter().position(|&o| o == Some(step)) else {
+            continue;
+        };
+        seen[step] = true;
+        owner[held] = None;
+        owner[at] = Some(step);
+        if loose(held) || release(held, fits, loose, owner, seen) {
+            return true;
+        }
+        owner[at] = None;
+        owner[held] = Some(step);
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet, DigicodeMemory, DigicodeOutputAlphabet, DigicodePhi, DigicodeState, Door,
+    };
+    use crate::feasibility::{Feasibility, FeasibilityOf, FeasibilityOracle};
+    use crate::mbt::TestCase;
+    use crate::network::Adapters;
+    use std::borrow::Cow;
+
+    type DoorInput = Tagged<<Digicode as XMachine>::Input, <Door as XMachine>::Input>;
+    type DoorOutput = Tagged<<Digicode as XMachine>::Output, <Door as XMachine>::Output>;
+
+    crate::sxm_store! {
+        #[derive(Debug)]
+        struct Tally {
+            count: u32,
+            #[sxm(ghost)]
+            presses: u32,
+        }
+    }
+
+    /// Counts presses in `count`, and again in the ghost `presses`, emitting
+    /// the count. The first count is internal.
+    struct Counter;
+
+    impl XMachine for Counter {
+        type Input = ();
+        type Output = u32;
+        type State = ();
+        type Memory = Tally;
+        type Phi = ();
+
+        fn next_state(_state: (), _phi: ()) -> Option<()> {
+            Some(())
+        }
+
+        fn initial_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn final_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn initial_store() -> Tally {
+            Tally { count: 0, presses: 0 }
+        }
+
+        fn execute_phi(_phi: (), store: &mut Tally, _input: &()) -> Result<Option<u32>, ()> {
+            store.count += 1;
+            store.presses += 1;
+            Ok(Some(store.count))
+        }
+
+        fn all_inputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[()])
+        }
+
+        fn all_outputs() -> Cow<'static, [u32]> {
+            Cow::Borrowed(&[1, 2])
+        }
+
+        fn all_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn all_phis() -> &'static [()] {
+            &[()]
+        }
+
+        fn get_phi_for_input(_state: (), _input: &()) -> Option<()> {
+            Some(())
+        }
+
+        fn output_visibility(output: &u32) -> Option<Visibility> {
+            (*output == 1).then_some(Visibility::Internal)
+        }
+    }
+
+    /// A SUT emitting the listed outputs, whatever its inputs.
+    struct Script(Vec<u32>);
+
+    impl SutAdapter<Counter> for Script {
+        fn apply(&mut self, _input: &()) -> Option<u32> {
+            (!self.0.is_empty()).then(|| self.0.remove(0))
+        }
+    }
+
+    #[test]
+    fn unordered_matching_leaves_extra_outputs_where_anything_goes() {
+        let case = TestCase::builder::<Counter>("first press").input(()).expect(Some(1)).verify([()]).build().unwrap();
+        let options = ExecutorOptions {
+            output_matching: OutputMatching::Unordered,
+        };
+        let result = Executor::run_case_with::<Counter, _>(&mut Script(vec![2, 2]), &case, &options);
+        assert_eq!(result.verdict, Verdict::Pass);
+        let result = Executor::run_case_with::<Counter, _>(&mut Script(vec![2, 3]), &case, &options);
+        assert_ne!(result.verdict, Verdict::Pass);
+    }
+
+    /// The spec as a SUT whose reported memory is skewed by `skew`.
+    struct Skewed {
+        runner: MachineRunner<Counter>,
+        skew: fn(&mut Tally),
+    }
+
+    impl SutAdapter<Counter> for Skewed {
+        fn apply(&mut self, input: &()) -> Option<u32> {
+            self.runner.apply(input)
+        }
+
+        fn observe_store(&self) -> Option<Tally> {
+            let mut store = self.runner.store().clone();
+            (self.skew)(&mut store);
+            Some(store)
+        }
+    }
+
+    fn case() -> TestCaseOf<Counter> {
+        TestCase::builder::<Counter>("second press").setup([()]).input(()).expect(Some(2)).build().unwrap()
+    }
+
+    #[test]
+    fn parallel_workers_reuse_one_reset_sut_each() {
+        let cases: Vec<_> = (0..6).map(|_| case()).collect();
+        let created = AtomicUsize::new(0);
+        let report = Executor::run_suite_parallel::<Counter, _>(&cases, 2, || {
+            created.fetch_add(1, Ordering::Relaxed);
+            MachineRunner::<Counter>::new()
+        });
+        assert!(report.results.iter().all(|result| result.verdict == Verdict::Pass));
+        assert!(created.load(Ordering::Relaxed) <= 2);
+    }
+
+    #[test]
+    fn ghost_fields_of_the_sut_memory_are_not_compared() {
+        let mut sut = Skewed {
+            runner: MachineRunner::new(),
+            skew: |store| store.presses = 0,
+        };
+        assert_eq!(Executor::run_case::<Counter, _>(&mut sut, &case()).verdict, Verdict::Pass);
+    }
+
+    #[test]
+    fn a_differing_field_of_the_sut_memory_is_a_store_mismatch() {
+        let mut sut = Skewed {
+            runner: MachineRunner::new(),
+            skew: |store| store.count += 1,
+        };
+        let result = Executor::run_case::<Counter, _>(&mut sut, &case());
+        let Verdict::StoreMismatch { changes, .. } = result.verdict else {
+            panic!("expected a store mismatch, got {:?}", result.verdict);
+        };
+        assert_eq!(
+            changes,
+            [FieldChange {
+                field: "count",
+                before: "2".to_string(),
+                after: "3".to_string(),
+            }]
+        );
+    }
+
+    type SecureDoor = crate::network::MachineNetwork<Digicode, Door>;
+
+    /// The secure door network as a SUT that starts over on every input its
+    /// addressed component rejects.
+    struct Forgetful(SecureDoor);
+
+    impl NetworkSutAdapter<Digicode, Door> for Forgetful {
+        fn apply(&mut self, input: &DoorInput) -> Vec<DoorOutput> {
+            let rejected = match input {
+                Tagged::A(i) => self.0.a().configuration().step(i).is_err(),
+                Tagged::B(i) => self.0.b().configuration().step(i).is_err(),
+            };
+            let outputs = self.0.apply(input);
+            if rejected {
+                self.0 = SecureDoor::new();
+            }
+            outputs
+        }
+
+        fn observe_configurations(&self) -> Option<(Configuration<Digicode>, Configuration<Door>)> {
+            self.0.observe_configurations()
+        }
+    }
+
+    fn network_cases() -> Vec<NetworkTestCaseOf<Digicode, Door>> {
+        crate::mbt::SxMTester::generate_network_robustness_tests::<Digicode, Door, Adapters>(3, &|_| Vec::new())
+    }
+
+    #[test]
+    fn the_spec_network_keeps_its_configuration_on_rejected_inputs() {
+        let cases = network_cases();
+        assert!(!cases.is_empty());
+        for case in &cases {
+            let verdict = Executor::run_network_case::<Digicode, Door, _>(&mut SecureDoor::new(), case);
+            assert_eq!(verdict, NetworkVerdict::Pass, "{}", case.name);
+        }
+    }
+
+    #[test]
+    fn a_component_leaving_its_configuration_on_a_rejected_input_fails() {
+        let changed: Vec<_> = network_cases()
+            .iter()
+            .map(|case| Executor::run_network_case::<Digicode, Door, _>(&mut Forgetful(SecureDoor::new()), case))
+            .filter(|verdict| *verdict != NetworkVerdict::Pass)
+            .collect();
+        assert!(!changed.is_empty());
+        assert!(changed.iter().all(|verdict| matches!(verdict, NetworkVerdict::ConfigurationChanged { .. })));
+    }
+
+
+    /// Vouches for a store no input sequence produces: a code of its own, already entered.
+    struct ForeignCode;
+
+    impl FeasibilityOracle<Digicode> for ForeignCode {
+        fn check(
+            &self,
+            _state: DigicodeState,
+            phi: DigicodePhi,
+            _input: &DigicodeInputAlphabet,
+            _explored: &Configuration<Digicode>,
+        ) -> FeasibilityOf<Digicode> {
+            match phi {
+                DigicodePhi::Finish => Feasibility::Witness(DigicodeMemory {
+                    current_sequence: vec![7],
+                    valid_code: vec![7],
+                }),
+                _ => Feasibility::Unknown,
+            }
+        }
+    }
+
+    /// The spec as a SUT that can only be driven through its inputs.
+    struct Replaying(MachineRunner<Digicode>);
+
+    impl SutAdapter<Digicode> for Replaying {
+        fn apply(&mut self, input: &DigicodeInputAlphabet) -> Option<DigicodeOutputAlphabet> {
+            self.0.apply(input)
+        }
+    }
+
+    #[test]
+    fn a_witness_no_sequence_produces_requires_injection() {
+        let cases = SxMTester::generate_phi_coverage_tests_with_oracle::<Digicode>(&|_| Vec::new(), &ForeignCode);
+        let witnessed: Vec<_> = cases.iter().filter(|case| case.requires_injection::<Digicode>()).collect();
+        assert!(!witnessed.is_empty());
+        for case in witnessed {
+            let replayed = Executor::run_case::<Digicode, _>(&mut Replaying(MachineRunner::new()), case);
+            assert_eq!(replayed.verdict, Verdict::InjectionRequired);
+            let injected = Executor::run_case::<Digicode, _>(&mut MachineRunner::new(), case);
+            assert!(injected.injected);
+            assert_eq!(injected.verdict, Verdict::Pass);
+        }
+    }
+
+    /// The spec as a SUT that `disturb`s itself on every input the spec rejects.
+    struct Unsettled {
+        runner: MachineRunner<Digicode>,
+        disturb: fn(&mut MachineRunner<Digicode>),
+    }
+
+    impl SutAdapter<Digicode> for Unsettled {
+        fn apply(&mut self, input: &DigicodeInputAlphabet) -> Option<DigicodeOutputAlphabet> {
+            if self.runner.configuration().step(input).is_err() {
+                (self.disturb)(&mut self.runner);
+                return None;
+            }
+            self.runner.apply(input)
+        }
+
+        fn observe_state(&self) -> Option<DigicodeState> {
+            self.runner.observe_state()
+        }
+
+        fn observe_store(&self) -> Option<DigicodeMemory> {
+            self.runner.observe_store()
+        }
+
+        fn inject(&mut self, state: DigicodeState, store: &DigicodeMemory) -> bool {
+            self.runner.inject(state, store)
+        }
+    }
+
+    fn run_robustness(disturb: fn(&mut MachineRunner<Digicode>)) -> Vec<VerdictOf<Digicode>> {
+        let cases = SxMTester::generate_robustness_tests::<Digicode>();
+        assert!(!cases.is_empty());
+        cases
+            .iter()
+            .map(|case| {
+                let mut sut = Unsettled {
+                    runner: MachineRunner::new(),
+                    disturb,
+                };
+                Executor::run_case::<Digicode, _>(&mut sut, case).verdict
+            })
+            .collect()
+    }
+
+    #[test]
+    fn robustness_cases_check_the_configuration_left_by_a_rejected_input() {
+        assert!(run_robustness(|_| {}).iter().all(|verdict| *verdict == Verdict::Pass));
+
+        let forgot_the_code = run_robustness(|runner| {
+            let state = runner.state();
+            let mut store = runner.store().clone();
+            store.valid_code.clear();
+            *runner = MachineRunner::from_parts(state, store);
+        });
+        assert!(forgot_the_code.iter().all(|verdict| matches!(verdict, Verdict::StoreMismatch { .. })));
+
+        let started_over = run_robustness(MachineRunner::reset);
+        assert!(started_over.iter().any(|verdict| matches!(verdict, Verdict::StateMismatch { .. })));
+    }
+}
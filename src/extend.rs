@@ -0,0 +1,394 @@
+//! Machines defined as an extension of another, for product variants such as
+//! a digicode with an admin override, without copying the base spec.
+//!
+//! An `Extension` names its base machine and declares only what it adds:
+//! states, inputs, outputs, phis and memory, plus the transitions it adds or
+//! overrides. `Extended<E>` then implements `XMachine` with the combined
+//! tables, so every generator, diagram and executor works on the variant.
+//! Base symbols appear as `Ext::Base`, added ones as `Ext::Own`, and both
+//! render as the symbol they wrap.
+//!
+//! Base phis run on the base part of the memory and only accept base inputs;
+//! an added input routed to a base phi is rejected. Base state invariants
+//! are not carried over, their checks being written against the base memory.
+//! The other hooks of the base, such as `output_kind`, `labeling` or
+//! `tick_input`, carry over to base symbols; the extension classifies the
+//! symbols it adds through hooks of its own.
+
+use crate::labels::{Labeling, Symbol};
+use crate::{OutputKind, TransitionSpec, Visibility, XMachine};
+use std::any::{Any, TypeId};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Mutex, OnceLock};
+
+/// A symbol of an extended machine: one of the base, or one the extension adds.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Ext<Base, Own> {
+    Base(Base),
+    Own(Own),
+}
+
+impl<Base: fmt::Debug, Own: fmt::Debug> fmt::Debug for Ext<Base, Own> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ext::Base(symbol) => symbol.fmt(f),
+            Ext::Own(symbol) => symbol.fmt(f),
+        }
+    }
+}
+
+/// What an extension does with a table entry of the base machine.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Override<T> {
+    /// Keep the entry of the base machine.
+    Inherit,
+    /// Use this entry instead; `None` removes it.
+    Replace(Option<T>),
+}
+
+/// The states of extension `E`, base ones included.
+pub type ExtStateOf<E> = Ext<<<E as Extension>::Base as XMachine>::State, <E as Extension>::State>;
+/// The inputs of extension `E`, base ones included.
+pub type ExtInputOf<E> = Ext<<<E as Extension>::Base as XMachine>::Input, <E as Extension>::Input>;
+/// The outputs of extension `E`, base ones included.
+pub type ExtOutputOf<E> = Ext<<<E as Extension>::Base as XMachine>::Output, <E as Extension>::Output>;
+/// The phis of extension `E`, base ones included.
+pub type ExtPhiOf<E> = Ext<<<E as Extension>::Base as XMachine>::Phi, <E as Extension>::Phi>;
+/// The memory of extension `E`: the base memory and the added part.
+pub type ExtMemoryOf<E> = (<<E as Extension>::Base as XMachine>::Memory, <E as Extension>::Memory);
+
+/// What a machine adds to its `Base`. Use it through `Extended<Self>`.
+pub trait Extension: 'static {
+    type Base: XMachine;
+    type State: Copy + PartialEq + fmt::Debug + Send + Sync + 'static;
+    type Input: Clone + PartialEq + fmt::Debug + 'static;
+    type Output: Clone + PartialEq + fmt::Debug + 'static;
+    type Phi: Copy + PartialEq + fmt::Debug + Send + Sync + 'static;
+    /// Memory next to the base memory, `()` if none.
+    type Memory: Clone;
+
+    fn initial_store() -> Self::Memory;
+
+    /// Added states.
+    fn states() -> &'static [Self::State];
+
+    /// Added states that are final.
+    fn final_states() -> &'static [Self::State] {
+        &[]
+    }
+
+    /// Added inputs.
+    fn inputs() -> Cow<'static, [Self::Input]>;
+
+    /// Added outputs.
+    fn outputs() -> Cow<'static, [Self::Output]>;
+
+    /// Added phis.
+    fn phis() -> &'static [Self::Phi];
+
+    /// The phi `input` selects in `state`, over the whole machine. Inherited
+    /// entries come from the base for base states and inputs, and are
+    /// undefined otherwise.
+    fn get_phi_for_input(state: ExtStateOf<Self>, input: &ExtInputOf<Self>) -> Override<ExtPhiOf<Self>>;
+
+    /// The state `phi` leads to from `state`, inherited like `get_phi_for_input`.
+    fn next_state(state: ExtStateOf<Self>, phi: ExtPhiOf<Self>) -> Override<ExtStateOf<Self>>;
+
+    /// Executes an added phi on the whole memory.
+    #[allow(clippy::result_unit_err)]
+    fn execute_phi(
+        phi: Self::Phi,
+        store: &mut ExtMemoryOf<Self>,
+        input: &ExtInputOf<Self>,
+    ) -> Result<Option<ExtOutputOf<Self>>, ()>;
+
+    fn phi_guard_doc(_phi: Self::Phi) -> Option<&'static str> {
+        None
+    }
+
+    fn state_doc(_state: Self::State) -> Option<&'static str> {
+        None
+    }
+
+    /// Requirements of the transitions of added phis.
+    fn requirement_ids(_state: ExtStateOf<Self>, _phi: Self::Phi) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// `XMachine::input_visibility` of an added input.
+    fn input_visibility(_input: &Self::Input) -> Option<Visibility> {
+        None
+    }
+
+    /// `XMachine::output_visibility` of an added output.
+    fn output_visibility(_output: &Self::Output) -> Option<Visibility> {
+        None
+    }
+
+    /// `XMachine::output_kind` of an added output.
+    fn output_kind(_output: &Self::Output) -> OutputKind {
+        OutputKind::Regular
+    }
+
+    /// `XMachine::ignore_output` of an added phi.
+    fn ignore_output(_phi: Self::Phi) -> Option<ExtOutputOf<Self>> {
+        None
+    }
+}
+
+/// The machine extension `E` defines: `E::Base` with its additions and overrides.
+pub struct Extended<E>(PhantomData<E>);
+
+/// Tags telling apart the tables `Extended` builds once per extension.
+struct AllStates;
+struct InitialStates;
+struct FinalStates;
+struct AllPhis;
+
+/// A table built on first use and kept for the life of the program, since
+/// `XMachine` hands out `'static` slices and statics cannot be generic.
+fn table<Tag: 'static, E: 'static, V: Send + Sync + 'static>(build: impl FnOnce() -> Vec<V>) -> &'static [V] {
+    static TABLES: OnceLock<Mutex<HashMap<TypeId, &'static (dyn Any + Send + Sync)>>> = OnceLock::new();
+    let key = TypeId::of::<(Tag, E, V)>();
+    let mut tables = TABLES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    let table = *tables
+        .entry(key)
+        .or_insert_with(|| Box::leak(Box::new(build())) as &'static (dyn Any + Send + Sync));
+    table.downcast_ref::<Vec<V>>().expect("table keyed by its type")
+}
+
+impl<E> XMachine for Extended<E>
+where
+    E: Extension,
+    <E::Base as XMachine>::State: Send + Sync,
+    <E::Base as XMachine>::Phi: Send + Sync,
+{
+    type Input = ExtInputOf<E>;
+    type Output = ExtOutputOf<E>;
+    type State = ExtStateOf<E>;
+    type Memory = ExtMemoryOf<E>;
+    type Phi = ExtPhiOf<E>;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        match E::next_state(state, phi) {
+            Override::Replace(target) => target,
+            Override::Inherit => match (state, phi) {
+                (Ext::Base(state), Ext::Base(phi)) => E::Base::next_state(state, phi).map(Ext::Base),
+                _ => None,
+            },
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        table::<InitialStates, E, _>(|| E::Base::initial_states().iter().map(|&s| Ext::Base(s)).collect())
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        table::<FinalStates, E, _>(|| {
+            let base = E::Base::final_states().iter().map(|&s| Ext::Base(s));
+            base.chain(E::final_states().iter().map(|&s| Ext::Own(s))).collect()
+        })
+    }
+
+    fn initial_store() -> Self::Memory {
+        (E::Base::initial_store(), E::initial_store())
+    }
+
+    fn execute_phi(phi: Self::Phi, store: &mut Self::Memory, input: &Self::Input) -> Result<Option<Self::Output>, ()> {
+        match (phi, input) {
+            (Ext::Base(phi), Ext::Base(input)) => {
+                E::Base::execute_phi(phi, &mut store.0, input).map(|output| output.map(Ext::Base))
+            }
+            (Ext::Base(_), Ext::Own(_)) => Err(()),
+            (Ext::Own(phi), _) => E::execute_phi(phi, store, input),
+        }
+    }
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        let (base, own) = (E::Base::all_inputs(), E::inputs());
+        base.iter().cloned().map(Ext::Base).chain(own.iter().cloned().map(Ext::Own)).collect()
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        let (base, own) = (E::Base::all_outputs(), E::outputs());
+        base.iter().cloned().map(Ext::Base).chain(own.iter().cloned().map(Ext::Own)).collect()
+    }
+
+    fn all_states() -> &'static [Self::State] {
+        table::<AllStates, E, _>(|| {
+            let base = E::Base::all_states().iter().map(|&s| Ext::Base(s));
+            base.chain(E::states().iter().map(|&s| Ext::Own(s))).collect()
+        })
+    }
+
+    fn all_phis() -> &'static [Self::Phi] {
+        table::<AllPhis, E, _>(|| {
+            let base = E::Base::all_phis().iter().map(|&p| Ext::Base(p));
+            base.chain(E::phis().iter().map(|&p| Ext::Own(p))).collect()
+        })
+    }
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        match E::get_phi_for_input(state, input) {
+            Override::Replace(phi) => phi,
+            Override::Inherit => match (state, input) {
+                (Ext::Base(state), Ext::Base(input)) => E::Base::get_phi_for_input(state, input).map(Ext::Base),
+                _ => None,
+            },
+        }
+    }
+
+    /// Inherited entries keep the base's distinction between forbidden and
+    /// unspecified inputs.
+    fn transition_spec(state: Self::State, input: &Self::Input) -> TransitionSpec<Self::Phi> {
+        match (E::get_phi_for_input(state, input), state, input) {
+            (Override::Inherit, Ext::Base(state), Ext::Base(input)) => match E::Base::transition_spec(state, input) {
+                TransitionSpec::Defined(phi) => TransitionSpec::Defined(Ext::Base(phi)),
+                TransitionSpec::Forbidden => TransitionSpec::Forbidden,
+                TransitionSpec::Unspecified => TransitionSpec::Unspecified,
+            },
+            _ => match Self::get_phi_for_input(state, input) {
+                Some(phi) => TransitionSpec::Defined(phi),
+                None => TransitionSpec::Forbidden,
+            },
+        }
+    }
+
+    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {
+        match phi {
+            Ext::Base(phi) => E::Base::phi_guard_doc(phi),
+            Ext::Own(phi) => E::phi_guard_doc(phi),
+        }
+    }
+
+    fn state_doc(state: Self::State) -> Option<&'static str> {
+        match state {
+            Ext::Base(state) => E::Base::state_doc(state),
+            Ext::Own(state) => E::state_doc(state),
+        }
+    }
+
+    fn requirement_ids(state: Self::State, phi: Self::Phi) -> &'static [&'static str] {
+        match (state, phi) {
+            (Ext::Base(state), Ext::Base(phi)) => E::Base::requirement_ids(state, phi),
+            (_, Ext::Base(_)) => &[],
+            (_, Ext::Own(phi)) => E::requirement_ids(state, phi),
+        }
+    }
+
+    fn phi_kind(phi: Self::Phi) -> Self::Phi {
+        match phi {
+            Ext::Base(phi) => Ext::Base(E::Base::phi_kind(phi)),
+            Ext::Own(phi) => Ext::Own(phi),
+        }
+    }
+
+    /// Inherited transitions keep the base's cost; added ones cost 1.
+    fn transition_cost(state: Self::State, phi: Self::Phi) -> u32 {
+        match (state, phi) {
+            (Ext::Base(state), Ext::Base(phi)) => E::Base::transition_cost(state, phi),
+            _ => 1,
+        }
+    }
+
+    fn reset_input() -> Option<Self::Input> {
+        E::Base::reset_input().map(Ext::Base)
+    }
+
+    fn tick_input() -> Option<Self::Input> {
+        E::Base::tick_input().map(Ext::Base)
+    }
+
+    fn ignore_output(phi: Self::Phi) -> Option<Self::Output> {
+        match phi {
+            Ext::Base(phi) => E::Base::ignore_output(phi).map(Ext::Base),
+            Ext::Own(phi) => E::ignore_output(phi),
+        }
+    }
+
+    fn input_visibility(input: &Self::Input) -> Option<Visibility> {
+        match input {
+            Ext::Base(input) => E::Base::input_visibility(input),
+            Ext::Own(input) => E::input_visibility(input),
+        }
+    }
+
+    fn output_visibility(output: &Self::Output) -> Option<Visibility> {
+        match output {
+            Ext::Base(output) => E::Base::output_visibility(output),
+            Ext::Own(output) => E::output_visibility(output),
+        }
+    }
+
+    fn output_kind(output: &Self::Output) -> OutputKind {
+        match output {
+            Ext::Base(output) => E::Base::output_kind(output),
+            Ext::Own(output) => E::output_kind(output),
+        }
+    }
+
+    /// Base symbols keep the base's labels; added ones are labelled by
+    /// `Debug`, like every symbol when the base keeps `Labeling::Debug`.
+    fn labeling() -> Labeling<Self> {
+        if E::Base::labeling().is_debug() {
+            Labeling::Debug
+        } else {
+            Labeling::Custom(label::<E>)
+        }
+    }
+
+    fn active_states(state: Self::State) -> Vec<String> {
+        match state {
+            Ext::Base(state) => E::Base::active_states(state),
+            Ext::Own(_) => vec![Self::labeling().label(Symbol::State(state))],
+        }
+    }
+}
+
+fn label<E>(symbol: Symbol<'_, Extended<E>>) -> String
+where
+    E: Extension,
+    <E::Base as XMachine>::State: Send + Sync,
+    <E::Base as XMachine>::Phi: Send + Sync,
+{
+    let base = E::Base::labeling();
+    match symbol {
+        Symbol::State(Ext::Base(state)) => base.label(Symbol::State(state)),
+        Symbol::Input(Ext::Base(input)) => base.label(Symbol::Input(input)),
+        Symbol::Output(Ext::Base(output)) => base.label(Symbol::Output(output)),
+        Symbol::Phi(Ext::Base(phi)) => base.label(Symbol::Phi(phi)),
+        symbol => Labeling::Debug.label(symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::admin_digicode::{AdminDigicode, AdminInput, AdminPhi};
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, DigicodeState};
+    use crate::runner::MachineRunner;
+
+    #[test]
+    fn base_transitions_are_inherited_next_to_added_ones() {
+        assert_eq!(AdminDigicode::all_states().len(), Digicode::all_states().len());
+        assert_eq!(AdminDigicode::all_phis().len(), Digicode::all_phis().len() + 2);
+
+        let mut runner = MachineRunner::<AdminDigicode>::new();
+        let digit = runner.step(&Ext::Base(DigicodeInputAlphabet::Digit(4))).unwrap();
+        assert_eq!(digit.output, Some(Ext::Base(DigicodeOutputAlphabet::Digit(4))));
+        assert_eq!(runner.state(), Ext::Base(DigicodeState::Accepting));
+
+        let admin = runner.step(&Ext::Own(AdminInput::AdminKey)).unwrap();
+        assert_eq!(admin.phi, Ext::Own(AdminPhi::Override));
+        assert_eq!(admin.output, Some(Ext::Base(DigicodeOutputAlphabet::Open)));
+        assert_eq!(runner.store().1, 1);
+
+        runner.step(&Ext::Own(AdminInput::AdminKey)).unwrap();
+        assert_eq!(runner.state(), Ext::Base(DigicodeState::Ready));
+        assert!(runner.store().0.current_sequence.is_empty());
+        assert_eq!(format!("{:?}", runner.state()), "Ready");
+    }
+}
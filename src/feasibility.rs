@@ -0,0 +1,76 @@
+use crate::configuration::Configuration;
+use crate::XMachine;
+
+/// An oracle's answer for one explored configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Feasibility<Memory> {
+    /// No continuation of this configuration satisfies the guard; the search prunes it.
+    Infeasible,
+    /// The oracle cannot tell; the search explores as usual.
+    Unknown,
+    /// A store, reachable in the target state, on which the guard holds.
+    Witness(Memory),
+}
+
+/// `Feasibility` specialised to the memory of machine `T`.
+pub type FeasibilityOf<T> = Feasibility<<T as XMachine>::Memory>;
+
+/// Domain knowledge about guards, e.g. backed by an SMT solver, that steers
+/// the search for a memory satisfying a phi's guard.
+///
+/// Consulted by `SxMTester::generate_phi_coverage_tests_with_oracle` on every
+/// configuration the search explores while looking for a store on which
+/// `phi` succeeds in `state` for `input`.
+pub trait FeasibilityOracle<T: XMachine> {
+    fn check(
+        &self,
+        state: T::State,
+        phi: T::Phi,
+        input: &T::Input,
+        explored: &Configuration<T>,
+    ) -> FeasibilityOf<T>;
+}
+
+/// The oracle that knows nothing: the search is a plain bounded BFS.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NoOracle;
+
+impl<T: XMachine> FeasibilityOracle<T> for NoOracle {
+    fn check(&self, _: T::State, _: T::Phi, _: &T::Input, _: &Configuration<T>) -> FeasibilityOf<T> {
+        Feasibility::Unknown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet as In, DigicodePhi, DigicodeState};
+    use crate::mbt::PathConstraints;
+    use crate::search::path_to_satisfy_phi;
+
+    /// Knows that nothing satisfies any guard.
+    struct Pessimist;
+
+    impl FeasibilityOracle<Digicode> for Pessimist {
+        fn check(
+            &self,
+            _: DigicodeState,
+            _: DigicodePhi,
+            _: &In,
+            _: &Configuration<Digicode>,
+        ) -> FeasibilityOf<Digicode> {
+            Feasibility::Infeasible
+        }
+    }
+
+    #[test]
+    fn the_oracle_steers_the_guard_search() {
+        let search = |oracle: &dyn FeasibilityOracle<Digicode>| {
+            let (state, phi) = (DigicodeState::Accepting, DigicodePhi::Finish);
+            path_to_satisfy_phi::<Digicode>(state, phi, &In::OkEnter, oracle, &PathConstraints::new())
+        };
+        let (path, _) = search(&NoOracle).unwrap();
+        assert_eq!(path, [In::Digit(4), In::Digit(9), In::Digit(2)]);
+        assert!(search(&Pessimist).is_none());
+    }
+}
@@ -0,0 +1,493 @@
+//! Spec-quality checks gathered into one machine-readable list.
+//!
+//! `findings` runs the structural and bounded behavioural checks of a spec,
+//! well-formedness, determinism of the phis, reachability and
+//! distinguishability of the states, dead transitions and the use of the
+//! alphabets, and reports each problem as a `Finding` with a stable rule id
+//! and a severity. `to_json` and `to_sarif` render the list for CI and code
+//! review tooling; SARIF results carry logical locations only, as a spec has
+//! no source position of its own.
+//!
+//! Locations are labels under `T::labeling()`: a state, `state/phi`,
+//! `state/input` or an output, as the rule describes. Witnesses are input
+//! sequences from an initial configuration that exhibit the problem.
+
+use crate::analysis::{self, SpecIssue};
+use crate::configuration::Configuration;
+use crate::json;
+use crate::labels;
+use crate::mbt::PathConstraints;
+use crate::search::{self, Goal};
+use crate::store::Store;
+use crate::XMachine;
+use std::fmt::{self, Write};
+
+/// How much a finding matters, as the SARIF `level` of its result.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The spec is broken: generated suites or runs will misbehave.
+    Error,
+    /// Probably a mistake, though the spec still runs.
+    Warning,
+    /// Worth a look; often intended.
+    Note,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One problem found in a spec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+    /// Rule id, one of `RULES`.
+    pub id: &'static str,
+    pub severity: Severity,
+    pub location: String,
+    pub message: String,
+    /// Labels of an input sequence exhibiting the problem, when one was found.
+    pub witness: Option<Vec<String>>,
+}
+
+/// Every rule `findings` checks: id, severity and what it reports, with the
+/// form of its location.
+pub const RULES: &[(&str, Severity, &str)] = &[
+    ("undeclared-state", Severity::Error, "An initial or final state missing from all_states() (state)."),
+    ("undeclared-target", Severity::Error, "next_state leads to a state missing from all_states() (state/phi)."),
+    ("undeclared-phi", Severity::Error, "get_phi_for_input selects a phi missing from all_phis() (state/phi)."),
+    ("missing-target", Severity::Error, "A phi is selected but next_state is undefined for it (state/phi)."),
+    ("unselected-phi", Severity::Warning, "next_state defines a phi that no input selects (state/phi)."),
+    ("unreliable-reset", Severity::Warning, "reset_input does not lead to an initial state (state)."),
+    (
+        "nondeterministic-phi",
+        Severity::Error,
+        "execute_phi gave different results on the same input and memory (state/phi).",
+    ),
+    ("unreachable-state", Severity::Warning, "No transition path leads to the state from an initial one (state)."),
+    (
+        "indistinguishable-states",
+        Severity::Note,
+        "States selecting the same phis for every input sequence; only memory tells them apart (state).",
+    ),
+    ("dead-transition", Severity::Warning, "The guard of a phi never held within the explored depth (state/phi)."),
+    ("undeclared-output", Severity::Error, "An output produced by a step is missing from all_outputs() (output)."),
+    ("unused-output", Severity::Note, "A declared output no step produced within the explored depth (output)."),
+    ("unspecified-input", Severity::Note, "Inputs the spec leaves TransitionSpec::Unspecified (state/input)."),
+];
+
+/// Runs every check of `RULES` on `T`, exploring configurations reachable
+/// within `depth` inputs for the behavioural ones. Findings are grouped by
+/// rule in `RULES` order.
+pub fn findings<T: XMachine>(depth: usize) -> Vec<Finding>
+where
+    T::Memory: Store,
+{
+    let mut findings = Vec::new();
+    let at = |state: T::State, phi: T::Phi| format!("{}/{}", labels::state::<T>(state), labels::phi::<T>(phi));
+    let finding = |id: &'static str, location: String, message: String, witness: Option<Vec<String>>| {
+        let severity = RULES.iter().find(|(rule, _, _)| *rule == id).map_or(Severity::Error, |(_, s, _)| *s);
+        Finding {
+            id,
+            severity,
+            location,
+            message,
+            witness,
+        }
+    };
+    let witness = |path: Vec<T::Input>| Some(path.iter().map(labels::input::<T>).collect::<Vec<_>>());
+    let constraints = PathConstraints::new().max_len(depth);
+
+    for issue in analysis::well_formedness::<T>() {
+        findings.push(match issue {
+            SpecIssue::UndeclaredState(state) => finding(
+                "undeclared-state",
+                labels::state::<T>(state),
+                "initial or final state is missing from all_states()".to_string(),
+                None,
+            ),
+            SpecIssue::UndeclaredTarget { state, phi, target } => finding(
+                "undeclared-target",
+                at(state, phi),
+                format!("leads to {}, which is missing from all_states()", labels::state::<T>(target)),
+                None,
+            ),
+            SpecIssue::UndeclaredPhi { state, phi } => {
+                finding("undeclared-phi", at(state, phi), "phi is missing from all_phis()".to_string(), None)
+            }
+            SpecIssue::MissingTarget { state, phi } => {
+                finding("missing-target", at(state, phi), "phi is selected but has no target".to_string(), None)
+            }
+            SpecIssue::UnreachablePhi { state, phi } => {
+                finding("unselected-phi", at(state, phi), "no input selects the phi".to_string(), None)
+            }
+            SpecIssue::UnreliableReset { state } => finding(
+                "unreliable-reset",
+                labels::state::<T>(state),
+                "reset_input does not lead to an initial state".to_string(),
+                None,
+            ),
+        });
+    }
+
+    let mut nondeterministic: Vec<(Configuration<T>, T::Phi, T::Input)> = Vec::new();
+    analysis::for_each_reachable_step::<T>(depth, |step| {
+        if nondeterministic.iter().any(|(config, phi, _)| config.state == step.state && *phi == step.phi) {
+            return;
+        }
+        let mut again = step.store.clone();
+        let result = T::execute_phi(step.phi, &mut again, step.input);
+        if result != *step.result || (result.is_ok() && again != *step.next_store) {
+            let config = Configuration::new(step.state, step.store.clone());
+            nondeterministic.push((config, step.phi, step.input.clone()));
+        }
+    });
+    for (target, phi, input) in nondeterministic {
+        let reach = |config: &Configuration<T>| config.state == target.state && config.store == target.store;
+        let path = search::search::<T>(&Goal::Configuration(&reach), &constraints).map(|found| {
+            let mut path = found.path;
+            path.push(input.clone());
+            path
+        });
+        findings.push(finding(
+            "nondeterministic-phi",
+            at(target.state, phi),
+            format!("running the phi twice on {} gave different results", labels::input::<T>(&input)),
+            path.and_then(witness),
+        ));
+    }
+
+    let reachable = reachable_states::<T>();
+    for &state in T::all_states() {
+        if !reachable.contains(&state) {
+            findings.push(finding(
+                "unreachable-state",
+                labels::state::<T>(state),
+                "no transition path leads here from an initial state".to_string(),
+                None,
+            ));
+        }
+    }
+
+    for block in indistinguishable_states::<T>() {
+        let names: Vec<String> = block.iter().map(|&state| labels::state::<T>(state)).collect();
+        findings.push(finding(
+            "indistinguishable-states",
+            names[0].clone(),
+            format!("{} select the same phis for every input sequence", names.join(", ")),
+            None,
+        ));
+    }
+
+    // Transitions no input selects are already reported as unselected.
+    let inputs = T::all_inputs();
+    let selected = |state: T::State, phi: T::Phi| {
+        inputs.iter().any(|input| T::get_phi_for_input(state, input).map(T::phi_kind) == Some(phi))
+    };
+    for entry in analysis::d_reachability::<T>(depth).unreached() {
+        if reachable.contains(&entry.state) && selected(entry.state, entry.phi) {
+            findings.push(finding(
+                "dead-transition",
+                at(entry.state, entry.phi),
+                format!("the guard never held within {} input(s)", depth),
+                None,
+            ));
+        }
+    }
+
+    let outputs = analysis::output_completeness::<T>(depth);
+    for output in &outputs.undeclared {
+        let path = search::search::<T>(&Goal::Output(output.clone()), &constraints).map(|found| found.path);
+        findings.push(finding(
+            "undeclared-output",
+            labels::output::<T>(output),
+            "produced but missing from all_outputs()".to_string(),
+            path.and_then(witness),
+        ));
+    }
+    for output in &outputs.never_produced {
+        findings.push(finding(
+            "unused-output",
+            labels::output::<T>(output),
+            format!("never produced within {} input(s)", depth),
+            None,
+        ));
+    }
+
+    for (state, inputs) in analysis::unspecified_inputs::<T>() {
+        for input in &inputs {
+            findings.push(finding(
+                "unspecified-input",
+                format!("{}/{}", labels::state::<T>(state), labels::input::<T>(input)),
+                "the spec leaves the input unspecified".to_string(),
+                None,
+            ));
+        }
+    }
+    findings.sort_by_key(|finding| RULES.iter().position(|(id, _, _)| *id == finding.id));
+    findings
+}
+
+/// The findings as a JSON object with the machine name and one entry per finding.
+pub fn to_json(machine_name: &str, findings: &[Finding]) -> String {
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "  \"machine\": {},", json::quote(machine_name)).unwrap();
+    write!(out, "  \"findings\": [").unwrap();
+    for (idx, finding) in findings.iter().enumerate() {
+        out.push_str(if idx == 0 { "\n" } else { ",\n" });
+        write!(
+            out,
+            "    {{\"id\": \"{}\", \"severity\": \"{}\", \"location\": {}, \"message\": {}, \"witness\": {}}}",
+            finding.id,
+            finding.severity,
+            json::quote(&finding.location),
+            json::quote(&finding.message),
+            witness_json(finding)
+        )
+        .unwrap();
+    }
+    if !findings.is_empty() {
+        out.push_str("\n  ");
+    }
+    writeln!(out, "]").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+/// The findings as a SARIF 2.1.0 log of one run, with every rule of `RULES`
+/// declared on the `sxm` driver. Locations are logical, qualified by the
+/// machine name, e.g. `Door::Closed/OpenDoor`; witnesses are kept in the
+/// result properties.
+pub fn to_sarif(machine_name: &str, findings: &[Finding]) -> String {
+    let mut out = String::new();
+    writeln!(out, "{{").unwrap();
+    writeln!(out, "  \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\",").unwrap();
+    writeln!(out, "  \"version\": \"2.1.0\",").unwrap();
+    writeln!(out, "  \"runs\": [").unwrap();
+    writeln!(out, "    {{").unwrap();
+    writeln!(out, "      \"tool\": {{").unwrap();
+    writeln!(out, "        \"driver\": {{").unwrap();
+    writeln!(out, "          \"name\": \"sxm\",").unwrap();
+    writeln!(out, "          \"version\": \"{}\",", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(out, "          \"rules\": [").unwrap();
+    for (idx, (id, severity, description)) in RULES.iter().enumerate() {
+        write!(
+            out,
+            "            {{\"id\": \"{}\", \"shortDescription\": {{\"text\": {}}}, \
+             \"defaultConfiguration\": {{\"level\": \"{}\"}}}}",
+            id,
+            json::quote(description),
+            severity
+        )
+        .unwrap();
+        out.push_str(if idx + 1 < RULES.len() { ",\n" } else { "\n" });
+    }
+    writeln!(out, "          ]").unwrap();
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "      }},").unwrap();
+    write!(out, "      \"results\": [").unwrap();
+    for (idx, finding) in findings.iter().enumerate() {
+        out.push_str(if idx == 0 { "\n" } else { ",\n" });
+        let name = format!("{}::{}", machine_name, finding.location);
+        write!(
+            out,
+            "        {{\"ruleId\": \"{}\", \"level\": \"{}\", \"message\": {{\"text\": {}}}, \
+             \"locations\": [{{\"logicalLocations\": [{{\"fullyQualifiedName\": {}}}]}}], \
+             \"properties\": {{\"witness\": {}}}}}",
+            finding.id,
+            finding.severity,
+            json::quote(&format!("{}: {}", finding.location, finding.message)),
+            json::quote(&name),
+            witness_json(finding)
+        )
+        .unwrap();
+    }
+    if !findings.is_empty() {
+        out.push_str("\n      ");
+    }
+    writeln!(out, "]").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "  ]").unwrap();
+    writeln!(out, "}}").unwrap();
+    out
+}
+
+fn witness_json(finding: &Finding) -> String {
+    match &finding.witness {
+        Some(inputs) => {
+            let inputs: Vec<String> = inputs.iter().map(|input| json::quote(input)).collect();
+            format!("[{}]", inputs.join(", "))
+        }
+        None => "null".to_string(),
+    }
+}
+
+/// States the control graph leads to from an initial state, ignoring guards.
+fn reachable_states<T: XMachine>() -> Vec<T::State> {
+    let mut reachable: Vec<T::State> = T::initial_states().to_vec();
+    let mut idx = 0;
+    while let Some(&state) = reachable.get(idx) {
+        for &phi in T::all_phis() {
+            if let Some(target) = T::next_state(state, phi) {
+                if !reachable.contains(&target) {
+                    reachable.push(target);
+                }
+            }
+        }
+        idx += 1;
+    }
+    reachable
+}
+
+/// Groups of two or more states that no input sequence tells apart by the
+/// phis it selects and by finality, found by partition refinement.
+fn indistinguishable_states<T: XMachine>() -> Vec<Vec<T::State>> {
+    let states = T::all_states();
+    let inputs = T::all_inputs();
+
+    // Blocks start from finality and the phi each input selects, then split
+    // on the blocks the selected phis lead to until nothing changes.
+    let mut block: Vec<usize> = assign_blocks(states.iter().map(|&state| {
+        let row: Vec<Option<T::Phi>> =
+            inputs.iter().map(|input| T::get_phi_for_input(state, input).map(T::phi_kind)).collect();
+        (T::final_states().contains(&state), row)
+    }));
+    loop {
+        let refined = assign_blocks(states.iter().enumerate().map(|(idx, &state)| {
+            let row: Vec<Option<usize>> = inputs
+                .iter()
+                .map(|input| {
+                    let target = T::get_phi_for_input(state, input).and_then(|phi| T::next_state(state, phi))?;
+                    states.iter().position(|&s| s == target).map(|target| block[target])
+                })
+                .collect();
+            (block[idx], row)
+        }));
+        let count = |blocks: &[usize]| blocks.iter().max().map_or(0, |max| max + 1);
+        let done = count(&refined) == count(&block);
+        block = refined;
+        if done {
+            break;
+        }
+    }
+
+    let mut groups: Vec<Vec<T::State>> = Vec::new();
+    for id in 0..block.iter().max().map_or(0, |max| max + 1) {
+        let members: Vec<T::State> = states.iter().zip(&block).filter(|(_, &b)| b == id).map(|(&s, _)| s).collect();
+        if members.len() > 1 {
+            groups.push(members);
+        }
+    }
+    groups
+}
+
+/// Numbers equal keys alike, in order of first appearance.
+fn assign_blocks<K: PartialEq>(keys: impl Iterator<Item = K>) -> Vec<usize> {
+    let mut seen: Vec<K> = Vec::new();
+    keys.map(|key| match seen.iter().position(|k| *k == key) {
+        Some(idx) => idx,
+        None => {
+            seen.push(key);
+            seen.len() - 1
+        }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Door;
+    use std::borrow::Cow;
+
+    /// A switch with a state nothing leads to and an output it never emits.
+    struct Switch;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Position {
+        Off,
+        On,
+        Broken,
+    }
+
+    impl XMachine for Switch {
+        type Input = ();
+        type Output = &'static str;
+        type State = Position;
+        type Memory = ();
+        type Phi = Position;
+
+        fn next_state(state: Position, phi: Position) -> Option<Position> {
+            (Self::get_phi_for_input(state, &()) == Some(phi)).then_some(phi)
+        }
+
+        fn initial_states() -> &'static [Position] {
+            &[Position::Off]
+        }
+
+        fn final_states() -> &'static [Position] {
+            &[Position::Off]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(phi: Position, _store: &mut (), _input: &()) -> Result<Option<&'static str>, ()> {
+            Ok(Some(if phi == Position::On { "on" } else { "off" }))
+        }
+
+        fn all_inputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[()])
+        }
+
+        fn all_outputs() -> Cow<'static, [&'static str]> {
+            Cow::Borrowed(&["on", "off", "spark"])
+        }
+
+        fn all_states() -> &'static [Position] {
+            &[Position::Off, Position::On, Position::Broken]
+        }
+
+        fn all_phis() -> &'static [Position] {
+            &[Position::Off, Position::On]
+        }
+
+        fn get_phi_for_input(state: Position, _input: &()) -> Option<Position> {
+            match state {
+                Position::Off => Some(Position::On),
+                Position::On => Some(Position::Off),
+                Position::Broken => None,
+            }
+        }
+    }
+
+    #[test]
+    fn a_clean_spec_has_no_findings() {
+        assert_eq!(findings::<Door>(4), []);
+    }
+
+    #[test]
+    fn problems_are_reported_by_rule_and_rendered() {
+        let found = findings::<Switch>(4);
+        let rules: Vec<_> = found.iter().map(|f| (f.id, f.location.as_str())).collect();
+        assert_eq!(rules, [("unreachable-state", "Broken"), ("unused-output", "\"spark\"")]);
+        assert!(found.iter().all(|f| f.severity != Severity::Error));
+
+        let report = json::parse(&to_json("switch", &found)).unwrap();
+        assert_eq!(report.get("findings").and_then(json::Value::as_array).map(<[_]>::len), Some(2));
+        let sarif = json::parse(&to_sarif("switch", &found)).unwrap();
+        let runs = sarif.get("runs").and_then(json::Value::as_array).unwrap();
+        assert_eq!(runs[0].get("results").and_then(json::Value::as_array).map(<[_]>::len), Some(2));
+    }
+}
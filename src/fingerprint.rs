@@ -0,0 +1,108 @@
+use crate::XMachine;
+use std::fmt::{self, Debug};
+
+/// A stable hash of a machine's structure.
+///
+/// Covers the alphabets (Σ, Γ), the states (Q, I, T), the phi symbols (Φ),
+/// the next-state function F and the input dispatch table. Guard logic inside
+/// `execute_phi` is opaque and therefore not part of the fingerprint.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpecFingerprint(pub u64);
+
+impl SpecFingerprint {
+    /// Computes the fingerprint of `T`.
+    ///
+    /// Symbols are hashed through their `Debug` rendering with FNV-1a, so the
+    /// value is identical across runs, platforms and compiler versions.
+    pub fn of<T: XMachine>() -> Self {
+        let mut hasher = Fnv1a::new();
+
+        hasher.section("states", T::all_states());
+        hasher.section("initial", T::initial_states());
+        hasher.section("final", T::final_states());
+        hasher.section("phis", T::all_phis());
+        hasher.section("inputs", &T::all_inputs());
+        hasher.section("outputs", &T::all_outputs());
+
+        hasher.write_str("next_state");
+        for &state in T::all_states() {
+            for &phi in T::all_phis() {
+                hasher.write_debug(&(state, phi, T::next_state(state, phi)));
+            }
+        }
+
+        hasher.write_str("dispatch");
+        for &state in T::all_states() {
+            for input in T::all_inputs().iter() {
+                hasher.write_debug(&(state, input, T::get_phi_for_input(state, input)));
+            }
+        }
+
+        SpecFingerprint(hasher.finish())
+    }
+
+    /// Parses the hexadecimal form produced by `Display`.
+    pub fn from_hex(s: &str) -> Option<Self> {
+        u64::from_str_radix(s.trim(), 16).ok().map(SpecFingerprint)
+    }
+}
+
+impl fmt::Display for SpecFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// 64-bit FNV-1a, chosen over `DefaultHasher` because its output is specified.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write(&[0xff]);
+    }
+
+    fn write_debug<D: Debug>(&mut self, value: &D) {
+        self.write_str(&format!("{:?}", value));
+    }
+
+    fn section<D: Debug>(&mut self, name: &str, items: &[D]) {
+        self.write_str(name);
+        self.write_str(&items.len().to_string());
+        for item in items {
+            self.write_debug(item);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::admin_digicode::AdminDigicode;
+    use crate::examples::secure_door::{Digicode, Door};
+
+    #[test]
+    fn fingerprints_are_stable_and_tell_specs_apart() {
+        let digicode = SpecFingerprint::of::<Digicode>();
+        assert_eq!(digicode, SpecFingerprint::of::<Digicode>());
+        assert_ne!(digicode, SpecFingerprint::of::<Door>());
+        assert_ne!(digicode, SpecFingerprint::of::<AdminDigicode>());
+        assert_eq!(SpecFingerprint::from_hex(&digicode.to_string()), Some(digicode));
+        assert_eq!(digicode.to_string().len(), 16);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::mbt::{Expectation, TestCaseOf};
+use crate::XMachine;
+use std::fmt::Write;
+
+/// Phrasing of an input step, e.g. "the user presses OK".
+pub type InputPhrase<'a, T> = Box<dyn Fn(&<T as XMachine>::Input) -> String + 'a>;
+
+/// Phrasing of the expected output.
+pub type OutputPhrase<'a, T> = Box<dyn Fn(&Expectation<<T as XMachine>::Output>) -> String + 'a>;
+
+/// Renders test cases as a Gherkin feature file, one scenario per case.
+///
+/// The setup sequence becomes the `Given` steps, the test input the `When`
+/// step and the expected output the `Then` step. The verification sequence
+/// follows as a second `When` with `And` steps, closed by a `Then` that the
+/// machine keeps responding as specified. Requirements are emitted as
+/// scenario tags.
+pub struct GherkinExporter<'a, T: XMachine> {
+    feature: String,
+    input_phrase: InputPhrase<'a, T>,
+    output_phrase: OutputPhrase<'a, T>,
+    verification_phrase: String,
+}
+
+impl<'a, T: XMachine> GherkinExporter<'a, T> {
+    /// Uses the `Debug` form of each symbol in the steps.
+    pub fn new(feature: &str) -> Self {
+        Self {
+            feature: feature.to_string(),
+            input_phrase: Box::new(|input| format!("the input {:?} is applied", input)),
+            output_phrase: Box::new(|expectation| match expectation {
+                Expectation::Exact(output) => format!("the output {:?} is produced", output),
+                Expectation::Predicate(label, _) => format!("an output that {} is produced", label),
+                Expectation::AnyOf(outputs) => format!("one of the outputs {:?} is produced", outputs),
+                Expectation::None => "no output is produced".to_string(),
+            }),
+            verification_phrase: "the outputs match the specification".to_string(),
+        }
+    }
+
+    /// Overrides the wording of input steps.
+    pub fn with_input_phrase(mut self, phrase: impl Fn(&T::Input) -> String + 'a) -> Self {
+        self.input_phrase = Box::new(phrase);
+        self
+    }
+
+    /// Overrides the wording of the expected-output step.
+    pub fn with_output_phrase(mut self, phrase: impl Fn(&Expectation<T::Output>) -> String + 'a) -> Self {
+        self.output_phrase = Box::new(phrase);
+        self
+    }
+
+    /// Overrides the wording of the `Then` step closing the verification
+    /// sequence.
+    pub fn with_verification_phrase(mut self, phrase: &str) -> Self {
+        self.verification_phrase = phrase.to_string();
+        self
+    }
+
+    pub fn export(&self, tests: &[TestCaseOf<T>]) -> String {
+        let mut output = String::new();
+        writeln!(output, "Feature: {}", single_line(&self.feature)).unwrap();
+
+        for test in tests {
+            writeln!(output).unwrap();
+            if !test.requirements.is_empty() {
+                let tags: Vec<String> = test.requirements.iter().map(|id| format!("@{}", tag(id))).collect();
+                writeln!(output, "  {}", tags.join(" ")).unwrap();
+            }
+            writeln!(output, "  Scenario: {}", single_line(&test.name)).unwrap();
+            writeln!(output, "    Given the machine is in its initial configuration").unwrap();
+            for input in &test.setup_sequence {
+                writeln!(output, "    And {}", (self.input_phrase)(input)).unwrap();
+            }
+            writeln!(output, "    When {}", (self.input_phrase)(&test.test_input)).unwrap();
+            writeln!(output, "    Then {}", (self.output_phrase)(&test.expected_output)).unwrap();
+            for (idx, input) in test.verification_sequence.iter().enumerate() {
+                let keyword = if idx == 0 { "When" } else { "And" };
+                writeln!(output, "    {} {}", keyword, (self.input_phrase)(input)).unwrap();
+            }
+            if !test.verification_sequence.is_empty() {
+                writeln!(output, "    Then {}", single_line(&self.verification_phrase)).unwrap();
+            }
+        }
+        output
+    }
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\r', '\n'], " ")
+}
+
+/// Gherkin tags end at whitespace.
+fn tag(id: &str) -> String {
+    id.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet as In, DigicodeOutputAlphabet as Out};
+    use crate::mbt::TestCase;
+
+    #[test]
+    fn a_case_becomes_a_scenario() {
+        let case = TestCase {
+            name: "unlock\nwith the code".to_string(),
+            setup_sequence: vec![In::Digit(4), In::Digit(9), In::Digit(2)],
+            test_input: In::OkEnter,
+            expected_output: Expectation::Exact(Out::Open),
+            verification_sequence: vec![In::DoorCloses],
+            requirements: vec!["REQ 7"],
+            setup_configuration: None,
+        };
+        let feature = GherkinExporter::<Digicode>::new("Keypad")
+            .with_input_phrase(|input| format!("the user enters {:?}", input))
+            .export(&[case]);
+        assert_eq!(
+            feature,
+            "Feature: Keypad\n\
+             \n  @REQ_7\n  Scenario: unlock with the code\n    \
+             Given the machine is in its initial configuration\n    \
+             And the user enters Digit(4)\n    And the user enters Digit(9)\n    And the user enters Digit(2)\n    \
+             When the user enters OkEnter\n    Then the output Open is produced\n    \
+             When the user enters DoorCloses\n    Then the outputs match the specification\n"
+        );
+    }
+}
@@ -1,8 +1,485 @@
-use crate::XMachine;
+use crate::analysis::{for_each_reachable_step, unspecified_inputs};
+use crate::dynamic::{DynMachine, DynMachineError};
+use crate::labels;
+use crate::multicast::MulticastNetwork;
+use crate::network::PortSet;
+use crate::occupancy::Occupancy;
+use crate::store::Store;
+use crate::{OutputKind, Visibility, XMachine};
 use std::fmt::{Debug, Write};
+use std::io;
 use std::convert::TryFrom;
 
 pub fn generate_dot<T: XMachine>(machine_name: &str) -> String {
+    generate_dot_view::<T>(machine_name, &DotView::new())
+}
+
+/// Streams the diagram of `generate_dot` to `output`.
+pub fn write_dot<T: XMachine, W: io::Write>(output: &mut W, machine_name: &str) -> io::Result<()> {
+    write_dot_view::<T, W>(output, machine_name, &DotView::new())
+}
+
+/// Selects the part of a machine diagram to render.
+///
+/// Filters combine: an edge is drawn only if it passes all of them. States
+/// are drawn only if a remaining edge touches them, or they are the focus.
+#[derive(Clone, Debug)]
+pub struct DotView<State, Phi> {
+    focus: Option<(State, usize)>,
+    phis: Vec<Phi>,
+    edge_filter: Option<fn(Phi) -> bool>,
+    cluster: Option<fn(State) -> &'static str>,
+    input_labels: bool,
+}
+
+/// `DotView` specialised to the symbols of machine `T`.
+pub type DotViewOf<T> = DotView<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+impl<State, Phi> DotView<State, Phi> {
+    /// The whole machine.
+    pub fn new() -> Self {
+        Self {
+            focus: None,
+            phis: Vec::new(),
+            edge_filter: None,
+            cluster: None,
+            input_labels: false,
+        }
+    }
+
+    /// Keeps the transitions reachable from `state` within `hops` steps.
+    pub fn around(mut self, state: State, hops: usize) -> Self {
+        self.focus = Some((state, hops));
+        self
+    }
+
+    /// Keeps the transitions of `phi`. Repeat to keep several phis.
+    pub fn phi(mut self, phi: Phi) -> Self {
+        self.phis.push(phi);
+        self
+    }
+
+    /// Keeps the transitions whose phi satisfies `filter`, e.g. the
+    /// error-handling phis of a spec.
+    pub fn edges(mut self, filter: fn(Phi) -> bool) -> Self {
+        self.edge_filter = Some(filter);
+        self
+    }
+
+    /// Boxes the drawn states `cluster` maps to the same name, when there are
+    /// several, e.g. the values of a parameterized state with
+    /// `state_space::StateSpace::family`. Does not filter anything.
+    pub fn clusters(mut self, cluster: fn(State) -> &'static str) -> Self {
+        self.cluster = Some(cluster);
+        self
+    }
+
+    /// Labels each edge with the inputs selecting it before its phi. Inputs
+    /// sharing a transition share one edge, with numbered variants collapsed
+    /// into ranges, as in `Digit(0..=9) / InputDigit`. Phis no input selects
+    /// directly, such as those a guard picks, keep the bare phi label.
+    pub fn input_labels(mut self) -> Self {
+        self.input_labels = true;
+        self
+    }
+
+    fn is_whole(&self) -> bool {
+        self.focus.is_none() && self.phis.is_empty() && self.edge_filter.is_none()
+    }
+}
+
+impl<State, Phi> Default for DotView<State, Phi> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders the part of the diagram of `generate_dot` selected by `view`.
+pub fn generate_dot_view<T: XMachine>(machine_name: &str, view: &DotViewOf<T>) -> String {
+    let mut output = Vec::new();
+    write_dot_view::<T, _>(&mut output, machine_name, view).expect("writing to a Vec cannot fail");
+    String::from_utf8(output).expect("DOT output is UTF-8")
+}
+
+/// Streams the diagram of `generate_dot_view` to `output`.
+pub fn write_dot_view<T: XMachine, W: io::Write>(
+    output: &mut W,
+    machine_name: &str,
+    view: &DotViewOf<T>,
+) -> io::Result<()> {
+    let edges = view_edges::<T>(view);
+    let visible = |state: &T::State| {
+        view.is_whole()
+            || view.focus.is_some_and(|(focus, _)| focus == *state)
+            || edges.iter().any(|(source, _, target)| source == state || target == state)
+    };
+
+    writeln!(output, "digraph {} {{", machine_name)?;
+    writeln!(output, "    rankdir=LR;")?;
+    writeln!(output, "    node [shape=circle];")?;
+    writeln!(output, "    // Initial States")?;
+    for state in T::initial_states().iter().filter(|s| visible(s)) {
+        writeln!(output, "    \"_start_{:?}\" [style=invisible, label=\"\", width=0, height=0];", state)?;
+        writeln!(output, "    \"_start_{:?}\" -> \"{:?}\" [penwidth=2.0];", state, state)?;
+    }
+
+    writeln!(output, "    // Terminal States")?;
+    for state in T::final_states().iter().filter(|s| visible(s)) {
+        writeln!(output, "    \"{:?}\" [shape=doublecircle];", state)?;
+    }
+
+    writeln!(output, "    // State invariants")?;
+    for &state in T::all_states().iter().filter(|s| visible(s)) {
+        let invariants = T::state_invariants(state);
+        let mut label = labels::state::<T>(state);
+        if invariants.is_empty() && label == format!("{:?}", state) {
+            continue;
+        }
+        if !invariants.is_empty() {
+            label.push_str("\\n");
+        }
+        for invariant in &invariants {
+            label.push_str(&format!("\\n{}", invariant.doc));
+        }
+        writeln!(output, "    \"{:?}\" [label=\"{}\"];", state, escape_label(&label))?;
+    }
+
+    writeln!(output, "    // Partially specified states")?;
+    for (state, inputs) in unspecified_inputs::<T>().into_iter().filter(|(s, _)| visible(s)) {
+        let inputs: Vec<String> = inputs.iter().map(labels::input::<T>).collect();
+        writeln!(
+            output,
+            "    \"{:?}\" [style=dashed, tooltip=\"unspecified: {}\"];",
+            state,
+            escape_label(&inputs.join(", "))
+        )?;
+    }
+
+    if !view.is_whole() && edges.is_empty() {
+        if let Some((focus, _)) = view.focus {
+            writeln!(output, "    \"{:?}\";", focus)?;
+        }
+    }
+
+    if let Some(cluster) = view.cluster {
+        writeln!(output, "    // State clusters")?;
+        let mut clusters: Vec<(&str, Vec<T::State>)> = Vec::new();
+        for &state in T::all_states().iter().filter(|s| visible(s)) {
+            let name = cluster(state);
+            match clusters.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, states)) => states.push(state),
+                None => clusters.push((name, vec![state])),
+            }
+        }
+        for (name, states) in clusters.iter().filter(|(_, states)| states.len() > 1) {
+            writeln!(output, "    subgraph \"cluster_{}\" {{", escape_label(name))?;
+            writeln!(output, "        label=\"{}\";", escape_label(name))?;
+            for state in states {
+                writeln!(output, "        \"{:?}\";", state)?;
+            }
+            writeln!(output, "    }}")?;
+        }
+    }
+
+    writeln!(output, "    // Transitions")?;
+    let inputs = T::all_inputs();
+    for (source, phi, target) in &edges {
+        let mut label = labels::phi::<T>(*phi);
+        if view.input_labels {
+            let selecting: Vec<String> = inputs
+                .iter()
+                .filter(|input| T::get_phi_for_input(*source, input).map(T::phi_kind) == Some(*phi))
+                .map(labels::input::<T>)
+                .collect();
+            if !selecting.is_empty() {
+                label = format!("{} / {}", compact_inputs(&selecting), label);
+            }
+        }
+        writeln!(
+            output,
+            "    \"{:?}\" -> \"{:?}\" [label=\"{}\"];",
+            source, target, escape_label(&label)
+        )?;
+    }
+
+    writeln!(output, "}}")
+}
+
+/// Joins input names, merging variants that differ only in an integer
+/// parameter: `Digit(0)` to `Digit(9)` become `Digit(0..=9)`, and gaps give
+/// `Digit(0..=3, 5..=9)`. Other names are kept as they are, in order.
+fn compact_inputs(inputs: &[String]) -> String {
+    let numbered = |input: &str| -> Option<(String, i64)> {
+        let (name, rest) = input.split_once('(')?;
+        let value = rest.strip_suffix(')')?.parse().ok()?;
+        Some((name.to_string(), value))
+    };
+
+    let mut groups: Vec<(String, Vec<i64>)> = Vec::new();
+    let mut parts: Vec<Result<usize, &str>> = Vec::new();
+    for input in inputs {
+        match numbered(input) {
+            Some((name, value)) => match groups.iter().position(|(n, _)| *n == name) {
+                Some(idx) => groups[idx].1.push(value),
+                None => {
+                    parts.push(Ok(groups.len()));
+                    groups.push((name, vec![value]));
+                }
+            },
+            None => parts.push(Err(input)),
+        }
+    }
+
+    let render_group = |(name, values): &(String, Vec<i64>)| {
+        let mut values = values.clone();
+        values.sort_unstable();
+        let mut ranges: Vec<String> = Vec::new();
+        let mut start = 0;
+        while start < values.len() {
+            let mut end = start;
+            while end + 1 < values.len() && values[end + 1] == values[end] + 1 {
+                end += 1;
+            }
+            ranges.push(match end - start {
+                0 => values[start].to_string(),
+                1 => format!("{}, {}", values[start], values[end]),
+                _ => format!("{}..={}", values[start], values[end]),
+            });
+            start = end + 1;
+        }
+        format!("{}({})", name, ranges.join(", "))
+    };
+    parts
+        .into_iter()
+        .map(|part| match part {
+            Ok(group) => render_group(&groups[group]),
+            Err(input) => input.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The `(source, phi, target)` transitions `view` keeps, in diagram order.
+fn view_edges<T: XMachine>(view: &DotViewOf<T>) -> Vec<(T::State, T::Phi, T::State)> {
+    // Hop distance from the focus state, by breadth-first search over the state graph.
+    let distances = view.focus.map(|(focus, _)| {
+        let mut distances = vec![(focus, 0)];
+        let mut next = 0;
+        while let Some(&(state, distance)) = distances.get(next) {
+            next += 1;
+            for &phi in T::all_phis() {
+                if let Some(target) = T::next_state(state, phi) {
+                    if !distances.iter().any(|(s, _)| *s == target) {
+                        distances.push((target, distance + 1));
+                    }
+                }
+            }
+        }
+        distances
+    });
+
+    let mut edges = Vec::new();
+    for &source in T::all_states() {
+        if let (Some(distances), Some((_, hops))) = (&distances, view.focus) {
+            if !distances.iter().any(|&(s, d)| s == source && d < hops) {
+                continue;
+            }
+        }
+        for &phi in T::all_phis() {
+            if !view.phis.is_empty() && !view.phis.contains(&phi) {
+                continue;
+            }
+            if view.edge_filter.is_some_and(|filter| !filter(phi)) {
+                continue;
+            }
+            if let Some(target) = T::next_state(source, phi) {
+                edges.push((source, phi, target));
+            }
+        }
+    }
+    edges
+}
+
+/// Renders the reachable configuration graph: nodes are (state, abstracted memory)
+/// pairs, edges are phi applications.
+///
+/// Every concrete configuration reachable within `depth` inputs is explored;
+/// `abstraction` then maps each store to the data that matters for the diagram
+/// (e.g. the number of digits entered), and configurations with the same state
+/// and abstraction are drawn as one node. Edges along which an
+/// `OutputKind::Error` output is emitted are drawn in red.
+pub fn generate_configuration_dot<T, A>(
+    machine_name: &str,
+    abstraction: impl Fn(&T::Memory) -> A,
+    depth: usize,
+) -> String
+where
+    T: XMachine,
+    T::Memory: Store,
+    A: PartialEq + Debug,
+{
+    let mut output = Vec::new();
+    write_configuration_dot::<T, A, _>(&mut output, machine_name, abstraction, depth)
+        .expect("writing to a Vec cannot fail");
+    String::from_utf8(output).expect("DOT output is UTF-8")
+}
+
+/// Streams the diagram of `generate_configuration_dot` to `output`.
+///
+/// Nodes and edges are still collected before writing, but the rendered text
+/// is never held in memory, which dominates for large graphs.
+pub fn write_configuration_dot<T, A, W>(
+    output: &mut W,
+    machine_name: &str,
+    abstraction: impl Fn(&T::Memory) -> A,
+    depth: usize,
+) -> io::Result<()>
+where
+    T: XMachine,
+    T::Memory: Store,
+    A: PartialEq + Debug,
+    W: io::Write,
+{
+    let mut nodes: Vec<(T::State, A)> = Vec::new();
+    // The flag marks edges along which some step emitted an error output.
+    let mut edges: Vec<(usize, T::Phi, usize, bool)> = Vec::new();
+
+    let intern = |nodes: &mut Vec<(T::State, A)>, state: T::State, abstract_mem: A| -> usize {
+        match nodes.iter().position(|(s, a)| *s == state && *a == abstract_mem) {
+            Some(idx) => idx,
+            None => {
+                nodes.push((state, abstract_mem));
+                nodes.len() - 1
+            }
+        }
+    };
+
+    let initial: Vec<usize> = T::initial_states()
+        .iter()
+        .map(|&start| intern(&mut nodes, start, abstraction(&T::initial_store())))
+        .collect();
+
+    for_each_reachable_step::<T>(depth, |step| {
+        let Some(next_state) = T::next_state(step.state, step.phi) else {
+            return;
+        };
+        let Ok(emitted) = step.result else {
+            return;
+        };
+        let error = emitted.as_ref().is_some_and(|output| T::output_kind(output) == OutputKind::Error);
+        let from = intern(&mut nodes, step.state, abstraction(step.store));
+        let to = intern(&mut nodes, next_state, abstraction(step.next_store));
+        match edges.iter_mut().find(|(f, p, t, _)| (*f, *p, *t) == (from, step.phi, to)) {
+            Some(edge) => edge.3 |= error,
+            None => edges.push((from, step.phi, to, error)),
+        }
+    });
+
+    writeln!(output, "digraph {} {{", machine_name)?;
+    writeln!(output, "    rankdir=LR;")?;
+    writeln!(output, "    node [shape=box, style=rounded];")?;
+    writeln!(output, "    // Configurations")?;
+    for (idx, (state, abstract_mem)) in nodes.iter().enumerate() {
+        let label = escape_label(&format!("{}\\n{:?}", labels::state::<T>(*state), abstract_mem));
+        writeln!(output, "    \"c{}\" [label=\"{}\"];", idx, label)?;
+    }
+
+    writeln!(output, "    // Initial Configurations")?;
+    for idx in initial {
+        writeln!(output, "    \"_start_c{}\" [style=invisible, label=\"\", width=0, height=0];", idx)?;
+        writeln!(output, "    \"_start_c{}\" -> \"c{}\" [penwidth=2.0];", idx, idx)?;
+    }
+
+    writeln!(output, "    // Transitions")?;
+    for (from, phi, to, error) in edges {
+        let style = if error { ", color=red, fontcolor=red" } else { "" };
+        let label = escape_label(&labels::phi::<T>(phi));
+        writeln!(output, "    \"c{}\" -> \"c{}\" [label=\"{}\"{}];", from, to, label, style)?;
+    }
+
+    writeln!(output, "}}")
+}
+
+/// Renders the union of two versions of a machine: the baseline read from
+/// `old_model_json`, as written by `DynMachine::to_json`, and `new_model`,
+/// e.g. `DynMachine::from_machine`. States and transitions only in the
+/// baseline are drawn dashed and red, those only in the new version green,
+/// and the rest as in `generate_dot`. Transitions are drawn per source,
+/// phi and target, so an input moved to another phi shows as one edge
+/// removed and one added.
+pub fn generate_diff_dot(old_model_json: &str, new_model: &DynMachine) -> Result<String, DynMachineError> {
+    let old_model = DynMachine::from_json(old_model_json)?;
+    let edges = |model: &DynMachine| {
+        let mut edges: Vec<(String, String, String)> = Vec::new();
+        for t in &model.transitions {
+            let edge = (t.from.clone(), t.phi.clone(), t.to.clone());
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+        edges
+    };
+    let (old_edges, new_edges) = (edges(&old_model), edges(new_model));
+    let style = |in_old: bool, in_new: bool| match (in_old, in_new) {
+        (true, false) => ", style=dashed, color=red, fontcolor=red",
+        (false, true) => ", color=green, fontcolor=green",
+        _ => "",
+    };
+
+    let mut output = String::new();
+    writeln!(output, "digraph {} {{", new_model.name).unwrap();
+    writeln!(output, "    rankdir=LR;").unwrap();
+    writeln!(output, "    node [shape=circle];").unwrap();
+    writeln!(output, "    // States").unwrap();
+    let removed_states = old_model.states.iter().filter(|s| !new_model.states.contains(s));
+    for state in new_model.states.iter().chain(removed_states) {
+        let final_state = new_model.final_states.contains(state) || old_model.final_states.contains(state);
+        writeln!(
+            output,
+            "    \"{}\" [shape={}{}];",
+            escape_label(state),
+            if final_state { "doublecircle" } else { "circle" },
+            style(old_model.states.contains(state), new_model.states.contains(state))
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "    // Initial States").unwrap();
+    let removed_initial = old_model.initial_states.iter().filter(|s| !new_model.initial_states.contains(s));
+    for state in new_model.initial_states.iter().chain(removed_initial) {
+        let style = style(old_model.initial_states.contains(state), new_model.initial_states.contains(state));
+        let state = escape_label(state);
+        writeln!(output, "    \"_start_{}\" [style=invisible, label=\"\", width=0, height=0];", state).unwrap();
+        writeln!(output, "    \"_start_{}\" -> \"{}\" [penwidth=2.0{}];", state, state, style).unwrap();
+    }
+
+    writeln!(output, "    // Transitions").unwrap();
+    let removed_edges = old_edges.iter().filter(|edge| !new_edges.contains(edge));
+    for edge @ (from, phi, to) in new_edges.iter().chain(removed_edges) {
+        writeln!(
+            output,
+            "    \"{}\" -> \"{}\" [label=\"{}\"{}];",
+            escape_label(from),
+            escape_label(to),
+            escape_label(phi),
+            style(old_edges.contains(edge), new_edges.contains(edge))
+        )
+        .unwrap();
+    }
+
+    writeln!(output, "}}").unwrap();
+    Ok(output)
+}
+
+/// Renders the diagram of `generate_dot` with the statistics of `occupancy`
+/// overlaid: states are labelled with their occupancy, edges with their
+/// count and drawn thicker the more often they fire. States and transitions
+/// never taken are drawn dotted and grey.
+pub fn generate_occupancy_dot<T: XMachine>(machine_name: &str, occupancy: &Occupancy<T>) -> String {
+    let transitions = occupancy.transitions();
+    let busiest = transitions.iter().map(|t| t.count).max().unwrap_or(0).max(1);
+    let untouched = ", style=dotted, color=gray, fontcolor=gray";
+
     let mut output = String::new();
     writeln!(output, "digraph {} {{", machine_name).unwrap();
     writeln!(output, "    rankdir=LR;").unwrap();
@@ -13,28 +490,46 @@ pub fn generate_dot<T: XMachine>(machine_name: &str) -> String {
         writeln!(output, "    \"_start_{:?}\" -> \"{:?}\" [penwidth=2.0];", state, state).unwrap();
     }
 
-    writeln!(output, "    // Terminal States").unwrap();
-    for state in T::final_states() {
-        writeln!(output, "    \"{:?}\" [shape=doublecircle];", state).unwrap();
+    writeln!(output, "    // State occupancy").unwrap();
+    for &state in T::all_states() {
+        let label = format!("{}\\n{:.1}%", labels::state::<T>(state), occupancy.occupancy(state) * 100.0);
+        writeln!(
+            output,
+            "    \"{:?}\" [label=\"{}\"{}{}];",
+            state,
+            escape_label(&label),
+            if T::final_states().contains(&state) { ", shape=doublecircle" } else { "" },
+            if occupancy.entry_probability(state) == 0.0 { untouched } else { "" }
+        )
+        .unwrap();
     }
 
-    writeln!(output, "    // Transitions").unwrap();
-    for &source in T::all_states() {
-        for &phi in T::all_phis() {
-            if let Some(target) = T::next_state(source, phi) {
-                writeln!(
-                    output,
-                    "    \"{:?}\" -> \"{:?}\" [label=\"{:?}\"];",
-                    source, target, phi
-                ).unwrap();
-            }
-        }
+    writeln!(output, "    // Transition frequencies").unwrap();
+    for t in &transitions {
+        let label = format!("{} ({})", labels::phi::<T>(t.phi), t.count);
+        let penwidth = 0.5 + 4.5 * t.count as f64 / busiest as f64;
+        writeln!(
+            output,
+            "    \"{:?}\" -> \"{:?}\" [label=\"{}\", penwidth={:.1}{}];",
+            t.from,
+            t.to,
+            escape_label(&label),
+            penwidth,
+            if t.count == 0 { untouched } else { "" }
+        )
+        .unwrap();
     }
 
     writeln!(output, "}}").unwrap();
     output
 }
 
+/// Escapes double quotes so `Debug` renderings can sit inside a quoted DOT label.
+/// `\n` sequences are left intact as DOT line breaks.
+fn escape_label(label: &str) -> String {
+    label.replace('"', "\\\"")
+}
+
 pub fn generate_generic_context_dot<MA, MB>() -> String
 where
     MA: XMachine,
@@ -47,26 +542,68 @@ where
     MB::Input: Debug + PartialEq + Clone,
 {
     let mut output = String::new();
+    // Shared outputs are consumed internally but still reach the environment.
+    let mut shared_a_outputs = Vec::new();
     let mut internal_a_outputs = Vec::new();
     let mut internal_b_inputs = Vec::new();
 
-    for out in MA::all_outputs() {
-        if let Ok(derived_input) = MB::Input::try_from(out.clone()) {
-            internal_a_outputs.push(out.clone());
-            internal_b_inputs.push(derived_input);
+    // Explicit `Visibility` hooks take precedence; otherwise a symbol is internal
+    // when an adapter accepts it.
+    for out in MA::all_outputs().iter() {
+        let converted = MB::Input::try_from(out.clone()).ok();
+        match MA::output_visibility(out) {
+            Some(Visibility::External) => {}
+            Some(Visibility::Shared) => {
+                shared_a_outputs.push(out.clone());
+                internal_b_inputs.extend(converted);
+            }
+            Some(Visibility::Internal) => {
+                internal_a_outputs.push(out.clone());
+                internal_b_inputs.extend(converted);
+            }
+            None => {
+                if let Some(derived_input) = converted {
+                    internal_a_outputs.push(out.clone());
+                    internal_b_inputs.push(derived_input);
+                }
+            }
         }
     }
 
+    let mut shared_b_outputs = Vec::new();
     let mut internal_b_outputs = Vec::new();
     let mut internal_a_inputs = Vec::new();
 
-    for out in MB::all_outputs() {
-        if let Ok(derived_input) = MA::Input::try_from(out.clone()) {
-            internal_b_outputs.push(out.clone());
-            internal_a_inputs.push(derived_input);
+    for out in MB::all_outputs().iter() {
+        let converted = MA::Input::try_from(out.clone()).ok();
+        match MB::output_visibility(out) {
+            Some(Visibility::External) => {}
+            Some(Visibility::Shared) => {
+                shared_b_outputs.push(out.clone());
+                internal_a_inputs.extend(converted);
+            }
+            Some(Visibility::Internal) => {
+                internal_b_outputs.push(out.clone());
+                internal_a_inputs.extend(converted);
+            }
+            None => {
+                if let Some(derived_input) = converted {
+                    internal_b_outputs.push(out.clone());
+                    internal_a_inputs.push(derived_input);
+                }
+            }
         }
     }
 
+    let a_input_internal = |input: &MA::Input| match MA::input_visibility(input) {
+        Some(visibility) => visibility != Visibility::External,
+        None => internal_a_inputs.contains(input),
+    };
+    let b_input_internal = |input: &MB::Input| match MB::input_visibility(input) {
+        Some(visibility) => visibility != Visibility::External,
+        None => internal_b_inputs.contains(input),
+    };
+
     writeln!(output, "digraph GenericContext {{").unwrap();
     writeln!(output, "    rankdir=LR;").unwrap();
     writeln!(output, "    node [fontname=\"Arial\", fontsize=12];").unwrap();
@@ -75,32 +612,272 @@ where
     writeln!(output, "    node [shape=none, style=none, fillcolor=none, height=0.5];").unwrap();
     writeln!(output, "    Environment_In [label=\"Environment\"];").unwrap();
 
-    for input in MA::all_inputs() {
-        if !internal_a_inputs.contains(input) {
-            writeln!(output, "    Environment_In -> System [label=\"{:?}\"];", input).unwrap();
+    for input in MA::all_inputs().iter() {
+        if !a_input_internal(input) {
+            let label = escape_label(&labels::input::<MA>(input));
+            writeln!(output, "    Environment_In -> System [label=\"{}\"];", label).unwrap();
         }
     }
 
-    for input in MB::all_inputs() {
-        if !internal_b_inputs.contains(input) {
-            writeln!(output, "    Environment_In -> System [label=\"{:?}\"];", input).unwrap();
+    for input in MB::all_inputs().iter() {
+        if !b_input_internal(input) {
+            let label = escape_label(&labels::input::<MB>(input));
+            writeln!(output, "    Environment_In -> System [label=\"{}\"];", label).unwrap();
         }
     }
 
     writeln!(output, "    Environment_Out [label=\"Environment\"];").unwrap();
 
-    for out in MA::all_outputs() {
+    for out in MA::all_outputs().iter() {
         if !internal_a_outputs.contains(out) {
-            writeln!(output, "    System -> Environment_Out [label=\"{:?}\"];", out).unwrap();
+            let mut style = if shared_a_outputs.contains(out) { ", style=bold" } else { "" }.to_string();
+            if MA::output_kind(out) == OutputKind::Error {
+                style.push_str(", color=red, fontcolor=red");
+            }
+            let label = escape_label(&labels::output::<MA>(out));
+            writeln!(output, "    System -> Environment_Out [label=\"{}\"{}];", label, style).unwrap();
         }
     }
 
-    for out in MB::all_outputs() {
+    for out in MB::all_outputs().iter() {
         if !internal_b_outputs.contains(out) {
-            writeln!(output, "    System -> Environment_Out [label=\"{:?}\"];", out).unwrap();
+            let mut style = if shared_b_outputs.contains(out) { ", style=bold" } else { "" }.to_string();
+            if MB::output_kind(out) == OutputKind::Error {
+                style.push_str(", color=red, fontcolor=red");
+            }
+            let label = escape_label(&labels::output::<MB>(out));
+            writeln!(output, "    System -> Environment_Out [label=\"{}\"{}];", label, style).unwrap();
         }
     }
 
     writeln!(output, "}}").unwrap();
     output
 }
+
+/// Renders the port connections of a network of `A` and `B` wired by
+/// `network::Ports<AB, BA>`: one edge per port, labelled by its name.
+pub fn generate_port_dot<A, B, AB, BA>(a_name: &str, b_name: &str) -> String
+where
+    A: XMachine,
+    B: XMachine,
+    AB: PortSet<A, B>,
+    BA: PortSet<B, A>,
+{
+    let mut output = String::new();
+    writeln!(output, "digraph Ports {{").unwrap();
+    writeln!(output, "    rankdir=LR;").unwrap();
+    writeln!(output, "    node [shape=component];").unwrap();
+    writeln!(output, "    \"{}\";", escape_label(a_name)).unwrap();
+    writeln!(output, "    \"{}\";", escape_label(b_name)).unwrap();
+    for port in AB::names() {
+        writeln!(output, "    \"{}\" -> \"{}\" [label=\"{}\"];", escape_label(a_name), escape_label(b_name), escape_label(port)).unwrap();
+    }
+    for port in BA::names() {
+        writeln!(output, "    \"{}\" -> \"{}\" [label=\"{}\"];", escape_label(b_name), escape_label(a_name), escape_label(port)).unwrap();
+    }
+    writeln!(output, "}}").unwrap();
+    output
+}
+
+/// Renders the components of a `MulticastNetwork` and its routes: one edge
+/// per route, labelled by its name, so an output carried to several
+/// components fans out into one edge per target.
+pub fn generate_multicast_dot(network: &MulticastNetwork) -> String {
+    let mut output = String::new();
+    writeln!(output, "digraph Multicast {{").unwrap();
+    writeln!(output, "    rankdir=LR;").unwrap();
+    writeln!(output, "    node [shape=component];").unwrap();
+    for name in network.names() {
+        writeln!(output, "    \"{}\";", escape_label(name)).unwrap();
+    }
+    for (from, to, route) in network.routes() {
+        let (from, to, route) = (escape_label(from), escape_label(to), escape_label(route));
+        writeln!(output, "    \"{}\" -> \"{}\" [label=\"{}\"];", from, to, route).unwrap();
+    }
+    writeln!(output, "}}").unwrap();
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeState, Door};
+    use std::borrow::Cow;
+
+    #[test]
+    fn every_transition_is_drawn() {
+        let dot = generate_dot::<Door>("door");
+        assert!(dot.starts_with("digraph door {\n"));
+        assert!(dot.contains("\"_start_Closed\" -> \"Closed\" [penwidth=2.0];"));
+        for edge in [
+            "\"Closed\" -> \"Opened\" [label=\"OpenDoor\"];",
+            "\"Closed\" -> \"Closed\" [label=\"IgnoreClose\"];",
+            "\"Opened\" -> \"Closed\" [label=\"CloseDoor\"];",
+            "\"Opened\" -> \"Opened\" [label=\"IgnoreOpen\"];",
+        ] {
+            assert!(dot.contains(edge), "{} missing from\n{}", edge, dot);
+        }
+        let mut streamed = Vec::new();
+        write_dot::<Door, _>(&mut streamed, "door").unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), dot);
+    }
+
+    #[test]
+    fn a_view_keeps_the_edges_around_its_focus() {
+        let dot = generate_dot_view::<Digicode>("digicode", &DotView::new().around(DigicodeState::Ready, 1));
+        assert!(dot.contains("\"Ready\" -> \"Accepting\" [label=\"InputDigit\"];"));
+        assert!(!dot.contains("CodeEntered"));
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Pad {
+        Idle,
+        Typing,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    enum PadInput {
+        Key(u8),
+        Clear,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum PadPhi {
+        Press,
+        Wipe,
+    }
+
+    /// A four-key pad whose symbols are named from a glossary.
+    struct Glossary;
+
+    impl XMachine for Glossary {
+        type Input = PadInput;
+        type Output = ();
+        type State = Pad;
+        type Memory = ();
+        type Phi = PadPhi;
+
+        fn next_state(state: Pad, phi: PadPhi) -> Option<Pad> {
+            match (state, phi) {
+                (_, PadPhi::Press) => Some(Pad::Typing),
+                (Pad::Typing, PadPhi::Wipe) => Some(Pad::Idle),
+                (Pad::Idle, PadPhi::Wipe) => None,
+            }
+        }
+
+        fn initial_states() -> &'static [Pad] {
+            &[Pad::Idle]
+        }
+
+        fn final_states() -> &'static [Pad] {
+            &[Pad::Idle]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(_phi: PadPhi, _store: &mut (), _input: &PadInput) -> Result<Option<()>, ()> {
+            Ok(None)
+        }
+
+        fn all_inputs() -> Cow<'static, [PadInput]> {
+            use PadInput::*;
+            Cow::Borrowed(&[Key(0), Key(1), Key(2), Key(3), Clear])
+        }
+
+        fn all_outputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[])
+        }
+
+        fn all_states() -> &'static [Pad] {
+            &[Pad::Idle, Pad::Typing]
+        }
+
+        fn all_phis() -> &'static [PadPhi] {
+            &[PadPhi::Press, PadPhi::Wipe]
+        }
+
+        fn get_phi_for_input(state: Pad, input: &PadInput) -> Option<PadPhi> {
+            match (state, input) {
+                (_, PadInput::Key(_)) => Some(PadPhi::Press),
+                (Pad::Typing, PadInput::Clear) => Some(PadPhi::Wipe),
+                (Pad::Idle, PadInput::Clear) => None,
+            }
+        }
+
+        fn labeling() -> labels::Labeling<Self> {
+            labels::Labeling::Custom(|symbol| match symbol {
+                labels::Symbol::Input(PadInput::Key(key)) => format!("Button({})", key),
+                labels::Symbol::Input(PadInput::Clear) => "C".to_string(),
+                labels::Symbol::Phi(PadPhi::Press) => "press".to_string(),
+                labels::Symbol::Phi(PadPhi::Wipe) => "wipe".to_string(),
+                labels::Symbol::State(state) => format!("{:?}", state),
+                labels::Symbol::Output(()) => String::new(),
+            })
+        }
+    }
+
+    #[test]
+    fn input_labels_use_the_machine_labeling() {
+        let dot = generate_dot_view::<Glossary>("pad", &DotView::new().input_labels());
+        for edge in [
+            "\"Idle\" -> \"Typing\" [label=\"Button(0..=3) / press\"];",
+            "\"Typing\" -> \"Typing\" [label=\"Button(0..=3) / press\"];",
+            "\"Typing\" -> \"Idle\" [label=\"C / wipe\"];",
+        ] {
+            assert!(dot.contains(edge), "{} missing from\n{}", edge, dot);
+        }
+        let plain = generate_dot::<Glossary>("pad");
+        assert!(plain.contains("\"Typing\" -> \"Idle\" [label=\"wipe\"];"), "{}", plain);
+        assert!(!plain.contains("Button"));
+    }
+
+    #[test]
+    fn configuration_nodes_show_the_abstracted_store() {
+        let dot = generate_configuration_dot::<Digicode, _>("digicode", |store| store.current_sequence.len(), 1);
+        for line in [
+            "\"c0\" [label=\"Ready\\n0\"];",
+            "\"c1\" [label=\"Accepting\\n1\"];",
+            "\"c2\" [label=\"Accepting\\n2\"];",
+            "\"_start_c0\" -> \"c0\" [penwidth=2.0];",
+            "\"c0\" -> \"c0\" [label=\"Reject\", color=red, fontcolor=red];",
+            "\"c0\" -> \"c1\" [label=\"InputDigit\"];",
+            "\"c1\" -> \"c2\" [label=\"InputDigit\"];",
+        ] {
+            assert!(dot.contains(line), "{} missing from\n{}", line, dot);
+        }
+        assert_eq!(dot.matches(" -> ").count(), 4, "{}", dot);
+    }
+
+    #[test]
+    fn a_diff_styles_added_and_removed_edges_apart() {
+        use crate::dynamic::DynMachine;
+        use crate::examples::admin_digicode::AdminDigicode;
+
+        let (base, admin) = (
+            DynMachine::from_machine::<Digicode>("digicode"),
+            DynMachine::from_machine::<AdminDigicode>("admin_digicode"),
+        );
+        let admin_edges = [
+            "\"Ready\" -> \"CodeEntered\" [label=\"Override\"",
+            "\"Accepting\" -> \"CodeEntered\" [label=\"Override\"",
+            "\"CodeEntered\" -> \"Ready\" [label=\"Rearm\"",
+        ];
+        let unchanged = "\"Ready\" -> \"Accepting\" [label=\"InputDigit\"];";
+
+        let added = generate_diff_dot(&base.to_json(), &admin).unwrap();
+        for edge in admin_edges {
+            let styled = format!("{}, color=green, fontcolor=green];", edge);
+            assert!(added.contains(&styled), "{} missing from\n{}", styled, added);
+        }
+        assert!(added.contains(unchanged));
+        assert!(!added.contains("dashed"));
+
+        let removed = generate_diff_dot(&admin.to_json(), &base).unwrap();
+        for edge in admin_edges {
+            let styled = format!("{}, style=dashed, color=red, fontcolor=red];", edge);
+            assert!(removed.contains(&styled), "{} missing from\n{}", styled, removed);
+        }
+        assert!(removed.contains(unchanged));
+        assert!(!removed.contains("green"));
+    }
+}
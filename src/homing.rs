@@ -0,0 +1,306 @@
+//! Preambles bringing a SUT of unknown state back to an initial one.
+//!
+//! Without a reliable reset, a SUT may start a session anywhere. A homing
+//! sequence is an input sequence whose outputs tell which state it leaves the
+//! machine in, whatever it started from; a synchronizing sequence leaves it in
+//! the same state whatever the outputs. Either way the state reached is then
+//! known, and a transfer sequence leads from it to an initial state, where the
+//! setup sequences of test cases start. `Executor::home` applies such a
+//! preamble and `Executor::run_suite_homed` runs one before every case.
+//!
+//! Like `XMachine::reset_input`, a preamble only promises an initial state:
+//! the store it leaves may differ from `initial_store()` when no input
+//! restores it, which the results of the cases run after it then rest on.
+//!
+//! The sequences are built over the configurations the SUT may start in, e.g.
+//! `adaptive::reachable_configurations`, so guards are honoured, the classical
+//! greedy way: while two candidates in different states remain confused,
+//! append the shortest sequence that tells them apart or brings them to the
+//! same state. They need not be the shortest, but only pairs of
+//! configurations are ever searched. An input the spec rejects leaves a
+//! candidate where it is and counts as producing no output, and internal
+//! outputs count as none, as in `Executor`.
+
+use crate::configuration::Configuration;
+use crate::executor::Executor;
+use crate::store::Store;
+use crate::XMachine;
+use std::collections::VecDeque;
+
+/// A state the homing inputs may lead to, with the outputs they produce on
+/// the way there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HomingOutcome<Input, Output, State> {
+    /// Observable output of each homing input. Empty for a synchronizing
+    /// sequence, whose outputs are not looked at.
+    pub outputs: Vec<Option<Output>>,
+    pub state: State,
+    /// Inputs leading from `state` to an initial state.
+    pub transfer: Vec<Input>,
+}
+
+/// `HomingOutcome` specialised to the symbols of machine `T`.
+pub type HomingOutcomeOf<T> =
+    HomingOutcome<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+/// A preamble found by `homing_sequence` or `synchronizing_sequence`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HomingSequence<Input, Output, State> {
+    inputs: Vec<Input>,
+    outcomes: Vec<HomingOutcome<Input, Output, State>>,
+    synchronizing: bool,
+}
+
+/// `HomingSequence` specialised to the symbols of machine `T`.
+pub type HomingSequenceOf<T> =
+    HomingSequence<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Input, Output: PartialEq, State> HomingSequence<Input, Output, State> {
+    /// Inputs applied whatever the start.
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    /// One outcome per distinct output sequence of the inputs, or a single
+    /// one for a synchronizing sequence.
+    pub fn outcomes(&self) -> &[HomingOutcome<Input, Output, State>] {
+        &self.outcomes
+    }
+
+    /// `true` if the inputs alone determine the state reached.
+    pub fn is_synchronizing(&self) -> bool {
+        self.synchronizing
+    }
+
+    /// The outcome of observing `outputs` over the inputs, or `None` if the
+    /// spec cannot produce them from any candidate.
+    pub fn outcome(&self, outputs: &[Option<Output>]) -> Option<&HomingOutcome<Input, Output, State>> {
+        if self.synchronizing {
+            return self.outcomes.first();
+        }
+        self.outcomes.iter().find(|outcome| outcome.outputs == outputs)
+    }
+
+    /// Length of the longest preamble, transfer included.
+    pub fn len(&self) -> usize {
+        self.inputs.len() + self.outcomes.iter().map(|o| o.transfer.len()).max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A homing sequence from `candidates`, in which every pair is told apart or
+/// brought to the same state within `max_len` inputs, and each outcome with
+/// a transfer of at most `max_len` inputs.
+///
+/// `None` if there is no candidate, if a pair or a transfer needs more
+/// inputs, or if candidates brought to the same state part again so often
+/// that the sequence outgrows `max_len` inputs per candidate.
+pub fn homing_sequence<T>(candidates: &[Configuration<T>], max_len: usize) -> Option<HomingSequenceOf<T>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    find::<T>(candidates, max_len, true)
+}
+
+/// Like `homing_sequence`, for a sequence whose outputs need not be
+/// observed, e.g. for a SUT that reports nothing until it is homed.
+pub fn synchronizing_sequence<T>(candidates: &[Configuration<T>], max_len: usize) -> Option<HomingSequenceOf<T>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    find::<T>(candidates, max_len, false)
+}
+
+/// Candidates still possible, grouped by the outputs observed so far.
+type Groups<T> = Vec<(Vec<Option<<T as XMachine>::Output>>, Vec<Configuration<T>>)>;
+
+fn find<T>(candidates: &[Configuration<T>], max_len: usize, observe: bool) -> Option<HomingSequenceOf<T>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut start: Vec<Configuration<T>> = Vec::new();
+    for config in candidates {
+        if !start.contains(config) {
+            start.push(config.clone());
+        }
+    }
+    if start.is_empty() {
+        return None;
+    }
+
+    let budget = max_len * start.len();
+    let mut inputs = Vec::new();
+    let mut groups: Groups<T> = vec![(Vec::new(), start)];
+    while let Some((a, b)) = groups.iter().find_map(|(_, group)| confused::<T>(group)) {
+        let sequence = pair_sequence::<T>(a, b, max_len, observe)?;
+        if inputs.len() + sequence.len() > budget {
+            return None;
+        }
+        for input in sequence {
+            groups = advance::<T>(&groups, &input, observe);
+            inputs.push(input);
+        }
+    }
+
+    let outcomes = groups
+        .iter()
+        .map(|(outputs, group)| {
+            Some(HomingOutcome {
+                outputs: outputs.clone(),
+                state: group[0].state,
+                transfer: transfer::<T>(group, max_len)?,
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(HomingSequence {
+        inputs,
+        outcomes,
+        synchronizing: !observe,
+    })
+}
+
+/// Two configurations of `group` in different states, if any.
+fn confused<T: XMachine>(group: &[Configuration<T>]) -> Option<(Configuration<T>, Configuration<T>)> {
+    let first = group.first()?;
+    let other = group.iter().find(|config| config.state != first.state)?;
+    Some((first.clone(), other.clone()))
+}
+
+/// `config` after `input`, with the observable output. A rejected input
+/// leaves it unchanged.
+fn step<T: XMachine>(config: &Configuration<T>, input: &T::Input) -> (Option<T::Output>, Configuration<T>) {
+    match config.step(input) {
+        Ok((transition, next)) => (Executor::observable::<T>(transition.output), next),
+        Err(_) => (None, config.clone()),
+    }
+}
+
+/// The shortest sequence of at most `max_len` inputs leading `a` and `b` to
+/// the same state or, when `observe` is set, telling them apart by an output.
+fn pair_sequence<T>(a: Configuration<T>, b: Configuration<T>, max_len: usize, observe: bool) -> Option<Vec<T::Input>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let inputs = T::all_inputs();
+    let mut seen = vec![(a.clone(), b.clone())];
+    let mut queue = VecDeque::from([(a, b, Vec::new())]);
+    while let Some((a, b, path)) = queue.pop_front() {
+        if path.len() >= max_len {
+            continue;
+        }
+        for input in inputs.iter() {
+            let ((output_a, next_a), (output_b, next_b)) = (step(&a, input), step(&b, input));
+            let mut new_path = path.clone();
+            new_path.push(input.clone());
+            if next_a.state == next_b.state || (observe && output_a != output_b) {
+                return Some(new_path);
+            }
+            let pair = (next_a, next_b);
+            if !seen.contains(&pair) {
+                seen.push(pair.clone());
+                queue.push_back((pair.0, pair.1, new_path));
+            }
+        }
+    }
+    None
+}
+
+/// `groups` after `input`, split by its output when `observe` is set.
+fn advance<T>(groups: &Groups<T>, input: &T::Input, observe: bool) -> Groups<T>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut next: Groups<T> = Vec::new();
+    for (outputs, group) in groups {
+        let first = next.len();
+        for config in group {
+            let (output, config) = step(config, input);
+            let mut outputs = outputs.clone();
+            if observe {
+                outputs.push(output);
+            }
+            match next[first..].iter_mut().find(|(o, _)| *o == outputs) {
+                Some((_, group)) if !group.contains(&config) => group.push(config),
+                Some(_) => {}
+                None => next.push((outputs, vec![config])),
+            }
+        }
+    }
+    next
+}
+
+/// The shortest sequence of at most `max_len` inputs leading every
+/// configuration of `group` to an initial state.
+fn transfer<T>(group: &[Configuration<T>], max_len: usize) -> Option<Vec<T::Input>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let inputs = T::all_inputs();
+    let start = group.to_vec();
+    let mut seen = vec![start.clone()];
+    let mut queue = VecDeque::from([(start, Vec::new())]);
+    while let Some((configs, path)) = queue.pop_front() {
+        if configs.iter().all(|config| T::initial_states().contains(&config.state)) {
+            return Some(path);
+        }
+        if path.len() >= max_len {
+            continue;
+        }
+        for input in inputs.iter() {
+            let mut next: Vec<Configuration<T>> = Vec::new();
+            for config in &configs {
+                let (_, config) = step(config, input);
+                if !next.contains(&config) {
+                    next.push(config);
+                }
+            }
+            if !seen.contains(&next) {
+                seen.push(next.clone());
+                let mut new_path = path.clone();
+                new_path.push(input.clone());
+                queue.push_back((next, new_path));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::{LightInput, LightState, TrafficLight};
+    use crate::runner::MachineRunner;
+
+    fn anywhere() -> Vec<Configuration<TrafficLight>> {
+        TrafficLight::all_states().iter().map(|&state| Configuration::new(state, 0)).collect()
+    }
+
+    #[test]
+    fn a_homing_sequence_brings_any_state_back_to_an_initial_one() {
+        let homing = homing_sequence::<TrafficLight>(&anywhere(), 4).unwrap();
+        assert!(!homing.is_synchronizing());
+        for &start in TrafficLight::all_states() {
+            let mut sut = MachineRunner::<TrafficLight>::from_parts(start, 0);
+            assert!(Executor::home::<TrafficLight, _>(&mut sut, &homing).is_some());
+            assert_eq!(sut.state(), LightState::Red);
+        }
+    }
+
+    #[test]
+    fn a_fault_synchronizes_the_light() {
+        let synchronizing = synchronizing_sequence::<TrafficLight>(&anywhere(), 4).unwrap();
+        assert!(synchronizing.is_synchronizing());
+        assert_eq!(synchronizing.inputs(), [LightInput::Fault]);
+        assert_eq!(synchronizing.outcomes().len(), 1);
+        assert!(synchronizing.outcomes()[0].transfer.is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use crate::XMachine;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Dense ordinal of a state within `XMachine::all_states()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateIndex(pub usize);
+
+/// Dense ordinal of a processing function within `XMachine::all_phis()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PhiIndex(pub usize);
+
+/// Dense ordinal of an input symbol within `XMachine::all_inputs()`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InputIndex(pub usize);
+
+/// Fixed-size bitset over ordinal indices.
+///
+/// Used for visited sets and coverage tracking where a `Vec::contains`
+/// scan would otherwise be needed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl IndexSet {
+    /// Creates an empty set able to hold indices in `0..len`.
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; len.div_ceil(64)],
+            len,
+        }
+    }
+
+    /// Inserts `index`, returning `true` if it was not already present.
+    pub fn insert(&mut self, index: usize) -> bool {
+        assert!(index < self.len, "index {} out of range 0..{}", index, self.len);
+        let (word, bit) = (index / 64, 1u64 << (index % 64));
+        let fresh = self.words[word] & bit == 0;
+        self.words[word] |= bit;
+        fresh
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] & (1u64 << (index % 64)) != 0
+    }
+
+    /// Number of indices present in the set.
+    pub fn count(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Size of the index domain (`0..capacity()`).
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(move |&i| self.contains(i))
+    }
+}
+
+/// The input alphabet of `T`, built on first use in each thread and kept,
+/// so index lookups do not rebuild an owned `all_inputs()` every time.
+pub(crate) fn inputs<T: XMachine + ?Sized>() -> Rc<Vec<T::Input>> {
+    thread_local! {
+        static TABLES: RefCell<HashMap<TypeId, Rc<dyn Any>>> = RefCell::default();
+    }
+    TABLES.with(|tables| {
+        let table = tables
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Rc::new(T::all_inputs().into_owned()))
+            .clone();
+        table.downcast::<Vec<T::Input>>().expect("table keyed by its machine")
+    })
+}
+
+/// A symbol type numbered densely, so that its index is computed rather than
+/// searched for in the machine's alphabet.
+///
+/// Declared with `sxm_indexed!` for fieldless enums, and implemented by
+/// `sxm_state_space!`. `sxm_index_tables!` turns it into the matching
+/// `XMachine` hooks.
+pub trait Indexed: Sized + 'static {
+    /// Every value, in index order.
+    fn all() -> &'static [Self];
+
+    /// Position of `self` in `all()`.
+    fn index(&self) -> usize;
+}
+
+/// Declares a fieldless enum implementing `index::Indexed`, with `Copy`,
+/// `Clone`, `PartialEq` and `Debug` derived.
+///
+/// Values are numbered in declaration order and the table of values is
+/// generated as a static slice, so `index` is a cast and `all` a constant.
+/// The enum also gets `all()` as an inherent function. Other attributes are
+/// kept.
+#[macro_export]
+macro_rules! sxm_indexed {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, PartialEq, Debug)]
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant),*
+        }
+
+        impl $name {
+            /// Every value, in declaration order.
+            pub fn all() -> &'static [Self] {
+                &[$($name::$variant),*]
+            }
+        }
+
+        impl $crate::index::Indexed for $name {
+            fn all() -> &'static [Self] {
+                $name::all()
+            }
+
+            fn index(&self) -> usize {
+                *self as usize
+            }
+        }
+    };
+}
+
+/// Generates `XMachine` hooks from `index::Indexed` symbol types, for use
+/// inside an `impl XMachine` block: `states` gives `all_states`,
+/// `state_index` and `state_from_index`, `phis` the phi equivalents and
+/// `inputs` the input ones. Lookups become constant-time instead of scans of
+/// the alphabet.
+///
+/// `phis` indexes `phi_kind(phi)`, so it suits machines whose kinds are the
+/// `Indexed` values, as they are when every phi is its own kind.
+#[macro_export]
+macro_rules! sxm_index_tables {
+    ($($table:ident),* $(,)?) => {
+        $($crate::sxm_index_tables!(@$table);)*
+    };
+    (@states) => {
+        fn all_states() -> &'static [Self::State] {
+            <Self::State as $crate::index::Indexed>::all()
+        }
+
+        fn state_index(state: Self::State) -> Option<$crate::index::StateIndex> {
+            Some($crate::index::StateIndex($crate::index::Indexed::index(&state)))
+        }
+
+        fn state_from_index(index: $crate::index::StateIndex) -> Option<Self::State> {
+            <Self::State as $crate::index::Indexed>::all().get(index.0).copied()
+        }
+    };
+    (@phis) => {
+        fn all_phis() -> &'static [Self::Phi] {
+            <Self::Phi as $crate::index::Indexed>::all()
+        }
+
+        fn phi_index(phi: Self::Phi) -> Option<$crate::index::PhiIndex> {
+            Some($crate::index::PhiIndex($crate::index::Indexed::index(&Self::phi_kind(phi))))
+        }
+
+        fn phi_from_index(index: $crate::index::PhiIndex) -> Option<Self::Phi> {
+            <Self::Phi as $crate::index::Indexed>::all().get(index.0).copied()
+        }
+    };
+    (@inputs) => {
+        fn all_inputs() -> ::std::borrow::Cow<'static, [Self::Input]> {
+            ::std::borrow::Cow::Borrowed(<Self::Input as $crate::index::Indexed>::all())
+        }
+
+        fn input_index(input: &Self::Input) -> Option<$crate::index::InputIndex> {
+            Some($crate::index::InputIndex($crate::index::Indexed::index(input)))
+        }
+
+        fn input_from_index(index: $crate::index::InputIndex) -> Option<Self::Input> {
+            <Self::Input as $crate::index::Indexed>::all().get(index.0).cloned()
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn an_index_set_spans_several_words() {
+        let mut set = IndexSet::new(130);
+        assert!(set.insert(3));
+        assert!(set.insert(129));
+        assert!(!set.insert(3));
+        assert!(set.contains(129) && !set.contains(64) && !set.contains(200));
+        assert_eq!(set.count(), 2);
+        assert_eq!(set.iter().collect::<Vec<_>>(), [3, 129]);
+        assert_eq!(set.capacity(), 130);
+    }
+
+    crate::sxm_indexed! {
+        enum Phase {
+            Idle,
+            Busy,
+            Done,
+        }
+    }
+
+    #[test]
+    fn indexed_enums_are_numbered_in_declaration_order() {
+        assert_eq!(Phase::all(), [Phase::Idle, Phase::Busy, Phase::Done]);
+        assert_eq!(Phase::all().iter().map(Indexed::index).collect::<Vec<_>>(), [0, 1, 2]);
+    }
+}
@@ -0,0 +1,187 @@
+//! Containing panics raised by user phis.
+//!
+//! A runner built `with_panic_isolation` turns a panic in a step into
+//! `StepError::Panicked` and keeps its configuration. For generation,
+//! `isolate_panics` runs a closure, e.g. a suite build, in which every phi
+//! the crate executes that panics is treated as a rejecting guard and
+//! recorded with the configuration and input that triggered it, so one buggy
+//! phi costs the cases depending on it rather than the whole run. A runner
+//! keeps the same record of the last panic it caught in `last_panic`.
+//!
+//! Panics are caught with `catch_unwind`, so code built with `panic = "abort"`
+//! still aborts, and the panic hook still reports each one.
+
+use crate::XMachine;
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::{self, AssertUnwindSafe};
+
+/// A panic raised by `execute_phi` in the configuration (`state`, `memory`) on `input`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhiPanic<State, Phi, Input, Memory> {
+    pub state: State,
+    /// The store before the phi ran.
+    pub memory: Memory,
+    pub phi: Phi,
+    pub input: Input,
+    pub message: String,
+}
+
+/// `PhiPanic` specialised to the symbols of machine `T`.
+pub type PhiPanicOf<T> =
+    PhiPanic<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Input, <T as XMachine>::Memory>;
+
+thread_local! {
+    /// Panics caught inside the innermost `isolate_panics` of this thread, if any.
+    static CAUGHT: RefCell<Option<Vec<Box<dyn Any>>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, treating a panic in any phi of `T` the crate executes on this
+/// thread as a rejection of the input, and returns the panics caught, one per
+/// (state, phi) pair, in the order they first occurred.
+///
+/// Only phis executed through the crate are covered: generators, searches
+/// and runners, not calls `f` makes to `T::execute_phi` itself.
+pub fn isolate_panics<T: XMachine, R>(f: impl FnOnce() -> R) -> (R, Vec<PhiPanicOf<T>>) {
+    let outer = CAUGHT.with(|caught| caught.borrow_mut().replace(Vec::new()));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    let caught = CAUGHT.with(|caught| std::mem::replace(&mut *caught.borrow_mut(), outer)).unwrap_or_default();
+    let result = result.unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+    let mut panics: Vec<PhiPanicOf<T>> = Vec::new();
+    for record in caught {
+        if let Ok(record) = record.downcast::<PhiPanicOf<T>>() {
+            if !panics.iter().any(|p| p.state == record.state && p.phi == record.phi) {
+                panics.push(*record);
+            }
+        }
+    }
+    (result, panics)
+}
+
+/// `T::execute_phi`, except inside `isolate_panics`, where a panic is
+/// recorded and reported as a rejection.
+pub(crate) fn execute_phi<T: XMachine>(
+    state: T::State,
+    phi: T::Phi,
+    store: &mut T::Memory,
+    input: &T::Input,
+) -> Result<Option<T::Output>, ()> {
+    if !CAUGHT.with(|caught| caught.borrow().is_some()) {
+        return T::execute_phi(phi, store, input);
+    }
+    let memory = store.clone();
+    match panic::catch_unwind(AssertUnwindSafe(|| T::execute_phi(phi, store, input))) {
+        Ok(result) => result,
+        Err(payload) => {
+            let record: PhiPanicOf<T> = PhiPanic {
+                state,
+                memory,
+                phi,
+                input: input.clone(),
+                message: panic_message(payload.as_ref()),
+            };
+            CAUGHT.with(|caught| {
+                if let Some(caught) = caught.borrow_mut().as_mut() {
+                    caught.push(Box::new(record));
+                }
+            });
+            Err(())
+        }
+    }
+}
+
+/// The message of a panic payload, for the usual `&str` and `String` ones.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::configuration::Configuration;
+    use crate::runner::{MachineRunner, StepError};
+    use std::borrow::Cow;
+
+    /// Divides 12 by its input, counting divisions, and panics on zero.
+    struct Divider;
+
+    impl XMachine for Divider {
+        type Input = u8;
+        type Output = u8;
+        type State = ();
+        type Memory = u8;
+        type Phi = ();
+
+        fn next_state(_state: (), _phi: ()) -> Option<()> {
+            Some(())
+        }
+
+        fn initial_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn final_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn initial_store() -> u8 {
+            0
+        }
+
+        fn execute_phi(_phi: (), divisions: &mut u8, input: &u8) -> Result<Option<u8>, ()> {
+            let quotient = 12 / *input;
+            *divisions += 1;
+            Ok(Some(quotient))
+        }
+
+        fn all_inputs() -> Cow<'static, [u8]> {
+            Cow::Borrowed(&[0, 3])
+        }
+
+        fn all_outputs() -> Cow<'static, [u8]> {
+            Cow::Borrowed(&[4])
+        }
+
+        fn all_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn all_phis() -> &'static [()] {
+            &[()]
+        }
+
+        fn get_phi_for_input(_state: (), _input: &u8) -> Option<()> {
+            Some(())
+        }
+    }
+
+    #[test]
+    fn panicking_phis_are_recorded_as_rejections() {
+        let start = Configuration::<Divider>::new((), 5);
+        let (outputs, panics) = isolate_panics::<Divider, _>(|| {
+            [0, 3, 0].map(|input| start.step(&input).ok().and_then(|(transition, _)| transition.output))
+        });
+        assert_eq!(outputs, [None, Some(4), None]);
+        assert_eq!(panics.len(), 1);
+        assert_eq!((panics[0].input, panics[0].message.contains("divide by zero")), (0, true));
+        assert_eq!(panics[0].memory, 5);
+    }
+
+    #[test]
+    fn an_isolated_runner_keeps_its_configuration() {
+        let mut runner = MachineRunner::<Divider>::new().with_panic_isolation();
+        assert_eq!(runner.step(&3).unwrap().output, Some(4));
+        assert!(matches!(runner.step(&0), Err(StepError::Panicked { .. })));
+        let panic = runner.last_panic().unwrap();
+        assert_eq!((panic.state, panic.memory, panic.input), ((), 1, 0));
+        assert_eq!(runner.step(&3).unwrap().output, Some(4));
+        assert_eq!(*runner.store(), 2);
+    }
+}
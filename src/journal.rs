@@ -0,0 +1,379 @@
+//! Persistent progress of a suite run, so an interrupted run can resume.
+//!
+//! A `Journal` is a JSON Lines file: a header naming the spec fingerprint and
+//! the suite size, then one line per finished case with its index, name and
+//! verdict. `Executor::run_suite_journaled` appends and syncs each line as
+//! soon as the case ends, so a run killed midway, e.g. by a power cycle of the
+//! device under test, loses at most the case in flight. `Executor::resume`
+//! reads the journal back, skips the cases it records and runs the others.
+//!
+//! Symbols are written as their `Debug` rendering, as in `archive`, and the
+//! fields of a store mismatch as their name and `Debug` renderings. The
+//! expectation of a mismatch is not stored; it is the one of the case, which
+//! the resuming caller passes again.
+
+use crate::executor::{CaseResult, CaseResultOf, Verdict, VerdictOf};
+use crate::fingerprint::SpecFingerprint;
+use crate::json::{self, Value};
+use crate::mbt::TestCaseOf;
+use crate::store::{FieldChange, Store};
+use crate::XMachine;
+use std::fmt::{self, Debug};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Version written in the header. Journals of another version are rejected.
+pub const FORMAT_VERSION: u64 = 1;
+
+const FORMAT: &str = "sxm-journal";
+
+/// Why a journal could not be written or resumed.
+#[derive(Debug)]
+pub enum JournalError {
+    Io(io::Error),
+    /// A line other than the last is not valid JSON. The last one is only
+    /// dropped, as it may have been cut short by the interruption.
+    Parse { line: usize, message: String },
+    /// A line does not follow the journal schema.
+    Schema { line: usize, message: String },
+    /// The journal was written by another version of the format.
+    UnsupportedVersion(u64),
+    /// The journal was written against another structure of the spec.
+    Stale { recorded: SpecFingerprint },
+    /// The journal records another suite: its size or a case name differs.
+    OtherSuite(String),
+}
+
+impl fmt::Display for JournalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JournalError::Io(error) => write!(f, "{}", error),
+            JournalError::Parse { line, message } | JournalError::Schema { line, message } => {
+                write!(f, "line {}: {}", line, message)
+            }
+            JournalError::UnsupportedVersion(version) => {
+                write!(f, "unsupported journal format version {} (expected {})", version, FORMAT_VERSION)
+            }
+            JournalError::Stale { recorded } => write!(f, "journal written for spec {}", recorded),
+            JournalError::OtherSuite(message) => write!(f, "journal of another suite: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+impl From<io::Error> for JournalError {
+    fn from(error: io::Error) -> Self {
+        JournalError::Io(error)
+    }
+}
+
+/// A journal file of suite progress.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    /// A journal backed by `path`. Nothing is read or written yet.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The recorded result of each case of `cases`, `None` for the cases
+    /// still to run. A missing file records nothing.
+    ///
+    /// An entry whose symbols are no longer declared is treated as not run.
+    /// When a case was recorded more than once, the last entry counts.
+    pub fn results<T: XMachine>(&self, cases: &[TestCaseOf<T>]) -> Result<Vec<Option<CaseResultOf<T>>>, JournalError>
+    where
+        T::Memory: Store,
+    {
+        let mut results = vec![None; cases.len()];
+        let text = match fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(results),
+            Err(e) => return Err(e.into()),
+        };
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        for (idx, line) in lines.iter().enumerate() {
+            let doc = match json::parse(line) {
+                Ok(doc) => doc,
+                Err(_) if idx + 1 == lines.len() && !text.ends_with('\n') => break,
+                Err((_, message)) => return Err(JournalError::Parse { line: idx + 1, message }),
+            };
+            if idx == 0 {
+                check_header::<T>(&doc, cases.len())?;
+                continue;
+            }
+            let schema = |message: &str| JournalError::Schema {
+                line: idx + 1,
+                message: message.to_string(),
+            };
+            let index = doc
+                .get("index")
+                .and_then(Value::as_u64)
+                .ok_or_else(|| schema("missing numeric \"index\""))? as usize;
+            let name = doc.get("name").and_then(Value::as_str).ok_or_else(|| schema("missing \"name\""))?;
+            let case = cases
+                .get(index)
+                .ok_or_else(|| JournalError::OtherSuite(format!("no case at index {}", index)))?;
+            if case.name != name {
+                return Err(JournalError::OtherSuite(format!(
+                    "case {} is {:?}, not {:?}",
+                    index, case.name, name
+                )));
+            }
+            let injected = match doc.get("injected") {
+                Some(Value::Bool(injected)) => *injected,
+                _ => return Err(schema("missing boolean \"injected\"")),
+            };
+            let verdict = doc.get("verdict").ok_or_else(|| schema("missing \"verdict\""))?;
+            results[index] = decode_verdict::<T>(verdict, case).map(|verdict| CaseResult {
+                name: case.name.clone(),
+                verdict,
+                injected,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Deletes the journal, if any, e.g. once its report has been consumed.
+    pub fn remove(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes a journal holding the header and `results`, replacing the file
+    /// atomically, and opens it for appending.
+    pub(crate) fn rewrite<T: XMachine>(
+        &self,
+        case_count: usize,
+        results: &[Option<CaseResultOf<T>>],
+    ) -> Result<JournalWriter, JournalError> {
+        let mut text = format!(
+            "{{\"format\": {}, \"version\": {}, \"fingerprint\": \"{}\", \"cases\": {}}}\n",
+            json::quote(FORMAT),
+            FORMAT_VERSION,
+            SpecFingerprint::of::<T>(),
+            case_count
+        );
+        for (index, result) in results.iter().enumerate() {
+            if let Some(result) = result {
+                text.push_str(&entry::<T>(index, result));
+            }
+        }
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, &self.path)?;
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(JournalWriter { file })
+    }
+}
+
+/// Appends entries to an open journal.
+pub(crate) struct JournalWriter {
+    file: File,
+}
+
+impl JournalWriter {
+    /// Records the result of case `index` and syncs it to disk.
+    pub(crate) fn record<T: XMachine>(&mut self, index: usize, result: &CaseResultOf<T>) -> io::Result<()> {
+        self.file.write_all(entry::<T>(index, result).as_bytes())?;
+        self.file.sync_data()
+    }
+}
+
+fn check_header<T: XMachine>(doc: &Value, case_count: usize) -> Result<(), JournalError> {
+    let schema = |message: &str| JournalError::Schema {
+        line: 1,
+        message: message.to_string(),
+    };
+    if doc.get("format").and_then(Value::as_str) != Some(FORMAT) {
+        return Err(schema(&format!("missing \"format\": {:?}", FORMAT)));
+    }
+    let version = doc
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| schema("missing numeric \"version\""))?;
+    if version != FORMAT_VERSION {
+        return Err(JournalError::UnsupportedVersion(version));
+    }
+    let recorded = doc
+        .get("fingerprint")
+        .and_then(Value::as_str)
+        .and_then(SpecFingerprint::from_hex)
+        .ok_or_else(|| schema("missing hexadecimal \"fingerprint\""))?;
+    if recorded != SpecFingerprint::of::<T>() {
+        return Err(JournalError::Stale { recorded });
+    }
+    match doc.get("cases").and_then(Value::as_u64) {
+        Some(count) if count as usize == case_count => Ok(()),
+        Some(count) => Err(JournalError::OtherSuite(format!("{} cases, not {}", count, case_count))),
+        None => Err(schema("missing numeric \"cases\"")),
+    }
+}
+
+fn entry<T: XMachine>(index: usize, result: &CaseResultOf<T>) -> String {
+    format!(
+        "{{\"index\": {}, \"name\": {}, \"injected\": {}, \"verdict\": {}}}\n",
+        index,
+        json::quote(&result.name),
+        result.injected,
+        encode_verdict::<T>(&result.verdict)
+    )
+}
+
+fn encode_verdict<T: XMachine>(verdict: &VerdictOf<T>) -> String {
+    match verdict {
+        Verdict::Pass => "{\"kind\": \"pass\"}".to_string(),
+        Verdict::OutputMismatch { actual, .. } => {
+            format!("{{\"kind\": \"output_mismatch\", \"actual\": {}}}", optional(actual.as_ref()))
+        }
+        Verdict::UnexpectedError { actual, .. } => {
+            format!("{{\"kind\": \"unexpected_error\", \"actual\": {}}}", symbol(actual))
+        }
+        Verdict::StateMismatch { expected, actual } => format!(
+            "{{\"kind\": \"state_mismatch\", \"expected\": {}, \"actual\": {}}}",
+            symbol(expected),
+            symbol(actual)
+        ),
+        Verdict::Unidentified { expected } => {
+            format!("{{\"kind\": \"unidentified\", \"expected\": {}}}", symbol(expected))
+        }
+        Verdict::StoreMismatch { state, changes } => {
+            let changes: Vec<String> = changes
+                .iter()
+                .map(|change| {
+                    format!(
+                        "{{\"field\": {}, \"before\": {}, \"after\": {}}}",
+                        json::quote(change.field),
+                        json::quote(&change.before),
+                        json::quote(&change.after)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"kind\": \"store_mismatch\", \"state\": {}, \"changes\": [{}]}}",
+                symbol(state),
+                changes.join(", ")
+            )
+        }
+        Verdict::InjectionRequired => "{\"kind\": \"injection_required\"}".to_string(),
+        Verdict::VerificationMismatch {
+            index,
+            expected,
+            actual,
+        } => format!(
+            "{{\"kind\": \"verification_mismatch\", \"index\": {}, \"expected\": {}, \"actual\": {}}}",
+            index,
+            optional(expected.as_ref()),
+            optional(actual.as_ref())
+        ),
+    }
+}
+
+/// The verdict of `value`, or `None` if it names an undeclared symbol or a
+/// field the memory no longer has.
+fn decode_verdict<T: XMachine>(value: &Value, case: &TestCaseOf<T>) -> Option<VerdictOf<T>>
+where
+    T::Memory: Store,
+{
+    let outputs = T::all_outputs();
+    let output = |key: &str| match value.get(key)? {
+        Value::Null => Some(None),
+        value => decode_symbol(value, &outputs).map(Some),
+    };
+    let state = |key: &str| decode_symbol(value.get(key)?, T::all_states());
+    match value.get("kind")?.as_str()? {
+        "pass" => Some(Verdict::Pass),
+        "output_mismatch" => Some(Verdict::OutputMismatch {
+            expected: case.expected_output.clone(),
+            actual: output("actual")?,
+        }),
+        "unexpected_error" => Some(Verdict::UnexpectedError {
+            expected: case.expected_output.clone(),
+            actual: output("actual")??,
+        }),
+        "state_mismatch" => Some(Verdict::StateMismatch {
+            expected: state("expected")?,
+            actual: state("actual")?,
+        }),
+        "injection_required" => Some(Verdict::InjectionRequired),
+        "unidentified" => Some(Verdict::Unidentified {
+            expected: state("expected")?,
+        }),
+        "verification_mismatch" => Some(Verdict::VerificationMismatch {
+            index: value.get("index")?.as_u64()? as usize,
+            expected: output("expected")?,
+            actual: output("actual")?,
+        }),
+        "store_mismatch" => {
+            let fields = T::initial_store().abstraction();
+            let changes = value
+                .get("changes")?
+                .as_array()?
+                .iter()
+                .map(|change| {
+                    let name = change.get("field")?.as_str()?;
+                    Some(FieldChange {
+                        field: fields.iter().find(|(field, _)| *field == name)?.0,
+                        before: change.get("before")?.as_str()?.to_string(),
+                        after: change.get("after")?.as_str()?.to_string(),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(Verdict::StoreMismatch {
+                state: state("state")?,
+                changes,
+            })
+        }
+        _ => None,
+    }
+}
+
+fn symbol<S: Debug>(symbol: &S) -> String {
+    json::quote(&format!("{:?}", symbol))
+}
+
+fn optional<S: Debug>(symbol: Option<&S>) -> String {
+    symbol.map_or_else(|| "null".to_string(), self::symbol)
+}
+
+fn decode_symbol<S: Debug + Clone>(value: &Value, alphabet: &[S]) -> Option<S> {
+    let name = value.as_str()?;
+    alphabet.iter().find(|s| format!("{:?}", s) == name).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Digicode;
+    use crate::executor::Executor;
+    use crate::runner::MachineRunner;
+    use crate::suite::TestSuiteBuilder;
+
+    #[test]
+    fn journaled_results_are_read_back_and_resumed() {
+        let path = std::env::temp_dir().join(format!("sxm-journal-{}.jsonl", std::process::id()));
+        let journal = Journal::new(&path);
+        let cases = TestSuiteBuilder::<Digicode>::new().robustness().build().cases;
+        let report = Executor::run_suite_journaled::<Digicode, _>(&cases, MachineRunner::new, &journal).unwrap();
+        assert_eq!(report.passed(), cases.len());
+
+        let recorded = journal.results::<Digicode>(&cases).unwrap();
+        assert_eq!(recorded.into_iter().collect::<Option<Vec<_>>>(), Some(report.results.clone()));
+        // Every case is journaled, so resuming runs none of them again.
+        let resumed = Executor::resume::<Digicode, MachineRunner<Digicode>>(&cases, || unreachable!(), &journal);
+        assert_eq!(resumed.unwrap(), report);
+        journal.remove().unwrap();
+    }
+}
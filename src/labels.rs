@@ -0,0 +1,135 @@
+//! How the symbols of a machine are named in diagrams, documents and test
+//! names, as chosen by `XMachine::labeling`.
+//!
+//! Exporters render every state, input, output and phi through the helpers
+//! of this module, so a machine switching to `Labeling::display` shows
+//! `Digit 4` instead of `Digit(4)` in its DOT and Mermaid diagrams, its
+//! Markdown document and the names of generated test cases alike. Graph node
+//! identifiers keep the `Debug` form, so diagrams stay stable when labels change.
+
+use crate::XMachine;
+use std::fmt;
+
+/// A symbol of machine `T` to be labelled.
+pub enum Symbol<'a, T: XMachine + ?Sized> {
+    State(T::State),
+    Input(&'a T::Input),
+    Output(&'a T::Output),
+    Phi(T::Phi),
+}
+
+/// A labeling strategy for the symbols of machine `T`.
+pub enum Labeling<T: XMachine + ?Sized> {
+    /// `Debug` renderings, e.g. `Digit(4)`. The default.
+    Debug,
+    /// `Display` renderings, for stakeholder-facing artifacts. Built by
+    /// `Labeling::display`, which requires the symbol types to implement it.
+    Display(fn(Symbol<'_, T>) -> String),
+    /// Any other rendering, e.g. looked up in a glossary.
+    Custom(fn(Symbol<'_, T>) -> String),
+}
+
+impl<T: XMachine + ?Sized> Labeling<T> {
+    /// Labels every symbol with its `Display` rendering.
+    pub fn display() -> Self
+    where
+        T::State: fmt::Display,
+        T::Input: fmt::Display,
+        T::Output: fmt::Display,
+        T::Phi: fmt::Display,
+    {
+        Labeling::Display(|symbol| match symbol {
+            Symbol::State(state) => state.to_string(),
+            Symbol::Input(input) => input.to_string(),
+            Symbol::Output(output) => output.to_string(),
+            Symbol::Phi(phi) => phi.to_string(),
+        })
+    }
+
+    pub fn label(&self, symbol: Symbol<'_, T>) -> String {
+        match self {
+            Labeling::Debug => match symbol {
+                Symbol::State(state) => format!("{:?}", state),
+                Symbol::Input(input) => format!("{:?}", input),
+                Symbol::Output(output) => format!("{:?}", output),
+                Symbol::Phi(phi) => format!("{:?}", phi),
+            },
+            Labeling::Display(label) | Labeling::Custom(label) => label(symbol),
+        }
+    }
+
+    /// `true` for `Labeling::Debug`, whose labels are Rust syntax and are
+    /// set in code spans by the Markdown document.
+    pub fn is_debug(&self) -> bool {
+        matches!(self, Labeling::Debug)
+    }
+}
+
+impl<T: XMachine + ?Sized> Clone for Labeling<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: XMachine + ?Sized> Copy for Labeling<T> {}
+
+impl<T: XMachine + ?Sized> fmt::Debug for Labeling<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Labeling::Debug => "Debug",
+            Labeling::Display(_) => "Display",
+            Labeling::Custom(_) => "Custom",
+        })
+    }
+}
+
+/// Label of `state` under `T::labeling()`.
+pub fn state<T: XMachine>(state: T::State) -> String {
+    T::labeling().label(Symbol::State(state))
+}
+
+/// Label of `input` under `T::labeling()`.
+pub fn input<T: XMachine>(input: &T::Input) -> String {
+    T::labeling().label(Symbol::Input(input))
+}
+
+/// Label of `output` under `T::labeling()`.
+pub fn output<T: XMachine>(output: &T::Output) -> String {
+    T::labeling().label(Symbol::Output(output))
+}
+
+/// Label of `phi` under `T::labeling()`.
+pub fn phi<T: XMachine>(phi: T::Phi) -> String {
+    T::labeling().label(Symbol::Phi(phi))
+}
+
+/// Labels of `inputs`, as `[a, b, c]`.
+pub fn inputs<T: XMachine>(inputs: &[T::Input]) -> String {
+    let labels: Vec<String> = inputs.iter().map(input::<T>).collect();
+    format!("[{}]", labels.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodePhi};
+
+    fn glossary(symbol: Symbol<'_, Digicode>) -> String {
+        match symbol {
+            Symbol::Input(DigicodeInputAlphabet::Digit(digit)) => format!("key {}", digit),
+            Symbol::Phi(DigicodePhi::Finish) => "unlock".to_string(),
+            other => Labeling::<Digicode>::Debug.label(other),
+        }
+    }
+
+    #[test]
+    fn symbols_are_labelled_by_the_strategy() {
+        let entered = [DigicodeInputAlphabet::Digit(4), DigicodeInputAlphabet::OkEnter];
+        assert_eq!(inputs::<Digicode>(&entered), "[Digit(4), OkEnter]");
+        let custom = Labeling::<Digicode>::Custom(glossary);
+        assert!(!custom.is_debug());
+        assert_eq!(custom.label(Symbol::Input(&DigicodeInputAlphabet::Digit(4))), "key 4");
+        assert_eq!(custom.label(Symbol::Phi(DigicodePhi::Finish)), "unlock");
+        assert_eq!(custom.label(Symbol::Phi(DigicodePhi::Lock)), "Lock");
+    }
+}
@@ -0,0 +1,408 @@
+//! Experimental: active automata learning, the inverse of model-based testing.
+//!
+//! Given only a `SutAdapter`, `learn` infers a description of what the
+//! implementation does, e.g. to recover a spec for legacy firmware before
+//! writing an `XMachine` for it. The machine type `T` supplies nothing but the
+//! alphabets; its states and phis are never consulted.
+//!
+//! The loop follows L* for Mealy machines. Membership queries run an input
+//! word on a fresh SUT and record its outputs. The observation table keeps
+//! access words with pairwise different rows, so the hypothesis is always
+//! consistent, and counterexamples add all their suffixes as new columns.
+//! Equivalence queries are approximated by testing: the W-method suite of the
+//! hypothesis, then seeded random walks. A hypothesis passing both is the
+//! result, which is only as complete as those suites.
+//!
+//! A rejected input and a silent transition look alike from outside, so both
+//! are learned as transitions without output. Memory shows up as extra states.
+
+use crate::dynamic::{DynMachine, DynPhi, DynTransition};
+use crate::rng::SplitMix64;
+use crate::suite::GenerationSeed;
+use crate::sut::SutAdapter;
+use crate::XMachine;
+use std::collections::HashMap;
+
+/// Bounds and equivalence testing effort of `learn`.
+#[derive(Clone, Debug)]
+pub struct LearnOptions {
+    max_states: usize,
+    extra_states: usize,
+    random_walks: usize,
+    walk_length: usize,
+    seed: GenerationSeed,
+}
+
+impl LearnOptions {
+    /// At most 64 states, W-method suites allowing one extra state, and 100
+    /// random walks of 20 inputs drawn from `seed`.
+    pub fn new(seed: impl Into<GenerationSeed>) -> Self {
+        Self {
+            max_states: 64,
+            extra_states: 1,
+            random_walks: 100,
+            walk_length: 20,
+            seed: seed.into(),
+        }
+    }
+
+    /// Gives up once the hypothesis would exceed `max_states` states.
+    pub fn max_states(mut self, max_states: usize) -> Self {
+        self.max_states = max_states;
+        self
+    }
+
+    /// How many states the SUT may have beyond the hypothesis for the
+    /// W-method suite to still find the difference.
+    pub fn extra_states(mut self, extra_states: usize) -> Self {
+        self.extra_states = extra_states;
+        self
+    }
+
+    pub fn random_walks(mut self, walks: usize, walk_length: usize) -> Self {
+        self.random_walks = walks;
+        self.walk_length = walk_length;
+        self
+    }
+}
+
+/// The outcome of `learn`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Learned {
+    /// States are named `q0`, `q1`, ... in discovery order, `q0` being
+    /// initial. Each transition carries its output, and phis are named after
+    /// the output they emit, `silent` for none, followed by the target, as in
+    /// `silent to q2`, where a state emits that output towards several states.
+    pub machine: DynMachine,
+    /// `false` if `LearnOptions::max_states` stopped learning early; the
+    /// machine is then the last hypothesis.
+    pub converged: bool,
+    /// Input words run on a fresh SUT.
+    pub membership_queries: usize,
+    /// Hypotheses tested for equivalence, including the final one.
+    pub equivalence_queries: usize,
+}
+
+/// Infers a machine over the alphabets of `T` from the SUTs `new_sut`
+/// creates, one per membership query.
+pub fn learn<T, S>(name: &str, options: &LearnOptions, new_sut: impl FnMut() -> S) -> Learned
+where
+    T: XMachine,
+    S: SutAdapter<T>,
+{
+    let inputs = T::all_inputs();
+    let mut oracle = Oracle::<T, _> {
+        inputs: &inputs,
+        new_sut,
+        cache: HashMap::new(),
+        queries: 0,
+    };
+    let mut table = Table {
+        prefixes: vec![Vec::new()],
+        suffixes: (0..inputs.len()).map(|a| vec![a]).collect(),
+    };
+    let mut rng = SplitMix64::new(options.seed.0);
+    let mut equivalence_queries = 0;
+
+    loop {
+        let converged = table.close(&mut oracle, options.max_states);
+        let hypothesis = table.hypothesis(&mut oracle);
+        if !converged {
+            return hypothesis.into_learned(name, false, oracle.queries, equivalence_queries);
+        }
+
+        equivalence_queries += 1;
+        let Some(counterexample) = find_counterexample(&hypothesis, &table, options, &mut rng, &mut oracle) else {
+            return hypothesis.into_learned(name, true, oracle.queries, equivalence_queries);
+        };
+        for start in 0..counterexample.len() {
+            let suffix = counterexample[start..].to_vec();
+            if !table.suffixes.contains(&suffix) {
+                table.suffixes.push(suffix);
+            }
+        }
+    }
+}
+
+/// Answers membership queries, each word on a fresh SUT.
+struct Oracle<'a, T: XMachine, F> {
+    inputs: &'a [T::Input],
+    new_sut: F,
+    cache: HashMap<Vec<usize>, Vec<Option<T::Output>>>,
+    queries: usize,
+}
+
+impl<T, S, F> Oracle<'_, T, F>
+where
+    T: XMachine,
+    S: SutAdapter<T>,
+    F: FnMut() -> S,
+{
+    /// The outputs of `word`, one per input.
+    fn query(&mut self, word: &[usize]) -> Vec<Option<T::Output>> {
+        if let Some(outputs) = self.cache.get(word) {
+            return outputs.clone();
+        }
+        self.queries += 1;
+        let mut sut = (self.new_sut)();
+        let outputs: Vec<Option<T::Output>> = word.iter().map(|&a| sut.apply(&self.inputs[a])).collect();
+        self.cache.insert(word.to_vec(), outputs.clone());
+        outputs
+    }
+
+    /// The outputs of `suffix` after `prefix`.
+    fn query_suffix(&mut self, prefix: &[usize], suffix: &[usize]) -> Vec<Option<T::Output>> {
+        let word: Vec<usize> = prefix.iter().chain(suffix).copied().collect();
+        self.query(&word).split_off(prefix.len())
+    }
+}
+
+/// The observation table: the rows of `prefixes` are pairwise different.
+struct Table {
+    prefixes: Vec<Vec<usize>>,
+    suffixes: Vec<Vec<usize>>,
+}
+
+impl Table {
+    fn row<T, S, F>(&self, oracle: &mut Oracle<'_, T, F>, prefix: &[usize]) -> Vec<Option<T::Output>>
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+        F: FnMut() -> S,
+    {
+        self.suffixes.iter().flat_map(|suffix| oracle.query_suffix(prefix, suffix)).collect()
+    }
+
+    /// Adds every one-input extension whose row is new as a prefix, until
+    /// the table is closed. `false` if that would exceed `max_states`.
+    fn close<T, S, F>(&mut self, oracle: &mut Oracle<'_, T, F>, max_states: usize) -> bool
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+        F: FnMut() -> S,
+    {
+        let mut rows: Vec<_> = self.prefixes.iter().map(|p| self.row(oracle, p)).collect();
+        let mut idx = 0;
+        while idx < self.prefixes.len() {
+            for a in 0..oracle.inputs.len() {
+                let mut extension = self.prefixes[idx].clone();
+                extension.push(a);
+                let row = self.row(oracle, &extension);
+                if rows.contains(&row) {
+                    continue;
+                }
+                if self.prefixes.len() == max_states {
+                    return false;
+                }
+                self.prefixes.push(extension);
+                rows.push(row);
+            }
+            idx += 1;
+        }
+        true
+    }
+
+    /// One state per prefix. The target of an extension is the prefix with
+    /// the same row, or the source itself if the table is not closed.
+    fn hypothesis<T, S, F>(&self, oracle: &mut Oracle<'_, T, F>) -> Hypothesis<T>
+    where
+        T: XMachine,
+        S: SutAdapter<T>,
+        F: FnMut() -> S,
+    {
+        let rows: Vec<_> = self.prefixes.iter().map(|p| self.row(oracle, p)).collect();
+        let mut transitions = Vec::with_capacity(self.prefixes.len());
+        for (state, prefix) in self.prefixes.iter().enumerate() {
+            let mut row_transitions = Vec::with_capacity(oracle.inputs.len());
+            for a in 0..oracle.inputs.len() {
+                let mut extension = prefix.clone();
+                extension.push(a);
+                let row = self.row(oracle, &extension);
+                let target = rows.iter().position(|r| *r == row).unwrap_or(state);
+                let output = oracle.query(&extension).pop().flatten();
+                row_transitions.push((target, output));
+            }
+            transitions.push(row_transitions);
+        }
+        Hypothesis {
+            inputs: oracle.inputs.to_vec(),
+            transitions,
+        }
+    }
+}
+
+/// A Mealy machine: `transitions[state][input]` is the target and output.
+struct Hypothesis<T: XMachine> {
+    inputs: Vec<T::Input>,
+    transitions: Vec<Vec<(usize, Option<T::Output>)>>,
+}
+
+impl<T: XMachine> Hypothesis<T> {
+    fn outputs(&self, word: &[usize]) -> Vec<Option<T::Output>> {
+        let mut state = 0;
+        word.iter()
+            .map(|&a| {
+                let (target, output) = &self.transitions[state][a];
+                state = *target;
+                output.clone()
+            })
+            .collect()
+    }
+
+    fn into_learned(
+        self,
+        name: &str,
+        converged: bool,
+        membership_queries: usize,
+        equivalence_queries: usize,
+    ) -> Learned {
+        let state_name = |state: usize| format!("q{}", state);
+        let phi_name = |output: &Option<T::Output>| match output {
+            Some(output) => format!("{:?}", output),
+            None => "silent".to_string(),
+        };
+
+        // A phi leads to a single state from each source, so an output the
+        // same state emits towards several states names one phi per target.
+        let mut phis: Vec<DynPhi> = Vec::new();
+        let mut transitions = Vec::new();
+        for (from, row) in self.transitions.iter().enumerate() {
+            for (input, (to, output)) in self.inputs.iter().zip(row) {
+                let ambiguous = row.iter().any(|(other, o)| o == output && other != to);
+                let phi = if ambiguous {
+                    format!("{} to {}", phi_name(output), state_name(*to))
+                } else {
+                    phi_name(output)
+                };
+                if !phis.iter().any(|p| p.name == phi) {
+                    phis.push(DynPhi {
+                        name: phi.clone(),
+                        guard: None,
+                    });
+                }
+                transitions.push(DynTransition {
+                    from: state_name(from),
+                    input: format!("{:?}", input),
+                    phi,
+                    to: state_name(*to),
+                    output: output.as_ref().map(|output| format!("{:?}", output)),
+                });
+            }
+        }
+
+        Learned {
+            machine: DynMachine {
+                name: name.to_string(),
+                states: (0..self.transitions.len()).map(state_name).collect(),
+                initial_states: vec![state_name(0)],
+                final_states: Vec::new(),
+                inputs: self.inputs.iter().map(|input| format!("{:?}", input)).collect(),
+                outputs: T::all_outputs().iter().map(|output| format!("{:?}", output)).collect(),
+                phis,
+                transitions,
+            },
+            converged,
+            membership_queries,
+            equivalence_queries,
+        }
+    }
+}
+
+/// The shortest failing prefix of the first word, W-method suite first, on
+/// which the SUT and `hypothesis` disagree.
+fn find_counterexample<T, S, F>(
+    hypothesis: &Hypothesis<T>,
+    table: &Table,
+    options: &LearnOptions,
+    rng: &mut SplitMix64,
+    oracle: &mut Oracle<'_, T, F>,
+) -> Option<Vec<usize>>
+where
+    T: XMachine,
+    S: SutAdapter<T>,
+    F: FnMut() -> S,
+{
+    let mut check = |word: Vec<usize>| {
+        let expected = hypothesis.outputs(&word);
+        let actual = oracle.query(&word);
+        let mismatch = expected.iter().zip(&actual).position(|(e, a)| e != a)?;
+        Some(word[..=mismatch].to_vec())
+    };
+
+    // Access words, then every middle part of up to `extra_states + 1`
+    // inputs, then each characterizing suffix.
+    let input_count = hypothesis.inputs.len();
+    let mut middles: Vec<Vec<usize>> = vec![Vec::new()];
+    let mut layer = middles.clone();
+    for _ in 0..=options.extra_states {
+        layer = layer
+            .iter()
+            .flat_map(|word| (0..input_count).map(move |a| [word.as_slice(), &[a]].concat()))
+            .collect();
+        middles.extend(layer.iter().cloned());
+    }
+    for prefix in &table.prefixes {
+        for middle in &middles {
+            for suffix in &table.suffixes {
+                let word = [prefix.as_slice(), middle, suffix].concat();
+                if let Some(counterexample) = check(word) {
+                    return Some(counterexample);
+                }
+            }
+        }
+    }
+
+    if input_count == 0 {
+        return None;
+    }
+    for _ in 0..options.random_walks {
+        let word = (0..options.walk_length)
+            .map(|_| (rng.next_u64() % input_count as u64) as usize)
+            .collect();
+        if let Some(counterexample) = check(word) {
+            return Some(counterexample);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Door;
+    use crate::examples::traffic_light::{LightInput, LightOutput, TrafficLight};
+    use crate::runner::MachineRunner;
+
+    #[test]
+    fn learning_the_spec_recovers_its_states() {
+        let learned = learn::<Door, _>("door", &LearnOptions::new(1), MachineRunner::<Door>::new);
+        assert!(learned.converged);
+        assert_eq!(learned.machine.states.len(), 2);
+        assert_eq!(learned.machine.validate(), Ok(()));
+    }
+
+    /// Silent until a fault arms it, then shows green on every tick.
+    struct Latch(bool);
+
+    impl SutAdapter<TrafficLight> for Latch {
+        fn apply(&mut self, input: &LightInput) -> Option<LightOutput> {
+            match input {
+                LightInput::Fault => {
+                    self.0 = true;
+                    None
+                }
+                LightInput::Tick => self.0.then_some(LightOutput::ShowGreen),
+            }
+        }
+    }
+
+    #[test]
+    fn one_output_towards_several_states_names_one_phi_per_target() {
+        let learned = learn::<TrafficLight, _>("latch", &LearnOptions::new(1), || Latch(false));
+        assert_eq!(learned.machine.states.len(), 2);
+        assert_eq!(learned.machine.validate(), Ok(()));
+        let transitions = learned.machine.transitions.iter().filter(|t| t.from == "q0");
+        let phis: Vec<&str> = transitions.map(|t| t.phi.as_str()).collect();
+        assert_eq!(phis, ["silent to q0", "silent to q1"]);
+    }
+}
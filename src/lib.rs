@@ -1,4 +1,63 @@
+pub mod access;
+pub mod adaptive;
+pub mod analysis;
+pub mod archive;
+pub mod artifacts;
+pub mod cache;
+pub mod chaos;
+pub mod closed_loop;
+pub mod codec;
+pub mod compose;
+pub mod configuration;
+pub mod conformance;
+pub mod coverage;
+pub mod debug;
+pub mod docgen;
+pub mod dynamic;
+pub mod erased;
+#[cfg(feature = "examples")]
+pub mod examples;
+pub mod executor;
+pub mod extend;
+pub mod feasibility;
+pub mod findings;
+pub mod fingerprint;
+pub mod gherkin;
 pub mod graphviz;
+pub mod homing;
+pub mod index;
+pub mod isolation;
+pub mod journal;
+mod json;
+pub mod labels;
+pub mod learn;
 pub mod mbt;
+pub mod mock;
+pub mod monitor;
+pub mod multicast;
+pub mod mutant;
+pub mod network;
+pub mod noise;
+pub mod occupancy;
+pub mod parse;
+pub mod pool;
+pub mod regions;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod rng;
+pub mod runner;
+pub mod scaffold;
+pub mod scenario;
+pub mod search;
+pub mod shadow;
+pub mod simulation;
+pub mod state_space;
+pub mod store;
+pub mod suite;
+pub mod sut;
+pub mod timeline;
 pub mod traits;
-pub use traits::*;
\ No newline at end of file
+pub mod verify;
+mod yaml;
+pub use index::*;
+pub use traits::*;
@@ -1,13 +1,25 @@
-use crate::XMachine;
+use crate::rng::SplitMix64;
+use crate::analysis::{self, InputHandling};
+use crate::configuration::Configuration;
+use crate::dynamic::SpecDiff;
+use crate::feasibility::{FeasibilityOracle, NoOracle};
+use crate::isolation;
+use crate::labels;
+use crate::network::{MachineNetwork, Tagged, Wiring};
+use crate::runner::{MachineRunner, StepError};
+use crate::search;
+use crate::suite::GenerationSeed;
+use crate::store::Store;
+use crate::{TransitionSpec, XMachine};
 use std::fmt::Debug;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 
 /// Represents a generated test vector used to validate the implementation.
 ///
 /// Based on the stream X-machine testing method, a test suite is constructed
 /// by traversing the associated finite automaton
 #[derive(Debug)]
-pub struct TestCase<Input, Output> {
+pub struct TestCase<Input, Output, State, Memory> {
     /// A human-readable identifier for the test scenario.
     pub name: String,
 
@@ -21,11 +33,430 @@ pub struct TestCase<Input, Output> {
 
     /// γ (Gamma): The expected output symbol produced by the processing function.
     /// Used to satisfy Output-Distinguishability.
-    pub expected_output: Option<Output>,
+    pub expected_output: Expectation<Output>,
 
     /// W (Characterization): The sequence of inputs used to verify the resulting state.
     /// Derived from the Characterization Set (W-set) to distinguish the final state.
     pub verification_sequence: Vec<Input>,
+
+    /// Requirements exercised by this case, from `XMachine::requirement_ids`.
+    pub requirements: Vec<&'static str>,
+
+    /// (State, Memory) the spec is in after the setup sequence, when known.
+    /// Lets the executor inject the configuration directly instead of replaying
+    /// `setup_sequence` on SUTs that support it. A phi coverage case built
+    /// from a `FeasibilityOracle` witness may hold a configuration the setup
+    /// sequence does not produce; see `requires_injection`.
+    pub setup_configuration: Option<(State, Memory)>,
+}
+
+/// `TestCase` specialised to the symbols and memory of machine `T`.
+pub type TestCaseOf<T> = TestCase<
+    <T as XMachine>::Input,
+    <T as XMachine>::Output,
+    <T as XMachine>::State,
+    <T as XMachine>::Memory,
+>;
+
+/// A robustness test for a network of two components, driven from the environment.
+#[derive(Clone, Debug)]
+pub struct NetworkTestCase<InputA, InputB, OutputA, OutputB> {
+    pub name: String,
+    /// Environment inputs leading to the stable configuration under test.
+    pub setup_sequence: Vec<Tagged<InputA, InputB>>,
+    /// An environment input the addressed component rejects in that configuration.
+    pub test_input: Tagged<InputA, InputB>,
+    /// Everything the network must emit for `test_input`; it must also stay
+    /// stable, with both components in the configuration they had before it.
+    pub expected_outputs: Vec<Tagged<OutputA, OutputB>>,
+}
+
+/// `NetworkTestCase` specialised to the symbols of components `A` and `B`.
+pub type NetworkTestCaseOf<A, B> = NetworkTestCase<
+    <A as XMachine>::Input,
+    <B as XMachine>::Input,
+    <A as XMachine>::Output,
+    <B as XMachine>::Output,
+>;
+
+/// Expected emissions of a network for an environment input it rejects.
+pub type Rejection<'a, A, B> = &'a dyn Fn(
+    &Tagged<<A as XMachine>::Input, <B as XMachine>::Input>,
+) -> Vec<Tagged<<A as XMachine>::Output, <B as XMachine>::Output>>;
+
+/// What a test accepts as the output of its test input.
+///
+/// Generators produce `Exact` or `None`; loosen a case to `AnyOf` or
+/// `Predicate` when the payload depends on memory the SUT does not expose
+/// (e.g. a click counter).
+#[derive(Clone, Debug)]
+pub enum Expectation<Output> {
+    /// Exactly this output.
+    Exact(Output),
+    /// Any output accepted by the function; the label names it in reports.
+    Predicate(&'static str, fn(&Output) -> bool),
+    /// Any one of these outputs.
+    AnyOf(Vec<Output>),
+    /// No output at all.
+    None,
+}
+
+impl<Output: PartialEq> Expectation<Output> {
+    /// `true` if `actual` satisfies the expectation.
+    pub fn matches(&self, actual: Option<&Output>) -> bool {
+        match (self, actual) {
+            (Expectation::Exact(expected), Some(actual)) => expected == actual,
+            (Expectation::Predicate(_, check), Some(actual)) => check(actual),
+            (Expectation::AnyOf(options), Some(actual)) => options.contains(actual),
+            (Expectation::None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<Output> From<Option<Output>> for Expectation<Output> {
+    fn from(output: Option<Output>) -> Self {
+        match output {
+            Some(output) => Expectation::Exact(output),
+            None => Expectation::None,
+        }
+    }
+}
+
+/// Predicates compare by label: function pointers have no reliable identity.
+impl<Output: PartialEq> PartialEq for Expectation<Output> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expectation::Exact(a), Expectation::Exact(b)) => a == b,
+            (Expectation::Predicate(a, _), Expectation::Predicate(b, _)) => a == b,
+            (Expectation::AnyOf(a), Expectation::AnyOf(b)) => a == b,
+            (Expectation::None, Expectation::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl<Input, Output, State, Memory> TestCase<Input, Output, State, Memory> {
+    /// Starts a hand-written case named `name`, validated against spec `T`
+    /// by `TestCaseBuilder::build`.
+    pub fn builder<T>(name: &str) -> TestCaseBuilder<T>
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+    {
+        TestCaseBuilder {
+            name: name.to_string(),
+            setup_sequence: Vec::new(),
+            state: None,
+            test_input: None,
+            expected_output: None,
+            verification_sequence: Vec::new(),
+            requirements: None,
+        }
+    }
+
+    /// `true` if replaying `setup_sequence` on spec `T` does not reach
+    /// `setup_configuration`. The case then only runs faithfully on a SUT
+    /// that supports `SutAdapter::inject`; otherwise the executor fails it
+    /// with `Verdict::InjectionRequired`.
+    pub fn requires_injection<T>(&self) -> bool
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+        Memory: Store,
+    {
+        !SxMTester::reproduces::<T>(&self.setup_sequence, self.setup_configuration.as_ref())
+    }
+}
+
+/// Why a hand-written case does not match the spec.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TestCaseError<Input, Output, State, Phi> {
+    /// No test input was given.
+    MissingInput,
+    /// No expected output was given.
+    MissingExpectation,
+    /// The spec rejects the setup input at `index`.
+    SetupRejected { index: usize, error: StepError<State, Phi> },
+    /// The setup sequence leads elsewhere than the state the case claims.
+    StateMismatch { claimed: State, reached: State },
+    /// The spec defines no transition for the test input after the setup.
+    InputRejected { input: Input, error: StepError<State, Phi> },
+    /// The spec emits `spec` for the test input, which the expectation does not accept.
+    OutputInfeasible { expected: Expectation<Output>, spec: Option<Output> },
+}
+
+/// `TestCaseError` specialised to the symbols of machine `T`.
+pub type TestCaseErrorOf<T> = TestCaseError<
+    <T as XMachine>::Input,
+    <T as XMachine>::Output,
+    <T as XMachine>::State,
+    <T as XMachine>::Phi,
+>;
+
+/// A hand-written test case of spec `T`, checked against the spec when built,
+/// so cases added by hand cannot silently drift from the model.
+pub struct TestCaseBuilder<T: XMachine> {
+    name: String,
+    setup_sequence: Vec<T::Input>,
+    state: Option<T::State>,
+    test_input: Option<T::Input>,
+    expected_output: Option<Expectation<T::Output>>,
+    verification_sequence: Vec<T::Input>,
+    requirements: Option<Vec<&'static str>>,
+}
+
+impl<T: XMachine> TestCaseBuilder<T> {
+    pub fn setup(mut self, inputs: impl IntoIterator<Item = T::Input>) -> Self {
+        self.setup_sequence = inputs.into_iter().collect();
+        self
+    }
+
+    /// The state the setup sequence is meant to reach.
+    pub fn in_state(mut self, state: T::State) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    pub fn input(mut self, input: T::Input) -> Self {
+        self.test_input = Some(input);
+        self
+    }
+
+    /// What the test input must produce: an output, `None`, or any `Expectation`.
+    pub fn expect(mut self, expected: impl Into<Expectation<T::Output>>) -> Self {
+        self.expected_output = Some(expected.into());
+        self
+    }
+
+    pub fn verify(mut self, inputs: impl IntoIterator<Item = T::Input>) -> Self {
+        self.verification_sequence = inputs.into_iter().collect();
+        self
+    }
+
+    /// Replaces the requirements taken from `XMachine::requirement_ids`.
+    pub fn requirements(mut self, ids: &[&'static str]) -> Self {
+        self.requirements = Some(ids.to_vec());
+        self
+    }
+
+    /// Replays the case on the spec from an initial configuration: the setup
+    /// sequence must be accepted and reach the claimed state, the spec must
+    /// take a transition on the test input, and the output it emits must
+    /// satisfy the expectation.
+    pub fn build(self) -> Result<TestCaseOf<T>, TestCaseErrorOf<T>> {
+        let test_input = self.test_input.ok_or(TestCaseError::MissingInput)?;
+        let expected_output = self.expected_output.ok_or(TestCaseError::MissingExpectation)?;
+
+        let mut runner = MachineRunner::<T>::new();
+        for (index, input) in self.setup_sequence.iter().enumerate() {
+            runner
+                .step(input)
+                .map_err(|error| TestCaseError::SetupRejected { index, error })?;
+        }
+        let reached = runner.state();
+        if let Some(claimed) = self.state.filter(|&claimed| claimed != reached) {
+            return Err(TestCaseError::StateMismatch { claimed, reached });
+        }
+        let setup_configuration = (reached, runner.store().clone());
+
+        let transition = match runner.step(&test_input) {
+            Ok(transition) => transition,
+            Err(error) => return Err(TestCaseError::InputRejected { input: test_input, error }),
+        };
+        if !expected_output.matches(transition.output.as_ref()) {
+            return Err(TestCaseError::OutputInfeasible {
+                expected: expected_output,
+                spec: transition.output,
+            });
+        }
+
+        Ok(TestCase {
+            name: self.name,
+            setup_sequence: self.setup_sequence,
+            test_input,
+            expected_output,
+            verification_sequence: self.verification_sequence,
+            requirements: self
+                .requirements
+                .unwrap_or_else(|| T::requirement_ids(reached, transition.phi).to_vec()),
+            setup_configuration: Some(setup_configuration),
+        })
+    }
+}
+
+/// Maps every requirement declared on a machine to the tests that cover it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceabilityMatrix {
+    pub entries: BTreeMap<&'static str, Vec<String>>,
+}
+
+impl TraceabilityMatrix {
+    /// Requirements that no test covers.
+    pub fn uncovered(&self) -> Vec<&'static str> {
+        self.entries
+            .iter()
+            .filter(|(_, tests)| tests.is_empty())
+            .map(|(&id, _)| id)
+            .collect()
+    }
+
+    /// Renders one `requirement,test` row per covering test; uncovered
+    /// requirements get a row with an empty test column.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("requirement,test\n");
+        for (id, tests) in &self.entries {
+            if tests.is_empty() {
+                out.push_str(&format!("{},\n", csv_field(id)));
+            }
+            for test in tests {
+                out.push_str(&format!("{},{}\n", csv_field(id), csv_field(test)));
+            }
+        }
+        out
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Reliability estimate from the verdicts of statistically sampled test cases.
+///
+/// Only meaningful for suites drawn from an operational profile, where the
+/// failure frequency approximates the failure rate seen in the field.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReliabilityEstimate {
+    pub runs: usize,
+    pub failures: usize,
+    /// Point estimate of the probability that a usage scenario succeeds.
+    pub reliability: f64,
+    /// Upper bound of the 95% Wilson score interval on the failure probability.
+    pub failure_upper_bound: f64,
+}
+
+impl ReliabilityEstimate {
+    /// Estimates reliability from one verdict (`true` = passed) per executed case.
+    pub fn from_verdicts(verdicts: impl IntoIterator<Item = bool>) -> Self {
+        let (mut runs, mut failures) = (0, 0);
+        for passed in verdicts {
+            runs += 1;
+            if !passed {
+                failures += 1;
+            }
+        }
+        Self::from_counts(runs, failures)
+    }
+
+    pub fn from_counts(runs: usize, failures: usize) -> Self {
+        if runs == 0 {
+            return Self {
+                runs,
+                failures,
+                reliability: 0.0,
+                failure_upper_bound: 1.0,
+            };
+        }
+        let n = runs as f64;
+        let p = failures as f64 / n;
+        let z = 1.96_f64;
+        let centre = p + z * z / (2.0 * n);
+        let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+        Self {
+            runs,
+            failures,
+            reliability: 1.0 - p,
+            failure_upper_bound: ((centre + margin) / (1.0 + z * z / n)).min(1.0),
+        }
+    }
+}
+
+/// Restrictions on the setup sequences `SxMTester` searches for, e.g. to keep
+/// a hardware rig out of states it cannot tolerate.
+///
+/// A constrained search still returns a shortest path among those allowed,
+/// trying preferred inputs first when several are equally short. Initial
+/// states are allowed even if forbidden, since a path cannot avoid them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathConstraints<State, Phi, Input> {
+    forbidden_states: Vec<State>,
+    forbidden_phis: Vec<Phi>,
+    preferred_inputs: Vec<Input>,
+    pub(crate) max_len: Option<usize>,
+}
+
+/// `PathConstraints` specialised to the symbols of machine `T`.
+pub type PathConstraintsOf<T> = PathConstraints<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Input>;
+
+impl<State: PartialEq, Phi: PartialEq, Input: PartialEq> PathConstraints<State, Phi, Input> {
+    /// No restriction beyond the default depth of the phi coverage search.
+    pub fn new() -> Self {
+        Self {
+            forbidden_states: Vec::new(),
+            forbidden_phis: Vec::new(),
+            preferred_inputs: Vec::new(),
+            max_len: None,
+        }
+    }
+
+    /// Never enters `state` before the test input.
+    pub fn avoid_state(mut self, state: State) -> Self {
+        self.forbidden_states.push(state);
+        self
+    }
+
+    /// Never applies `phi`, or another instance of its kind, before the test input.
+    pub fn avoid_phi(mut self, phi: Phi) -> Self {
+        self.forbidden_phis.push(phi);
+        self
+    }
+
+    /// Tries `input` before the inputs not preferred. Repeat to prefer several,
+    /// in decreasing order of preference.
+    pub fn prefer_input(mut self, input: Input) -> Self {
+        self.preferred_inputs.push(input);
+        self
+    }
+
+    /// Rejects setup sequences longer than `max_len` inputs.
+    pub fn max_len(mut self, max_len: usize) -> Self {
+        self.max_len = Some(max_len);
+        self
+    }
+
+    /// `true` unless `phi`, compared by `T::phi_kind`, or `to` is forbidden,
+    /// so forbidding a kind's representative forbids all its instances.
+    pub(crate) fn permits<T: XMachine<State = State, Phi = Phi>>(&self, phi: &Phi, to: &State) -> bool
+    where
+        Phi: Copy,
+    {
+        let kind = T::phi_kind(*phi);
+        let forbidden_phi = self.forbidden_phis.iter().any(|&forbidden| T::phi_kind(forbidden) == kind);
+        !forbidden_phi && !self.forbidden_states.contains(to)
+    }
+
+    pub(crate) fn within(&self, len: usize) -> bool {
+        self.max_len.map_or(true, |max| len <= max)
+    }
+
+    /// `inputs` with the preferred ones first, in preference order.
+    pub(crate) fn order<'a>(&self, inputs: &'a [Input]) -> Vec<&'a Input> {
+        let mut ordered: Vec<&Input> = self
+            .preferred_inputs
+            .iter()
+            .filter_map(|preferred| inputs.iter().find(|i| *i == preferred))
+            .collect();
+        ordered.extend(inputs.iter().filter(|i| !self.preferred_inputs.contains(i)));
+        ordered
+    }
+}
+
+impl<State: PartialEq, Phi: PartialEq, Input: PartialEq> Default for PathConstraints<State, Phi, Input> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct SxMTester;
@@ -33,57 +464,351 @@ pub struct SxMTester;
 impl SxMTester {
     /// Generates conformance tests (W-Method).
     /// These prove the implementation logic matches the Spec.
-    pub fn generate_logic_tests<T: XMachine>(
+    pub fn generate_logic_tests<T>(
         distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
-    ) -> Vec<TestCase<T::Input, T::Output>> {
-        let mut tests = Vec::new();
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::logic_tests_iter::<T>(distinguishing_sequences).collect()
+    }
 
-        for &target_state in T::all_states() {
-            if let Some(path_to_state) = Self::find_path_to_state::<T>(target_state) {
-                for input in T::all_inputs() {
-                    if let Some(phi) = T::get_phi_for_input(target_state, input) {
-                        if let Some(expected_next_state) = T::next_state(target_state, phi) {
-                            let verify_seq = distinguishing_sequences(expected_next_state);
-                            let mut dummy_mem = T::initial_store();
-                            let expected_out =
-                                T::execute_phi(phi, &mut dummy_mem, input).ok().flatten();
-
-                            tests.push(TestCase {
-                                name: format!(
-                                    "Logic Verify: {:?} + {:?} -> {:?}",
-                                    target_state, input, expected_next_state
-                                ),
-                                setup_sequence: path_to_state.clone(),
-                                test_input: input.clone(),
-                                expected_output: expected_out,
-                                verification_sequence: verify_seq,
-                            });
-                        }
-                    }
-                }
+    /// Lazy `generate_logic_tests`: cases are produced on demand, in the same order.
+    pub fn logic_tests_iter<'a, T>(
+        distinguishing_sequences: &'a dyn Fn(T::State) -> Vec<T::Input>,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a
+    where
+        T: XMachine + 'a,
+        T::Memory: Store,
+    {
+        Self::logic_tests_with_constraints_iter::<T>(distinguishing_sequences, &PathConstraints::new())
+    }
+
+    /// Like `generate_logic_tests`, with setup sequences searched under
+    /// `constraints`. States no allowed path reaches are not tested.
+    pub fn generate_logic_tests_with_constraints<T>(
+        distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
+        constraints: &PathConstraintsOf<T>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::logic_tests_with_constraints_iter::<T>(distinguishing_sequences, constraints).collect()
+    }
+
+    /// Lazy `generate_logic_tests_with_constraints`.
+    pub fn logic_tests_with_constraints_iter<'a, T>(
+        distinguishing_sequences: &'a dyn Fn(T::State) -> Vec<T::Input>,
+        constraints: &PathConstraintsOf<T>,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a
+    where
+        T: XMachine + 'a,
+        T::Memory: Store,
+    {
+        let constraints = constraints.clone();
+        T::all_states().iter().flat_map(move |&target_state| {
+            let path_to_state = Self::setup_path::<T>(target_state, &constraints);
+            let setup_configuration = path_to_state.as_deref().and_then(Self::replay_setup::<T>);
+            path_to_state
+                .map(|path| Self::inputs::<T>().map(move |input| (path.clone(), input)))
+                .into_iter()
+                .flatten()
+                .filter_map(move |(path_to_state, input)| {
+                    let phi = T::get_phi_for_input(target_state, &input)?;
+                    let expected_next_state = T::next_state(target_state, phi)?;
+                    let verify_seq = distinguishing_sequences(expected_next_state);
+                    let mut dummy_mem = T::initial_store();
+                    let expected_out =
+                        isolation::execute_phi::<T>(target_state, phi, &mut dummy_mem, &input).ok().flatten();
+
+                    Some(TestCase {
+                        name: format!(
+                            "Logic Verify: {} + {} -> {}{}",
+                            labels::state::<T>(target_state),
+                            labels::input::<T>(&input),
+                            labels::state::<T>(expected_next_state),
+                            Self::guard_suffix::<T>(phi)
+                        ),
+                        setup_sequence: path_to_state,
+                        test_input: input,
+                        expected_output: expected_out.into(),
+                        verification_sequence: verify_seq,
+                        requirements: T::requirement_ids(target_state, phi).to_vec(),
+                        setup_configuration: setup_configuration.clone(),
+                    })
+                })
+        })
+    }
+
+    /// Generates Input-Completeness tests.
+    /// These prove the hardware handles invalid inputs safely.
+    /// Only `TransitionSpec::Forbidden` inputs are tested; `Unspecified` ones are left open,
+    /// and inputs an explicit ignore handles are left to `generate_ignore_tests`.
+    pub fn generate_robustness_tests<T>() -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::robustness_tests_iter::<T>().collect()
+    }
+
+    /// Lazy `generate_robustness_tests`: cases are produced on demand, in the same order.
+    pub fn robustness_tests_iter<T>() -> impl Iterator<Item = TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::robustness_tests_with_constraints_iter::<T>(&PathConstraints::new())
+    }
+
+    /// Like `generate_robustness_tests`, with setup sequences searched under
+    /// `constraints`. States no allowed path reaches are not tested.
+    pub fn generate_robustness_tests_with_constraints<T>(
+        constraints: &PathConstraintsOf<T>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::robustness_tests_with_constraints_iter::<T>(constraints).collect()
+    }
+
+    /// Lazy `generate_robustness_tests_with_constraints`.
+    pub fn robustness_tests_with_constraints_iter<T>(
+        constraints: &PathConstraintsOf<T>,
+    ) -> impl Iterator<Item = TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        let constraints = constraints.clone();
+        T::all_states().iter().flat_map(move |&state| {
+            let path = Self::setup_path::<T>(state, &constraints);
+            let setup_configuration = path.as_deref().and_then(Self::replay_setup::<T>);
+            path.map(|path| Self::inputs::<T>().map(move |input| (path.clone(), input)))
+                .into_iter()
+                .flatten()
+                .filter(move |(_, input)| T::transition_spec(state, input) == TransitionSpec::Forbidden)
+                .map(move |(path, input)| TestCase {
+                    name: format!(
+                        "Robustness: {} should reject {}",
+                        labels::state::<T>(state),
+                        labels::input::<T>(&input)
+                    ),
+                    setup_sequence: path,
+                    test_input: input,
+                    expected_output: Expectation::None,
+                    verification_sequence: vec![],
+                    requirements: Vec::new(),
+                    setup_configuration: setup_configuration.clone(),
+                })
+        })
+    }
+
+    /// Generates a case per input an explicit ignore phi handles, as told by
+    /// `analysis::input_handling`: the implementation must emit the output
+    /// `XMachine::ignore_output` declares and stay in its state, unlike a
+    /// robustness case, which expects no output at all.
+    pub fn generate_ignore_tests<T>() -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::generate_ignore_tests_with_constraints::<T>(&PathConstraints::new())
+    }
+
+    /// Like `generate_ignore_tests`, with setup sequences searched under
+    /// `constraints`. States no allowed path reaches are not tested.
+    pub fn generate_ignore_tests_with_constraints<T>(
+        constraints: &PathConstraintsOf<T>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        let mut tests = Vec::new();
+        for (state, inputs) in analysis::ignored_inputs::<T>() {
+            let Some(path) = Self::setup_path::<T>(state, constraints) else {
+                continue;
+            };
+            let setup_configuration = Self::replay_setup::<T>(&path);
+            for input in inputs {
+                let InputHandling::Ignored { phi, output } = analysis::input_handling::<T>(state, &input) else {
+                    continue;
+                };
+                tests.push(TestCase {
+                    name: format!(
+                        "Ignore: {} should ignore {} with {}",
+                        labels::state::<T>(state),
+                        labels::input::<T>(&input),
+                        labels::output::<T>(&output)
+                    ),
+                    setup_sequence: path.clone(),
+                    test_input: input,
+                    expected_output: Expectation::Exact(output),
+                    verification_sequence: vec![],
+                    requirements: T::requirement_ids(state, phi).to_vec(),
+                    setup_configuration: setup_configuration.clone(),
+                });
             }
         }
         tests
     }
 
-    /// Generates Input-Completeness tests.
-    /// These prove the hardware handles invalid inputs safely.
-    pub fn generate_robustness_tests<T: XMachine>() -> Vec<TestCase<T::Input, T::Output>> {
+    /// Generates the logic and robustness cases a spec change affects, for a
+    /// focused run before the full suite. `diff` is usually
+    /// `previous.diff(&DynMachine::from_machine::<T>(..))`, taken when the
+    /// archived `SpecFingerprint` no longer matches.
+    ///
+    /// A case is kept if its setup, test input or verification sequence
+    /// crosses a transition `diff` touches, followed on the associated
+    /// automaton from the first initial state. Removed transitions are
+    /// covered by the robustness cases of the inputs they used to handle.
+    pub fn generate_delta_tests<T>(
+        diff: &SpecDiff,
+        distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        if diff.is_empty() {
+            return Vec::new();
+        }
+        let affected = |case: &TestCaseOf<T>| {
+            let Some(&start) = T::initial_states().first() else {
+                return false;
+            };
+            let word = case
+                .setup_sequence
+                .iter()
+                .chain(std::iter::once(&case.test_input))
+                .chain(&case.verification_sequence);
+            let mut state = start;
+            for input in word {
+                if diff.touches(&format!("{:?}", state), &format!("{:?}", input)) {
+                    return true;
+                }
+                match T::get_phi_for_input(state, input).and_then(|phi| T::next_state(state, phi)) {
+                    Some(next) => state = next,
+                    None => return false,
+                }
+            }
+            false
+        };
+
+        let mut cases: Vec<TestCaseOf<T>> =
+            Self::logic_tests_iter::<T>(distinguishing_sequences).filter(|case| affected(case)).collect();
+        cases.extend(Self::robustness_tests_iter::<T>().filter(|case| affected(case)));
+        cases
+    }
+
+    /// Generates tests checking that `XMachine::reset_input` is a reliable
+    /// reset, for SUTs without a reset of their own: from every reachable
+    /// state, the reset input must produce the spec's output and lead to the
+    /// state `distinguishing_sequences` then verifies. Empty if `T` declares
+    /// no reset input.
+    pub fn generate_reset_tests<T>(
+        distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        let Some(reset) = T::reset_input() else {
+            return Vec::new();
+        };
+        let constraints = PathConstraints::new();
+        T::all_states()
+            .iter()
+            .filter_map(|&state| {
+                let path = Self::setup_path::<T>(state, &constraints)?;
+                let (setup_state, store) = Self::replay_setup::<T>(&path)?;
+                let transition = MachineRunner::<T>::from_parts(setup_state, store.clone()).step(&reset).ok();
+                let (expected_output, target, requirements) = match &transition {
+                    Some(t) => (t.output.clone(), t.to, T::requirement_ids(t.from, t.phi).to_vec()),
+                    None => (None, setup_state, Vec::new()),
+                };
+                Some(TestCase {
+                    name: format!(
+                        "Reset: {} + {} -> {}",
+                        labels::state::<T>(state),
+                        labels::input::<T>(&reset),
+                        labels::state::<T>(target)
+                    ),
+                    setup_sequence: path,
+                    test_input: reset.clone(),
+                    expected_output: expected_output.into(),
+                    verification_sequence: distinguishing_sequences(target),
+                    requirements,
+                    setup_configuration: Some((setup_state, store)),
+                })
+            })
+            .collect()
+    }
+
+    /// Generates Input-Completeness tests for a network in quiescent states.
+    ///
+    /// Explores every stable network configuration reachable within `depth`
+    /// environment inputs and, in each, every environment input its addressed
+    /// component rejects. The network must then stay stable, emit exactly
+    /// `rejection(input)` (nothing for silent rejection, or e.g. an error
+    /// code) and leave both components in their configuration, which
+    /// `Executor::run_network_case` checks when the SUT exposes them.
+    pub fn generate_network_robustness_tests<A, B, W>(
+        depth: usize,
+        rejection: Rejection<'_, A, B>,
+    ) -> Vec<NetworkTestCaseOf<A, B>>
+    where
+        A: XMachine,
+        B: XMachine,
+        W: Wiring<A, B>,
+        A::Memory: Store,
+        B::Memory: Store,
+    {
+        let mut inputs: Vec<Tagged<A::Input, B::Input>> = A::all_inputs().iter().cloned().map(Tagged::A).collect();
+        inputs.extend(B::all_inputs().iter().cloned().map(Tagged::B));
+
+        let mut seen: Vec<(Configuration<A>, Configuration<B>)> = Vec::new();
+        let mut queue = VecDeque::new();
+        queue.push_back((MachineNetwork::<A, B, W>::new(), Vec::new()));
         let mut tests = Vec::new();
 
-        for &state in T::all_states() {
-            if let Some(path) = Self::find_path_to_state::<T>(state) {
-                for input in T::all_inputs() {
-                    let is_defined = T::get_phi_for_input(state, input).is_some();
-
-                    if !is_defined {
-                        tests.push(TestCase {
-                            name: format!("Robustness: {:?} should reject {:?}", state, input),
-                            setup_sequence: path.clone(),
-                            test_input: input.clone(),
-                            expected_output: None,
-                            verification_sequence: vec![],
-                        });
+        while let Some((network, path)) = queue.pop_front() {
+            let config = (network.a().configuration().clone(), network.b().configuration().clone());
+            if seen.contains(&config) {
+                continue;
+            }
+            seen.push(config);
+
+            for input in &inputs {
+                let rejected = match input {
+                    Tagged::A(i) => network.a().configuration().step(i).is_err(),
+                    Tagged::B(i) => network.b().configuration().step(i).is_err(),
+                };
+                if rejected {
+                    tests.push(NetworkTestCase {
+                        name: format!(
+                            "Network Robustness: ({}, {}) should reject {}",
+                            labels::state::<A>(network.a().state()),
+                            labels::state::<B>(network.b().state()),
+                            match input {
+                                Tagged::A(i) => format!("A({})", labels::input::<A>(i)),
+                                Tagged::B(i) => format!("B({})", labels::input::<B>(i)),
+                            }
+                        ),
+                        setup_sequence: path.clone(),
+                        test_input: input.clone(),
+                        expected_outputs: rejection(input),
+                    });
+                } else if path.len() < depth {
+                    let mut next = network.clone();
+                    if next.process_input(input.clone()).stable {
+                        let mut next_path = path.clone();
+                        next_path.push(input.clone());
+                        queue.push_back((next, next_path));
                     }
                 }
             }
@@ -91,110 +816,545 @@ impl SxMTester {
         tests
     }
 
-    /// Breadth-First Search to find the shortest input sequence to a target state
-    fn find_path_to_state<T: XMachine>(target: T::State) -> Option<Vec<T::Input>> {
-        let mut queue: VecDeque<(T::State, Vec<T::Input>)> = VecDeque::new();
-        let mut visited: Vec<T::State> = Vec::new();
-
-        for &start in T::initial_states() {
-            if start == target {
-                return Some(vec![]);
+    /// Builds the requirement → covering tests matrix for `tests`.
+    /// Every requirement declared through `XMachine::requirement_ids` gets an
+    /// entry, so uncovered requirements show up with an empty list.
+    pub fn traceability_matrix<T: XMachine>(tests: &[TestCaseOf<T>]) -> TraceabilityMatrix {
+        let mut matrix = TraceabilityMatrix::default();
+        for id in Self::declared_requirements::<T>() {
+            matrix.entries.entry(id).or_default();
+        }
+        for test in tests {
+            for &id in &test.requirements {
+                matrix.entries.entry(id).or_default().push(test.name.clone());
             }
-            queue.push_back((start, vec![]));
-            visited.push(start);
-        }
-
-        while let Some((current_state, path)) = queue.pop_front() {
-            for input in T::all_inputs() {
-                if let Some(phi) = T::get_phi_for_input(current_state, input) {
-                    if let Some(next_state) = T::next_state(current_state, phi) {
-                        if next_state == target {
-                            let mut full_path = path.clone();
-                            full_path.push(input.clone());
-                            return Some(full_path);
-                        }
-
-                        if !visited.contains(&next_state) {
-                            visited.push(next_state);
-                            let mut new_path = path.clone();
-                            new_path.push(input.clone());
-                            queue.push_back((next_state, new_path));
-                        }
+        }
+        matrix
+    }
+
+    /// All requirement identifiers attached to any transition of `T`.
+    pub fn declared_requirements<T: XMachine>() -> Vec<&'static str> {
+        let mut ids: Vec<&'static str> = Vec::new();
+        for &state in T::all_states() {
+            for &phi in T::all_phis() {
+                for &id in T::requirement_ids(state, phi) {
+                    if !ids.contains(&id) {
+                        ids.push(id);
                     }
                 }
             }
         }
-        None
+        ids
+    }
+
+    /// Configuration the spec reaches by processing `setup` from its initial
+    /// configuration, or `None` if some input is rejected on the way.
+    pub fn replay_setup<T: XMachine>(setup: &[T::Input]) -> Option<(T::State, T::Memory)> {
+        let mut runner = MachineRunner::<T>::new();
+        runner.run(setup).ok()?;
+        Some(runner.into_configuration().into_parts())
+    }
+
+    /// `true` if the spec reaches `configuration` by `setup`, or nothing is claimed.
+    pub(crate) fn reproduces<T>(setup: &[T::Input], configuration: Option<&(T::State, T::Memory)>) -> bool
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        configuration.map_or(true, |(state, store)| {
+            Self::replay_setup::<T>(setup).is_some_and(|(reached, memory)| reached == *state && memory == *store)
+        })
+    }
+
+    /// The setup sequence of cases testing `state`: `search::path_to_state`
+    /// if the spec accepts it, else the cheapest sequence reaching `state`
+    /// with every guard on the way holding. If there is none either, the
+    /// automaton path is kept, without a setup configuration.
+    fn setup_path<T>(state: T::State, constraints: &PathConstraintsOf<T>) -> Option<Vec<T::Input>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        let path = search::path_to_state::<T>(state, constraints)?;
+        if Self::replay_setup::<T>(&path).is_some() {
+            return Some(path);
+        }
+        Some(search::search_settled::<T>(&search::Goal::State(state), constraints).map_or(path, |found| found.path))
+    }
+
+    /// Every input symbol of `T`, cloned one at a time.
+    fn inputs<T: XMachine>() -> impl Iterator<Item = T::Input> {
+        let inputs = T::all_inputs();
+        (0..inputs.len()).map(move |i| inputs[i].clone())
+    }
+
+    /// Renders the guard documentation of `phi` as a name suffix, if any.
+    fn guard_suffix<T: XMachine>(phi: T::Phi) -> String {
+        T::phi_guard_doc(phi)
+            .map(|doc| format!(" [{}]", doc))
+            .unwrap_or_default()
     }
 
     /// Generates tests by finding a path to execute EVERY valid Phi function.
     /// This discovers data-dependent paths (like the PIN code).
-    pub fn generate_phi_coverage_tests<T: XMachine>(
+    pub fn generate_phi_coverage_tests<T>(
         distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>
-    ) -> Vec<TestCase<T::Input, T::Output>> {
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::phi_coverage_tests_iter::<T>(distinguishing_sequences).collect()
+    }
 
-        let mut tests = Vec::new();
-        for &start_state in T::all_states() {
-            for input in T::all_inputs() {
-                if let Some(target_phi) = T::get_phi_for_input(start_state, input) {
-                    if let Some((setup_path, resulting_memory)) = Self::find_path_to_satisfy_phi::<T>(start_state, target_phi, input) {
-                        let mut test_mem = resulting_memory.clone();
-                        let expected_output = T::execute_phi(target_phi, &mut test_mem, input).ok().flatten();
-                        let next_state = T::next_state(start_state, target_phi).unwrap();
-
-                        tests.push(TestCase {
-                            name: format!("Phi Verify: {:?} (via {:?})", target_phi, setup_path),
-                            setup_sequence: setup_path,
-                            test_input: input.clone(),
-                            expected_output,
-                            verification_sequence: distinguishing_sequences(next_state),
-                        });
-                    } else {
-                        println!("Warning: Could not find data path to execute Phi '{:?}' from State '{:?}'", target_phi, start_state);
-                    }
-                }
+    /// Lazy `generate_phi_coverage_tests`: cases are produced on demand, in the same order.
+    pub fn phi_coverage_tests_iter<'a, T>(
+        distinguishing_sequences: &'a dyn Fn(T::State) -> Vec<T::Input>,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a
+    where
+        T: XMachine + 'a,
+        T::Memory: Store,
+    {
+        Self::phi_coverage_tests_with_oracle_iter::<T>(distinguishing_sequences, &NoOracle)
+    }
+
+    /// Like `generate_phi_coverage_tests`, but the search for a satisfying
+    /// memory consults `oracle` to prune hopeless configurations and to jump
+    /// straight to witness stores.
+    ///
+    /// A case built from a witness has the cheapest sequence producing the
+    /// witness store as setup sequence. If no sequence within the search bound
+    /// does, it has the shortest path to its state instead, and
+    /// `TestCase::requires_injection`.
+    pub fn generate_phi_coverage_tests_with_oracle<T>(
+        distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
+        oracle: &dyn FeasibilityOracle<T>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::phi_coverage_tests_with_oracle_iter::<T>(distinguishing_sequences, oracle).collect()
+    }
+
+    /// Lazy `generate_phi_coverage_tests_with_oracle`.
+    pub fn phi_coverage_tests_with_oracle_iter<'a, T>(
+        distinguishing_sequences: &'a dyn Fn(T::State) -> Vec<T::Input>,
+        oracle: &'a dyn FeasibilityOracle<T>,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a
+    where
+        T: XMachine + 'a,
+        T::Memory: Store,
+    {
+        Self::phi_coverage_tests_with_constraints_iter::<T>(distinguishing_sequences, oracle, &PathConstraints::new())
+    }
+
+    /// Like `generate_phi_coverage_tests_with_oracle`, with setup sequences
+    /// searched under `constraints`. Their `max_len` replaces the default
+    /// search depth of 10 inputs.
+    pub fn generate_phi_coverage_tests_with_constraints<T>(
+        distinguishing_sequences: &dyn Fn(T::State) -> Vec<T::Input>,
+        oracle: &dyn FeasibilityOracle<T>,
+        constraints: &PathConstraintsOf<T>,
+    ) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine,
+        T::Memory: Store,
+    {
+        Self::phi_coverage_tests_with_constraints_iter::<T>(distinguishing_sequences, oracle, constraints).collect()
+    }
+
+    /// Lazy `generate_phi_coverage_tests_with_constraints`.
+    pub fn phi_coverage_tests_with_constraints_iter<'a, T>(
+        distinguishing_sequences: &'a dyn Fn(T::State) -> Vec<T::Input>,
+        oracle: &'a dyn FeasibilityOracle<T>,
+        constraints: &PathConstraintsOf<T>,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a
+    where
+        T: XMachine + 'a,
+        T::Memory: Store,
+    {
+        let constraints = constraints.clone();
+        T::all_states().iter().flat_map(move |&start_state| {
+            let constraints = constraints.clone();
+            Self::inputs::<T>().filter_map(move |input| {
+                let target_phi = T::get_phi_for_input(start_state, &input)?;
+                let Some((setup_path, resulting_memory)) = search::path_to_satisfy_phi::<T>(start_state, target_phi, &input, oracle, &constraints) else {
+                    println!("Warning: Could not find data path to execute Phi '{:?}' from State '{:?}'", target_phi, start_state);
+                    return None;
+                };
+                let mut test_mem = resulting_memory.clone();
+                let expected_output =
+                    isolation::execute_phi::<T>(start_state, target_phi, &mut test_mem, &input).ok().flatten();
+                let next_state = T::next_state(start_state, target_phi).unwrap();
+
+                Some(TestCase {
+                    name: format!(
+                        "Phi Verify: {}{} (via {})",
+                        labels::phi::<T>(target_phi),
+                        Self::guard_suffix::<T>(target_phi),
+                        labels::inputs::<T>(&setup_path)
+                    ),
+                    setup_sequence: setup_path,
+                    test_input: input,
+                    expected_output: expected_output.into(),
+                    verification_sequence: distinguishing_sequences(next_state),
+                    requirements: T::requirement_ids(start_state, target_phi).to_vec(),
+                    setup_configuration: Some((start_state, resulting_memory)),
+                })
+            })
+        })
+    }
+
+    /// Generates statistical usage tests from an operational profile.
+    ///
+    /// Each case is a random walk of up to `walk_length` inputs from the initial
+    /// configuration. At every step the next input is drawn with probability
+    /// proportional to `profile(state, input)`; inputs weighted `0` are never
+    /// chosen, and a walk ends early when no input has positive weight. The last
+    /// input of the walk is the test input, the preceding ones form the setup.
+    /// The same `seed` always yields the same suite.
+    pub fn generate_profile_tests<T: XMachine>(
+        profile: &dyn Fn(T::State, &T::Input) -> f64,
+        cases: usize,
+        walk_length: usize,
+        seed: GenerationSeed,
+    ) -> Vec<TestCaseOf<T>> {
+        Self::profile_tests_iter::<T>(profile, cases, walk_length, seed).collect()
+    }
+
+    /// Lazy `generate_profile_tests`: walks are drawn on demand, in the same order.
+    pub fn profile_tests_iter<'a, T: XMachine + 'a>(
+        profile: &'a dyn Fn(T::State, &T::Input) -> f64,
+        cases: usize,
+        walk_length: usize,
+        seed: GenerationSeed,
+    ) -> impl Iterator<Item = TestCaseOf<T>> + 'a {
+        let mut rng = SplitMix64::new(seed.0);
+        let inputs = T::all_inputs();
+
+        (0..cases).filter_map(move |case| {
+            let mut runner = MachineRunner::<T>::new();
+            let mut walk = Vec::new();
+            let mut last = None;
+
+            for _ in 0..walk_length {
+                let state = runner.state();
+                let weights: Vec<f64> = inputs.iter().map(|i| profile(state, i)).collect();
+                let Some(idx) = rng.weighted_index(&weights) else {
+                    break;
+                };
+                let input = inputs[idx].clone();
+                let before = (state, runner.store().clone());
+                last = Some((before, runner.step(&input)));
+                walk.push(input);
             }
+
+            let (Some(test_input), Some((before, result))) = (walk.pop(), last) else {
+                return None;
+            };
+            let (expected_output, requirements) = match &result {
+                Ok(t) => (t.output.clone(), T::requirement_ids(t.from, t.phi).to_vec()),
+                Err(_) => (None, Vec::new()),
+            };
+
+            Some(TestCase {
+                name: format!(
+                    "Profile Walk #{}: {} then {}",
+                    case,
+                    labels::inputs::<T>(&walk),
+                    labels::input::<T>(&test_input)
+                ),
+                setup_sequence: walk,
+                test_input,
+                expected_output: expected_output.into(),
+                verification_sequence: vec![],
+                requirements,
+                setup_configuration: Some(before),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Digicode;
+    use crate::executor::{Executor, Verdict};
+    use crate::sut::SutAdapter;
+    use std::borrow::Cow;
+
+    #[test]
+    fn robustness_setups_are_accepted_by_the_spec() {
+        let cases = SxMTester::generate_robustness_tests::<Digicode>();
+        assert!(!cases.is_empty());
+        for case in &cases {
+            assert!(case.setup_configuration.is_some(), "{}", case.name);
+            assert!(!case.requires_injection::<Digicode>(), "{}", case.name);
         }
-        tests
     }
 
-    /// BFS that tracks Memory to find a path where `execute_phi` succeeds.
-    fn find_path_to_satisfy_phi<T: XMachine>(
-        target_state: T::State,
-        target_phi: T::Phi,
-        trigger_input: &T::Input
-    ) -> Option<(Vec<T::Input>, T::Memory)> {
-        let mut queue = VecDeque::new();
-        for &start in T::initial_states() {
-            queue.push_back((start, T::initial_store(), Vec::new()));
+    #[test]
+    fn traceability_rows_escape_commas_and_quotes() {
+        use crate::examples::secure_door::DigicodeInputAlphabet::Digit;
+        use crate::examples::secure_door::DigicodeOutputAlphabet;
+
+        let case = |name: &str, ids: &[&'static str]| {
+            TestCase::builder::<Digicode>(name)
+                .input(Digit(4))
+                .expect(Some(DigicodeOutputAlphabet::Digit(4)))
+                .requirements(ids)
+                .build()
+                .unwrap()
+        };
+        let tests = [case("first digit, accepted", &["REQ-1"]), case("the \"4\" key", &["REQ-1", "REQ-2"])];
+        let mut matrix = SxMTester::traceability_matrix::<Digicode>(&tests);
+        matrix.entries.insert("REQ-3", Vec::new());
+
+        assert_eq!(matrix.entries["REQ-1"], ["first digit, accepted", "the \"4\" key"]);
+        assert_eq!(matrix.uncovered(), ["REQ-3"]);
+        assert_eq!(
+            matrix.to_csv(),
+            "requirement,test\n\
+             REQ-1,\"first digit, accepted\"\n\
+             REQ-1,\"the \"\"4\"\" key\"\n\
+             REQ-2,\"the \"\"4\"\" key\"\n\
+             REQ-3,\n"
+        );
+    }
+
+    #[test]
+    fn reliability_bounds_match_the_wilson_interval() {
+        let cases = [(100, 0, 1.0, 0.036995), (10, 5, 0.5, 0.763410), (20, 2, 0.9, 0.301038)];
+        for (runs, failures, reliability, bound) in cases {
+            let estimate = ReliabilityEstimate::from_counts(runs, failures);
+            assert_eq!(estimate.reliability, reliability);
+            assert!((estimate.failure_upper_bound - bound).abs() < 1e-6, "{runs} runs, {failures} failures");
         }
+        let verdicts = [true, false, true, true, true, false, true, true, true, true];
+        let estimate = ReliabilityEstimate::from_verdicts(verdicts);
+        assert_eq!((estimate.runs, estimate.failures), (10, 2));
+        assert_eq!(ReliabilityEstimate::from_counts(0, 0).failure_upper_bound, 1.0);
+    }
 
-        let max_depth = 10;
-        while let Some((curr_state, curr_mem, path)) = queue.pop_front() {
-            if curr_state == target_state {
-                let mut check_mem = curr_mem.clone();
-                if T::execute_phi(target_phi, &mut check_mem, trigger_input).is_ok() {
-                    return Some((path, curr_mem));
-                }
-            }
-            if path.len() >= max_depth {
-                continue;
+    #[test]
+    fn profile_tests_are_reproducible_from_their_seed() {
+        use crate::examples::traffic_light::{LightInput, LightOutput, TrafficLight};
+
+        let uniform = |_, _: &LightInput| 1.0;
+        let names = |seed: u64| -> Vec<String> {
+            SxMTester::generate_profile_tests::<TrafficLight>(&uniform, 8, 4, GenerationSeed(seed))
+                .into_iter()
+                .map(|case| case.name)
+                .collect()
+        };
+        assert_eq!(names(7), names(7));
+        assert_ne!(names(7), names(8));
+
+        let ticks_only = |_, input: &LightInput| if *input == LightInput::Tick { 1.0 } else { 0.0 };
+        let cases = SxMTester::generate_profile_tests::<TrafficLight>(&ticks_only, 3, 3, GenerationSeed(7));
+        assert_eq!(cases.len(), 3);
+        for case in cases {
+            assert_eq!(case.setup_sequence, [LightInput::Tick, LightInput::Tick]);
+            assert_eq!(case.test_input, LightInput::Tick);
+            assert_eq!(case.expected_output, Some(LightOutput::ShowRed).into());
+        }
+    }
+
+    #[test]
+    fn delta_tests_cover_only_the_changed_transitions() {
+        use crate::dynamic::DynMachine;
+        use crate::examples::admin_digicode::{AdminDigicode, AdminInput};
+        use crate::extend::Ext;
+
+        let diff = DynMachine::from_machine::<Digicode>("digicode")
+            .diff(&DynMachine::from_machine::<AdminDigicode>("admin_digicode"));
+        let touched: Vec<_> = diff.added.iter().map(|t| (t.from.as_str(), t.input.as_str())).collect();
+        assert_eq!(touched, [("Ready", "AdminKey"), ("Accepting", "AdminKey"), ("CodeEntered", "AdminKey")]);
+        assert!(diff.removed.is_empty() && diff.changed.is_empty());
+
+        // Every added transition handles the admin key, so a case is affected
+        // exactly when its input word presents the key.
+        let no_distinguishing = |_| Vec::new();
+        let presents_key = |case: &TestCaseOf<AdminDigicode>| {
+            let admin_key = Ext::Own(AdminInput::AdminKey);
+            case.setup_sequence.contains(&admin_key)
+                || case.test_input == admin_key
+                || case.verification_sequence.contains(&admin_key)
+        };
+        let mut full = SxMTester::generate_logic_tests::<AdminDigicode>(&no_distinguishing);
+        full.extend(SxMTester::generate_robustness_tests::<AdminDigicode>());
+        let expected: Vec<&str> = full
+            .iter()
+            .filter(|case| presents_key(case))
+            .map(|case| case.name.as_str())
+            .collect();
+
+        let delta = SxMTester::generate_delta_tests::<AdminDigicode>(&diff, &no_distinguishing);
+        let names: Vec<&str> = delta.iter().map(|case| case.name.as_str()).collect();
+        assert!(!delta.is_empty() && delta.len() < full.len());
+        assert_eq!(names, expected);
+
+        let unchanged = DynMachine::from_machine::<AdminDigicode>("admin_digicode")
+            .diff(&DynMachine::from_machine::<AdminDigicode>("admin_digicode"));
+        assert!(SxMTester::generate_delta_tests::<AdminDigicode>(&unchanged, &no_distinguishing).is_empty());
+    }
+
+    #[test]
+    fn ignore_tests_expect_the_declared_ignore_output() {
+        use crate::examples::secure_door::{Door, DoorInputAlphabet, DoorOutputAlphabet, DoorState};
+
+        let cases = SxMTester::generate_ignore_tests::<Door>();
+        let summary: Vec<_> = cases
+            .iter()
+            .map(|case| (case.setup_sequence.clone(), case.test_input.clone(), case.expected_output.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (vec![], DoorInputAlphabet::Close, Expectation::Exact(DoorOutputAlphabet::CloseIgnored)),
+                (
+                    vec![DoorInputAlphabet::Open],
+                    DoorInputAlphabet::Open,
+                    Expectation::Exact(DoorOutputAlphabet::OpenIgnored)
+                ),
+            ]
+        );
+        assert_eq!(cases[1].name, "Ignore: Opened should ignore Open with OpenIgnored");
+        assert_eq!(cases[1].setup_configuration, Some((DoorState::Opened, 1)));
+
+        // Without an `ignore_output` override, no phi is an explicit ignore.
+        assert!(SxMTester::generate_ignore_tests::<Digicode>().is_empty());
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Gate {
+        Locked,
+        Unlocked,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Turn {
+        Coin,
+        Push,
+        Reset,
+    }
+
+    /// Counts the coins inserted, up to three; `Reset` locks and clears the count.
+    struct Turnstile;
+
+    impl XMachine for Turnstile {
+        type Input = Turn;
+        type Output = u8;
+        type State = Gate;
+        type Memory = u8;
+        type Phi = Turn;
+
+        fn next_state(state: Gate, phi: Turn) -> Option<Gate> {
+            match (state, phi) {
+                (_, Turn::Coin) => Some(Gate::Unlocked),
+                (Gate::Unlocked, Turn::Push) | (_, Turn::Reset) => Some(Gate::Locked),
+                (Gate::Locked, Turn::Push) => None,
             }
+        }
 
-            for input in T::all_inputs() {
-                if let Some(phi) = T::get_phi_for_input(curr_state, input) {
-                    let mut next_mem = curr_mem.clone();
+        fn initial_states() -> &'static [Gate] {
+            &[Gate::Locked]
+        }
 
-                    if let Ok(_) = T::execute_phi(phi, &mut next_mem, input) {
-                        if let Some(next_state) = T::next_state(curr_state, phi) {
-                            let mut new_path = path.clone();
-                            new_path.push(input.clone());
-                            queue.push_back((next_state, next_mem, new_path));
-                        }
-                    }
+        fn final_states() -> &'static [Gate] {
+            &[Gate::Locked]
+        }
+
+        fn initial_store() -> u8 {
+            0
+        }
+
+        fn execute_phi(phi: Turn, coins: &mut u8, _input: &Turn) -> Result<Option<u8>, ()> {
+            match phi {
+                Turn::Coin => {
+                    *coins = (*coins + 1).min(3);
+                    Ok(Some(*coins))
                 }
+                Turn::Push => Ok(None),
+                Turn::Reset => {
+                    *coins = 0;
+                    Ok(None)
+                }
+            }
+        }
+
+        fn all_inputs() -> Cow<'static, [Turn]> {
+            Cow::Borrowed(&[Turn::Coin, Turn::Push, Turn::Reset])
+        }
+
+        fn all_outputs() -> Cow<'static, [u8]> {
+            Cow::Borrowed(&[1, 2, 3])
+        }
+
+        fn all_states() -> &'static [Gate] {
+            &[Gate::Locked, Gate::Unlocked]
+        }
+
+        fn all_phis() -> &'static [Turn] {
+            &[Turn::Coin, Turn::Push, Turn::Reset]
+        }
+
+        fn get_phi_for_input(state: Gate, input: &Turn) -> Option<Turn> {
+            Self::next_state(state, *input).map(|_| *input)
+        }
+
+        fn reset_input() -> Option<Turn> {
+            Some(Turn::Reset)
+        }
+    }
+
+    /// A turnstile whose reset locks it but keeps the coin count.
+    struct KeepsCoins(MachineRunner<Turnstile>);
+
+    impl SutAdapter<Turnstile> for KeepsCoins {
+        fn apply(&mut self, input: &Turn) -> Option<u8> {
+            let coins = *self.0.store();
+            let output = self.0.apply(input);
+            if *input == Turn::Reset {
+                self.0 = MachineRunner::from_parts(self.0.state(), coins);
             }
+            output
+        }
+
+        fn observe_state(&self) -> Option<Gate> {
+            Some(self.0.state())
         }
-        None
+
+        fn observe_store(&self) -> Option<u8> {
+            Some(*self.0.store())
+        }
+
+        fn reset(&mut self) -> bool {
+            self.0.reset();
+            true
+        }
+    }
+
+    #[test]
+    fn reset_tests_end_with_the_reset_input_in_the_initial_configuration() {
+        let cases = SxMTester::generate_reset_tests::<Turnstile>(&|_| Vec::new());
+        let setups: Vec<_> = cases.iter().map(|case| case.setup_sequence.clone()).collect();
+        assert_eq!(setups, [vec![], vec![Turn::Coin]]);
+        for case in &cases {
+            assert_eq!(case.test_input, Turn::Reset);
+            let mut spec = MachineRunner::<Turnstile>::new();
+            spec.run(case.setup_sequence.iter().chain([&case.test_input])).unwrap();
+            assert_eq!(spec.into_configuration(), Configuration::initial()[0]);
+            let verdict = Executor::run_case::<Turnstile, _>(&mut MachineRunner::new(), case).verdict;
+            assert_eq!(verdict, Verdict::Pass, "{}", case.name);
+        }
+
+        let verdicts: Vec<_> = cases
+            .iter()
+            .map(|case| Executor::run_case::<Turnstile, _>(&mut KeepsCoins(MachineRunner::new()), case).verdict)
+            .collect();
+        assert_eq!(verdicts[0], Verdict::Pass);
+        assert!(matches!(verdicts[1], Verdict::StoreMismatch { state: Gate::Locked, .. }), "{:?}", verdicts[1]);
     }
 }
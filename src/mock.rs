@@ -0,0 +1,181 @@
+//! Spec-driven stubs for testing one component of a network in isolation.
+//!
+//! A `SpecMock<A, B>` stands in for component `B` while the implementation of
+//! `A` is tested alone: it takes the outputs of `A`, routes those the wiring
+//! carries to `B` through `B`'s own model, and hands back what `B` emits,
+//! including the input of `A` that `B`'s output travels back as. The stub
+//! therefore behaves exactly as the spec of the peer does, memory and guards
+//! included, instead of replaying canned responses.
+//!
+//! A test loop applies an input to the implementation of `A`, passes its
+//! output to `SpecMock::receive`, and applies `MockReply::to_a`, if any, to the
+//! implementation again.
+
+use crate::network::{route, Adapters, Wiring};
+use crate::runner::{MachineRunner, StepResultOf};
+use crate::XMachine;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// How the mocked peer reacted to one input.
+pub struct MockReply<A: XMachine, B: XMachine> {
+    /// The input `B` consumed.
+    pub input: B::Input,
+    /// `B`'s step according to its spec.
+    pub result: StepResultOf<B>,
+    /// The input of `A` that `B`'s output is routed back as.
+    pub to_a: Option<A::Input>,
+    /// Name of the `Port` carrying `to_a`, if the wiring uses ports.
+    pub port: Option<&'static str>,
+    /// `true` if `B`'s output also leaves the network.
+    pub to_environment: bool,
+}
+
+/// The spec of `B` standing in for the peer of component `A`, connected by
+/// wiring `W` as in `MachineNetwork<A, B, W>`.
+pub struct SpecMock<A: XMachine, B: XMachine, W = Adapters> {
+    runner: MachineRunner<B>,
+    replies: Vec<MockReply<A, B>>,
+    wiring: PhantomData<W>,
+}
+
+impl<A, B, W> SpecMock<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    /// `B` starts in its initial configuration.
+    pub fn new() -> Self {
+        Self::from_runner(MachineRunner::new())
+    }
+
+    pub fn from_runner(runner: MachineRunner<B>) -> Self {
+        Self {
+            runner,
+            replies: Vec::new(),
+            wiring: PhantomData,
+        }
+    }
+
+    /// The model of `B`, e.g. to assert on the state the peer reached.
+    pub fn runner(&self) -> &MachineRunner<B> {
+        &self.runner
+    }
+
+    /// Takes an output of `A`. If the wiring routes it to `B`, steps `B`'s
+    /// model and returns its reply; outputs meant for the environment only
+    /// give `None`.
+    pub fn receive(&mut self, output: &A::Output) -> Option<&MockReply<A, B>> {
+        let (routed, _) = route::<A, _>(output, W::a_to_b);
+        let (input, _) = routed?;
+        Some(self.stimulate(input))
+    }
+
+    /// Applies an input to `B` directly, as its environment would.
+    pub fn stimulate(&mut self, input: B::Input) -> &MockReply<A, B> {
+        let result = self.runner.step(&input);
+        let mut reply = MockReply {
+            input,
+            result,
+            to_a: None,
+            port: None,
+            to_environment: false,
+        };
+        if let Ok(Some(output)) = reply.result.as_ref().map(|t| t.output.clone()) {
+            let (routed, to_environment) = route::<B, _>(&output, W::b_to_a);
+            reply.to_environment = to_environment;
+            if let Some((to_a, port)) = routed {
+                reply.to_a = Some(to_a);
+                reply.port = port;
+            }
+        }
+        self.replies.push(reply);
+        self.replies.last().unwrap()
+    }
+
+    /// Every reply so far, oldest first, for asserting on the interaction.
+    pub fn replies(&self) -> &[MockReply<A, B>] {
+        &self.replies
+    }
+
+    /// Returns `B` to its initial configuration and forgets the replies.
+    pub fn reset(&mut self) {
+        self.runner.reset();
+        self.replies.clear();
+    }
+}
+
+impl<A, B, W> Default for SpecMock<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: XMachine, B: XMachine> fmt::Debug for MockReply<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MockReply")
+            .field("input", &self.input)
+            .field("result", &self.result)
+            .field("to_a", &self.to_a)
+            .field("port", &self.port)
+            .field("to_environment", &self.to_environment)
+            .finish()
+    }
+}
+
+impl<A: XMachine, B: XMachine> Clone for MockReply<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            input: self.input.clone(),
+            result: self.result.clone(),
+            to_a: self.to_a.clone(),
+            port: self.port,
+            to_environment: self.to_environment,
+        }
+    }
+}
+
+impl<A: XMachine, B: XMachine, W> Clone for SpecMock<A, B, W> {
+    fn clone(&self) -> Self {
+        Self {
+            runner: self.runner.clone(),
+            replies: self.replies.clone(),
+            wiring: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, Door, DoorInputAlphabet, DoorOutputAlphabet,
+        DoorState,
+    };
+
+    #[test]
+    fn the_peer_answers_as_its_spec_does() {
+        let mut door = SpecMock::<Digicode, Door>::new();
+        assert!(door.receive(&DigicodeOutputAlphabet::Digit(4)).is_none());
+
+        let opened = door.receive(&DigicodeOutputAlphabet::Open).unwrap();
+        assert_eq!(opened.input, DoorInputAlphabet::Open);
+        assert_eq!(opened.result.as_ref().unwrap().output, Some(DoorOutputAlphabet::DoorOpens));
+        assert_eq!(opened.to_a, None);
+
+        let closed = door.stimulate(DoorInputAlphabet::Close);
+        assert_eq!(closed.to_a, Some(DigicodeInputAlphabet::DoorCloses));
+        assert_eq!(door.runner().state(), DoorState::Closed);
+        assert_eq!(door.replies().len(), 2);
+
+        door.reset();
+        assert!(door.replies().is_empty());
+        assert_eq!(door.runner().store(), &0);
+    }
+}
@@ -0,0 +1,245 @@
+//! Safety monitors of the form "never emit X unless E occurred since the last R".
+//!
+//! A `SafetyRule` is compiled into `NeverMonitor`, an X-machine reading the
+//! spec's outputs as its inputs. It can run standalone in a `MonitorRunner`,
+//! be attached to a `shadow::ShadowRunner`, or join the spec in a
+//! `network::MachineNetwork` through `MonitorPort`.
+
+use crate::network::Port;
+use crate::runner::MachineRunner;
+use crate::XMachine;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+
+/// A safety property over the outputs of `Spec`.
+///
+/// `forbidden` outputs violate the rule unless the rule is enabled: an
+/// `enables` output enables it until the next `disables` output. With the
+/// defaults the rule is a plain "never emit a forbidden output".
+pub trait SafetyRule: 'static {
+    type Spec: XMachine;
+
+    /// Names the rule in violations.
+    const NAME: &'static str;
+
+    fn forbidden(output: &<Self::Spec as XMachine>::Output) -> bool;
+
+    fn enables(_output: &<Self::Spec as XMachine>::Output) -> bool {
+        false
+    }
+
+    fn disables(_output: &<Self::Spec as XMachine>::Output) -> bool {
+        false
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MonitorState {
+    /// Forbidden outputs are violations.
+    Disabled,
+    /// Forbidden outputs are allowed.
+    Enabled,
+    /// A violation was observed. Absorbing.
+    Violated,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MonitorPhi {
+    Observe,
+    Enable,
+    Disable,
+    Violate,
+}
+
+/// Emitted by a `NeverMonitor` on the output that violates its rule.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: &'static str,
+}
+
+/// The X-machine compiled from rule `R`.
+pub struct NeverMonitor<R>(PhantomData<R>);
+
+impl<R: SafetyRule> XMachine for NeverMonitor<R> {
+    type Input = <R::Spec as XMachine>::Output;
+    type Output = Violation;
+    type State = MonitorState;
+    type Memory = ();
+    type Phi = MonitorPhi;
+
+    fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+        use MonitorPhi::*;
+        use MonitorState::*;
+
+        match (state, phi) {
+            (state, Observe) => Some(state),
+            (Disabled, Enable) => Some(Enabled),
+            (Enabled, Disable) => Some(Disabled),
+            (Disabled, Violate) => Some(Violated),
+            _ => None,
+        }
+    }
+
+    fn initial_states() -> &'static [Self::State] {
+        &[MonitorState::Disabled]
+    }
+
+    fn final_states() -> &'static [Self::State] {
+        &[MonitorState::Disabled, MonitorState::Enabled]
+    }
+
+    fn initial_store() -> Self::Memory {}
+
+    fn execute_phi(phi: Self::Phi, _store: &mut Self::Memory, _input: &Self::Input) -> Result<Option<Self::Output>, ()> {
+        Ok((phi == MonitorPhi::Violate).then_some(Violation { rule: R::NAME }))
+    }
+
+    fn all_inputs() -> Cow<'static, [Self::Input]> {
+        <R::Spec as XMachine>::all_outputs()
+    }
+
+    fn all_outputs() -> Cow<'static, [Self::Output]> {
+        Cow::Owned(vec![Violation { rule: R::NAME }])
+    }
+
+    fn all_states() -> &'static [Self::State] {
+        &[MonitorState::Disabled, MonitorState::Enabled, MonitorState::Violated]
+    }
+
+    fn all_phis() -> &'static [Self::Phi] {
+        &[MonitorPhi::Observe, MonitorPhi::Enable, MonitorPhi::Disable, MonitorPhi::Violate]
+    }
+
+    fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+        Some(match state {
+            MonitorState::Disabled if R::forbidden(input) => MonitorPhi::Violate,
+            MonitorState::Disabled if R::enables(input) => MonitorPhi::Enable,
+            MonitorState::Enabled if R::disables(input) => MonitorPhi::Disable,
+            _ => MonitorPhi::Observe,
+        })
+    }
+}
+
+/// A rule violation with the outputs that led to it, the violating one last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorViolation<Output> {
+    pub rule: &'static str,
+    pub trace: Vec<Output>,
+}
+
+/// Anything that watches a stream of outputs for violations.
+pub trait OutputMonitor<Output> {
+    /// Observes the next output. Once a violation is reported, every later
+    /// call reports it again.
+    fn observe(&mut self, output: &Output) -> Result<(), MonitorViolation<Output>>;
+}
+
+/// Runs the monitor of rule `R` and keeps the observed trace.
+pub struct MonitorRunner<R: SafetyRule> {
+    runner: MachineRunner<NeverMonitor<R>>,
+    trace: Vec<<R::Spec as XMachine>::Output>,
+}
+
+impl<R: SafetyRule> MonitorRunner<R> {
+    pub fn new() -> Self {
+        Self {
+            runner: MachineRunner::new(),
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> MonitorState {
+        self.runner.state()
+    }
+
+    /// Outputs observed so far.
+    pub fn trace(&self) -> &[<R::Spec as XMachine>::Output] {
+        &self.trace
+    }
+}
+
+impl<R: SafetyRule> Default for MonitorRunner<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: SafetyRule> OutputMonitor<<R::Spec as XMachine>::Output> for MonitorRunner<R> {
+    fn observe(
+        &mut self,
+        output: &<R::Spec as XMachine>::Output,
+    ) -> Result<(), MonitorViolation<<R::Spec as XMachine>::Output>> {
+        if self.runner.state() != MonitorState::Violated {
+            self.trace.push(output.clone());
+            // The monitor accepts every output, so stepping cannot fail.
+            let _ = self.runner.step(output);
+        }
+        match self.runner.state() {
+            MonitorState::Violated => Err(MonitorViolation {
+                rule: R::NAME,
+                trace: self.trace.clone(),
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Carries every output of the spec to the monitor of `R` in a network
+/// wired with `network::Ports<MonitorPort<R>, ()>`.
+///
+/// Routed outputs no longer reach the environment unless the spec marks them
+/// `Visibility::Shared`.
+pub struct MonitorPort<R>(PhantomData<R>);
+
+impl<R: SafetyRule> Port for MonitorPort<R> {
+    type From = R::Spec;
+    type To = NeverMonitor<R>;
+
+    const NAME: &'static str = R::NAME;
+
+    fn carry(output: &<R::Spec as XMachine>::Output) -> Option<<R::Spec as XMachine>::Output> {
+        Some(output.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeOutputAlphabet as Out};
+
+    /// The door only opens after the last digit of the code, until the keypad is re-armed.
+    struct OpensAfterTheCode;
+
+    impl SafetyRule for OpensAfterTheCode {
+        type Spec = Digicode;
+
+        const NAME: &'static str = "opens-after-the-code";
+
+        fn forbidden(output: &Out) -> bool {
+            *output == Out::Open
+        }
+
+        fn enables(output: &Out) -> bool {
+            *output == Out::Digit(2)
+        }
+
+        fn disables(output: &Out) -> bool {
+            *output == Out::Initialise
+        }
+    }
+
+    #[test]
+    fn forbidden_outputs_are_allowed_only_while_enabled() {
+        let mut monitor = MonitorRunner::<OpensAfterTheCode>::new();
+        for output in [Out::Digit(4), Out::Digit(2), Out::Open, Out::Initialise] {
+            assert_eq!(monitor.observe(&output), Ok(()));
+        }
+        assert_eq!(monitor.state(), MonitorState::Disabled);
+
+        let violation = monitor.observe(&Out::Open).unwrap_err();
+        assert_eq!(violation.rule, OpensAfterTheCode::NAME);
+        assert_eq!(violation.trace.last(), Some(&Out::Open));
+        assert_eq!(monitor.observe(&Out::Digit(2)), Err(violation));
+        assert_eq!(monitor.trace().len(), 5);
+    }
+}
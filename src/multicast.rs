@@ -0,0 +1,447 @@
+//! Networks of any number of components, where one output can reach several.
+//!
+//! `MachineNetwork` connects exactly two machines, so an output has at most
+//! one receiver besides the environment. A `MulticastNetwork` holds any
+//! number of components, of different machines, connected by named routes.
+//! Several routes may leave a component and carry the same output, e.g. an
+//! alarm consumed by both a door and a logger.
+//!
+//! Components are typed through the `ComponentId` that `add` returns, but
+//! kept behind one interface, so steps are reported by label, as in `erased`.
+
+use crate::labels;
+use crate::network::{Channel, ChannelPolicy};
+use crate::runner::MachineRunner;
+use crate::{Visibility, XMachine};
+use std::any::{self, Any};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A component of a `MulticastNetwork` running machine `T`, as returned by
+/// `MulticastNetwork::add`.
+pub struct ComponentId<T> {
+    index: usize,
+    machine: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for ComponentId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ComponentId<T> {}
+
+impl<T> fmt::Debug for ComponentId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ComponentId").field(&self.index).finish()
+    }
+}
+
+/// One component step in a `MulticastNetwork`, with symbols named by the
+/// component's `labeling()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MulticastStep {
+    /// Name of the component that consumed the input.
+    pub component: String,
+    pub input: String,
+    /// `true` if the input came from the environment rather than a route.
+    pub external: bool,
+    /// The output produced, or the `StepError` rejecting the input in `Debug` form.
+    pub result: Result<Option<String>, String>,
+    /// Components the output was queued for, in the order they receive it.
+    pub targets: Vec<String>,
+}
+
+/// Everything that happened while a `MulticastNetwork` processed one environment input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MulticastReport {
+    /// Component steps in execution order.
+    pub micro_steps: Vec<MulticastStep>,
+    /// `(component, output)` pairs that left the network, in emission order.
+    pub environment_outputs: Vec<(String, String)>,
+    /// `(component, input)` messages discarded by the receiving channel's `ChannelPolicy`.
+    pub dropped: Vec<(String, String)>,
+    /// `true` if no routed message was left pending; `false` if the step
+    /// budget ran out first.
+    pub stable: bool,
+}
+
+/// A routed message: the receiver's input, and its label for reports.
+struct Message {
+    input: Box<dyn Any>,
+    label: String,
+}
+
+/// What a component's step leaves for routing.
+struct Stepped {
+    result: Result<Option<String>, String>,
+    output: Option<Box<dyn Any>>,
+    visibility: Option<Visibility>,
+}
+
+/// A `MachineRunner` of any machine, stepped with inputs behind `dyn Any`.
+trait Component {
+    fn step(&mut self, input: &dyn Any) -> Stepped;
+
+    fn input_label(&self, input: &dyn Any) -> String;
+
+    fn state(&self) -> String;
+
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: XMachine> Component for MachineRunner<T> {
+    fn step(&mut self, input: &dyn Any) -> Stepped {
+        match MachineRunner::step(self, downcast::<T::Input>(input)) {
+            Ok(transition) => Stepped {
+                result: Ok(transition.output.as_ref().map(labels::output::<T>)),
+                visibility: transition.output.as_ref().and_then(T::output_visibility),
+                output: transition.output.map(|output| Box::new(output) as Box<dyn Any>),
+            },
+            Err(error) => Stepped {
+                result: Err(format!("{:?}", error)),
+                output: None,
+                visibility: None,
+            },
+        }
+    }
+
+    fn input_label(&self, input: &dyn Any) -> String {
+        labels::input::<T>(downcast::<T::Input>(input))
+    }
+
+    fn state(&self) -> String {
+        labels::state::<T>(MachineRunner::state(self))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// `value` as the `V` a `ComponentId` guarantees it is.
+fn downcast<V: 'static>(value: &dyn Any) -> &V {
+    value.downcast_ref().unwrap_or_else(|| panic!("expected a {}", any::type_name::<V>()))
+}
+
+struct Node {
+    name: String,
+    runner: Box<dyn Component>,
+    channel: Channel<Message>,
+}
+
+/// Maps an output of the route's source to a message for its target, if the route carries it.
+type Carry = Box<dyn Fn(&dyn Any) -> Option<Message>>;
+
+/// A route from one component to another.
+struct Route {
+    from: usize,
+    to: usize,
+    name: &'static str,
+    carry: Carry,
+}
+
+/// A network of any number of components, where an output is delivered to
+/// every component a route carries it to.
+///
+/// An output is offered to the routes leaving its component in the order
+/// they were connected, and each route that carries it queues one message
+/// in its target's channel, so the targets receive it in that order.
+/// Messages are delivered one micro-step each, in the order they were
+/// queued across all channels, until none is pending (a stable
+/// configuration) or the step budget runs out. Each channel buffers
+/// according to its `ChannelPolicy`, an unbounded FIFO by default.
+///
+/// As in `MachineNetwork`, an output also goes to the environment when no
+/// route carries it or `XMachine::output_visibility` marks it `Shared`, and
+/// only there when it is marked `External`.
+pub struct MulticastNetwork {
+    nodes: Vec<Node>,
+    routes: Vec<Route>,
+    next_seq: u64,
+    step_budget: usize,
+}
+
+impl MulticastNetwork {
+    /// Default bound on micro-steps per environment input.
+    pub const DEFAULT_STEP_BUDGET: usize = 64;
+
+    /// A network without components.
+    pub fn new() -> Self {
+        Self {
+            nodes: Vec::new(),
+            routes: Vec::new(),
+            next_seq: 0,
+            step_budget: Self::DEFAULT_STEP_BUDGET,
+        }
+    }
+
+    /// Bounds the number of micro-steps per environment input, guarding
+    /// against components that keep messaging each other forever.
+    pub fn with_step_budget(mut self, step_budget: usize) -> Self {
+        self.step_budget = step_budget;
+        self
+    }
+
+    /// Adds a component named `name`, in the configuration of `runner`.
+    pub fn add<T: XMachine>(&mut self, name: &str, runner: MachineRunner<T>) -> ComponentId<T> {
+        self.nodes.push(Node {
+            name: name.to_string(),
+            runner: Box::new(runner),
+            channel: Channel::new(ChannelPolicy::Unbounded),
+        });
+        ComponentId {
+            index: self.nodes.len() - 1,
+            machine: PhantomData,
+        }
+    }
+
+    /// Routes the outputs of `from` that `carry` maps to an input of `to`,
+    /// through a route named `name`.
+    pub fn connect<F, T>(
+        &mut self,
+        from: ComponentId<F>,
+        to: ComponentId<T>,
+        name: &'static str,
+        carry: fn(&F::Output) -> Option<T::Input>,
+    ) where
+        F: XMachine,
+        T: XMachine,
+    {
+        self.routes.push(Route {
+            from: from.index,
+            to: to.index,
+            name,
+            carry: Box::new(move |output| {
+                let input = carry(downcast::<F::Output>(output))?;
+                let label = labels::input::<T>(&input);
+                Some(Message {
+                    input: Box::new(input),
+                    label,
+                })
+            }),
+        });
+    }
+
+    /// Sets the buffering policy of the channel delivering to `component`.
+    pub fn set_policy<T>(&mut self, component: ComponentId<T>, policy: ChannelPolicy) {
+        self.nodes[component.index].channel.set_policy(policy);
+    }
+
+    pub fn runner<T: XMachine>(&self, component: ComponentId<T>) -> &MachineRunner<T> {
+        downcast(self.nodes[component.index].runner.as_any())
+    }
+
+    /// Component names, in the order they were added.
+    pub fn names(&self) -> Vec<&str> {
+        self.nodes.iter().map(|node| node.name.as_str()).collect()
+    }
+
+    /// `(from, to, route)` names of every route, in the order they were connected.
+    pub fn routes(&self) -> Vec<(&str, &str, &'static str)> {
+        self.routes
+            .iter()
+            .map(|route| (self.nodes[route.from].name.as_str(), self.nodes[route.to].name.as_str(), route.name))
+            .collect()
+    }
+
+    /// `true` when no routed message is waiting to be consumed.
+    pub fn is_stable(&self) -> bool {
+        self.nodes.iter().all(|node| node.channel.front_seq().is_none())
+    }
+
+    /// `(component, input)` messages waiting in the channels, in delivery order.
+    pub fn pending(&self) -> Vec<(String, String)> {
+        let mut pending: Vec<(u64, &Node, &Message)> = self
+            .nodes
+            .iter()
+            .flat_map(|node| node.channel.iter().map(move |(seq, message)| (*seq, node, message)))
+            .collect();
+        pending.sort_by_key(|(seq, _, _)| *seq);
+        pending.into_iter().map(|(_, node, message)| (node.name.clone(), message.label.clone())).collect()
+    }
+
+    /// Feeds `input` from the environment to `component` and delivers the
+    /// messages it causes until the network is stable.
+    ///
+    /// The environment input itself always counts as the first micro-step.
+    pub fn process_input<T: XMachine>(&mut self, component: ComponentId<T>, input: T::Input) -> MulticastReport {
+        let mut report = MulticastReport {
+            micro_steps: Vec::new(),
+            environment_outputs: Vec::new(),
+            dropped: Vec::new(),
+            stable: false,
+        };
+        let step = self.micro_step(component.index, Box::new(input), true, &mut report);
+        report.micro_steps.push(step);
+
+        while report.micro_steps.len() < self.step_budget {
+            let Some((index, message)) = self.next_pending() else {
+                break;
+            };
+            let step = self.micro_step(index, message.input, false, &mut report);
+            report.micro_steps.push(step);
+        }
+
+        report.stable = self.is_stable();
+        report
+    }
+
+    /// Pops the message queued first among those at the front of the channels.
+    fn next_pending(&mut self) -> Option<(usize, Message)> {
+        let (_, index) = (0..self.nodes.len())
+            .filter_map(|index| self.nodes[index].channel.front_seq().map(|seq| (seq, index)))
+            .min()?;
+        self.nodes[index].channel.pop().map(|message| (index, message))
+    }
+
+    fn micro_step(
+        &mut self,
+        index: usize,
+        input: Box<dyn Any>,
+        external: bool,
+        report: &mut MulticastReport,
+    ) -> MulticastStep {
+        let label = self.nodes[index].runner.input_label(input.as_ref());
+        let stepped = self.nodes[index].runner.step(input.as_ref());
+        let mut targets = Vec::new();
+        if let (Ok(Some(output_label)), Some(output)) = (&stepped.result, &stepped.output) {
+            if stepped.visibility != Some(Visibility::External) {
+                for route in self.routes.iter().filter(|route| route.from == index) {
+                    let Some(message) = (route.carry)(output.as_ref()) else {
+                        continue;
+                    };
+                    let target = &mut self.nodes[route.to];
+                    if let Some(dropped) = target.channel.push(self.next_seq, message) {
+                        report.dropped.push((target.name.clone(), dropped.label));
+                    }
+                    self.next_seq += 1;
+                    targets.push(target.name.clone());
+                }
+            }
+            if targets.is_empty() || stepped.visibility == Some(Visibility::Shared) {
+                report.environment_outputs.push((self.nodes[index].name.clone(), output_label.clone()));
+            }
+        }
+
+        MulticastStep {
+            component: self.nodes[index].name.clone(),
+            input: label,
+            external,
+            result: stepped.result,
+            targets,
+        }
+    }
+}
+
+impl Default for MulticastNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MulticastNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let states: Vec<(&str, String)> =
+            self.nodes.iter().map(|node| (node.name.as_str(), node.runner.state())).collect();
+        f.debug_struct("MulticastNetwork")
+            .field("states", &states)
+            .field("routes", &self.routes())
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{
+        Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, Door, DoorInputAlphabet, DoorState,
+    };
+    use crate::network::Overflow;
+
+    struct Building {
+        network: MulticastNetwork,
+        keypad: ComponentId<Digicode>,
+        front: ComponentId<Door>,
+        back: ComponentId<Door>,
+    }
+
+    /// A keypad unlocking a front and a back door at once.
+    fn building() -> Building {
+        let mut network = MulticastNetwork::new();
+        let keypad = network.add("keypad", MachineRunner::<Digicode>::new());
+        let front = network.add("front", MachineRunner::<Door>::new());
+        let back = network.add("back", MachineRunner::<Door>::new());
+        let unlock = |output: &DigicodeOutputAlphabet| {
+            (*output == DigicodeOutputAlphabet::Open).then_some(DoorInputAlphabet::Open)
+        };
+        network.connect(keypad, front, "unlock", unlock);
+        network.connect(keypad, back, "unlock", unlock);
+        Building {
+            network,
+            keypad,
+            front,
+            back,
+        }
+    }
+
+    fn enter_code(building: &mut Building) -> MulticastReport {
+        use DigicodeInputAlphabet::*;
+
+        for digit in [4, 9, 2] {
+            building.network.process_input(building.keypad, Digit(digit));
+        }
+        building.network.process_input(building.keypad, OkEnter)
+    }
+
+    #[test]
+    fn an_output_reaches_every_route_carrying_it_in_connection_order() {
+        let mut building = building();
+        let report = enter_code(&mut building);
+        let steps: Vec<(&str, &str, Vec<String>)> = report
+            .micro_steps
+            .iter()
+            .map(|step| (step.component.as_str(), step.input.as_str(), step.targets.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                ("keypad", "OkEnter", vec!["front".to_string(), "back".to_string()]),
+                ("front", "Open", vec![]),
+                ("back", "Open", vec![]),
+            ]
+        );
+        let environment: Vec<(&str, &str)> =
+            report.environment_outputs.iter().map(|(c, o)| (c.as_str(), o.as_str())).collect();
+        assert_eq!(environment, [("front", "DoorOpens"), ("back", "DoorOpens")]);
+        assert!(report.stable);
+        assert_eq!(building.network.runner(building.front).state(), DoorState::Opened);
+        assert_eq!(building.network.runner(building.back).state(), DoorState::Opened);
+    }
+
+    #[test]
+    fn each_target_channel_applies_its_own_policy() {
+        let mut building = building();
+        building.network.set_policy(
+            building.back,
+            ChannelPolicy::Bounded {
+                capacity: 0,
+                overflow: Overflow::DropNewest,
+            },
+        );
+        let report = enter_code(&mut building);
+        assert_eq!(report.dropped, [("back".to_string(), "Open".to_string())]);
+        assert_eq!(report.micro_steps.len(), 2);
+        assert_eq!(building.network.runner(building.front).state(), DoorState::Opened);
+        assert_eq!(building.network.runner(building.back).state(), DoorState::Closed);
+    }
+
+    #[test]
+    fn the_diagram_draws_one_edge_per_target() {
+        let dot = crate::graphviz::generate_multicast_dot(&building().network);
+        assert!(dot.contains("    \"keypad\" -> \"front\" [label=\"unlock\"];\n"));
+        assert!(dot.contains("    \"keypad\" -> \"back\" [label=\"unlock\"];\n"));
+    }
+}
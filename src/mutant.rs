@@ -0,0 +1,304 @@
+use crate::executor::{Executor, Verdict};
+use crate::mbt::TestCaseOf;
+use crate::runner::{MachineRunner, StepError, StepResultOf, Transition};
+use crate::store::Store;
+use crate::sut::SutAdapter;
+use crate::XMachine;
+
+/// A deliberate deviation from the spec, injected by `MutantRunner`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fault<State, Phi, Output> {
+    /// Taking `phi` from `from` ends in `to` instead of the spec's target state.
+    WrongTarget { from: State, phi: Phi, to: State },
+    /// `phi` emits `output` instead of the spec's output.
+    SwappedOutput { phi: Phi, output: Option<Output> },
+    /// `phi` produces its output but leaves memory unchanged.
+    DroppedUpdate { phi: Phi },
+    /// The guard of `phi` always holds: where the spec rejects the input,
+    /// `phi` is taken anyway, without output or memory update.
+    GuardBypassed { phi: Phi },
+    /// The guard of `phi` never holds: the input is rejected.
+    GuardBlocked { phi: Phi },
+}
+
+/// `Fault` specialised to the symbols of machine `T`.
+pub type FaultOf<T> = Fault<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Output>;
+
+/// A faulty implementation of `T`: runs the spec and then applies its faults.
+///
+/// Used as a `SutAdapter` to check that a generated suite detects the fault
+/// classes it claims to cover.
+pub struct MutantRunner<T: XMachine> {
+    runner: MachineRunner<T>,
+    faults: Vec<FaultOf<T>>,
+}
+
+impl<T: XMachine> MutantRunner<T> {
+    /// Starts in the initial configuration with the given faults.
+    pub fn new(faults: Vec<FaultOf<T>>) -> Self {
+        Self {
+            runner: MachineRunner::new(),
+            faults,
+        }
+    }
+
+    /// Adds one more fault.
+    pub fn with_fault(mut self, fault: FaultOf<T>) -> Self {
+        self.faults.push(fault);
+        self
+    }
+
+    pub fn faults(&self) -> &[FaultOf<T>] {
+        &self.faults
+    }
+
+    pub fn runner(&self) -> &MachineRunner<T> {
+        &self.runner
+    }
+
+    /// Processes one input like `MachineRunner::step`, then applies every
+    /// fault matching the transition taken.
+    pub fn step(&mut self, input: &T::Input) -> StepResultOf<T> {
+        let before = self.runner.store().clone();
+        let spec = match self.runner.step(input) {
+            Err(StepError::GuardRejected { state, phi })
+                if self.has_fault(&Fault::GuardBypassed { phi: T::phi_kind(phi) }) =>
+            {
+                let Some(to) = T::next_state(state, phi) else {
+                    return Err(StepError::NoNextState { state, phi });
+                };
+                self.runner = MachineRunner::from_parts(to, before);
+                return Ok(Transition {
+                    from: state,
+                    phi,
+                    to,
+                    output: None,
+                });
+            }
+            result => result?,
+        };
+        if self.has_fault(&Fault::GuardBlocked { phi: T::phi_kind(spec.phi) }) {
+            self.runner = MachineRunner::from_parts(spec.from, before);
+            return Err(StepError::GuardRejected {
+                state: spec.from,
+                phi: spec.phi,
+            });
+        }
+        let mut to = spec.to;
+        let mut output = spec.output;
+        let mut store = self.runner.store().clone();
+
+        let kind = T::phi_kind(spec.phi);
+        for fault in &self.faults {
+            match fault {
+                Fault::WrongTarget { from, phi, to: wrong } if *from == spec.from && *phi == kind => {
+                    to = *wrong;
+                }
+                Fault::SwappedOutput { phi, output: wrong } if *phi == kind => {
+                    output = wrong.clone();
+                }
+                Fault::DroppedUpdate { phi } if *phi == kind => {
+                    store = before.clone();
+                }
+                _ => {}
+            }
+        }
+
+        self.runner = MachineRunner::from_parts(to, store);
+        Ok(Transition {
+            from: spec.from,
+            phi: spec.phi,
+            to,
+            output,
+        })
+    }
+
+    fn has_fault(&self, fault: &FaultOf<T>) -> bool {
+        self.faults.contains(fault)
+    }
+
+    /// Every single fault applicable to `T`: each transition redirected to each
+    /// other state, each phi emitting each declared output (or none),
+    /// each phi dropping its memory update, and each guard bypassed or blocked.
+    pub fn single_faults() -> Vec<FaultOf<T>> {
+        let mut faults = Vec::new();
+        for &from in T::all_states() {
+            for &phi in T::all_phis() {
+                let Some(target) = T::next_state(from, phi) else {
+                    continue;
+                };
+                for &to in T::all_states().iter().filter(|&&s| s != target) {
+                    faults.push(Fault::WrongTarget { from, phi, to });
+                }
+            }
+        }
+        for &phi in T::all_phis() {
+            for output in T::all_outputs().iter() {
+                faults.push(Fault::SwappedOutput {
+                    phi,
+                    output: Some(output.clone()),
+                });
+            }
+            faults.push(Fault::SwappedOutput { phi, output: None });
+            faults.push(Fault::DroppedUpdate { phi });
+            faults.push(Fault::GuardBypassed { phi });
+            faults.push(Fault::GuardBlocked { phi });
+        }
+        faults
+    }
+}
+
+/// Which mutants a suite detects, from `mutation_score`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MutationReport<State, Phi, Output> {
+    /// Mutants failing at least one case.
+    pub killed: Vec<Fault<State, Phi, Output>>,
+    /// Mutants passing every case: either the suite misses them, or they
+    /// behave like the spec on every input sequence.
+    pub survived: Vec<Fault<State, Phi, Output>>,
+}
+
+/// `MutationReport` specialised to the symbols of machine `T`.
+pub type MutationReportOf<T> = MutationReport<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Output>;
+
+impl<State, Phi, Output> MutationReport<State, Phi, Output> {
+    /// Fraction of mutants killed, 1.0 when there are none.
+    pub fn score(&self) -> f64 {
+        let total = self.killed.len() + self.survived.len();
+        if total == 0 {
+            1.0
+        } else {
+            self.killed.len() as f64 / total as f64
+        }
+    }
+}
+
+/// Runs `cases` against every mutant of `MutantRunner::single_faults`.
+///
+/// Mutants are run as black boxes, their state hidden, so a mutant is killed
+/// only by an output the cases predict: of a test input, or of a
+/// verification sequence. Cases failing on the spec itself kill nothing. The score answers how much of the transition
+/// function and guards a suite, typically a hand-written one, pins down.
+pub fn mutation_score<T: XMachine>(cases: &[TestCaseOf<T>]) -> MutationReportOf<T>
+where
+    T::Memory: Store,
+{
+    mutation_score_with::<T>(cases, MutantRunner::<T>::single_faults())
+}
+
+/// Like `mutation_score`, over the single-fault mutants of `faults`.
+pub fn mutation_score_with<T: XMachine>(cases: &[TestCaseOf<T>], faults: Vec<FaultOf<T>>) -> MutationReportOf<T>
+where
+    T::Memory: Store,
+{
+    let mut report = MutationReport {
+        killed: Vec::new(),
+        survived: Vec::new(),
+    };
+    let run = |faults: Vec<FaultOf<T>>| {
+        let mutant = MutantRunner::<T>::new(faults);
+        Executor::run_suite::<T, _>(cases, || BlackBox(mutant.clone())).results
+    };
+    let baseline = run(Vec::new());
+    for fault in faults {
+        let results = run(vec![fault.clone()]);
+        let killed = results
+            .iter()
+            .zip(&baseline)
+            .any(|(result, spec)| spec.verdict == Verdict::Pass && result.verdict != Verdict::Pass);
+        if killed {
+            report.killed.push(fault);
+        } else {
+            report.survived.push(fault);
+        }
+    }
+    report
+}
+
+/// A mutant whose state cannot be observed.
+struct BlackBox<T: XMachine>(MutantRunner<T>);
+
+impl<T: XMachine> SutAdapter<T> for BlackBox<T> {
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output> {
+        self.0.apply(input)
+    }
+
+    fn inject(&mut self, state: T::State, store: &T::Memory) -> bool {
+        self.0.inject(state, store)
+    }
+}
+
+impl<T: XMachine> Clone for MutantRunner<T> {
+    fn clone(&self) -> Self {
+        Self {
+            runner: self.runner.clone(),
+            faults: self.faults.clone(),
+        }
+    }
+}
+
+impl<T: XMachine> SutAdapter<T> for MutantRunner<T> {
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output> {
+        self.step(input).ok().and_then(|t| t.output)
+    }
+
+    fn observe_state(&self) -> Option<T::State> {
+        Some(self.runner.state())
+    }
+
+    fn observe_store(&self) -> Option<T::Memory> {
+        Some(self.runner.store().clone())
+    }
+
+    fn inject(&mut self, state: T::State, store: &T::Memory) -> bool {
+        self.runner = MachineRunner::from_parts(state, store.clone());
+        true
+    }
+
+    fn reset(&mut self) -> bool {
+        self.runner.reset();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Door, DoorInputAlphabet, DoorOutputAlphabet, DoorPhi, DoorState};
+    use crate::suite::TestSuiteBuilder;
+
+    #[test]
+    fn a_mutant_deviates_where_its_fault_applies() {
+        let wrong_target = Fault::WrongTarget {
+            from: DoorState::Closed,
+            phi: DoorPhi::OpenDoor,
+            to: DoorState::Closed,
+        };
+        let mut mutant = MutantRunner::<Door>::new(Vec::new()).with_fault(wrong_target);
+        assert_eq!(mutant.step(&DoorInputAlphabet::Open).unwrap().output, Some(DoorOutputAlphabet::DoorOpens));
+        assert_eq!(mutant.runner().state(), DoorState::Closed);
+    }
+
+    #[test]
+    fn a_logic_suite_kills_every_observable_mutant() {
+        let distinguishing = |_| vec![DoorInputAlphabet::Open];
+        let cases = TestSuiteBuilder::<Door>::new().logic(&distinguishing).build().cases;
+        let report = mutation_score::<Door>(&cases);
+        assert!(report.score() > 0.5);
+        // The door's guards always hold and its counter is never observed,
+        // so only mutants of its transitions and outputs can be told apart.
+        assert!(report.survived.iter().all(|fault| match fault {
+            Fault::SwappedOutput { phi, output } => {
+                let own = match phi {
+                    DoorPhi::OpenDoor => DoorOutputAlphabet::DoorOpens,
+                    DoorPhi::CloseDoor => DoorOutputAlphabet::DoorCloses,
+                    DoorPhi::IgnoreOpen => DoorOutputAlphabet::OpenIgnored,
+                    DoorPhi::IgnoreClose => DoorOutputAlphabet::CloseIgnored,
+                };
+                *output == Some(own)
+            }
+            Fault::DroppedUpdate { .. } | Fault::GuardBypassed { .. } => true,
+            Fault::WrongTarget { .. } | Fault::GuardBlocked { .. } => false,
+        }));
+    }
+}
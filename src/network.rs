@@ -0,0 +1,808 @@
+use crate::configuration::Configuration;
+use crate::runner::{MachineRunner, StepResultOf};
+use crate::{Visibility, XMachine};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A value belonging to one of the two components of a `MachineNetwork`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tagged<XA, XB> {
+    A(XA),
+    B(XB),
+}
+
+/// One component step inside a network change of configuration.
+pub struct MicroStep<A: XMachine, B: XMachine> {
+    /// The input consumed, tagged with the component that consumed it.
+    pub input: Tagged<A::Input, B::Input>,
+    /// `true` if the input came from the environment rather than the peer.
+    pub external: bool,
+    /// Outcome of the component's step.
+    pub result: Tagged<StepResultOf<A>, StepResultOf<B>>,
+    /// `true` if the produced output was routed to the peer.
+    pub routed: bool,
+    /// Name of the `Port` the output was routed through, if the network is wired by ports.
+    pub port: Option<&'static str>,
+    /// Messages waiting in the channel to A after this step, in its delivery order.
+    pub queued_to_a: Vec<A::Input>,
+    /// Messages waiting in the channel to B after this step, in its delivery order.
+    pub queued_to_b: Vec<B::Input>,
+    /// The message the receiving channel's `ChannelPolicy` discarded when the
+    /// output was routed: the routed message itself, or the one it displaced.
+    pub dropped: Option<Tagged<A::Input, B::Input>>,
+}
+
+/// Everything that happened while the network processed one environment input.
+pub struct StepReport<A: XMachine, B: XMachine> {
+    /// Component steps in execution order.
+    pub micro_steps: Vec<MicroStep<A, B>>,
+    /// Outputs that left the network, in emission order.
+    pub environment_outputs: Vec<Tagged<A::Output, B::Output>>,
+    /// Routed messages discarded by a channel's `ChannelPolicy`.
+    pub dropped: Vec<Tagged<A::Input, B::Input>>,
+    /// `true` if no internal message was left pending; `false` if the step
+    /// budget ran out first.
+    pub stable: bool,
+}
+
+/// The configurations of both components of a `MachineNetwork` together
+/// with the routed messages waiting between them, taken by `snapshot`.
+///
+/// Restoring it puts the network back exactly: the same messages are
+/// delivered in the same order.
+pub struct NetworkSnapshot<A: XMachine, B: XMachine> {
+    pub a: Configuration<A>,
+    pub b: Configuration<B>,
+    to_a: VecDeque<(u64, A::Input)>,
+    to_b: VecDeque<(u64, B::Input)>,
+    next_seq: u64,
+}
+
+impl<A: XMachine, B: XMachine> NetworkSnapshot<A, B> {
+    /// Routed messages waiting in the channels, in delivery order.
+    pub fn pending(&self) -> Vec<Tagged<A::Input, B::Input>> {
+        merge_pending(&self.to_a, &self.to_b)
+    }
+}
+
+/// Buffering semantics of the channel feeding one component.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelPolicy {
+    /// FIFO without a size limit.
+    Unbounded,
+    /// FIFO holding at most `capacity` messages.
+    Bounded { capacity: usize, overflow: Overflow },
+    /// Holds only the most recent message, overwriting an undelivered one.
+    LatestOnly,
+}
+
+/// Which message a full `ChannelPolicy::Bounded` channel discards.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    DropNewest,
+    DropOldest,
+}
+
+/// Queue of routed messages, kept in delivery order; each carries a global
+/// sequence number so that delivery across both channels stays in send order.
+pub(crate) struct Channel<X> {
+    policy: ChannelPolicy,
+    /// Orders the queue by decreasing priority, in send order among equals.
+    priority: Option<fn(&X) -> u32>,
+    queue: VecDeque<(u64, X)>,
+}
+
+impl<X: Clone> Clone for Channel<X> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy,
+            priority: self.priority,
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+impl<X> Channel<X> {
+    pub(crate) fn new(policy: ChannelPolicy) -> Self {
+        Self {
+            policy,
+            priority: None,
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Enqueues `message`, returning the message discarded by the policy, if any.
+    /// `Overflow::DropOldest` discards the message sent first, wherever its
+    /// priority placed it.
+    pub(crate) fn push(&mut self, seq: u64, message: X) -> Option<X> {
+        match self.policy {
+            ChannelPolicy::Unbounded => {
+                self.insert(seq, message);
+                None
+            }
+            ChannelPolicy::Bounded { capacity, overflow } => {
+                if self.queue.len() < capacity {
+                    self.insert(seq, message);
+                    None
+                } else if overflow == Overflow::DropOldest && capacity > 0 {
+                    self.insert(seq, message);
+                    let oldest = (0..self.queue.len()).min_by_key(|&idx| self.queue[idx].0)?;
+                    self.queue.remove(oldest).map(|(_, m)| m)
+                } else {
+                    Some(message)
+                }
+            }
+            ChannelPolicy::LatestOnly => {
+                let overwritten = self.queue.pop_front().map(|(_, m)| m);
+                self.queue.push_back((seq, message));
+                overwritten
+            }
+        }
+    }
+
+    /// Places `message` after every message of at least its priority.
+    fn insert(&mut self, seq: u64, message: X) {
+        let Some(priority) = self.priority else {
+            self.queue.push_back((seq, message));
+            return;
+        };
+        let rank = priority(&message);
+        let at = self.queue.iter().position(|(_, queued)| priority(queued) < rank).unwrap_or(self.queue.len());
+        self.queue.insert(at, (seq, message));
+    }
+
+    pub(crate) fn front_seq(&self) -> Option<u64> {
+        self.queue.front().map(|(seq, _)| *seq)
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<X> {
+        self.queue.pop_front().map(|(_, m)| m)
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: ChannelPolicy) {
+        self.policy = policy;
+    }
+
+    /// Queued messages with their sequence numbers, in delivery order.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &(u64, X)> + '_ {
+        self.queue.iter()
+    }
+}
+
+/// How outputs of each component of a `MachineNetwork<A, B>` become inputs of the other.
+pub trait Wiring<A: XMachine, B: XMachine> {
+    /// The input of B that `output` of A travels as, with the port carrying it.
+    fn a_to_b(output: &A::Output) -> Option<(B::Input, Option<&'static str>)>;
+
+    /// The input of A that `output` of B travels as, with the port carrying it.
+    fn b_to_a(output: &B::Output) -> Option<(A::Input, Option<&'static str>)>;
+}
+
+/// Routes every output that converts (`TryFrom`) into a peer input.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Adapters;
+
+impl<A, B> Wiring<A, B> for Adapters
+where
+    A: XMachine,
+    B: XMachine,
+    B::Input: TryFrom<A::Output>,
+    A::Input: TryFrom<B::Output>,
+{
+    fn a_to_b(output: &A::Output) -> Option<(B::Input, Option<&'static str>)> {
+        B::Input::try_from(output.clone()).ok().map(|input| (input, None))
+    }
+
+    fn b_to_a(output: &B::Output) -> Option<(A::Input, Option<&'static str>)> {
+        A::Input::try_from(output.clone()).ok().map(|input| (input, None))
+    }
+}
+
+/// A named, typed interface carrying some outputs of `From` to `To`.
+///
+/// Connecting machines through ports instead of whole output alphabets makes
+/// each connection explicit, and its endpoint types are checked at compile time.
+pub trait Port {
+    type From: XMachine;
+    type To: XMachine;
+
+    const NAME: &'static str;
+
+    /// The input of `To` that `output` travels as, or `None` if this port does not carry it.
+    fn carry(output: &<Self::From as XMachine>::Output) -> Option<<Self::To as XMachine>::Input>;
+}
+
+/// The ports connecting `From` to `To`: a single `Port`, a tuple of port sets
+/// tried in order, or `()` for no connection.
+pub trait PortSet<From: XMachine, To: XMachine> {
+    fn carry(output: &From::Output) -> Option<(To::Input, &'static str)>;
+
+    /// Port names, in the order they are tried.
+    fn names() -> Vec<&'static str>;
+}
+
+impl<P: Port> PortSet<P::From, P::To> for P {
+    fn carry(output: &<P::From as XMachine>::Output) -> Option<(<P::To as XMachine>::Input, &'static str)> {
+        P::carry(output).map(|input| (input, P::NAME))
+    }
+
+    fn names() -> Vec<&'static str> {
+        vec![P::NAME]
+    }
+}
+
+impl<From: XMachine, To: XMachine> PortSet<From, To> for () {
+    fn carry(_: &From::Output) -> Option<(To::Input, &'static str)> {
+        None
+    }
+
+    fn names() -> Vec<&'static str> {
+        Vec::new()
+    }
+}
+
+impl<From, To, P1, P2> PortSet<From, To> for (P1, P2)
+where
+    From: XMachine,
+    To: XMachine,
+    P1: PortSet<From, To>,
+    P2: PortSet<From, To>,
+{
+    fn carry(output: &From::Output) -> Option<(To::Input, &'static str)> {
+        P1::carry(output).or_else(|| P2::carry(output))
+    }
+
+    fn names() -> Vec<&'static str> {
+        let mut names = P1::names();
+        names.extend(P2::names());
+        names
+    }
+}
+
+impl<From, To, P1, P2, P3> PortSet<From, To> for (P1, P2, P3)
+where
+    From: XMachine,
+    To: XMachine,
+    P1: PortSet<From, To>,
+    P2: PortSet<From, To>,
+    P3: PortSet<From, To>,
+{
+    fn carry(output: &From::Output) -> Option<(To::Input, &'static str)> {
+        P1::carry(output)
+            .or_else(|| P2::carry(output))
+            .or_else(|| P3::carry(output))
+    }
+
+    fn names() -> Vec<&'static str> {
+        let mut names = P1::names();
+        names.extend(P2::names());
+        names.extend(P3::names());
+        names
+    }
+}
+
+/// Routes only what the declared ports carry: `AB` from A to B, `BA` from B to A.
+pub struct Ports<AB, BA>(PhantomData<(AB, BA)>);
+
+impl<A, B, AB, BA> Wiring<A, B> for Ports<AB, BA>
+where
+    A: XMachine,
+    B: XMachine,
+    AB: PortSet<A, B>,
+    BA: PortSet<B, A>,
+{
+    fn a_to_b(output: &A::Output) -> Option<(B::Input, Option<&'static str>)> {
+        AB::carry(output).map(|(input, port)| (input, Some(port)))
+    }
+
+    fn b_to_a(output: &B::Output) -> Option<(A::Input, Option<&'static str>)> {
+        BA::carry(output).map(|(input, port)| (input, Some(port)))
+    }
+}
+
+/// A Communicating Stream X-Machine System of two components.
+///
+/// Outputs of one component that the wiring `W` maps to inputs of the other
+/// are routed to it, unless `XMachine::output_visibility` marks them external;
+/// all other outputs go to the environment. By default (`Adapters`) every
+/// output that converts (`TryFrom`) into a peer input is routed; `Ports`
+/// restricts routing to declared ports. Outputs marked `Visibility::Shared`
+/// go to both: the environment receives them first, and the peer consumes them
+/// in the order they were queued. Processing an environment input runs
+/// micro-steps until no routed message is pending (a stable configuration) or the
+/// step budget is exhausted.
+///
+/// Routed messages wait in one channel per receiving component, buffered
+/// according to its `ChannelPolicy` (unbounded FIFO by default). Messages are
+/// delivered in the order they were sent, across both channels, except that
+/// a channel made a priority queue by `with_priority_to_a` or
+/// `with_priority_to_b` offers its highest priority message first.
+///
+/// Networks of more components, where one output can be delivered to
+/// several of them, are built with `multicast::MulticastNetwork`.
+pub struct MachineNetwork<A: XMachine, B: XMachine, W = Adapters> {
+    a: MachineRunner<A>,
+    b: MachineRunner<B>,
+    to_a: Channel<A::Input>,
+    to_b: Channel<B::Input>,
+    next_seq: u64,
+    step_budget: usize,
+    wiring: PhantomData<W>,
+}
+
+impl<A, B, W> MachineNetwork<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    /// Default bound on micro-steps per environment input.
+    pub const DEFAULT_STEP_BUDGET: usize = 64;
+
+    /// Both components start in their initial configuration.
+    pub fn new() -> Self {
+        Self::from_runners(MachineRunner::new(), MachineRunner::new())
+    }
+
+    pub fn from_runners(a: MachineRunner<A>, b: MachineRunner<B>) -> Self {
+        Self {
+            a,
+            b,
+            to_a: Channel::new(ChannelPolicy::Unbounded),
+            to_b: Channel::new(ChannelPolicy::Unbounded),
+            next_seq: 0,
+            step_budget: Self::DEFAULT_STEP_BUDGET,
+            wiring: PhantomData,
+        }
+    }
+
+    /// Sets the buffering policy of the channel delivering to component A.
+    pub fn with_policy_to_a(mut self, policy: ChannelPolicy) -> Self {
+        self.to_a.policy = policy;
+        self
+    }
+
+    /// Sets the buffering policy of the channel delivering to component B.
+    pub fn with_policy_to_b(mut self, policy: ChannelPolicy) -> Self {
+        self.to_b.policy = policy;
+        self
+    }
+
+    /// Makes the channel delivering to component A a priority queue: the
+    /// message with the highest `priority` is delivered first, and messages
+    /// of equal priority in the order they were sent.
+    pub fn with_priority_to_a(mut self, priority: fn(&A::Input) -> u32) -> Self {
+        self.to_a.priority = Some(priority);
+        self
+    }
+
+    /// Makes the channel delivering to component B a priority queue, as
+    /// `with_priority_to_a`.
+    pub fn with_priority_to_b(mut self, priority: fn(&B::Input) -> u32) -> Self {
+        self.to_b.priority = Some(priority);
+        self
+    }
+
+    /// Bounds the number of micro-steps per environment input, guarding
+    /// against components that keep messaging each other forever.
+    pub fn with_step_budget(mut self, step_budget: usize) -> Self {
+        self.step_budget = step_budget;
+        self
+    }
+
+    pub fn a(&self) -> &MachineRunner<A> {
+        &self.a
+    }
+
+    pub fn b(&self) -> &MachineRunner<B> {
+        &self.b
+    }
+
+    pub fn a_mut(&mut self) -> &mut MachineRunner<A> {
+        &mut self.a
+    }
+
+    pub fn b_mut(&mut self) -> &mut MachineRunner<B> {
+        &mut self.b
+    }
+
+    /// `true` when no routed message is waiting to be consumed.
+    pub fn is_stable(&self) -> bool {
+        self.to_a.queue.is_empty() && self.to_b.queue.is_empty()
+    }
+
+    /// Routed messages waiting in the channels, in delivery order.
+    pub fn pending(&self) -> Vec<Tagged<A::Input, B::Input>> {
+        merge_pending(&self.to_a.queue, &self.to_b.queue)
+    }
+
+    /// Captures both configurations and the pending messages at once, e.g.
+    /// to explore several continuations from the same point or to simulate
+    /// a crash and recovery of the driver.
+    pub fn snapshot(&self) -> NetworkSnapshot<A, B> {
+        NetworkSnapshot {
+            a: self.a.configuration().clone(),
+            b: self.b.configuration().clone(),
+            to_a: self.to_a.queue.clone(),
+            to_b: self.to_b.queue.clone(),
+            next_seq: self.next_seq,
+        }
+    }
+
+    /// Puts both components and the channels back as captured by `snapshot`.
+    /// Channel policies, the step budget, and each runner's own queue, defer
+    /// policy and metrics are kept.
+    pub fn restore(&mut self, snapshot: &NetworkSnapshot<A, B>) {
+        self.a.set_configuration(snapshot.a.clone());
+        self.b.set_configuration(snapshot.b.clone());
+        self.to_a.queue = snapshot.to_a.clone();
+        self.to_b.queue = snapshot.to_b.clone();
+        self.next_seq = snapshot.next_seq;
+    }
+
+    /// Feeds an environment input to its component and propagates the
+    /// resulting internal messages until the network is stable.
+    ///
+    /// The environment input itself always counts as the first micro-step.
+    pub fn process_input(&mut self, input: Tagged<A::Input, B::Input>) -> StepReport<A, B> {
+        let mut report = StepReport::empty();
+
+        let step = self.micro_step(input, true, &mut report);
+        report.micro_steps.push(step);
+
+        while report.micro_steps.len() < self.step_budget {
+            let Some(input) = self.next_pending() else {
+                break;
+            };
+            let step = self.micro_step(input, false, &mut report);
+            report.micro_steps.push(step);
+        }
+
+        report.stable = self.is_stable();
+        report
+    }
+
+    /// Consumes an environment input as a single micro-step, leaving the
+    /// messages it routes pending for `deliver_next`.
+    pub fn feed(&mut self, input: Tagged<A::Input, B::Input>) -> StepReport<A, B> {
+        let mut report = StepReport::empty();
+        let step = self.micro_step(input, true, &mut report);
+        report.micro_steps.push(step);
+        report.stable = self.is_stable();
+        report
+    }
+
+    /// Delivers the next pending routed message as a single micro-step.
+    /// Returns `None` if the network is stable.
+    pub fn deliver_next(&mut self) -> Option<StepReport<A, B>> {
+        let input = self.next_pending()?;
+        let mut report = StepReport::empty();
+        let step = self.micro_step(input, false, &mut report);
+        report.micro_steps.push(step);
+        report.stable = self.is_stable();
+        Some(report)
+    }
+
+    /// Pops the older of the messages at the front of both channels.
+    fn next_pending(&mut self) -> Option<Tagged<A::Input, B::Input>> {
+        match (self.to_a.front_seq(), self.to_b.front_seq()) {
+            (Some(a), Some(b)) if a < b => self.to_a.pop().map(Tagged::A),
+            (Some(_), None) => self.to_a.pop().map(Tagged::A),
+            (_, Some(_)) => self.to_b.pop().map(Tagged::B),
+            (None, None) => None,
+        }
+    }
+
+    fn micro_step(
+        &mut self,
+        input: Tagged<A::Input, B::Input>,
+        external: bool,
+        report: &mut StepReport<A, B>,
+    ) -> MicroStep<A, B> {
+        let mut routed = false;
+        let mut port = None;
+        let mut dropped = None;
+        let seq = self.next_seq;
+        let result = match &input {
+            Tagged::A(inp) => {
+                let result = self.a.step(inp);
+                if let Ok(Some(out)) = result.as_ref().map(|t| t.output.clone()) {
+                    let (peer_input, to_environment) = route::<A, _>(&out, W::a_to_b);
+                    if to_environment {
+                        report.environment_outputs.push(Tagged::A(out));
+                    }
+                    if let Some((peer_input, via)) = peer_input {
+                        dropped = self.to_b.push(seq, peer_input).map(Tagged::B);
+                        routed = true;
+                        port = via;
+                    }
+                }
+                Tagged::A(result)
+            }
+            Tagged::B(inp) => {
+                let result = self.b.step(inp);
+                if let Ok(Some(out)) = result.as_ref().map(|t| t.output.clone()) {
+                    let (peer_input, to_environment) = route::<B, _>(&out, W::b_to_a);
+                    if to_environment {
+                        report.environment_outputs.push(Tagged::B(out));
+                    }
+                    if let Some((peer_input, via)) = peer_input {
+                        dropped = self.to_a.push(seq, peer_input).map(Tagged::A);
+                        routed = true;
+                        port = via;
+                    }
+                }
+                Tagged::B(result)
+            }
+        };
+        if routed {
+            self.next_seq += 1;
+        }
+        report.dropped.extend(dropped.clone());
+
+        MicroStep {
+            input,
+            external,
+            result,
+            routed,
+            port,
+            queued_to_a: self.to_a.queue.iter().map(|(_, m)| m.clone()).collect(),
+            queued_to_b: self.to_b.queue.iter().map(|(_, m)| m.clone()).collect(),
+            dropped,
+        }
+    }
+}
+
+impl<A: XMachine, B: XMachine> StepReport<A, B> {
+    fn empty() -> Self {
+        Self {
+            micro_steps: Vec::new(),
+            environment_outputs: Vec::new(),
+            dropped: Vec::new(),
+            stable: false,
+        }
+    }
+}
+
+/// Messages of both channels in send order.
+fn merge_pending<XA: Clone, XB: Clone>(to_a: &VecDeque<(u64, XA)>, to_b: &VecDeque<(u64, XB)>) -> Vec<Tagged<XA, XB>> {
+    let mut to_a = to_a.iter().peekable();
+    let mut to_b = to_b.iter().peekable();
+    let mut pending = Vec::new();
+    loop {
+        let take_a = match (to_a.peek(), to_b.peek()) {
+            (Some((seq_a, _)), Some((seq_b, _))) => seq_a < seq_b,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => break,
+        };
+        if take_a {
+            pending.extend(to_a.next().map(|(_, m)| Tagged::A(m.clone())));
+        } else {
+            pending.extend(to_b.next().map(|(_, m)| Tagged::B(m.clone())));
+        }
+    }
+    pending
+}
+
+/// Decides where `output` of `M` goes: the peer input `wire` maps it to, if it
+/// is delivered to the peer, and whether it is also emitted to the environment.
+pub(crate) fn route<M: XMachine, Peer>(
+    output: &M::Output,
+    wire: fn(&M::Output) -> Option<Peer>,
+) -> (Option<Peer>, bool) {
+    let visibility = M::output_visibility(output);
+    if visibility == Some(Visibility::External) {
+        return (None, true);
+    }
+    let peer_input = wire(output);
+    let to_environment = peer_input.is_none() || visibility == Some(Visibility::Shared);
+    (peer_input, to_environment)
+}
+
+impl<A, B, W> Default for MachineNetwork<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: XMachine, B: XMachine> fmt::Debug for MicroStep<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MicroStep")
+            .field("input", &self.input)
+            .field("external", &self.external)
+            .field("result", &self.result)
+            .field("routed", &self.routed)
+            .field("port", &self.port)
+            .field("queued_to_a", &self.queued_to_a)
+            .field("queued_to_b", &self.queued_to_b)
+            .field("dropped", &self.dropped)
+            .finish()
+    }
+}
+
+impl<A: XMachine, B: XMachine, W> Clone for MachineNetwork<A, B, W> {
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            to_a: self.to_a.clone(),
+            to_b: self.to_b.clone(),
+            next_seq: self.next_seq,
+            step_budget: self.step_budget,
+            wiring: PhantomData,
+        }
+    }
+}
+
+impl<A: XMachine, B: XMachine> Clone for NetworkSnapshot<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            a: self.a.clone(),
+            b: self.b.clone(),
+            to_a: self.to_a.clone(),
+            to_b: self.to_b.clone(),
+            next_seq: self.next_seq,
+        }
+    }
+}
+
+impl<A: XMachine, B: XMachine> fmt::Debug for NetworkSnapshot<A, B>
+where
+    A::Memory: fmt::Debug,
+    B::Memory: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetworkSnapshot")
+            .field("a", &self.a)
+            .field("b", &self.b)
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+impl<A: XMachine, B: XMachine> Clone for MicroStep<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            input: self.input.clone(),
+            external: self.external,
+            result: self.result.clone(),
+            routed: self.routed,
+            port: self.port,
+            queued_to_a: self.queued_to_a.clone(),
+            queued_to_b: self.queued_to_b.clone(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+impl<A: XMachine, B: XMachine> fmt::Debug for StepReport<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StepReport")
+            .field("micro_steps", &self.micro_steps)
+            .field("environment_outputs", &self.environment_outputs)
+            .field("dropped", &self.dropped)
+            .field("stable", &self.stable)
+            .finish()
+    }
+}
+
+impl<A: XMachine, B: XMachine> Clone for StepReport<A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            micro_steps: self.micro_steps.clone(),
+            environment_outputs: self.environment_outputs.clone(),
+            dropped: self.dropped.clone(),
+            stable: self.stable,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, Door, DoorInputAlphabet};
+
+    fn drain(channel: &mut Channel<u32>) -> Vec<u32> {
+        std::iter::from_fn(|| channel.pop()).collect()
+    }
+
+    #[test]
+    fn priority_channels_deliver_the_highest_priority_first_in_send_order() {
+        let mut channel = Channel::new(ChannelPolicy::Unbounded);
+        channel.priority = Some(|message: &u32| *message / 10);
+        for (seq, message) in [10, 50, 30, 51].into_iter().enumerate() {
+            channel.push(seq as u64, message);
+        }
+        assert_eq!(drain(&mut channel), [50, 51, 30, 10]);
+    }
+
+    #[test]
+    fn bounded_priority_channels_drop_the_message_sent_first() {
+        let mut channel = Channel::new(ChannelPolicy::Bounded {
+            capacity: 2,
+            overflow: Overflow::DropOldest,
+        });
+        channel.priority = Some(|message: &u32| *message);
+        assert_eq!(channel.push(0, 5), None);
+        assert_eq!(channel.push(1, 1), None);
+        assert_eq!(channel.push(2, 3), Some(5));
+        assert_eq!(drain(&mut channel), [3, 1]);
+    }
+
+    #[test]
+    fn micro_steps_list_the_queued_messages() {
+        use DigicodeInputAlphabet::*;
+
+        let mut network = MachineNetwork::<Digicode, Door>::new();
+        for digit in [4, 9, 2] {
+            network.process_input(Tagged::A(Digit(digit)));
+        }
+        let report = network.process_input(Tagged::A(OkEnter));
+        assert!(report.stable);
+        let queued: Vec<(usize, Vec<DoorInputAlphabet>)> =
+            report.micro_steps.iter().map(|step| (step.queued_to_a.len(), step.queued_to_b.clone())).collect();
+        assert_eq!(queued, [(0, vec![DoorInputAlphabet::Open]), (0, vec![])]);
+    }
+
+    #[test]
+    fn a_message_lost_to_overflow_shows_in_the_trace() {
+        use DoorInputAlphabet::*;
+
+        // `feed` leaves routed messages pending, so the second closing of
+        // the door overflows the one-message channel back to the keypad.
+        let mut network = MachineNetwork::<Digicode, Door>::new().with_policy_to_a(ChannelPolicy::Bounded {
+            capacity: 1,
+            overflow: Overflow::DropOldest,
+        });
+        let mut feed = |input| network.feed(Tagged::B(input)).micro_steps.remove(0);
+        feed(Open);
+        let first = feed(Close);
+        assert_eq!(first.queued_to_a, [DigicodeInputAlphabet::DoorCloses]);
+        assert_eq!(first.dropped, None);
+        feed(Open);
+        let second = feed(Close);
+        assert_eq!(second.queued_to_a, [DigicodeInputAlphabet::DoorCloses]);
+        assert_eq!(second.dropped, Some(Tagged::A(DigicodeInputAlphabet::DoorCloses)));
+    }
+
+    #[test]
+    fn restoring_a_snapshot_brings_back_the_queued_messages() {
+        use DigicodeInputAlphabet::*;
+
+        let mut network = MachineNetwork::<Digicode, Door>::new();
+        for digit in [4, 9, 2] {
+            network.process_input(Tagged::A(Digit(digit)));
+        }
+        network.feed(Tagged::A(OkEnter));
+        network.feed(Tagged::B(DoorInputAlphabet::Open));
+        network.feed(Tagged::B(DoorInputAlphabet::Close));
+        let pending = [Tagged::B(DoorInputAlphabet::Open), Tagged::A(DoorCloses)];
+        assert_eq!(network.pending(), pending);
+
+        let snapshot = network.snapshot();
+        assert_eq!(snapshot.pending(), pending);
+        let drain = |network: &mut MachineNetwork<Digicode, Door>| {
+            let steps: Vec<_> = std::iter::from_fn(|| network.deliver_next())
+                .map(|report| report.micro_steps[0].input.clone())
+                .collect();
+            (steps, network.a().state(), network.b().state())
+        };
+        let continued = drain(&mut network);
+        network.process_input(Tagged::A(Digit(1)));
+        assert!(network.pending().is_empty());
+
+        network.restore(&snapshot);
+        let restored = network.snapshot();
+        assert_eq!((&restored.a, &restored.b), (&snapshot.a, &snapshot.b));
+        assert_eq!(restored.pending(), pending);
+        assert_eq!(drain(&mut network), continued);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::rng::SplitMix64;
+use crate::runner::MachineRunner;
+use crate::simulation::TickSimulation;
+use crate::suite::GenerationSeed;
+use crate::XMachine;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// A change an unreliable transport made to the input stream. Indices refer
+/// to the nominal input sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Perturbation {
+    Dropped { index: usize },
+    Duplicated { index: usize },
+    /// The input was delivered after the one following it.
+    Swapped { index: usize },
+    /// The input arrived at `actual` instead of `nominal`, both measured from
+    /// the start of the run.
+    Jittered {
+        index: usize,
+        nominal: Duration,
+        actual: Duration,
+    },
+}
+
+/// How the machine behaved on a perturbed input stream compared to the nominal one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseReport<Input, Output, State> {
+    pub perturbations: Vec<Perturbation>,
+    /// The inputs actually delivered.
+    pub noisy_inputs: Vec<Input>,
+    /// Outputs emitted on the nominal stream, in order.
+    pub nominal_outputs: Vec<Output>,
+    /// Outputs emitted on the noisy stream, in order.
+    pub noisy_outputs: Vec<Output>,
+    pub nominal_state: State,
+    pub noisy_state: State,
+    /// Delivered inputs the machine rejected, on each stream.
+    pub nominal_rejections: usize,
+    pub noisy_rejections: usize,
+}
+
+/// `NoiseReport` specialised to the symbols of machine `T`.
+pub type NoiseReportOf<T> = NoiseReport<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Input, Output: PartialEq, State: PartialEq> NoiseReport<Input, Output, State> {
+    /// `true` if the noise changed the emitted outputs or the final state.
+    pub fn diverged(&self) -> bool {
+        self.nominal_outputs != self.noisy_outputs || self.nominal_state != self.noisy_state
+    }
+}
+
+/// Seeded model of an unreliable transport for simulation runs.
+///
+/// Each input is independently dropped, duplicated, or swapped with the next
+/// one, with the configured probabilities, in that order of precedence. The
+/// same seed always yields the same perturbations.
+///
+/// Timed runs also shift the arrival time of each input by up to the
+/// configured jitter either way, never before the previous input arrives,
+/// so an input can cross a tick it was meant to precede or follow.
+#[derive(Clone, Debug)]
+pub struct NoiseModel {
+    drop: f64,
+    duplicate: f64,
+    reorder: f64,
+    jitter: Duration,
+    rng: SplitMix64,
+}
+
+impl NoiseModel {
+    /// A model that changes nothing until probabilities are set.
+    pub fn new(seed: impl Into<GenerationSeed>) -> Self {
+        Self {
+            drop: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            jitter: Duration::ZERO,
+            rng: SplitMix64::new(seed.into().0),
+        }
+    }
+
+    pub fn with_drop(mut self, probability: f64) -> Self {
+        self.drop = probability;
+        self
+    }
+
+    pub fn with_duplicate(mut self, probability: f64) -> Self {
+        self.duplicate = probability;
+        self
+    }
+
+    pub fn with_reorder(mut self, probability: f64) -> Self {
+        self.reorder = probability;
+        self
+    }
+
+    /// Largest shift of an input's arrival time in timed runs.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Perturbs `inputs`, returning the delivered stream and what changed.
+    pub fn perturb<I: Clone>(&mut self, inputs: &[I]) -> (Vec<I>, Vec<Perturbation>) {
+        let mut delivered = Vec::with_capacity(inputs.len());
+        let mut perturbations = Vec::new();
+        let mut index = 0;
+        while index < inputs.len() {
+            let roll = self.rng.next_f64();
+            if roll < self.drop {
+                perturbations.push(Perturbation::Dropped { index });
+            } else if roll < self.drop + self.duplicate {
+                delivered.push(inputs[index].clone());
+                delivered.push(inputs[index].clone());
+                perturbations.push(Perturbation::Duplicated { index });
+            } else if roll < self.drop + self.duplicate + self.reorder && index + 1 < inputs.len() {
+                delivered.push(inputs[index + 1].clone());
+                delivered.push(inputs[index].clone());
+                perturbations.push(Perturbation::Swapped { index });
+                index += 1;
+            } else {
+                delivered.push(inputs[index].clone());
+            }
+            index += 1;
+        }
+        (delivered, perturbations)
+    }
+
+    /// Runs `T` from its initial configuration on `inputs` and on a perturbed
+    /// copy, and compares the two. Rejected inputs are skipped, as a
+    /// robust implementation would ignore them.
+    pub fn simulate<T: XMachine>(&mut self, inputs: &[T::Input]) -> NoiseReportOf<T> {
+        let (noisy_inputs, perturbations) = self.perturb(inputs);
+        let (nominal_outputs, nominal_state, nominal_rejections) = run::<T>(inputs);
+        let (noisy_outputs, noisy_state, noisy_rejections) = run::<T>(&noisy_inputs);
+        NoiseReport {
+            perturbations,
+            noisy_inputs,
+            nominal_outputs,
+            noisy_outputs,
+            nominal_state,
+            noisy_state,
+            nominal_rejections,
+            noisy_rejections,
+        }
+    }
+
+    /// Perturbs a script of inputs each applied its delay after the previous
+    /// one: shifts the arrival times by the jitter, then drops, duplicates
+    /// and swaps entries as `perturb` does. Returns the delivered script and
+    /// what changed.
+    pub fn perturb_timed<I: Clone>(&mut self, script: &[(Duration, I)]) -> (Vec<(Duration, I)>, Vec<Perturbation>) {
+        let mut perturbations = Vec::new();
+        let mut jittered = Vec::with_capacity(script.len());
+        let (mut nominal, mut previous) = (Duration::ZERO, Duration::ZERO);
+        for (index, (delay, input)) in script.iter().enumerate() {
+            nominal += *delay;
+            let mut actual = nominal;
+            if !self.jitter.is_zero() {
+                let shift = self.jitter.mul_f64(self.rng.next_f64());
+                actual = if self.rng.next_f64() < 0.5 {
+                    nominal.saturating_sub(shift)
+                } else {
+                    nominal + shift
+                };
+                actual = actual.max(previous);
+            }
+            if actual != nominal {
+                perturbations.push(Perturbation::Jittered { index, nominal, actual });
+            }
+            jittered.push((actual - previous, input.clone()));
+            previous = actual;
+        }
+        let (delivered, mut changes) = self.perturb(&jittered);
+        perturbations.append(&mut changes);
+        (delivered, perturbations)
+    }
+
+    /// Plays `script` on a `TickSimulation` of `T` ticking every `period`,
+    /// and a perturbed copy on another, and compares the two. Tick outputs
+    /// count as emitted outputs; rejections count script inputs only.
+    ///
+    /// `None` if `T` has no `XMachine::tick_input`.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn simulate_timed<T: XMachine>(
+        &mut self,
+        script: &[(Duration, T::Input)],
+        period: Duration,
+    ) -> Option<NoiseReportOf<T>> {
+        let (noisy_script, perturbations) = self.perturb_timed(script);
+        let (nominal_outputs, nominal_state, nominal_rejections) = run_timed::<T>(script, period)?;
+        let (noisy_outputs, noisy_state, noisy_rejections) = run_timed::<T>(&noisy_script, period)?;
+        Some(NoiseReport {
+            perturbations,
+            noisy_inputs: noisy_script.into_iter().map(|(_, input)| input).collect(),
+            nominal_outputs,
+            noisy_outputs,
+            nominal_state,
+            noisy_state,
+            nominal_rejections,
+            noisy_rejections,
+        })
+    }
+
+    /// `runs` independent `simulate` runs on the same nominal stream.
+    pub fn simulate_runs<T: XMachine>(&mut self, inputs: &[T::Input], runs: usize) -> Vec<NoiseReportOf<T>> {
+        (0..runs).map(|_| self.simulate::<T>(inputs)).collect()
+    }
+}
+
+/// Outputs, final state and number of rejected inputs of one run.
+fn run<T: XMachine>(inputs: &[T::Input]) -> (Vec<T::Output>, T::State, usize) {
+    let mut runner = MachineRunner::<T>::new();
+    let mut outputs = Vec::new();
+    let mut rejections = 0;
+    for input in inputs {
+        match runner.step(input) {
+            Ok(transition) => outputs.extend(transition.output),
+            Err(_) => rejections += 1,
+        }
+    }
+    (outputs, runner.state(), rejections)
+}
+
+/// Outputs, final state and number of rejected script inputs of one timed run.
+fn run_timed<T: XMachine>(
+    script: &[(Duration, T::Input)],
+    period: Duration,
+) -> Option<(Vec<T::Output>, T::State, usize)> {
+    let outputs = Rc::new(RefCell::new(Vec::new()));
+    let sink = Rc::clone(&outputs);
+    let mut simulation = TickSimulation::<T>::for_machine(period)?.on_step(move |_, _, _, result| {
+        if let Ok(transition) = result {
+            sink.borrow_mut().extend(transition.output.clone());
+        }
+    });
+    let rejections = simulation.run(script).iter().filter(|result| result.is_err()).count();
+    let outputs = outputs.borrow().clone();
+    Some((outputs, simulation.runner().state(), rejections))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::{LightInput, TrafficLight};
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn the_same_seed_yields_the_same_perturbations() {
+        let inputs: Vec<u32> = (0..50).collect();
+        let model = NoiseModel::new(7).with_drop(0.1).with_duplicate(0.1).with_reorder(0.1);
+        let (first, second) = (model.clone().perturb(&inputs), model.clone().perturb(&inputs));
+        assert_eq!(first, second);
+        assert!(!first.1.is_empty());
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds_and_keeps_arrival_order() {
+        let script: Vec<(Duration, u32)> = (0..50).map(|i| (10 * MS, i)).collect();
+        let (delivered, perturbations) = NoiseModel::new(3).with_jitter(4 * MS).perturb_timed(&script);
+        assert_eq!(delivered.iter().map(|(_, i)| *i).collect::<Vec<_>>(), (0..50).collect::<Vec<_>>());
+        assert!(!perturbations.is_empty());
+        for perturbation in perturbations {
+            let Perturbation::Jittered { nominal, actual, .. } = perturbation else {
+                panic!("unexpected {perturbation:?}");
+            };
+            assert!(actual.max(nominal) - actual.min(nominal) <= 4 * MS);
+        }
+    }
+
+    #[test]
+    fn jitter_across_a_tick_changes_the_behavior() {
+        let script = [(9 * MS, LightInput::Fault)];
+        let quiet = NoiseModel::new(0).simulate_timed::<TrafficLight>(&script, 10 * MS).unwrap();
+        assert!(quiet.perturbations.is_empty() && !quiet.diverged());
+
+        let diverged = (0..20).any(|seed| {
+            let mut model = NoiseModel::new(seed).with_jitter(5 * MS);
+            model.simulate_timed::<TrafficLight>(&script, 10 * MS).unwrap().diverged()
+        });
+        assert!(diverged);
+    }
+}
@@ -0,0 +1,319 @@
+//! Where realistic usage takes a spec, aggregated over many simulated runs.
+//!
+//! An `Occupancy` accumulates runs, each an initial state followed by the
+//! transitions taken, e.g. from random walks under an operational profile
+//! or from the runner behind a simulation. It reports how much of the time
+//! the spec spends in each state, how likely a run is to enter it at all,
+//! and how often each transition fires, so parts of the spec that realistic
+//! usage never or hardly touches stand out. `to_json` exports the figures
+//! and `graphviz::generate_occupancy_dot` overlays them on the diagram.
+
+use crate::coverage::defined_transitions;
+use crate::json;
+use crate::rng::SplitMix64;
+use crate::runner::{MachineRunner, TransitionOf};
+use crate::suite::{GenerationSeed, Profile};
+use crate::{IndexSet, XMachine};
+use std::fmt::{self, Write};
+use std::marker::PhantomData;
+
+/// A defined transition of the associated automaton and how often it fired.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransitionUsage<State, Phi> {
+    pub from: State,
+    pub phi: Phi,
+    pub to: State,
+    pub count: u64,
+    /// Share of all transitions taken.
+    pub frequency: f64,
+}
+
+/// `TransitionUsage` specialised to the symbols of machine `T`.
+pub type TransitionUsageOf<T> = TransitionUsage<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// State and transition statistics of spec `T` over many runs.
+///
+/// Occupancy counts configurations: the initial one of each run and the one
+/// after each transition. Transitions are counted per (state, phi) pair.
+pub struct Occupancy<T: XMachine> {
+    runs: u64,
+    steps: u64,
+    visits: Vec<u64>,
+    entries: Vec<u64>,
+    fired: Vec<u64>,
+    entered: IndexSet,
+    machine: PhantomData<T>,
+}
+
+impl<T: XMachine> Occupancy<T> {
+    /// No run recorded yet.
+    pub fn new() -> Self {
+        let (states, phis) = (T::all_states().len(), T::all_phis().len());
+        Self {
+            runs: 0,
+            steps: 0,
+            visits: vec![0; states],
+            entries: vec![0; states],
+            fired: vec![0; states * phis],
+            entered: IndexSet::new(states),
+            machine: PhantomData,
+        }
+    }
+
+    /// Records `runs` random walks of up to `walk_length` inputs from an
+    /// initial configuration. Each step draws an input the spec accepts,
+    /// weighed by `profile` or uniformly; a walk ends early when none is left.
+    pub fn from_walks(
+        runs: usize,
+        walk_length: usize,
+        profile: Option<Profile<'_, T>>,
+        seed: impl Into<GenerationSeed>,
+    ) -> Self {
+        let mut occupancy = Self::new();
+        let mut rng = SplitMix64::new(seed.into().0);
+        let inputs = T::all_inputs();
+        for _ in 0..runs {
+            let mut runner = MachineRunner::<T>::new();
+            occupancy.start_run(runner.state());
+            for _ in 0..walk_length {
+                let (state, configuration) = (runner.state(), runner.configuration());
+                let accepted: Vec<&T::Input> =
+                    inputs.iter().filter(|input| configuration.step(input).is_ok()).collect();
+                let weights: Vec<f64> = match profile {
+                    Some(profile) => accepted.iter().map(|input| profile(state, input)).collect(),
+                    None => vec![1.0; accepted.len()],
+                };
+                let Some(idx) = rng.weighted_index(&weights) else {
+                    break;
+                };
+                if let Ok(transition) = runner.step(accepted[idx]) {
+                    occupancy.record(&transition);
+                }
+            }
+        }
+        occupancy
+    }
+
+    /// Starts a new run in `initial`.
+    pub fn start_run(&mut self, initial: T::State) {
+        self.runs += 1;
+        self.entered = IndexSet::new(T::all_states().len());
+        self.visit(initial);
+    }
+
+    /// Records a transition of the current run.
+    pub fn record(&mut self, transition: &TransitionOf<T>) {
+        self.steps += 1;
+        if let (Some(state), Some(phi)) = (T::state_index(transition.from), T::phi_index(transition.phi)) {
+            self.fired[state.0 * T::all_phis().len() + phi.0] += 1;
+        }
+        self.visit(transition.to);
+    }
+
+    /// Records a whole run, e.g. the transitions a runner drained.
+    pub fn record_run(&mut self, initial: T::State, transitions: &[TransitionOf<T>]) {
+        self.start_run(initial);
+        for transition in transitions {
+            self.record(transition);
+        }
+    }
+
+    /// Adds the runs of `other`.
+    pub fn merge(&mut self, other: &Self) {
+        self.runs += other.runs;
+        self.steps += other.steps;
+        for (mine, theirs) in [
+            (&mut self.visits, &other.visits),
+            (&mut self.entries, &other.entries),
+            (&mut self.fired, &other.fired),
+        ] {
+            for (count, added) in mine.iter_mut().zip(theirs) {
+                *count += added;
+            }
+        }
+    }
+
+    pub fn runs(&self) -> u64 {
+        self.runs
+    }
+
+    /// Transitions recorded over all runs.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Share of the visited configurations in `state`; 0.0 without runs.
+    pub fn occupancy(&self, state: T::State) -> f64 {
+        let visits = T::state_index(state).map_or(0, |idx| self.visits[idx.0]);
+        ratio(visits, self.runs + self.steps)
+    }
+
+    /// Share of the runs that entered `state`, starting in it included.
+    pub fn entry_probability(&self, state: T::State) -> f64 {
+        let entries = T::state_index(state).map_or(0, |idx| self.entries[idx.0]);
+        ratio(entries, self.runs)
+    }
+
+    /// Times `phi` fired in `state`.
+    pub fn count(&self, state: T::State, phi: T::Phi) -> u64 {
+        match (T::state_index(state), T::phi_index(phi)) {
+            (Some(state), Some(phi)) => self.fired[state.0 * T::all_phis().len() + phi.0],
+            _ => 0,
+        }
+    }
+
+    /// Every defined transition with its count, in `all_states()` then
+    /// `all_phis()` order, the ones never taken included.
+    pub fn transitions(&self) -> Vec<TransitionUsageOf<T>> {
+        defined_transitions::<T>()
+            .into_iter()
+            .filter_map(|(from, phi)| {
+                let count = self.count(from, phi);
+                Some(TransitionUsage {
+                    from,
+                    phi,
+                    to: T::next_state(from, phi)?,
+                    count,
+                    frequency: ratio(count, self.steps),
+                })
+            })
+            .collect()
+    }
+
+    /// Defined transitions whose frequency is below `threshold`, rarest first.
+    /// A threshold of 0.0 lists nothing; any positive one lists those never taken.
+    pub fn rare_transitions(&self, threshold: f64) -> Vec<TransitionUsageOf<T>> {
+        let mut rare: Vec<_> = self.transitions().into_iter().filter(|t| t.frequency < threshold).collect();
+        rare.sort_by_key(|t| t.count);
+        rare
+    }
+
+    /// States no run entered.
+    pub fn untouched_states(&self) -> Vec<T::State> {
+        T::all_states().iter().copied().filter(|&state| self.entry_probability(state) == 0.0).collect()
+    }
+
+    /// The statistics as JSON, symbols named by their `Debug` form as in
+    /// `DynMachine`: run and step counts, then per state its occupancy and
+    /// entry probability, then per defined transition its count and frequency.
+    pub fn to_json(&self) -> String {
+        let name = |symbol: &dyn fmt::Debug| json::quote(&format!("{:?}", symbol));
+        let mut out = String::new();
+        writeln!(out, "{{").unwrap();
+        writeln!(out, "  \"runs\": {},", self.runs).unwrap();
+        writeln!(out, "  \"steps\": {},", self.steps).unwrap();
+        write!(out, "  \"states\": [").unwrap();
+        for (idx, &state) in T::all_states().iter().enumerate() {
+            out.push_str(if idx == 0 { "\n" } else { ",\n" });
+            write!(
+                out,
+                "    {{\"state\": {}, \"occupancy\": {}, \"entry_probability\": {}}}",
+                name(&state),
+                self.occupancy(state),
+                self.entry_probability(state)
+            )
+            .unwrap();
+        }
+        if !T::all_states().is_empty() {
+            out.push_str("\n  ");
+        }
+        writeln!(out, "],").unwrap();
+
+        let transitions = self.transitions();
+        write!(out, "  \"transitions\": [").unwrap();
+        for (idx, t) in transitions.iter().enumerate() {
+            out.push_str(if idx == 0 { "\n" } else { ",\n" });
+            write!(
+                out,
+                "    {{\"from\": {}, \"phi\": {}, \"to\": {}, \"count\": {}, \"frequency\": {}}}",
+                name(&t.from),
+                name(&t.phi),
+                name(&t.to),
+                t.count,
+                t.frequency
+            )
+            .unwrap();
+        }
+        if !transitions.is_empty() {
+            out.push_str("\n  ");
+        }
+        writeln!(out, "]").unwrap();
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    fn visit(&mut self, state: T::State) {
+        if let Some(idx) = T::state_index(state) {
+            self.visits[idx.0] += 1;
+            if self.entered.insert(idx.0) {
+                self.entries[idx.0] += 1;
+            }
+        }
+    }
+}
+
+fn ratio(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+impl<T: XMachine> Default for Occupancy<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: XMachine> Clone for Occupancy<T> {
+    fn clone(&self) -> Self {
+        Self {
+            runs: self.runs,
+            steps: self.steps,
+            visits: self.visits.clone(),
+            entries: self.entries.clone(),
+            fired: self.fired.clone(),
+            entered: self.entered.clone(),
+            machine: PhantomData,
+        }
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Occupancy<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Occupancy")
+            .field("runs", &self.runs)
+            .field("steps", &self.steps)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::traffic_light::{LightInput, LightPhi, LightState, TrafficLight};
+
+    fn ticks_only(_state: LightState, input: &LightInput) -> f64 {
+        if *input == LightInput::Tick {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    #[test]
+    fn walks_without_faults_never_take_the_fail_safe() {
+        let occupancy = Occupancy::<TrafficLight>::from_walks(10, 6, Some(&ticks_only), 1);
+        assert_eq!((occupancy.runs(), occupancy.steps()), (10, 60));
+        assert_eq!(occupancy.occupancy(LightState::Red), 3.0 / 7.0);
+        assert_eq!(occupancy.entry_probability(LightState::Yellow), 1.0);
+        assert_eq!(occupancy.count(LightState::Red, LightPhi::ToGreen), 20);
+        assert!(occupancy.untouched_states().is_empty());
+
+        let rare = occupancy.rare_transitions(0.01);
+        assert!(!rare.is_empty());
+        assert!(rare.iter().all(|usage| usage.phi == LightPhi::FailSafe && usage.count == 0));
+        assert!(json::parse(&occupancy.to_json()).is_ok());
+    }
+}
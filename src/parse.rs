@@ -0,0 +1,186 @@
+//! Textual input parsing for drivers and scripts.
+//!
+//! An input alphabet declared with `sxm_input!` implements `ParseInput`, so
+//! `"digit 4"`, `"Digit(4)"` and `"DIGIT 4"` all read as `Digit(4)` and
+//! `"ok enter"` or `"ok_enter"` as `OkEnter`. Variant names match without
+//! regard to case, spaces or underscores; fields follow as words, optionally
+//! in parentheses and separated by commas, and are read with `FromStr`.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// An input that can be read from text, usually implemented through `sxm_input!`.
+pub trait ParseInput: Sized {
+    fn parse_input(text: &str) -> Result<Self, ParseError>;
+}
+
+/// Why a text is not an input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// No variant has this name.
+    Unknown(String),
+    /// The variant takes `expected` fields but `found` were given.
+    Arity {
+        variant: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    /// A field of the variant does not parse as its type.
+    InvalidField { variant: &'static str, field: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Unknown(text) => write!(f, "unknown input {:?}", text),
+            ParseError::Arity {
+                variant,
+                expected,
+                found,
+            } => write!(f, "{} takes {} field(s), {} given", variant, expected, found),
+            ParseError::InvalidField { variant, field } => write!(f, "invalid field {:?} for {}", field, variant),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parses a script of inputs, one per line or `;`-separated. Blank lines
+/// and lines starting with `#` are skipped. Errors carry the 1-based line.
+pub fn parse_inputs<I: ParseInput>(text: &str) -> Result<Vec<I>, (usize, ParseError)> {
+    let mut inputs = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+        for item in line.split(';').map(str::trim).filter(|item| !item.is_empty()) {
+            inputs.push(I::parse_input(item).map_err(|e| (idx + 1, e))?);
+        }
+    }
+    Ok(inputs)
+}
+
+/// The words of `text`: runs of characters other than whitespace, parentheses and commas.
+#[doc(hidden)]
+pub fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// The fields following variant `name` if the leading words spell it, e.g.
+/// `["ok", "enter"]` for `OkEnter`.
+#[doc(hidden)]
+pub fn fields<'a, 'w>(words: &'a [&'w str], name: &str) -> Option<&'a [&'w str]> {
+    let wanted: Vec<char> = name.chars().filter(|&c| c != '_').flat_map(char::to_lowercase).collect();
+    let mut spelled = Vec::new();
+    for (idx, word) in words.iter().enumerate() {
+        spelled.extend(word.chars().filter(|&c| c != '_').flat_map(char::to_lowercase));
+        if spelled == wanted {
+            return Some(&words[idx + 1..]);
+        }
+        if spelled.len() >= wanted.len() {
+            return None;
+        }
+    }
+    None
+}
+
+#[doc(hidden)]
+pub fn field<V: FromStr>(variant: &'static str, word: &str) -> Result<V, ParseError> {
+    word.parse().map_err(|_| ParseError::InvalidField {
+        variant,
+        field: word.to_string(),
+    })
+}
+
+/// Declares an input enum implementing `parse::ParseInput`.
+///
+/// Variants are unit or tuple variants whose fields implement `FromStr`.
+/// Attributes are kept, so derives are declared as usual:
+/// `sxm_input! { #[derive(Clone, Debug, PartialEq)] pub enum Input { OkEnter, Digit(u8) } }`.
+#[macro_export]
+macro_rules! sxm_input {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$vmeta:meta])* $variant:ident $(($($field:ty),* $(,)?))?),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$vmeta])* $variant $(($($field),*))?),*
+        }
+
+        impl $crate::parse::ParseInput for $name {
+            fn parse_input(text: &str) -> Result<Self, $crate::parse::ParseError> {
+                let words = $crate::parse::words(text);
+                let mut error = None;
+                $(
+                    if let Some(fields) = $crate::parse::fields(&words, stringify!($variant)) {
+                        let expected = 0 $($(+ $crate::sxm_input!(@one $field))*)?;
+                        if fields.len() == expected {
+                            #[allow(unused_variables, unused_mut)]
+                            let mut fields = fields.iter();
+                            return Ok($name::$variant $(($(
+                                $crate::parse::field::<$field>(stringify!($variant), fields.next().unwrap())?
+                            ),*))?);
+                        }
+                        error.get_or_insert($crate::parse::ParseError::Arity {
+                            variant: stringify!($variant),
+                            expected,
+                            found: fields.len(),
+                        });
+                    }
+                )*
+                Err(error.unwrap_or_else(|| $crate::parse::ParseError::Unknown(text.trim().to_string())))
+            }
+        }
+    };
+    (@one $field:ty) => {
+        1
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::DigicodeInputAlphabet as In;
+
+    #[test]
+    fn inputs_read_in_any_spelling() {
+        for text in ["digit 4", "Digit(4)", "DIGIT 4", " digit ( 4 ) "] {
+            assert_eq!(In::parse_input(text), Ok(In::Digit(4)));
+        }
+        assert_eq!(In::parse_input("ok enter"), Ok(In::OkEnter));
+        assert_eq!(In::parse_input("ok_enter"), Ok(In::OkEnter));
+    }
+
+    #[test]
+    fn malformed_inputs_are_explained() {
+        assert_eq!(In::parse_input("open sesame"), Err(ParseError::Unknown("open sesame".to_string())));
+        assert_eq!(
+            In::parse_input("digit"),
+            Err(ParseError::Arity {
+                variant: "Digit",
+                expected: 1,
+                found: 0,
+            })
+        );
+        assert_eq!(
+            In::parse_input("digit x"),
+            Err(ParseError::InvalidField {
+                variant: "Digit",
+                field: "x".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn scripts_skip_comments_and_report_lines() {
+        let script = "# unlock\ndigit 4; digit 9\n\ndigit 2\nok enter\n";
+        assert_eq!(parse_inputs(script), Ok(vec![In::Digit(4), In::Digit(9), In::Digit(2), In::OkEnter]));
+        assert_eq!(parse_inputs::<In>("digit 4\ndigit\n").map_err(|(line, _)| line), Err(2));
+    }
+}
@@ -0,0 +1,182 @@
+use crate::runner::{MachineRunner, StepResultOf};
+use crate::XMachine;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+struct Session<T: XMachine> {
+    runner: MachineRunner<T>,
+    last_used: Instant,
+}
+
+/// Independent instances of machine `T`, one per session key, shared across threads.
+///
+/// Sessions are spread over shards, each behind its own lock, so steps on
+/// different sessions rarely contend. Sessions idle for longer than the idle
+/// timeout are evicted lazily when touched, or in bulk by `evict_idle`.
+pub struct RunnerPool<T: XMachine, K> {
+    shards: Vec<Mutex<HashMap<K, Session<T>>>>,
+    idle_timeout: Option<Duration>,
+}
+
+impl<T: XMachine, K: Hash + Eq> RunnerPool<T, K> {
+    /// Default number of shards.
+    pub const DEFAULT_SHARDS: usize = 16;
+
+    pub fn new() -> Self {
+        Self::with_shards(Self::DEFAULT_SHARDS)
+    }
+
+    /// # Panics
+    /// If `shards` is zero.
+    pub fn with_shards(shards: usize) -> Self {
+        assert!(shards > 0, "RunnerPool needs at least one shard");
+        Self {
+            shards: (0..shards).map(|_| Mutex::new(HashMap::new())).collect(),
+            idle_timeout: None,
+        }
+    }
+
+    /// Evicts sessions that were not used for `timeout`.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Starts a session in the initial configuration.
+    /// Returns `false`, leaving the existing session untouched, if `key` is taken.
+    pub fn create(&self, key: K) -> bool {
+        self.create_from(key, MachineRunner::new())
+    }
+
+    /// Starts a session from `runner`'s configuration.
+    /// Returns `false`, leaving the existing session untouched, if `key` is taken.
+    pub fn create_from(&self, key: K, runner: MachineRunner<T>) -> bool {
+        let now = Instant::now();
+        let mut shard = self.shard(&key);
+        if shard.get(&key).is_some_and(|s| !self.expired(s, now)) {
+            return false;
+        }
+        shard.insert(key, Session { runner, last_used: now });
+        true
+    }
+
+    /// Processes `input` in the session `key`.
+    /// Returns `None` if there is no such session or it has expired.
+    pub fn step(&self, key: &K, input: &T::Input) -> Option<StepResultOf<T>> {
+        self.with_session(key, |runner| runner.step(input))
+    }
+
+    /// Runs `f` on the session `key` under its shard lock and marks it as used.
+    /// Returns `None` if there is no such session or it has expired.
+    pub fn with_session<R>(&self, key: &K, f: impl FnOnce(&mut MachineRunner<T>) -> R) -> Option<R> {
+        let now = Instant::now();
+        let mut shard = self.shard(key);
+        if shard.get(key).is_some_and(|s| self.expired(s, now)) {
+            shard.remove(key);
+            return None;
+        }
+        let session = shard.get_mut(key)?;
+        session.last_used = now;
+        Some(f(&mut session.runner))
+    }
+
+    /// Ends the session `key`, returning its runner.
+    pub fn evict(&self, key: &K) -> Option<MachineRunner<T>> {
+        self.shard(key).remove(key).map(|s| s.runner)
+    }
+
+    /// Evicts every expired session and returns how many were removed.
+    pub fn evict_idle(&self) -> usize {
+        let now = Instant::now();
+        let mut evicted = 0;
+        for shard in &self.shards {
+            let mut shard = lock(shard);
+            let before = shard.len();
+            shard.retain(|_, s| !self.expired(s, now));
+            evicted += before - shard.len();
+        }
+        evicted
+    }
+
+    /// Number of sessions, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| lock(shard).len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, key: &K) -> MutexGuard<'_, HashMap<K, Session<T>>> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        lock(&self.shards[hasher.finish() as usize % self.shards.len()])
+    }
+
+    fn expired(&self, session: &Session<T>, now: Instant) -> bool {
+        self.idle_timeout
+            .is_some_and(|timeout| now.duration_since(session.last_used) > timeout)
+    }
+}
+
+/// A panic while stepping one session must not take the others down, so
+/// poisoned shards stay usable.
+fn lock<X>(mutex: &Mutex<X>) -> MutexGuard<'_, X> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<T: XMachine, K: Hash + Eq> Default for RunnerPool<T, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet as In, DigicodeState};
+    use std::thread;
+
+    #[test]
+    fn sessions_run_independently_across_threads() {
+        let pool = RunnerPool::<Digicode, u32>::with_shards(4);
+        for key in 0..8 {
+            assert!(pool.create(key));
+        }
+        assert!(!pool.create(0));
+        thread::scope(|scope| {
+            for key in 0..8 {
+                let pool = &pool;
+                scope.spawn(move || {
+                    let code = match key % 2 {
+                        0 => vec![In::Digit(4), In::Digit(9), In::Digit(2), In::OkEnter],
+                        _ => vec![In::Digit(1), In::OkEnter],
+                    };
+                    for input in &code {
+                        let _ = pool.step(&key, input);
+                    }
+                });
+            }
+        });
+        for key in 0..8 {
+            let expected = if key % 2 == 0 { DigicodeState::CodeEntered } else { DigicodeState::Accepting };
+            assert_eq!(pool.with_session(&key, |runner| runner.state()), Some(expected));
+        }
+        assert!(pool.evict(&3).is_some());
+        assert_eq!((pool.len(), pool.step(&3, &In::OkEnter).is_none()), (7, true));
+    }
+
+    #[test]
+    fn idle_sessions_expire() {
+        let pool = RunnerPool::<Digicode, &str>::new().with_idle_timeout(Duration::ZERO);
+        pool.create("stale");
+        thread::sleep(Duration::from_millis(2));
+        assert!(pool.create("stale"));
+        thread::sleep(Duration::from_millis(2));
+        assert_eq!(pool.evict_idle(), 1);
+        assert!(pool.is_empty());
+    }
+}
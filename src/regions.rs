@@ -0,0 +1,877 @@
+//! Orthogonal regions: one machine whose state is made of independent
+//! concurrent sub-states, e.g. "connection" × "battery level".
+//!
+//! `sxm_regions!` declares the product of two region machines sharing an
+//! input alphabet as an ordinary `XMachine`, so runners, analyses and test
+//! generation work on the synchronized product unchanged. Products nest, so
+//! more than two regions are expressed as a product of products.
+//!
+//! `sxm_composite!` confines regions to one state of a top-level machine,
+//! e.g. the regions of a phone only run while it is on. Either way,
+//! `MachineRunner::active_states` lists the active sub-state of every region.
+
+use crate::labels::{Labeling, Symbol};
+use crate::{OutputKind, TransitionSpec, Visibility, XMachine};
+use std::marker::PhantomData;
+
+/// Phi of a product machine: the phi each region applies to one input.
+/// `None` leaves that region where it is.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RegionPhi<A, B>(pub Option<A>, pub Option<B>);
+
+/// Output of a product machine: what each region emitted for one input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionOutput<A, B>(pub Option<A>, pub Option<B>);
+
+/// The product semantics behind `sxm_regions!`.
+///
+/// Every input is offered to both regions. A region without a phi for it
+/// stays put; the input is rejected only if neither region defines a phi,
+/// or if the phi of either region rejects it, in which case neither moves.
+/// A product state is final when every region is in a final state.
+///
+/// The other hooks are combined from the regions': an input is classified
+/// and a tick taken from the first region that has one, an output has the
+/// visibility its parts agree on and is an error if either part is, and a
+/// product phi is an ignore if each region's part is.
+///
+/// `all_phis` lists the combinations of the regions' phis some input selects
+/// in some product state, rather than the full product.
+pub struct Regions<A, B>(PhantomData<(A, B)>);
+
+/// Product phi of regions `A` and `B`.
+pub type RegionPhiOf<A, B> = RegionPhi<<A as XMachine>::Phi, <B as XMachine>::Phi>;
+
+/// Product output of regions `A` and `B`.
+pub type RegionOutputOf<A, B> = RegionOutput<<A as XMachine>::Output, <B as XMachine>::Output>;
+
+/// Product state of regions `A` and `B`.
+pub type RegionStateOf<A, B> = (<A as XMachine>::State, <B as XMachine>::State);
+
+impl<A, B> Regions<A, B>
+where
+    A: XMachine,
+    B: XMachine<Input = A::Input>,
+{
+    pub fn next_state(state: RegionStateOf<A, B>, phi: RegionPhiOf<A, B>) -> Option<RegionStateOf<A, B>> {
+        let (a, b) = state;
+        if phi.0.is_none() && phi.1.is_none() {
+            return None;
+        }
+        let a = match phi.0 {
+            Some(phi) => A::next_state(a, phi)?,
+            None => a,
+        };
+        let b = match phi.1 {
+            Some(phi) => B::next_state(b, phi)?,
+            None => b,
+        };
+        Some((a, b))
+    }
+
+    pub fn initial_states() -> Vec<RegionStateOf<A, B>> {
+        product(A::initial_states(), B::initial_states())
+    }
+
+    pub fn final_states() -> Vec<RegionStateOf<A, B>> {
+        product(A::final_states(), B::final_states())
+    }
+
+    pub fn initial_store() -> (A::Memory, B::Memory) {
+        (A::initial_store(), B::initial_store())
+    }
+
+    #[allow(clippy::result_unit_err)]
+    pub fn execute_phi(
+        phi: RegionPhiOf<A, B>,
+        store: &mut (A::Memory, B::Memory),
+        input: &A::Input,
+    ) -> Result<Option<RegionOutputOf<A, B>>, ()> {
+        let a = match phi.0 {
+            Some(phi) => A::execute_phi(phi, &mut store.0, input)?,
+            None => None,
+        };
+        let b = match phi.1 {
+            Some(phi) => B::execute_phi(phi, &mut store.1, input)?,
+            None => None,
+        };
+        Ok((a.is_some() || b.is_some()).then_some(RegionOutput(a, b)))
+    }
+
+    /// Inputs of `A`, followed by those only `B` declares.
+    pub fn all_inputs() -> Vec<A::Input> {
+        let mut inputs = A::all_inputs().into_owned();
+        for input in B::all_inputs().iter() {
+            if !inputs.contains(input) {
+                inputs.push(input.clone());
+            }
+        }
+        inputs
+    }
+
+    pub fn all_outputs() -> Vec<RegionOutputOf<A, B>> {
+        let a: Vec<_> = optional(&A::all_outputs());
+        let b: Vec<_> = optional(&B::all_outputs());
+        let mut outputs = Vec::new();
+        for a in &a {
+            for b in &b {
+                if a.is_some() || b.is_some() {
+                    outputs.push(RegionOutput(a.clone(), b.clone()));
+                }
+            }
+        }
+        outputs
+    }
+
+    pub fn all_states() -> Vec<RegionStateOf<A, B>> {
+        product(A::all_states(), B::all_states())
+    }
+
+    pub fn all_phis() -> Vec<RegionPhiOf<A, B>> {
+        dispatched(&Self::all_states(), &Self::all_inputs(), |state, input| {
+            Self::get_phi_for_input(state, input).map(Self::phi_kind)
+        })
+    }
+
+    /// The kinds of the regions' phis.
+    pub fn phi_kind(phi: RegionPhiOf<A, B>) -> RegionPhiOf<A, B> {
+        RegionPhi(phi.0.map(A::phi_kind), phi.1.map(B::phi_kind))
+    }
+
+    pub fn get_phi_for_input(state: RegionStateOf<A, B>, input: &A::Input) -> Option<RegionPhiOf<A, B>> {
+        let a = A::get_phi_for_input(state.0, input);
+        let b = B::get_phi_for_input(state.1, input);
+        (a.is_some() || b.is_some()).then_some(RegionPhi(a, b))
+    }
+
+    /// `Forbidden` only if both regions forbid the input; otherwise defined
+    /// if either region defines it, and `Unspecified` if neither does.
+    pub fn transition_spec(state: RegionStateOf<A, B>, input: &A::Input) -> TransitionSpec<RegionPhiOf<A, B>> {
+        let a = A::transition_spec(state.0, input);
+        let b = B::transition_spec(state.1, input);
+        match (a, b) {
+            (TransitionSpec::Forbidden, TransitionSpec::Forbidden) => TransitionSpec::Forbidden,
+            (a, b) => match (defined(a), defined(b)) {
+                (None, None) => TransitionSpec::Unspecified,
+                (a, b) => TransitionSpec::Defined(RegionPhi(a, b)),
+            },
+        }
+    }
+}
+
+impl<A, B> Regions<A, B>
+where
+    A: XMachine,
+    B: XMachine<Input = A::Input>,
+{
+    pub fn input_visibility(input: &A::Input) -> Option<Visibility> {
+        A::input_visibility(input).or_else(|| B::input_visibility(input))
+    }
+
+    /// The visibility of the regions' parts, `None` if they disagree.
+    pub fn output_visibility(output: &RegionOutputOf<A, B>) -> Option<Visibility> {
+        let a = output.0.as_ref().map(A::output_visibility);
+        let b = output.1.as_ref().map(B::output_visibility);
+        match (a, b) {
+            (Some(a), Some(b)) => (a == b).then_some(a).flatten(),
+            (a, b) => a.or(b).flatten(),
+        }
+    }
+
+    pub fn output_kind(output: &RegionOutputOf<A, B>) -> OutputKind {
+        let a = output.0.as_ref().map(A::output_kind);
+        let b = output.1.as_ref().map(B::output_kind);
+        if a == Some(OutputKind::Error) || b == Some(OutputKind::Error) {
+            OutputKind::Error
+        } else {
+            OutputKind::Regular
+        }
+    }
+
+    pub fn ignore_output(phi: RegionPhiOf<A, B>) -> Option<RegionOutputOf<A, B>> {
+        let a = match phi.0 {
+            Some(phi) => Some(A::ignore_output(phi)?),
+            None => None,
+        };
+        let b = match phi.1 {
+            Some(phi) => Some(B::ignore_output(phi)?),
+            None => None,
+        };
+        Some(RegionOutput(a, b))
+    }
+
+    pub fn tick_input() -> Option<A::Input> {
+        A::tick_input().or_else(B::tick_input)
+    }
+
+    /// The active sub-states of `A`, then those of `B`.
+    pub fn active_states(state: RegionStateOf<A, B>) -> Vec<String> {
+        let mut states = A::active_states(state.0);
+        states.extend(B::active_states(state.1));
+        states
+    }
+
+    /// `Labeling::Debug` if both regions keep it; otherwise the regions'
+    /// labels, states and phis as `(a, b)` and outputs as `a, b`, with `_`
+    /// for a region that takes no part.
+    pub fn labeling<M>() -> Labeling<M>
+    where
+        M: XMachine<
+            Input = A::Input,
+            Output = RegionOutputOf<A, B>,
+            State = RegionStateOf<A, B>,
+            Phi = RegionPhiOf<A, B>,
+        >,
+    {
+        if A::labeling().is_debug() && B::labeling().is_debug() {
+            Labeling::Debug
+        } else {
+            Labeling::Custom(Self::label::<M>)
+        }
+    }
+
+    fn label<M>(symbol: Symbol<'_, M>) -> String
+    where
+        M: XMachine<
+            Input = A::Input,
+            Output = RegionOutputOf<A, B>,
+            State = RegionStateOf<A, B>,
+            Phi = RegionPhiOf<A, B>,
+        >,
+    {
+        let (a, b) = (A::labeling(), B::labeling());
+        let part = |label: Option<String>| label.unwrap_or_else(|| "_".to_string());
+        match symbol {
+            Symbol::State((x, y)) => format!("({}, {})", a.label(Symbol::State(x)), b.label(Symbol::State(y))),
+            Symbol::Input(input) => a.label(Symbol::Input(input)),
+            Symbol::Output(RegionOutput(x, y)) => format!(
+                "{}, {}",
+                part(x.as_ref().map(|x| a.label(Symbol::Output(x)))),
+                part(y.as_ref().map(|y| b.label(Symbol::Output(y))))
+            ),
+            Symbol::Phi(RegionPhi(x, y)) => format!(
+                "({}, {})",
+                part(x.map(|x| a.label(Symbol::Phi(x)))),
+                part(y.map(|y| b.label(Symbol::Phi(y))))
+            ),
+        }
+    }
+}
+
+/// State of a composite machine: a top-level state, with the state of the
+/// regions while `top` is the composite state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CompositeState<Top, Sub> {
+    pub top: Top,
+    pub regions: Option<Sub>,
+}
+
+/// A phi or output of a composite machine, from the top level or from the regions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Layer<Top, Sub> {
+    Top(Top),
+    Regions(Sub),
+}
+
+/// The semantics behind `sxm_composite!`: top-level machine `P`, whose state
+/// `composite` is made of the regions of machine `R`, typically a
+/// `sxm_regions!` product.
+///
+/// Entering `composite` from another state starts the regions in their
+/// first initial state; a top-level transition from `composite` to itself
+/// keeps them, and leaving it deactivates them. Their memory persists across
+/// activations. While the regions are active they see each input first, and
+/// the top level handles the inputs they have no phi for, as in UML.
+///
+/// The hooks are combined as in `Regions`, each phi and output taking those
+/// of its layer.
+pub struct Composite<P, R>(PhantomData<(P, R)>);
+
+/// State of composite machine `P` with regions `R`.
+pub type CompositeStateOf<P, R> = CompositeState<<P as XMachine>::State, <R as XMachine>::State>;
+
+/// Phi of composite machine `P` with regions `R`.
+pub type CompositePhiOf<P, R> = Layer<<P as XMachine>::Phi, <R as XMachine>::Phi>;
+
+/// Output of composite machine `P` with regions `R`.
+pub type CompositeOutputOf<P, R> = Layer<<P as XMachine>::Output, <R as XMachine>::Output>;
+
+impl<P, R> Composite<P, R>
+where
+    P: XMachine,
+    R: XMachine<Input = P::Input>,
+{
+    /// `top`, with the regions started if it is `composite`.
+    fn enter(top: P::State, composite: P::State) -> CompositeStateOf<P, R> {
+        CompositeState {
+            top,
+            regions: R::initial_states().first().copied().filter(|_| top == composite),
+        }
+    }
+
+    pub fn next_state(
+        state: CompositeStateOf<P, R>,
+        phi: CompositePhiOf<P, R>,
+        composite: P::State,
+    ) -> Option<CompositeStateOf<P, R>> {
+        match phi {
+            Layer::Regions(phi) => Some(CompositeState {
+                top: state.top,
+                regions: Some(R::next_state(state.regions?, phi)?),
+            }),
+            Layer::Top(phi) => {
+                let top = P::next_state(state.top, phi)?;
+                if top == composite && state.top == composite {
+                    Some(CompositeState { top, ..state })
+                } else {
+                    Some(Self::enter(top, composite))
+                }
+            }
+        }
+    }
+
+    pub fn initial_states(composite: P::State) -> Vec<CompositeStateOf<P, R>> {
+        P::initial_states().iter().map(|&top| Self::enter(top, composite)).collect()
+    }
+
+    /// States whose top-level state is final, with final regions if active.
+    pub fn final_states(composite: P::State) -> Vec<CompositeStateOf<P, R>> {
+        Self::all_states(composite)
+            .into_iter()
+            .filter(|state| {
+                P::final_states().contains(&state.top)
+                    && state.regions.map_or(true, |regions| R::final_states().contains(&regions))
+            })
+            .collect()
+    }
+
+    pub fn initial_store() -> (P::Memory, R::Memory) {
+        (P::initial_store(), R::initial_store())
+    }
+
+    #[allow(clippy::result_unit_err)]
+    pub fn execute_phi(
+        phi: CompositePhiOf<P, R>,
+        store: &mut (P::Memory, R::Memory),
+        input: &P::Input,
+    ) -> Result<Option<CompositeOutputOf<P, R>>, ()> {
+        Ok(match phi {
+            Layer::Top(phi) => P::execute_phi(phi, &mut store.0, input)?.map(Layer::Top),
+            Layer::Regions(phi) => R::execute_phi(phi, &mut store.1, input)?.map(Layer::Regions),
+        })
+    }
+
+    /// Inputs of `P`, followed by those only `R` declares.
+    pub fn all_inputs() -> Vec<P::Input> {
+        let mut inputs = P::all_inputs().into_owned();
+        for input in R::all_inputs().iter() {
+            if !inputs.contains(input) {
+                inputs.push(input.clone());
+            }
+        }
+        inputs
+    }
+
+    pub fn all_outputs() -> Vec<CompositeOutputOf<P, R>> {
+        let mut outputs: Vec<_> = P::all_outputs().iter().cloned().map(Layer::Top).collect();
+        outputs.extend(R::all_outputs().iter().cloned().map(Layer::Regions));
+        outputs
+    }
+
+    /// Every top-level state, `composite` once per state of the regions.
+    pub fn all_states(composite: P::State) -> Vec<CompositeStateOf<P, R>> {
+        let mut states = Vec::new();
+        for &top in P::all_states() {
+            if top == composite {
+                states.extend(R::all_states().iter().map(|&regions| CompositeState {
+                    top,
+                    regions: Some(regions),
+                }));
+            } else {
+                states.push(CompositeState { top, regions: None });
+            }
+        }
+        states
+    }
+
+    /// The phis some input selects in some state.
+    pub fn all_phis(composite: P::State) -> Vec<CompositePhiOf<P, R>> {
+        dispatched(&Self::all_states(composite), &Self::all_inputs(), |state, input| {
+            Self::get_phi_for_input(state, input).map(Self::phi_kind)
+        })
+    }
+
+    pub fn phi_kind(phi: CompositePhiOf<P, R>) -> CompositePhiOf<P, R> {
+        match phi {
+            Layer::Top(phi) => Layer::Top(P::phi_kind(phi)),
+            Layer::Regions(phi) => Layer::Regions(R::phi_kind(phi)),
+        }
+    }
+
+    pub fn get_phi_for_input(state: CompositeStateOf<P, R>, input: &P::Input) -> Option<CompositePhiOf<P, R>> {
+        let regions = state.regions.and_then(|regions| R::get_phi_for_input(regions, input));
+        match regions {
+            Some(phi) => Some(Layer::Regions(phi)),
+            None => P::get_phi_for_input(state.top, input).map(Layer::Top),
+        }
+    }
+
+    /// Defined by the regions if they define the input, else by the top
+    /// level; `Forbidden` if the top level forbids it and active regions do too.
+    pub fn transition_spec(state: CompositeStateOf<P, R>, input: &P::Input) -> TransitionSpec<CompositePhiOf<P, R>> {
+        let regions = state.regions.map(|regions| R::transition_spec(regions, input));
+        match (regions, P::transition_spec(state.top, input)) {
+            (Some(TransitionSpec::Defined(phi)), _) => TransitionSpec::Defined(Layer::Regions(phi)),
+            (_, TransitionSpec::Defined(phi)) => TransitionSpec::Defined(Layer::Top(phi)),
+            (None | Some(TransitionSpec::Forbidden), TransitionSpec::Forbidden) => TransitionSpec::Forbidden,
+            _ => TransitionSpec::Unspecified,
+        }
+    }
+
+    pub fn input_visibility(input: &P::Input) -> Option<Visibility> {
+        P::input_visibility(input).or_else(|| R::input_visibility(input))
+    }
+
+    pub fn output_visibility(output: &CompositeOutputOf<P, R>) -> Option<Visibility> {
+        match output {
+            Layer::Top(output) => P::output_visibility(output),
+            Layer::Regions(output) => R::output_visibility(output),
+        }
+    }
+
+    pub fn output_kind(output: &CompositeOutputOf<P, R>) -> OutputKind {
+        match output {
+            Layer::Top(output) => P::output_kind(output),
+            Layer::Regions(output) => R::output_kind(output),
+        }
+    }
+
+    pub fn ignore_output(phi: CompositePhiOf<P, R>) -> Option<CompositeOutputOf<P, R>> {
+        match phi {
+            Layer::Top(phi) => P::ignore_output(phi).map(Layer::Top),
+            Layer::Regions(phi) => R::ignore_output(phi).map(Layer::Regions),
+        }
+    }
+
+    pub fn tick_input() -> Option<P::Input> {
+        P::tick_input().or_else(R::tick_input)
+    }
+
+    /// The active states of the top level, then those of the regions if active.
+    pub fn active_states(state: CompositeStateOf<P, R>) -> Vec<String> {
+        let mut states = P::active_states(state.top);
+        states.extend(state.regions.into_iter().flat_map(R::active_states));
+        states
+    }
+
+    /// `Labeling::Debug` if both levels keep it; otherwise states as
+    /// `top` or `top / regions`, and the other symbols as their level labels them.
+    pub fn labeling<M>() -> Labeling<M>
+    where
+        M: XMachine<
+            Input = P::Input,
+            Output = CompositeOutputOf<P, R>,
+            State = CompositeStateOf<P, R>,
+            Phi = CompositePhiOf<P, R>,
+        >,
+    {
+        if P::labeling().is_debug() && R::labeling().is_debug() {
+            Labeling::Debug
+        } else {
+            Labeling::Custom(Self::label::<M>)
+        }
+    }
+
+    fn label<M>(symbol: Symbol<'_, M>) -> String
+    where
+        M: XMachine<
+            Input = P::Input,
+            Output = CompositeOutputOf<P, R>,
+            State = CompositeStateOf<P, R>,
+            Phi = CompositePhiOf<P, R>,
+        >,
+    {
+        let (p, r) = (P::labeling(), R::labeling());
+        match symbol {
+            Symbol::State(CompositeState { top, regions: None }) => p.label(Symbol::State(top)),
+            Symbol::State(CompositeState {
+                top,
+                regions: Some(regions),
+            }) => format!("{} / {}", p.label(Symbol::State(top)), r.label(Symbol::State(regions))),
+            Symbol::Input(input) => p.label(Symbol::Input(input)),
+            Symbol::Output(Layer::Top(output)) => p.label(Symbol::Output(output)),
+            Symbol::Output(Layer::Regions(output)) => r.label(Symbol::Output(output)),
+            Symbol::Phi(Layer::Top(phi)) => p.label(Symbol::Phi(phi)),
+            Symbol::Phi(Layer::Regions(phi)) => r.label(Symbol::Phi(phi)),
+        }
+    }
+}
+
+fn product<X: Copy, Y: Copy>(xs: &[X], ys: &[Y]) -> Vec<(X, Y)> {
+    xs.iter().flat_map(|&x| ys.iter().map(move |&y| (x, y))).collect()
+}
+
+/// `None` followed by every symbol of `symbols`.
+fn optional<X: Clone>(symbols: &[X]) -> Vec<Option<X>> {
+    std::iter::once(None).chain(symbols.iter().cloned().map(Some)).collect()
+}
+
+/// The distinct phis `dispatch` selects for some state and input, in order.
+fn dispatched<State: Copy, Input, Phi: PartialEq>(
+    states: &[State],
+    inputs: &[Input],
+    dispatch: impl Fn(State, &Input) -> Option<Phi>,
+) -> Vec<Phi> {
+    let mut phis = Vec::new();
+    for &state in states {
+        for phi in inputs.iter().filter_map(|input| dispatch(state, input)) {
+            if !phis.contains(&phi) {
+                phis.push(phi);
+            }
+        }
+    }
+    phis
+}
+
+fn defined<Phi>(spec: TransitionSpec<Phi>) -> Option<Phi> {
+    match spec {
+        TransitionSpec::Defined(phi) => Some(phi),
+        _ => None,
+    }
+}
+
+/// Declares `$name` as the `XMachine` whose state is the pair of the states
+/// of regions `$a` and `$b`, with the semantics of `regions::Regions`.
+///
+/// Both regions must share an input type, and the product's states and phis
+/// must be `Send + Sync` since their tables are built once and cached.
+/// Invoked as `sxm_regions! { pub struct Device(Connection, Power); }`,
+/// optionally with attributes and doc comments on the struct.
+#[macro_export]
+macro_rules! sxm_regions {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident($a:ty, $b:ty);) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::XMachine for $name {
+            type Input = <$a as $crate::XMachine>::Input;
+            type Output = $crate::regions::RegionOutputOf<$a, $b>;
+            type State = $crate::regions::RegionStateOf<$a, $b>;
+            type Memory = (<$a as $crate::XMachine>::Memory, <$b as $crate::XMachine>::Memory);
+            type Phi = $crate::regions::RegionPhiOf<$a, $b>;
+
+            fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+                $crate::regions::Regions::<$a, $b>::next_state(state, phi)
+            }
+
+            fn initial_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init($crate::regions::Regions::<$a, $b>::initial_states)
+            }
+
+            fn final_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init($crate::regions::Regions::<$a, $b>::final_states)
+            }
+
+            fn initial_store() -> Self::Memory {
+                $crate::regions::Regions::<$a, $b>::initial_store()
+            }
+
+            fn execute_phi(
+                phi: Self::Phi,
+                store: &mut Self::Memory,
+                input: &Self::Input,
+            ) -> Result<Option<Self::Output>, ()> {
+                $crate::regions::Regions::<$a, $b>::execute_phi(phi, store, input)
+            }
+
+            fn all_inputs() -> ::std::borrow::Cow<'static, [Self::Input]> {
+                ::std::borrow::Cow::Owned($crate::regions::Regions::<$a, $b>::all_inputs())
+            }
+
+            fn all_outputs() -> ::std::borrow::Cow<'static, [Self::Output]> {
+                ::std::borrow::Cow::Owned($crate::regions::Regions::<$a, $b>::all_outputs())
+            }
+
+            fn all_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init($crate::regions::Regions::<$a, $b>::all_states)
+            }
+
+            fn all_phis() -> &'static [Self::Phi] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::Phi>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init($crate::regions::Regions::<$a, $b>::all_phis)
+            }
+
+            fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+                $crate::regions::Regions::<$a, $b>::get_phi_for_input(state, input)
+            }
+
+            fn phi_kind(phi: Self::Phi) -> Self::Phi {
+                $crate::regions::Regions::<$a, $b>::phi_kind(phi)
+            }
+
+            fn transition_spec(state: Self::State, input: &Self::Input) -> $crate::TransitionSpec<Self::Phi> {
+                $crate::regions::Regions::<$a, $b>::transition_spec(state, input)
+            }
+
+            fn input_visibility(input: &Self::Input) -> Option<$crate::Visibility> {
+                $crate::regions::Regions::<$a, $b>::input_visibility(input)
+            }
+
+            fn output_visibility(output: &Self::Output) -> Option<$crate::Visibility> {
+                $crate::regions::Regions::<$a, $b>::output_visibility(output)
+            }
+
+            fn output_kind(output: &Self::Output) -> $crate::OutputKind {
+                $crate::regions::Regions::<$a, $b>::output_kind(output)
+            }
+
+            fn ignore_output(phi: Self::Phi) -> Option<Self::Output> {
+                $crate::regions::Regions::<$a, $b>::ignore_output(phi)
+            }
+
+            fn tick_input() -> Option<Self::Input> {
+                $crate::regions::Regions::<$a, $b>::tick_input()
+            }
+
+            fn active_states(state: Self::State) -> Vec<String> {
+                $crate::regions::Regions::<$a, $b>::active_states(state)
+            }
+
+            fn labeling() -> $crate::labels::Labeling<Self> {
+                $crate::regions::Regions::<$a, $b>::labeling::<Self>()
+            }
+        }
+    };
+}
+
+/// Declares `$name` as the `XMachine` whose top-level machine is `$top` and
+/// whose state `$composite` of `$top` is made of the regions of `$regions`,
+/// with the semantics of `regions::Composite`.
+///
+/// Both machines must share an input type, and the composite's states and
+/// phis must be `Send + Sync`, as for `sxm_regions!`. Invoked as
+/// `sxm_composite! { pub struct Phone(Power, PowerState::On => Device); }`.
+#[macro_export]
+macro_rules! sxm_composite {
+    ($(#[$meta:meta])* $vis:vis struct $name:ident($top:ty, $composite:expr => $regions:ty);) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $crate::XMachine for $name {
+            type Input = <$top as $crate::XMachine>::Input;
+            type Output = $crate::regions::CompositeOutputOf<$top, $regions>;
+            type State = $crate::regions::CompositeStateOf<$top, $regions>;
+            type Memory = (<$top as $crate::XMachine>::Memory, <$regions as $crate::XMachine>::Memory);
+            type Phi = $crate::regions::CompositePhiOf<$top, $regions>;
+
+            fn next_state(state: Self::State, phi: Self::Phi) -> Option<Self::State> {
+                $crate::regions::Composite::<$top, $regions>::next_state(state, phi, $composite)
+            }
+
+            fn initial_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init(|| $crate::regions::Composite::<$top, $regions>::initial_states($composite))
+            }
+
+            fn final_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init(|| $crate::regions::Composite::<$top, $regions>::final_states($composite))
+            }
+
+            fn initial_store() -> Self::Memory {
+                $crate::regions::Composite::<$top, $regions>::initial_store()
+            }
+
+            fn execute_phi(
+                phi: Self::Phi,
+                store: &mut Self::Memory,
+                input: &Self::Input,
+            ) -> Result<Option<Self::Output>, ()> {
+                $crate::regions::Composite::<$top, $regions>::execute_phi(phi, store, input)
+            }
+
+            fn all_inputs() -> ::std::borrow::Cow<'static, [Self::Input]> {
+                ::std::borrow::Cow::Owned($crate::regions::Composite::<$top, $regions>::all_inputs())
+            }
+
+            fn all_outputs() -> ::std::borrow::Cow<'static, [Self::Output]> {
+                ::std::borrow::Cow::Owned($crate::regions::Composite::<$top, $regions>::all_outputs())
+            }
+
+            fn all_states() -> &'static [Self::State] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::State>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init(|| $crate::regions::Composite::<$top, $regions>::all_states($composite))
+            }
+
+            fn all_phis() -> &'static [Self::Phi] {
+                static TABLE: ::std::sync::OnceLock<Vec<<$name as $crate::XMachine>::Phi>> =
+                    ::std::sync::OnceLock::new();
+                TABLE.get_or_init(|| $crate::regions::Composite::<$top, $regions>::all_phis($composite))
+            }
+
+            fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi> {
+                $crate::regions::Composite::<$top, $regions>::get_phi_for_input(state, input)
+            }
+
+            fn phi_kind(phi: Self::Phi) -> Self::Phi {
+                $crate::regions::Composite::<$top, $regions>::phi_kind(phi)
+            }
+
+            fn transition_spec(state: Self::State, input: &Self::Input) -> $crate::TransitionSpec<Self::Phi> {
+                $crate::regions::Composite::<$top, $regions>::transition_spec(state, input)
+            }
+
+            fn input_visibility(input: &Self::Input) -> Option<$crate::Visibility> {
+                $crate::regions::Composite::<$top, $regions>::input_visibility(input)
+            }
+
+            fn output_visibility(output: &Self::Output) -> Option<$crate::Visibility> {
+                $crate::regions::Composite::<$top, $regions>::output_visibility(output)
+            }
+
+            fn output_kind(output: &Self::Output) -> $crate::OutputKind {
+                $crate::regions::Composite::<$top, $regions>::output_kind(output)
+            }
+
+            fn ignore_output(phi: Self::Phi) -> Option<Self::Output> {
+                $crate::regions::Composite::<$top, $regions>::ignore_output(phi)
+            }
+
+            fn tick_input() -> Option<Self::Input> {
+                $crate::regions::Composite::<$top, $regions>::tick_input()
+            }
+
+            fn active_states(state: Self::State) -> Vec<String> {
+                $crate::regions::Composite::<$top, $regions>::active_states(state)
+            }
+
+            fn labeling() -> $crate::labels::Labeling<Self> {
+                $crate::regions::Composite::<$top, $regions>::labeling::<Self>()
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::well_formedness;
+    use crate::examples::traffic_light::{LightInput, LightOutput, LightPhi, TrafficLight};
+    use crate::runner::MachineRunner;
+    use std::borrow::Cow;
+
+    crate::sxm_regions! {
+        struct Crossing(TrafficLight, TrafficLight);
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Key {
+        Power,
+        Tick,
+    }
+
+    /// Flips between `false` and `true` on `Key::Power` if `POWER`, else on `Key::Tick`.
+    struct Toggle<const POWER: bool>;
+
+    impl<const POWER: bool> XMachine for Toggle<POWER> {
+        type Input = Key;
+        type Output = bool;
+        type State = bool;
+        type Memory = ();
+        type Phi = ();
+
+        fn next_state(state: bool, _phi: ()) -> Option<bool> {
+            Some(!state)
+        }
+
+        fn initial_states() -> &'static [bool] {
+            &[false]
+        }
+
+        fn final_states() -> &'static [bool] {
+            &[false, true]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(_phi: (), _store: &mut (), _input: &Key) -> Result<Option<bool>, ()> {
+            Ok(None)
+        }
+
+        fn all_inputs() -> Cow<'static, [Key]> {
+            Cow::Borrowed(&[Key::Power, Key::Tick])
+        }
+
+        fn all_outputs() -> Cow<'static, [bool]> {
+            Cow::Borrowed(&[])
+        }
+
+        fn all_states() -> &'static [bool] {
+            &[false, true]
+        }
+
+        fn all_phis() -> &'static [()] {
+            &[()]
+        }
+
+        fn get_phi_for_input(_state: bool, input: &Key) -> Option<()> {
+            (*input == if POWER { Key::Power } else { Key::Tick }).then_some(())
+        }
+    }
+
+    crate::sxm_regions! {
+        struct Blinkers(Toggle<false>, Toggle<false>);
+    }
+
+    crate::sxm_composite! {
+        /// Two blinkers running while the lamp is switched on.
+        struct Lamp(Toggle<true>, true => Blinkers);
+    }
+
+    #[test]
+    fn products_take_the_hooks_of_their_regions() {
+        assert_eq!(Crossing::tick_input(), Some(LightInput::Tick));
+        let output = RegionOutput(Some(LightOutput::ShowRed), None);
+        assert_eq!(Crossing::output_kind(&output), OutputKind::Regular);
+        assert_eq!(Crossing::output_visibility(&output), None);
+        assert_eq!(Crossing::ignore_output(RegionPhi(Some(LightPhi::ToGreen), None)), None);
+        assert!(Crossing::labeling().is_debug());
+    }
+
+    #[test]
+    fn products_list_only_the_phis_some_input_selects() {
+        let phis = Crossing::all_phis();
+        assert_eq!(phis.len(), 10);
+        assert!(phis.iter().all(|phi| phi.0.is_some() && phi.1.is_some()));
+        assert!(well_formedness::<Crossing>().is_empty());
+    }
+
+    #[test]
+    fn composite_states_run_their_regions_while_active() {
+        assert_eq!(Lamp::all_states().len(), 5);
+        assert_eq!(Lamp::all_phis().len(), 2);
+        assert!(well_formedness::<Lamp>().is_empty());
+
+        let mut runner = MachineRunner::<Lamp>::new();
+        assert_eq!(runner.active_states(), ["false"]);
+        assert!(runner.step(&Key::Tick).is_err());
+        runner.step(&Key::Power).unwrap();
+        assert_eq!(runner.active_states(), ["true", "false", "false"]);
+        runner.step(&Key::Tick).unwrap();
+        assert_eq!(runner.active_states(), ["true", "true", "true"]);
+        runner.step(&Key::Power).unwrap();
+        assert_eq!(runner.state(), CompositeState { top: false, regions: None });
+        runner.step(&Key::Power).unwrap();
+        assert_eq!(runner.active_states(), ["true", "false", "false"]);
+    }
+}
@@ -0,0 +1,176 @@
+//! Link-time registry of machines, so tools can enumerate the machines of a
+//! program without a hardcoded list of types. Requires the `registry`
+//! feature.
+//!
+//! `register_machine!(Digicode);` records the machine under its type name, or
+//! under an explicit one with `register_machine!(Digicode, "digicode");`. The
+//! entry keeps the spec's type-erased entry points, so a tool holding only a
+//! `MachineInfo` can fingerprint it, render its diagrams and documents, or
+//! load its structure as a `DynMachine`.
+//!
+//! The macro is an item, placed next to the machine in any crate linked into
+//! the program, and is collected with `inventory` before `main` runs: nothing
+//! has to call it. A machine registered twice is listed once.
+
+use crate::analysis::{self, SpecStats};
+use crate::docgen;
+use crate::dynamic::DynMachine;
+use crate::fingerprint::SpecFingerprint;
+use crate::graphviz;
+use crate::store::Store;
+use crate::XMachine;
+use std::any::TypeId;
+use std::fmt;
+
+/// A registered machine and the type-erased entry points of its spec.
+#[derive(Copy, Clone)]
+pub struct MachineInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+    type_id: TypeId,
+    fingerprint: fn() -> SpecFingerprint,
+    dot: fn(&str) -> String,
+    mermaid: fn() -> String,
+    markdown: fn(&str) -> String,
+    stats: fn() -> SpecStats,
+    model: fn(&str) -> DynMachine,
+}
+
+impl MachineInfo {
+    pub fn of<T: XMachine + 'static>(name: &'static str) -> Self
+    where
+        T::Memory: Store,
+    {
+        Self {
+            name,
+            type_name: std::any::type_name::<T>(),
+            type_id: TypeId::of::<T>(),
+            fingerprint: SpecFingerprint::of::<T>,
+            dot: graphviz::generate_dot::<T>,
+            mermaid: docgen::mermaid::<T>,
+            markdown: docgen::markdown::<T>,
+            stats: analysis::stats::<T>,
+            model: DynMachine::from_machine::<T>,
+        }
+    }
+
+    /// `true` if this entry describes `T`.
+    pub fn is<T: 'static>(&self) -> bool {
+        self.type_id == TypeId::of::<T>()
+    }
+
+    pub fn fingerprint(&self) -> SpecFingerprint {
+        (self.fingerprint)()
+    }
+
+    /// The Graphviz diagram, as `graphviz::generate_dot`.
+    pub fn dot(&self) -> String {
+        (self.dot)(self.name)
+    }
+
+    /// The Mermaid state diagram, as `docgen::mermaid`.
+    pub fn mermaid(&self) -> String {
+        (self.mermaid)()
+    }
+
+    /// The specification document, as `docgen::markdown`.
+    pub fn markdown(&self) -> String {
+        (self.markdown)(self.name)
+    }
+
+    pub fn stats(&self) -> SpecStats {
+        (self.stats)()
+    }
+
+    /// The structure of the machine, as `DynMachine::from_machine`.
+    pub fn model(&self) -> DynMachine {
+        (self.model)(self.name)
+    }
+}
+
+impl fmt::Debug for MachineInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MachineInfo")
+            .field("name", &self.name)
+            .field("type_name", &self.type_name)
+            .finish()
+    }
+}
+
+/// A `register_machine!` entry, collected at link time.
+#[doc(hidden)]
+pub struct Registration {
+    name: &'static str,
+    info: fn(&'static str) -> MachineInfo,
+}
+
+impl Registration {
+    pub const fn new<T: XMachine + 'static>(name: &'static str) -> Self
+    where
+        T::Memory: Store,
+    {
+        Self {
+            name,
+            info: MachineInfo::of::<T>,
+        }
+    }
+}
+
+inventory::collect!(Registration);
+
+#[doc(hidden)]
+pub use inventory;
+
+/// Every registered machine, sorted by name.
+pub fn machines() -> Vec<MachineInfo> {
+    let mut machines: Vec<MachineInfo> = Vec::new();
+    for registration in inventory::iter::<Registration> {
+        let info = (registration.info)(registration.name);
+        if !machines.iter().any(|known| known.type_id == info.type_id) {
+            machines.push(info);
+        }
+    }
+    machines.sort_by_key(|info| info.name);
+    machines
+}
+
+/// The machine registered under `name`.
+pub fn find(name: &str) -> Option<MachineInfo> {
+    machines().into_iter().find(|info| info.name == name)
+}
+
+/// Registers a machine type in `registry`, under its type name unless one
+/// is given: `register_machine!(Digicode);` or `register_machine!(Digicode, "digicode");`.
+/// Place it at item level, outside any function.
+#[macro_export]
+macro_rules! register_machine {
+    ($machine:ty) => {
+        $crate::register_machine!($machine, stringify!($machine));
+    };
+    ($machine:ty, $name:expr) => {
+        $crate::registry::inventory::submit! {
+            $crate::registry::Registration::new::<$machine>($name)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, Door};
+    use crate::examples::traffic_light::TrafficLight;
+
+    crate::register_machine!(Digicode, "digicode");
+    crate::register_machine!(Door, "door");
+    crate::register_machine!(Door, "door again");
+    crate::register_machine!(TrafficLight);
+
+    #[test]
+    fn registered_machines_are_listed_once_without_being_called() {
+        let names: Vec<&str> = machines().iter().map(|info| info.name).collect();
+        assert_eq!(names.iter().filter(|name| name.starts_with("door")).count(), 1);
+        assert!(names.contains(&"digicode") && names.contains(&"TrafficLight"));
+        assert!(find("digicode").is_some_and(|info| info.is::<Digicode>()));
+        assert!(find("TrafficLight").is_some_and(|info| info.model().states.len() == TrafficLight::all_states().len()));
+    }
+}
@@ -0,0 +1,580 @@
+use crate::access::{undeclared_reads, undeclared_writes, AccessControl, MemoryRegions};
+use crate::configuration::Configuration;
+use crate::isolation::{self, PhiPanic, PhiPanicOf};
+use crate::{OutputKind, XMachine};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+/// Record of a single processed input: `from --phi--> to` emitting `output`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transition<State, Phi, Output> {
+    pub from: State,
+    pub phi: Phi,
+    pub to: State,
+    pub output: Option<Output>,
+}
+
+/// `Transition` specialised to the symbols of machine `T`.
+pub type TransitionOf<T> = Transition<<T as XMachine>::State, <T as XMachine>::Phi, <T as XMachine>::Output>;
+
+/// Why an input could not be processed in the current configuration.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepError<State, Phi> {
+    /// No phi is defined for the input in `state`.
+    NoPhi { state: State },
+    /// `execute_phi` rejected the input (the guard on memory did not hold).
+    GuardRejected { state: State, phi: Phi },
+    /// `next_state` is undefined for the selected phi.
+    NoNextState { state: State, phi: Phi },
+    /// A function of the spec panicked, caught by a runner `with_panic_isolation`.
+    /// `phi` is the one selected, unless selecting it panicked.
+    Panicked { state: State, phi: Option<Phi>, message: String },
+}
+
+/// `StepError` specialised to the symbols of machine `T`.
+pub type StepErrorOf<T> = StepError<<T as XMachine>::State, <T as XMachine>::Phi>;
+
+/// Outcome of `MachineRunner::step` for machine `T`.
+pub type StepResultOf<T> = Result<TransitionOf<T>, StepErrorOf<T>>;
+
+/// What `MachineRunner::drain` does with a queued input the current state rejects.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeferPolicy {
+    /// Discard it.
+    #[default]
+    Drop,
+    /// Retry it once after the next successful transition, then discard it.
+    Retry,
+    /// Keep it until it is accepted, retrying after every successful transition.
+    Park,
+}
+
+/// Counters and timings of one phi, as recorded by `RunnerMetrics`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PhiMetrics {
+    /// Inputs the phi processed successfully.
+    pub invocations: u64,
+    /// Inputs the phi's guard rejected.
+    pub guard_rejections: u64,
+    /// Time spent in steps selecting the phi, rejected ones included.
+    pub total_time: Duration,
+    /// Longest of those steps.
+    pub max_time: Duration,
+}
+
+/// What a `MachineRunner` has processed since metrics were enabled.
+///
+/// The crate has no dependencies, so nothing is published by itself: a
+/// deployment forwards the counters, e.g. to the `metrics` crate facade, by
+/// iterating `phis()` periodically.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunnerMetrics<Phi> {
+    phis: Vec<(Phi, PhiMetrics)>,
+    unhandled_inputs: u64,
+    error_outputs: u64,
+}
+
+/// `RunnerMetrics` specialised to the phis of machine `T`.
+pub type RunnerMetricsOf<T> = RunnerMetrics<<T as XMachine>::Phi>;
+
+impl<Phi: Copy + PartialEq> RunnerMetrics<Phi> {
+    /// Zeroed metrics for `phis`.
+    pub fn new(phis: &[Phi]) -> Self {
+        Self {
+            phis: phis.iter().map(|&phi| (phi, PhiMetrics::default())).collect(),
+            unhandled_inputs: 0,
+            error_outputs: 0,
+        }
+    }
+
+    /// Metrics of the kind `phi`, if it was declared.
+    pub fn phi(&self, phi: Phi) -> Option<&PhiMetrics> {
+        self.phis.iter().find(|(p, _)| *p == phi).map(|(_, metrics)| metrics)
+    }
+
+    /// Every phi with its metrics, in `all_phis()` order.
+    pub fn phis(&self) -> impl Iterator<Item = (Phi, &PhiMetrics)> {
+        self.phis.iter().map(|(phi, metrics)| (*phi, metrics))
+    }
+
+    /// Inputs rejected before any phi ran: no phi was defined for them, or
+    /// the selected phi had no next state.
+    pub fn unhandled_inputs(&self) -> u64 {
+        self.unhandled_inputs
+    }
+
+    /// Steps that emitted an `OutputKind::Error` output.
+    pub fn error_outputs(&self) -> u64 {
+        self.error_outputs
+    }
+
+    /// Inputs processed successfully, over all phis.
+    pub fn steps(&self) -> u64 {
+        self.phis.iter().map(|(_, metrics)| metrics.invocations).sum()
+    }
+
+    /// Zeroes every counter and timing.
+    pub fn clear(&mut self) {
+        for (_, metrics) in &mut self.phis {
+            *metrics = PhiMetrics::default();
+        }
+        self.unhandled_inputs = 0;
+        self.error_outputs = 0;
+    }
+
+    /// Counts an accepted step of `phi` as a guard rejection instead, for a
+    /// step undone after the fact, e.g. by an injected guard failure. `error`
+    /// tells whether its output was counted as an error output.
+    pub(crate) fn reject_accepted(&mut self, phi: Phi, error: bool, phi_kind: fn(Phi) -> Phi) {
+        if error {
+            self.error_outputs = self.error_outputs.saturating_sub(1);
+        }
+        let kind = phi_kind(phi);
+        if let Some((_, metrics)) = self.phis.iter_mut().find(|(p, _)| *p == kind) {
+            metrics.invocations = metrics.invocations.saturating_sub(1);
+            metrics.guard_rejections += 1;
+        }
+    }
+
+    fn record<State, Output>(
+        &mut self,
+        result: &Result<Transition<State, Phi, Output>, StepError<State, Phi>>,
+        error: bool,
+        elapsed: Duration,
+        phi_kind: fn(Phi) -> Phi,
+    ) {
+        if error {
+            self.error_outputs += 1;
+        }
+        let (phi, accepted) = match result {
+            Ok(transition) => (transition.phi, true),
+            Err(StepError::GuardRejected { phi, .. }) => (*phi, false),
+            Err(StepError::Panicked { .. }) => return,
+            Err(_) => {
+                self.unhandled_inputs += 1;
+                return;
+            }
+        };
+        let kind = phi_kind(phi);
+        if let Some((_, metrics)) = self.phis.iter_mut().find(|(p, _)| *p == kind) {
+            if accepted {
+                metrics.invocations += 1;
+            } else {
+                metrics.guard_rejections += 1;
+            }
+            metrics.total_time += elapsed;
+            metrics.max_time = metrics.max_time.max(elapsed);
+        }
+    }
+}
+
+/// Executes a machine one input at a time, holding its current state and memory.
+///
+/// A rejected input leaves the configuration untouched: `execute_phi` runs on a
+/// copy of the store, which is only committed once the whole step succeeds.
+///
+/// Inputs can also be queued with `enqueue` and processed by `drain`, where
+/// rejected ones are handled according to the runner's `DeferPolicy`.
+pub struct MachineRunner<T: XMachine> {
+    config: Configuration<T>,
+    queue: VecDeque<T::Input>,
+    deferred: Vec<T::Input>,
+    defer_policy: DeferPolicy,
+    metrics: Option<RunnerMetricsOf<T>>,
+    panic_isolation: bool,
+    last_panic: Option<PhiPanicOf<T>>,
+}
+
+impl<T: XMachine> MachineRunner<T> {
+    /// Starts at the first initial state with `initial_store()`.
+    ///
+    /// # Panics
+    /// If `initial_states()` is empty.
+    pub fn new() -> Self {
+        let state = *T::initial_states()
+            .first()
+            .expect("XMachine::initial_states() must not be empty");
+        Self::from_parts(state, T::initial_store())
+    }
+
+    /// Starts from an arbitrary configuration.
+    pub fn from_parts(state: T::State, store: T::Memory) -> Self {
+        Self::from_configuration(Configuration::new(state, store))
+    }
+
+    pub fn from_configuration(config: Configuration<T>) -> Self {
+        Self {
+            config,
+            queue: VecDeque::new(),
+            deferred: Vec::new(),
+            defer_policy: DeferPolicy::default(),
+            metrics: None,
+            panic_isolation: false,
+            last_panic: None,
+        }
+    }
+
+    /// Sets how `drain` handles rejected inputs.
+    pub fn with_defer_policy(mut self, policy: DeferPolicy) -> Self {
+        self.defer_policy = policy;
+        self
+    }
+
+    pub fn defer_policy(&self) -> DeferPolicy {
+        self.defer_policy
+    }
+
+    /// Records `RunnerMetrics` for every step from now on.
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = Some(RunnerMetrics::new(T::all_phis()));
+        self
+    }
+
+    /// Catches panics raised while stepping, e.g. by a buggy phi, and reports
+    /// them as `StepError::Panicked`, leaving the configuration as it was
+    /// before the step, so the runner can go on or be inspected.
+    pub fn with_panic_isolation(mut self) -> Self {
+        self.panic_isolation = true;
+        self
+    }
+
+    pub fn panic_isolation(&self) -> bool {
+        self.panic_isolation
+    }
+
+    /// The last panic of a phi caught `with_panic_isolation`, with the
+    /// configuration and input that triggered it.
+    pub fn last_panic(&self) -> Option<&PhiPanicOf<T>> {
+        self.last_panic.as_ref()
+    }
+
+    /// Metrics recorded so far, if enabled with `with_metrics`.
+    pub fn metrics(&self) -> Option<&RunnerMetricsOf<T>> {
+        self.metrics.as_ref()
+    }
+
+    pub fn metrics_mut(&mut self) -> Option<&mut RunnerMetricsOf<T>> {
+        self.metrics.as_mut()
+    }
+
+    pub fn configuration(&self) -> &Configuration<T> {
+        &self.config
+    }
+
+    pub fn into_configuration(self) -> Configuration<T> {
+        self.config
+    }
+
+    pub fn state(&self) -> T::State {
+        self.config.state
+    }
+
+    /// The active sub-state of each region of the current state, as `XMachine::active_states`.
+    pub fn active_states(&self) -> Vec<String> {
+        T::active_states(self.config.state)
+    }
+
+    pub fn store(&self) -> &T::Memory {
+        &self.config.store
+    }
+
+    pub fn store_mut(&mut self) -> &mut T::Memory {
+        &mut self.config.store
+    }
+
+    /// Moves to `config`, keeping queued inputs, the defer policy and the metrics.
+    pub(crate) fn set_configuration(&mut self, config: Configuration<T>) {
+        self.config = config;
+    }
+
+    /// Returns to the initial configuration, dropping queued and deferred inputs.
+    /// The defer policy, the metrics and panic isolation are kept.
+    pub fn reset(&mut self) {
+        let metrics = self.metrics.take();
+        let panic_isolation = self.panic_isolation;
+        *self = Self::new().with_defer_policy(self.defer_policy);
+        self.metrics = metrics;
+        self.panic_isolation = panic_isolation;
+    }
+
+    /// Processes one input according to the spec.
+    pub fn step(
+        &mut self,
+        input: &T::Input,
+    ) -> StepResultOf<T> {
+        if self.metrics.is_none() {
+            let (transition, next) = self.config_step(input)?;
+            self.config = next;
+            return Ok(transition);
+        }
+        let start = Instant::now();
+        let result = self.config_step(input);
+        let elapsed = start.elapsed();
+        let result = result.map(|(transition, next)| {
+            self.config = next;
+            transition
+        });
+        let error = matches!(
+            &result,
+            Ok(Transition { output: Some(output), .. }) if T::output_kind(output) == OutputKind::Error
+        );
+        if let Some(metrics) = self.metrics.as_mut() {
+            metrics.record(&result, error, elapsed, T::phi_kind);
+        }
+        result
+    }
+
+    fn config_step(&mut self, input: &T::Input) -> Result<(TransitionOf<T>, Configuration<T>), StepErrorOf<T>> {
+        let config = &self.config;
+        if !self.panic_isolation {
+            return config.step(input);
+        }
+        let payload = match panic::catch_unwind(AssertUnwindSafe(|| config.step(input))) {
+            Ok(result) => return result,
+            Err(payload) => payload,
+        };
+        let state = config.state;
+        let phi = panic::catch_unwind(AssertUnwindSafe(|| T::get_phi_for_input(state, input))).ok().flatten();
+        let message = isolation::panic_message(payload.as_ref());
+        if let Some(phi) = phi {
+            self.last_panic = Some(PhiPanic {
+                state,
+                memory: config.store.clone(),
+                phi,
+                input: input.clone(),
+                message: message.clone(),
+            });
+        }
+        Err(StepError::Panicked { state, phi, message })
+    }
+
+    /// Processes inputs in order, stopping at the first rejection.
+    pub fn run<'a, I>(
+        &mut self,
+        inputs: I,
+    ) -> Result<Vec<TransitionOf<T>>, StepErrorOf<T>>
+    where
+        I: IntoIterator<Item = &'a T::Input>,
+    {
+        inputs.into_iter().map(|input| self.step(input)).collect()
+    }
+
+    /// Runs `steps` as one all-or-nothing macro step: the configuration reached
+    /// is kept only if the closure returns `Ok`. On `Err`, or if it panics, the
+    /// runner returns to the configuration it had before, though metrics keep
+    /// the steps taken. Queued and deferred inputs are not touched.
+    ///
+    /// Step errors convert into `E`, so the closure can use `?` on
+    /// `Transaction::step` directly.
+    pub fn transaction<R, E>(
+        &mut self,
+        steps: impl FnOnce(&mut Transaction<'_, T>) -> Result<R, E>,
+    ) -> Result<R, E>
+    where
+        E: From<StepErrorOf<T>>,
+    {
+        let mut tx = Transaction {
+            saved: Some(self.config.clone()),
+            runner: self,
+            transitions: Vec::new(),
+        };
+        let result = steps(&mut tx);
+        if result.is_ok() {
+            tx.saved = None;
+        }
+        result
+    }
+
+    /// Queues `input` for the next `drain`.
+    pub fn enqueue(&mut self, input: T::Input) {
+        self.queue.push_back(input);
+    }
+
+    /// Inputs queued but not yet drained.
+    pub fn queued(&self) -> impl Iterator<Item = &T::Input> {
+        self.queue.iter()
+    }
+
+    /// Rejected inputs waiting for a retry, oldest first.
+    pub fn deferred(&self) -> &[T::Input] {
+        &self.deferred
+    }
+
+    /// Processes the queue in order and returns the transitions taken,
+    /// including those of deferred inputs accepted on a retry.
+    ///
+    /// Only transitions taken by `drain` trigger retries; `step` bypasses the queue.
+    pub fn drain(&mut self) -> Vec<TransitionOf<T>> {
+        let mut transitions = Vec::new();
+        while let Some(input) = self.queue.pop_front() {
+            match self.step(&input) {
+                Ok(transition) => {
+                    transitions.push(transition);
+                    self.retry_deferred(&mut transitions);
+                }
+                Err(_) if self.defer_policy == DeferPolicy::Drop => {}
+                Err(_) => self.deferred.push(input),
+            }
+        }
+        transitions
+    }
+
+    /// Retries deferred inputs in order until a round makes no progress.
+    fn retry_deferred(&mut self, transitions: &mut Vec<TransitionOf<T>>) {
+        let mut progressed = true;
+        while progressed && !self.deferred.is_empty() {
+            progressed = false;
+            for input in std::mem::take(&mut self.deferred) {
+                match self.step(&input) {
+                    Ok(transition) => {
+                        transitions.push(transition);
+                        progressed = true;
+                    }
+                    Err(_) if self.defer_policy == DeferPolicy::Park => self.deferred.push(input),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+}
+
+/// The steps of a `MachineRunner::transaction` in progress.
+pub struct Transaction<'a, T: XMachine> {
+    runner: &'a mut MachineRunner<T>,
+    /// The configuration to return to, until the transaction commits.
+    saved: Option<Configuration<T>>,
+    transitions: Vec<TransitionOf<T>>,
+}
+
+impl<T: XMachine> Transaction<'_, T> {
+    /// Processes one input, as `MachineRunner::step`.
+    pub fn step(&mut self, input: &T::Input) -> StepResultOf<T> {
+        let transition = self.runner.step(input)?;
+        self.transitions.push(transition.clone());
+        Ok(transition)
+    }
+
+    /// The state reached so far within the transaction.
+    pub fn state(&self) -> T::State {
+        self.runner.state()
+    }
+
+    pub fn store(&self) -> &T::Memory {
+        self.runner.store()
+    }
+
+    /// Transitions taken so far within the transaction.
+    pub fn transitions(&self) -> &[TransitionOf<T>] {
+        &self.transitions
+    }
+}
+
+impl<T: XMachine> Drop for Transaction<'_, T> {
+    fn drop(&mut self) {
+        if let Some(config) = self.saved.take() {
+            self.runner.config = config;
+        }
+    }
+}
+
+impl<T> MachineRunner<T>
+where
+    T: AccessControl,
+    T::Memory: MemoryRegions,
+{
+    /// Like `step`, but in debug builds panics if the phi wrote a memory region
+    /// outside its `AccessControl::writable_regions` declaration, or read one
+    /// outside `AccessControl::readable_regions`. Reads are checked against
+    /// `initial_store`, so only regions that left their initial value are.
+    pub fn step_checked(&mut self, input: &T::Input) -> StepResultOf<T> {
+        if !cfg!(debug_assertions) {
+            return self.step(input);
+        }
+        let before = self.config.store.clone();
+        let transition = self.step(input)?;
+        let regions = undeclared_writes::<T>(transition.phi, &before, &self.config.store);
+        assert!(
+            regions.is_empty(),
+            "phi {:?} wrote undeclared memory regions {:?}",
+            transition.phi,
+            regions
+        );
+        let regions = undeclared_reads::<T>(transition.phi, &before, input, &T::initial_store());
+        assert!(
+            regions.is_empty(),
+            "phi {:?} read undeclared memory regions {:?}",
+            transition.phi,
+            regions
+        );
+        Ok(transition)
+    }
+}
+
+impl<T: XMachine> Default for MachineRunner<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: XMachine> Clone for MachineRunner<T> {
+    fn clone(&self) -> Self {
+        Self {
+            config: self.config.clone(),
+            queue: self.queue.clone(),
+            deferred: self.deferred.clone(),
+            defer_policy: self.defer_policy,
+            metrics: self.metrics.clone(),
+            panic_isolation: self.panic_isolation,
+            last_panic: self.last_panic.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodeOutputAlphabet, DigicodeState};
+    use DigicodeInputAlphabet::*;
+
+    const CODE: [DigicodeInputAlphabet; 4] = [Digit(4), Digit(9), Digit(2), OkEnter];
+
+    #[test]
+    fn rejected_inputs_leave_the_configuration_unchanged() {
+        let mut runner = MachineRunner::<Digicode>::new().with_metrics();
+        let outputs: Vec<_> = runner.run(&CODE).unwrap().into_iter().map(|t| t.output).collect();
+        assert_eq!(outputs.last(), Some(&Some(DigicodeOutputAlphabet::Open)));
+        assert_eq!(runner.state(), DigicodeState::CodeEntered);
+
+        let before = runner.configuration().clone();
+        assert!(matches!(runner.step(&Digit(1)), Err(StepError::NoPhi { .. })));
+        assert!(*runner.configuration() == before);
+        let metrics = runner.metrics().unwrap();
+        assert_eq!((metrics.steps(), metrics.unhandled_inputs()), (4, 1));
+    }
+
+    #[test]
+    fn failed_transactions_roll_back() {
+        let mut runner = MachineRunner::<Digicode>::new();
+        let result: Result<(), StepErrorOf<Digicode>> = runner.transaction(|tx| {
+            tx.step(&Digit(4))?;
+            tx.step(&DoorCloses)?;
+            Ok(())
+        });
+        assert!(result.is_err());
+        assert_eq!(runner.state(), DigicodeState::Ready);
+        assert!(runner.store().current_sequence.is_empty());
+    }
+
+    #[test]
+    fn parked_inputs_are_retried_until_accepted() {
+        let mut runner = MachineRunner::<Digicode>::new().with_defer_policy(DeferPolicy::Park);
+        runner.enqueue(DoorCloses);
+        for input in CODE {
+            runner.enqueue(input);
+        }
+        assert_eq!(runner.drain().len(), 5);
+        assert_eq!(runner.state(), DigicodeState::Ready);
+        assert!(runner.deferred().is_empty());
+    }
+}
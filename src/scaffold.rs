@@ -0,0 +1,284 @@
+//! Source skeletons for new specs.
+//!
+//! `generate` turns a `DynMachine`, e.g. one drafted with its editing API or
+//! loaded from YAML, into a Rust file that compiles as it is: symbol enums,
+//! an empty memory struct and an `XMachine` impl whose tables follow the
+//! machine, with every `execute_phi` arm left as a `todo!()`. The file ends
+//! with a test module checking well-formedness, and a conformance run of the
+//! generated suite that is ignored until the phis are filled in.
+//!
+//! Symbol names must be Rust identifiers. `quickstart` drafts the machine
+//! from state and phi names alone.
+
+use crate::dynamic::{DynMachine, DynMachineError};
+use std::fmt::Write;
+
+/// A skeleton for machine `name` with the given states, the first one
+/// initial, and phis; inputs, outputs and transitions are left to fill in.
+pub fn quickstart(name: &str, states: &[&str], phis: &[&str]) -> Result<String, DynMachineError> {
+    let mut machine = DynMachine {
+        name: name.to_string(),
+        ..DynMachine::default()
+    };
+    for state in states {
+        machine.add_state(state)?;
+    }
+    if let Some(initial) = states.first() {
+        machine.set_initial(initial, true)?;
+    }
+    for phi in phis {
+        machine.add_phi(phi, None)?;
+    }
+    generate(&machine)
+}
+
+/// The Rust source of a skeleton implementing `machine`.
+///
+/// Types are prefixed with the machine name: `Door` gets `DoorState`,
+/// `DoorInput`, `DoorOutput`, `DoorPhi` and `DoorMemory`. Transition outputs
+/// and phi guards are carried into the `todo!()` messages and
+/// `phi_guard_doc`.
+pub fn generate(machine: &DynMachine) -> Result<String, DynMachineError> {
+    machine.validate()?;
+    identifier("machine", &machine.name)?;
+    for (kind, names) in [
+        ("state", &machine.states),
+        ("input", &machine.inputs),
+        ("output", &machine.outputs),
+    ] {
+        for name in names {
+            identifier(kind, name)?;
+        }
+    }
+    for phi in &machine.phis {
+        identifier("phi", &phi.name)?;
+    }
+
+    let mut arcs: Vec<(&str, &str, &str)> = Vec::new();
+    for t in &machine.transitions {
+        match arcs.iter().find(|(from, phi, _)| *from == t.from && *phi == t.phi) {
+            Some((_, _, to)) if *to != t.to => {
+                return Err(DynMachineError::Schema(format!(
+                    "phi {:?} leads from {:?} to both {:?} and {:?}",
+                    t.phi, t.from, to, t.to
+                )));
+            }
+            Some(_) => {}
+            None => arcs.push((&t.from, &t.phi, &t.to)),
+        }
+    }
+
+    let name = &machine.name;
+    let (state, input, output, phi, memory) = (
+        format!("{}State", name),
+        format!("{}Input", name),
+        format!("{}Output", name),
+        format!("{}Phi", name),
+        format!("{}Memory", name),
+    );
+    let variants = |ty: &str, names: &[String]| {
+        let names: Vec<String> = names.iter().map(|n| format!("{}::{}", ty, n)).collect();
+        names.join(", ")
+    };
+    let phis: Vec<String> = machine.phis.iter().map(|p| p.name.clone()).collect();
+
+    let mut out = String::new();
+    writeln!(out, "//! Skeleton of the `{}` machine generated by `sxm::scaffold`.", name).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "use std::borrow::Cow;").unwrap();
+    writeln!(out, "use sxm::XMachine;").unwrap();
+    writeln!(out).unwrap();
+    write_enum(&mut out, "Clone, Debug, PartialEq", &input, &machine.inputs);
+    write_enum(&mut out, "Clone, Debug, PartialEq", &output, &machine.outputs);
+    write_enum(&mut out, "Copy, Clone, Debug, PartialEq", &state, &machine.states);
+    write_enum(&mut out, "Copy, Clone, Debug, PartialEq", &phi, &phis);
+    writeln!(out, "/// Data the phis read and update.").unwrap();
+    writeln!(out, "#[derive(Clone, Debug, Default, PartialEq)]").unwrap();
+    writeln!(out, "pub struct {} {{}}", memory).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "pub struct {};", name).unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "impl XMachine for {} {{", name).unwrap();
+    for (item, ty) in [
+        ("Input", &input),
+        ("Output", &output),
+        ("State", &state),
+        ("Memory", &memory),
+        ("Phi", &phi),
+    ] {
+        writeln!(out, "    type {} = {};", item, ty).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    // An empty table is a plain `None`, with its parameters unused.
+    let unused = |empty: bool| if empty { "_" } else { "" };
+    let params = unused(arcs.is_empty());
+    writeln!(out, "    fn next_state({0}state: Self::State, {0}phi: Self::Phi) -> Option<Self::State> {{", params)
+        .unwrap();
+    if arcs.is_empty() {
+        writeln!(out, "        None").unwrap();
+    } else {
+        writeln!(out, "        match (state, phi) {{").unwrap();
+        for (from, via, to) in &arcs {
+            writeln!(out, "            ({}::{}, {}::{}) => Some({}::{}),", state, from, phi, via, state, to).unwrap();
+        }
+        if arcs.len() < machine.states.len() * phis.len() {
+            writeln!(out, "            _ => None,").unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "    fn initial_states() -> &'static [Self::State] {{").unwrap();
+    writeln!(out, "        &[{}]", variants(&state, &machine.initial_states)).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    fn final_states() -> &'static [Self::State] {{").unwrap();
+    writeln!(out, "        &[{}]", variants(&state, &machine.final_states)).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    fn initial_store() -> Self::Memory {{").unwrap();
+    writeln!(out, "        {}::default()", memory).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "    fn execute_phi(").unwrap();
+    writeln!(out, "        phi: Self::Phi,").unwrap();
+    writeln!(out, "        _store: &mut Self::Memory,").unwrap();
+    writeln!(out, "        _input: &Self::Input,").unwrap();
+    writeln!(out, "    ) -> Result<Option<Self::Output>, ()> {{").unwrap();
+    writeln!(out, "        match phi {{").unwrap();
+    for p in &phis {
+        let mut emits: Vec<&str> = Vec::new();
+        for t in machine.transitions.iter().filter(|t| t.phi == *p) {
+            if let Some(output) = t.output.as_deref().filter(|o| !emits.contains(o)) {
+                emits.push(output);
+            }
+        }
+        let hint = if emits.is_empty() {
+            "update the store and return the output".to_string()
+        } else {
+            format!("update the store and emit {}", emits.join(" or "))
+        };
+        writeln!(out, "            {}::{} => todo!(\"{}: {}\"),", phi, p, p, hint).unwrap();
+    }
+    writeln!(out, "        }}").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    for (item, ty, names) in [("inputs", &input, &machine.inputs), ("outputs", &output, &machine.outputs)] {
+        let symbol = if item == "inputs" { "Input" } else { "Output" };
+        writeln!(out, "    fn all_{}() -> Cow<'static, [Self::{}]> {{", item, symbol).unwrap();
+        writeln!(out, "        Cow::Borrowed(&[{}])", variants(ty, names)).unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out).unwrap();
+    }
+    writeln!(out, "    fn all_states() -> &'static [Self::State] {{").unwrap();
+    writeln!(out, "        &[{}]", variants(&state, &machine.states)).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    fn all_phis() -> &'static [Self::Phi] {{").unwrap();
+    writeln!(out, "        &[{}]", variants(&phi, &phis)).unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out).unwrap();
+
+    let params = unused(machine.transitions.is_empty());
+    writeln!(out, "    fn get_phi_for_input(").unwrap();
+    writeln!(out, "        {0}state: Self::State,\n        {0}input: &Self::Input,", params).unwrap();
+    writeln!(out, "    ) -> Option<Self::Phi> {{").unwrap();
+    if machine.transitions.is_empty() {
+        writeln!(out, "        None").unwrap();
+    } else {
+        writeln!(out, "        match (state, input) {{").unwrap();
+        for t in &machine.transitions {
+            let arm = format!("({}::{}, {}::{}) => Some({}::{}),", state, t.from, input, t.input, phi, t.phi);
+            writeln!(out, "            {}", arm).unwrap();
+        }
+        if machine.transitions.len() < machine.states.len() * machine.inputs.len() {
+            writeln!(out, "            _ => None,").unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+    }
+    writeln!(out, "    }}").unwrap();
+
+    if machine.phis.iter().any(|p| p.guard.is_some()) {
+        writeln!(out).unwrap();
+        writeln!(out, "    fn phi_guard_doc(phi: Self::Phi) -> Option<&'static str> {{").unwrap();
+        writeln!(out, "        match phi {{").unwrap();
+        for p in machine.phis.iter() {
+            if let Some(guard) = &p.guard {
+                writeln!(out, "            {}::{} => Some({:?}),", phi, p.name, guard).unwrap();
+            }
+        }
+        if machine.phis.iter().any(|p| p.guard.is_none()) {
+            writeln!(out, "            _ => None,").unwrap();
+        }
+        writeln!(out, "        }}").unwrap();
+        writeln!(out, "    }}").unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+
+    let snake = snake_case(name);
+    writeln!(out, "#[cfg(test)]").unwrap();
+    writeln!(out, "mod tests {{").unwrap();
+    writeln!(out, "    use super::*;").unwrap();
+    writeln!(out, "    use sxm::executor::Executor;").unwrap();
+    writeln!(out, "    use sxm::runner::MachineRunner;").unwrap();
+    writeln!(out, "    use sxm::suite::TestSuiteBuilder;").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    sxm::sxm_well_formed_test!({}_is_well_formed, {});", snake, name).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "    #[test]").unwrap();
+    writeln!(out, "    #[ignore = \"fill in execute_phi first\"]").unwrap();
+    writeln!(out, "    fn {}_conforms_to_itself() {{", snake).unwrap();
+    writeln!(out, "        let distinguishing = |_state: {}| Vec::new();", state).unwrap();
+    writeln!(out, "        let suite = TestSuiteBuilder::<{}>::new()", name).unwrap();
+    writeln!(out, "            .logic(&distinguishing)\n            .robustness()\n            .build();").unwrap();
+    writeln!(out, "        let report = Executor::run_suite(&suite.cases, MachineRunner::<{}>::new);", name).unwrap();
+    writeln!(out, "        assert!(report.all_passed(), \"{{:#?}}\", report.failures().collect::<Vec<_>>());").unwrap();
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "}}").unwrap();
+    Ok(out)
+}
+
+fn write_enum(out: &mut String, derives: &str, ty: &str, variants: &[String]) {
+    writeln!(out, "#[derive({})]", derives).unwrap();
+    writeln!(out, "pub enum {} {{", ty).unwrap();
+    for variant in variants {
+        writeln!(out, "    {},", variant).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+    writeln!(out).unwrap();
+}
+
+/// Fails unless `name` is an ASCII identifier usable as a type or variant.
+fn identifier(kind: &str, name: &str) -> Result<(), DynMachineError> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+        && name != "Self";
+    if valid {
+        Ok(())
+    } else {
+        Err(DynMachineError::Schema(format!("{} {:?} is not a Rust identifier", kind, name)))
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (idx, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if idx > 0 && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
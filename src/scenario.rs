@@ -0,0 +1,307 @@
+//! Hand-written traces of key flows, such as "happy path unlock" or "wrong
+//! PIN three times", kept next to a machine and its generated suites.
+//!
+//! A scenario is checked against the spec with `Scenario::check`, which the
+//! `#[test]` generated by `sxm_scenarios!` does for every declared scenario,
+//! and against an implementation with `Scenario::run`.
+//! `Scenario::test_cases` turns it into ordinary test cases, so it goes
+//! wherever a generated suite goes: executor reports, Gherkin, archives.
+
+use crate::executor::{Executor, Verdict};
+use crate::mbt::{Expectation, TestCase, TestCaseOf};
+use crate::runner::MachineRunner;
+use crate::sut::SutAdapter;
+use crate::XMachine;
+
+/// One input of a scenario with what it must produce.
+#[derive(Clone, Debug)]
+pub struct ScenarioStep<Input, Output, State> {
+    pub input: Input,
+    pub expected_output: Expectation<Output>,
+    /// State required after the input, checked when the SUT exposes it.
+    pub expected_state: Option<State>,
+}
+
+/// A named input sequence with the outputs and states expected along it.
+#[derive(Clone, Debug)]
+pub struct Scenario<Input, Output, State> {
+    pub name: String,
+    pub steps: Vec<ScenarioStep<Input, Output, State>>,
+}
+
+/// `Scenario` specialised to the symbols of machine `T`.
+pub type ScenarioOf<T> = Scenario<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+/// The first step of a scenario where the SUT did not behave as declared.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioFailure<Output, State> {
+    pub scenario: String,
+    /// Zero-based index of the step.
+    pub step: usize,
+    /// `OutputMismatch` or `StateMismatch`.
+    pub verdict: Verdict<Output, State>,
+}
+
+/// `ScenarioFailure` specialised to the symbols of machine `T`.
+pub type ScenarioFailureOf<T> = ScenarioFailure<<T as XMachine>::Output, <T as XMachine>::State>;
+
+impl<Input, Output, State> Scenario<Input, Output, State> {
+    /// A scenario without steps.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Appends a step emitting `output`.
+    pub fn step(self, input: Input, output: Output) -> Self {
+        self.step_expecting(input, Expectation::Exact(output))
+    }
+
+    /// Appends a step emitting nothing.
+    pub fn silent_step(self, input: Input) -> Self {
+        self.step_expecting(input, Expectation::None)
+    }
+
+    /// Appends a step whose output must satisfy `expected`.
+    pub fn step_expecting(mut self, input: Input, expected: Expectation<Output>) -> Self {
+        self.steps.push(ScenarioStep {
+            input,
+            expected_output: expected,
+            expected_state: None,
+        });
+        self
+    }
+
+    /// Requires the last step to end in `state`.
+    ///
+    /// # Panics
+    /// If the scenario has no step yet.
+    pub fn in_state(mut self, state: State) -> Self {
+        self.steps.last_mut().expect("Scenario::in_state called before any step").expected_state = Some(state);
+        self
+    }
+}
+
+impl<Input, Output, State> Scenario<Input, Output, State>
+where
+    Input: Clone,
+    Output: Clone + PartialEq,
+    State: Copy + PartialEq,
+{
+    /// Checks the scenario against the spec itself, from its initial configuration.
+    pub fn check<T>(&self) -> Result<(), ScenarioFailure<Output, State>>
+    where
+        T: XMachine<Input = Input, Output = Output, State = State>,
+    {
+        self.run::<T, _>(&mut MachineRunner::<T>::new())
+    }
+
+    /// Applies the steps to `sut` in order and stops at the first one whose
+    /// output, or state if the SUT exposes it, differs from the declaration.
+    /// As in `Executor`, internal outputs are not observed: the SUT need not
+    /// emit them, and a step declaring one only checks the state.
+    pub fn run<T, S>(&self, sut: &mut S) -> Result<(), ScenarioFailure<Output, State>>
+    where
+        T: XMachine<Input = Input, Output = Output, State = State>,
+        S: SutAdapter<T>,
+    {
+        for (idx, step) in self.steps.iter().enumerate() {
+            let actual = Executor::observable::<T>(sut.apply(&step.input));
+            let verdict = if !Executor::satisfies::<T>(&step.expected_output, actual.as_ref()) {
+                Executor::mismatch::<T>(step.expected_output.clone(), actual)
+            } else {
+                match (step.expected_state, sut.observe_state()) {
+                    (Some(expected), Some(actual)) if expected != actual => {
+                        Verdict::StateMismatch { expected, actual }
+                    }
+                    _ => continue,
+                }
+            };
+            return Err(ScenarioFailure {
+                scenario: self.name.clone(),
+                step: idx,
+                verdict,
+            });
+        }
+        Ok(())
+    }
+
+    /// One test case per step, named `"<scenario> #<step>"`, with the previous
+    /// inputs as setup sequence and the step's expectation. Requirements and
+    /// setup configurations come from replaying the spec.
+    pub fn test_cases<T>(&self) -> Vec<TestCaseOf<T>>
+    where
+        T: XMachine<Input = Input, Output = Output, State = State>,
+    {
+        let mut runner = MachineRunner::<T>::new();
+        let mut setup_configuration = Some(runner.configuration().clone());
+        let mut cases = Vec::with_capacity(self.steps.len());
+        for (idx, step) in self.steps.iter().enumerate() {
+            let transition = setup_configuration.as_ref().and_then(|_| runner.step(&step.input).ok());
+            cases.push(TestCase {
+                name: format!("{} #{}", self.name, idx + 1),
+                setup_sequence: self.steps[..idx].iter().map(|s| s.input.clone()).collect(),
+                test_input: step.input.clone(),
+                expected_output: step.expected_output.clone(),
+                verification_sequence: Vec::new(),
+                requirements: transition
+                    .as_ref()
+                    .map(|t| T::requirement_ids(t.from, t.phi).to_vec())
+                    .unwrap_or_default(),
+                setup_configuration: setup_configuration.map(|config| config.into_parts()),
+            });
+            setup_configuration = transition.map(|_| runner.configuration().clone());
+        }
+        cases
+    }
+}
+
+/// Declares a function returning the scenarios of a machine, in order.
+///
+/// Each scenario is a name followed by its steps, one per line:
+/// `input => output;` for a step emitting `output`, `input => !;` for a
+/// silent one, with the output optionally followed by `, state` to check the
+/// state reached, as in `Digit(2) => Digit(2), Accepting;`. Steps are
+/// expressions, so symbols are usually brought into scope with `use` next to
+/// the macro. A declaration reads `pub fn digicode_scenarios() for Digicode { "happy path
+/// unlock" { ... } "wrong code" { ... } }`.
+///
+/// Next to the function, a `#[cfg(test)]` module of the same name holds a
+/// `#[test]` checking every scenario against the spec, so `cargo test` runs
+/// them. Symbols used in the steps must be in scope of the declaring module.
+#[macro_export]
+macro_rules! sxm_scenarios {
+    (
+        $(#[$meta:meta])*
+        $vis:vis fn $name:ident() for $machine:ty {
+            $($scenario:literal { $($steps:tt)* })*
+        }
+    ) => {
+        $(#[$meta])*
+        $vis fn $name() -> Vec<$crate::scenario::ScenarioOf<$machine>> {
+            vec![$({
+                let scenario = $crate::scenario::Scenario::new($scenario);
+                $crate::sxm_scenarios!(@steps scenario; $($steps)*)
+            }),*]
+        }
+
+        #[cfg(test)]
+        mod $name {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #[test]
+            fn scenarios_hold_on_the_spec() {
+                for scenario in super::$name() {
+                    if let Err(failure) = scenario.check::<$machine>() {
+                        panic!("scenario {:?} failed: {:?}", scenario.name, failure);
+                    }
+                }
+            }
+        }
+    };
+    (@steps $scenario:expr;) => {
+        $scenario
+    };
+    (@steps $scenario:expr; $input:expr => !, $state:expr; $($rest:tt)*) => {
+        $crate::sxm_scenarios!(@steps $scenario.silent_step($input).in_state($state); $($rest)*)
+    };
+    (@steps $scenario:expr; $input:expr => !; $($rest:tt)*) => {
+        $crate::sxm_scenarios!(@steps $scenario.silent_step($input); $($rest)*)
+    };
+    (@steps $scenario:expr; $input:expr => $output:expr, $state:expr; $($rest:tt)*) => {
+        $crate::sxm_scenarios!(@steps $scenario.step($input, $output).in_state($state); $($rest)*)
+    };
+    (@steps $scenario:expr; $input:expr => $output:expr; $($rest:tt)*) => {
+        $crate::sxm_scenarios!(@steps $scenario.step($input, $output); $($rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Visibility;
+    use std::borrow::Cow;
+
+    /// Counts inputs, emitting the count. The first count is internal.
+    struct Counter;
+
+    impl XMachine for Counter {
+        type Input = ();
+        type Output = u32;
+        type State = ();
+        type Memory = u32;
+        type Phi = ();
+
+        fn next_state(_state: (), _phi: ()) -> Option<()> {
+            Some(())
+        }
+
+        fn initial_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn final_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn initial_store() -> u32 {
+            0
+        }
+
+        fn execute_phi(_phi: (), store: &mut u32, _input: &()) -> Result<Option<u32>, ()> {
+            *store += 1;
+            Ok(Some(*store))
+        }
+
+        fn all_inputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[()])
+        }
+
+        fn all_outputs() -> Cow<'static, [u32]> {
+            Cow::Borrowed(&[1, 2])
+        }
+
+        fn all_states() -> &'static [()] {
+            &[()]
+        }
+
+        fn all_phis() -> &'static [()] {
+            &[()]
+        }
+
+        fn get_phi_for_input(_state: (), _input: &()) -> Option<()> {
+            Some(())
+        }
+
+        fn output_visibility(output: &u32) -> Option<Visibility> {
+            (*output == 1).then_some(Visibility::Internal)
+        }
+    }
+
+    /// A SUT emitting the listed outputs, whatever its inputs.
+    struct Script(Vec<u32>);
+
+    impl SutAdapter<Counter> for Script {
+        fn apply(&mut self, _input: &()) -> Option<u32> {
+            (!self.0.is_empty()).then(|| self.0.remove(0))
+        }
+    }
+
+    #[test]
+    fn internal_outputs_are_not_observed() {
+        let scenario = ScenarioOf::<Counter>::new("count twice").step((), 1).step((), 2);
+        assert_eq!(scenario.check::<Counter>(), Ok(()));
+        assert_eq!(scenario.run::<Counter, _>(&mut Script(vec![0, 2])), Ok(()));
+        let failure = scenario.run::<Counter, _>(&mut Script(vec![0, 3])).unwrap_err();
+        assert_eq!(failure.step, 1);
+    }
+
+    #[test]
+    fn internal_outputs_emitted_by_the_sut_are_filtered() {
+        let scenario = ScenarioOf::<Counter>::new("count then stop").step((), 2).silent_step(());
+        assert_eq!(scenario.run::<Counter, _>(&mut Script(vec![2, 1])), Ok(()));
+    }
+}
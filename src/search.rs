@@ -0,0 +1,598 @@
+//! Cheapest-first searches for input sequences, the building blocks of the
+//! `SxMTester` generators, for custom generation strategies.
+//!
+//! Two spaces can be searched. `path_to_state` walks the associated
+//! automaton, ignoring memory and guards: it settles each state once, or
+//! once per length under a bound of d inputs, so it runs in O(|Q|·|Σ|) or
+//! O(d·|Q|·|Σ|) steps, but the sequence it returns may be rejected by a
+//! guard on the way. `search` and `path_to_satisfy_phi` execute
+//! the machine and explore configurations, so guards are honoured; memory is
+//! not deduplicated, so they may try up to |Σ|^d sequences for a bound of d
+//! inputs, taken from `PathConstraints::max_len` or `DEFAULT_MAX_LEN`.
+//! `search_settled` deduplicates memories that implement `Store`.
+//!
+//! Every search returns a cheapest sequence among those `PathConstraints`
+//! allow, the cost of a sequence being the sum of `XMachine::transition_cost`
+//! over its transitions, and tries preferred inputs first among equally
+//! cheap ones. With the default unit costs, cheapest means shortest.
+//!
+//! `phi_tour` strings many targets together instead of searching each from
+//! an initial state: from wherever the previous target left the machine it
+//! goes to the nearest target left, so a handful of long sequences exercise
+//! what would take one setup sequence per (state, phi) pair.
+
+use crate::configuration::Configuration;
+use crate::coverage::defined_transitions;
+use crate::feasibility::{Feasibility, FeasibilityOracle};
+use crate::isolation;
+use crate::mbt::PathConstraintsOf;
+use crate::runner::MachineRunner;
+use crate::scenario::{Scenario, ScenarioOf};
+use crate::store::Store;
+use crate::{IndexSet, XMachine};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+
+/// Bound on sequence length of the configuration searches when the
+/// constraints set none.
+pub const DEFAULT_MAX_LEN: usize = 10;
+
+/// What `search` looks for.
+pub enum Goal<'a, T: XMachine> {
+    /// A configuration in this state.
+    State(T::State),
+    /// A configuration satisfying the predicate.
+    Configuration(&'a dyn Fn(&Configuration<T>) -> bool),
+    /// A step emitting this output. The sequence ends with the input that
+    /// emits it.
+    Output(T::Output),
+}
+
+/// A sequence found by `search`, with the configuration it leads to.
+pub struct Found<T: XMachine> {
+    pub path: Vec<T::Input>,
+    pub configuration: Configuration<T>,
+}
+
+/// The cheapest input sequence leading from an initial state to `target` on
+/// the associated automaton, ignoring guards.
+///
+/// Under `PathConstraints::max_len` a state reached by a cheap but long path
+/// may still lead to `target` by a dearer, shorter one, so states are then
+/// settled once per path length rather than once.
+pub fn path_to_state<T: XMachine>(target: T::State, constraints: &PathConstraintsOf<T>) -> Option<Vec<T::Input>> {
+    let layers = constraints.max_len.map_or(1, |max| max + 1);
+    let layer = |len: usize| if constraints.max_len.is_some() { len } else { 0 };
+    let slot = |state: T::State, len: usize| T::state_index(state).map(|idx| idx.0 * layers + layer(len));
+
+    let mut frontier: Frontier<(T::State, Vec<T::Input>)> = Frontier::new();
+    let mut settled = IndexSet::new(T::all_states().len() * layers);
+    let mut costs: Vec<Option<u64>> = vec![None; T::all_states().len() * layers];
+    // States `next_state` returns without declaring them in `all_states`,
+    // with their layer, cost and whether they are settled.
+    let mut undeclared: Vec<(T::State, usize, u64, bool)> = Vec::new();
+
+    for &start in T::initial_states() {
+        if start == target {
+            return Some(vec![]);
+        }
+        frontier.push(0, (start, vec![]));
+        if let Some(slot) = slot(start, 0) {
+            costs[slot] = Some(0);
+        }
+    }
+
+    let inputs = T::all_inputs();
+    let inputs = constraints.order(&inputs);
+    while let Some((cost, (current_state, path))) = frontier.pop() {
+        if current_state == target {
+            return Some(path);
+        }
+        let current_layer = layer(path.len());
+        let fresh = match slot(current_state, path.len()) {
+            Some(slot) => settled.insert(slot),
+            None => match undeclared
+                .iter_mut()
+                .find(|(state, layer, _, _)| *state == current_state && *layer == current_layer)
+            {
+                Some((_, _, _, settled)) => !std::mem::replace(settled, true),
+                None => {
+                    undeclared.push((current_state, current_layer, cost, true));
+                    true
+                }
+            },
+        };
+        if !fresh {
+            continue;
+        }
+        if !constraints.within(path.len() + 1) {
+            continue;
+        }
+        let next_layer = layer(path.len() + 1);
+        for &input in &inputs {
+            if let Some(phi) = T::get_phi_for_input(current_state, input) {
+                if let Some(next_state) = T::next_state(current_state, phi) {
+                    if !constraints.permits::<T>(&phi, &next_state) {
+                        continue;
+                    }
+                    let next_cost = cost + u64::from(T::transition_cost(current_state, phi));
+                    let cheaper = match slot(next_state, path.len() + 1) {
+                        Some(slot) if costs[slot].map_or(true, |known| next_cost < known) => {
+                            costs[slot] = Some(next_cost);
+                            true
+                        }
+                        Some(_) => false,
+                        None => match undeclared
+                            .iter_mut()
+                            .find(|(state, layer, _, _)| *state == next_state && *layer == next_layer)
+                        {
+                            Some((_, _, known, _)) if next_cost < *known => {
+                                *known = next_cost;
+                                true
+                            }
+                            Some(_) => false,
+                            None => {
+                                undeclared.push((next_state, next_layer, next_cost, false));
+                                true
+                            }
+                        },
+                    };
+                    if cheaper {
+                        let mut new_path = path.clone();
+                        new_path.push(input.clone());
+                        frontier.push(next_cost, (next_state, new_path));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The cheapest input sequence the spec accepts from an initial
+/// configuration that reaches `goal`.
+pub fn search<T: XMachine>(goal: &Goal<'_, T>, constraints: &PathConstraintsOf<T>) -> Option<Found<T>> {
+    search_with(goal, constraints, &mut |_, _, _| true)
+}
+
+/// Like `search`, but settles each configuration once per path length, so
+/// it runs in O(d·|C|·|Σ|) steps for the |C| configurations reachable within
+/// d inputs instead of up to |Σ|^d, at the price of hashing memories.
+pub fn search_settled<T>(goal: &Goal<'_, T>, constraints: &PathConstraintsOf<T>) -> Option<Found<T>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut settled: HashMap<(T::Memory, usize, bool), Vec<T::State>> = HashMap::new();
+    search_with(goal, constraints, &mut |config, len, emitted| {
+        let states = settled.entry((config.store.clone(), len, emitted)).or_default();
+        let fresh = !states.contains(&config.state);
+        if fresh {
+            states.push(config.state);
+        }
+        fresh
+    })
+}
+
+/// `search`, skipping the configurations reached by a path of the given
+/// length, and whether it emitted the goal output, that `fresh` rejects.
+fn search_with<T: XMachine>(
+    goal: &Goal<'_, T>,
+    constraints: &PathConstraintsOf<T>,
+    fresh: &mut dyn FnMut(&Configuration<T>, usize, bool) -> bool,
+) -> Option<Found<T>> {
+    let mut frontier = Frontier::new();
+    for config in Configuration::<T>::initial() {
+        frontier.push(0, (config, Vec::new(), false));
+    }
+
+    let max_len = constraints.max_len.unwrap_or(DEFAULT_MAX_LEN);
+    let inputs = T::all_inputs();
+    let inputs = constraints.order(&inputs);
+    while let Some((cost, (config, path, emitted))) = frontier.pop() {
+        if !fresh(&config, path.len(), emitted) {
+            continue;
+        }
+        let reached = match goal {
+            Goal::State(state) => config.state == *state,
+            Goal::Configuration(predicate) => predicate(&config),
+            Goal::Output(_) => emitted,
+        };
+        if reached {
+            return Some(Found {
+                path,
+                configuration: config,
+            });
+        }
+        if path.len() >= max_len {
+            continue;
+        }
+
+        for &input in &inputs {
+            if let Ok((transition, next)) = config.step(input) {
+                if !constraints.permits::<T>(&transition.phi, &transition.to) {
+                    continue;
+                }
+                let mut new_path = path.clone();
+                new_path.push(input.clone());
+                let emitted = matches!(goal, Goal::Output(output) if transition.output.as_ref() == Some(output));
+                let next_cost = cost + u64::from(T::transition_cost(transition.from, transition.phi));
+                frontier.push(next_cost, (next, new_path, emitted));
+            }
+        }
+    }
+    None
+}
+
+/// The cheapest input sequence after which `trigger_input` in `target_state`
+/// runs `target_phi` with its guard holding, with the store it leaves.
+///
+/// Configurations `oracle` deems infeasible are pruned; a valid witness
+/// store ends the search with the cheapest sequence producing it in
+/// `target_state`. If none does within the bound, the sequence is
+/// `path_to_state` of `target_state`, and a case built on it
+/// `TestCase::requires_injection`.
+pub fn path_to_satisfy_phi<T>(
+    target_state: T::State,
+    target_phi: T::Phi,
+    trigger_input: &T::Input,
+    oracle: &dyn FeasibilityOracle<T>,
+    constraints: &PathConstraintsOf<T>,
+) -> Option<(Vec<T::Input>, T::Memory)>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut frontier = Frontier::new();
+    for config in Configuration::<T>::initial() {
+        frontier.push(0, (config, Vec::new()));
+    }
+
+    let max_depth = constraints.max_len.unwrap_or(DEFAULT_MAX_LEN);
+    let inputs = T::all_inputs();
+    let inputs = constraints.order(&inputs);
+    while let Some((cost, (config, path))) = frontier.pop() {
+        match oracle.check(target_state, target_phi, trigger_input, &config) {
+            Feasibility::Infeasible => continue,
+            Feasibility::Witness(store) => {
+                let mut check_mem = store.clone();
+                if isolation::execute_phi::<T>(target_state, target_phi, &mut check_mem, trigger_input).is_ok() {
+                    let witness = |config: &Configuration<T>| config.state == target_state && config.store == store;
+                    if let Some(found) = search_settled::<T>(&Goal::Configuration(&witness), constraints) {
+                        return Some((found.path, store));
+                    }
+                    if let Some(path) = path_to_state::<T>(target_state, constraints) {
+                        return Some((path, store));
+                    }
+                }
+            }
+            Feasibility::Unknown => {}
+        }
+        if config.state == target_state {
+            let mut check_mem = config.store.clone();
+            if isolation::execute_phi::<T>(target_state, target_phi, &mut check_mem, trigger_input).is_ok() {
+                return Some((path, config.store));
+            }
+        }
+        if path.len() >= max_depth {
+            continue;
+        }
+
+        for &input in &inputs {
+            if let Ok((transition, next)) = config.step(input) {
+                if !constraints.permits::<T>(&transition.phi, &transition.to) {
+                    continue;
+                }
+                let mut new_path = path.clone();
+                new_path.push(input.clone());
+                let next_cost = cost + u64::from(T::transition_cost(transition.from, transition.phi));
+                frontier.push(next_cost, (next, new_path));
+            }
+        }
+    }
+    None
+}
+
+/// Input sequences that together exercise a set of (state, phi) targets,
+/// found by `phi_tour`.
+pub struct Tour<T: XMachine> {
+    /// Sequences each applied from the configuration `MachineRunner::new`
+    /// starts in. A new leg starts only when no target left is reachable
+    /// from where the previous one ended.
+    pub legs: Vec<Vec<T::Input>>,
+    /// Targets exercised, in the order the tour first takes them.
+    pub covered: Vec<(T::State, T::Phi)>,
+    /// Targets no leg reached within the constraints.
+    pub missed: Vec<(T::State, T::Phi)>,
+}
+
+impl<T: XMachine> Tour<T> {
+    /// Inputs over all legs.
+    pub fn len(&self) -> usize {
+        self.legs.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.legs.is_empty()
+    }
+
+    /// One scenario per leg, named `"<name> #<leg>"`, expecting at every
+    /// step the output and state of the spec, so each step is checked
+    /// rather than only the last one.
+    pub fn scenarios(&self, name: &str) -> Vec<ScenarioOf<T>> {
+        self.legs
+            .iter()
+            .enumerate()
+            .map(|(idx, leg)| {
+                let mut runner = MachineRunner::<T>::new();
+                let mut scenario = Scenario::new(&format!("{} #{}", name, idx + 1));
+                for input in leg {
+                    let output = runner.step(input).ok().and_then(|transition| transition.output);
+                    scenario = scenario.step_expecting(input.clone(), output.into()).in_state(runner.state());
+                }
+                scenario
+            })
+            .collect()
+    }
+}
+
+/// A tour exercising every defined transition of the associated automaton,
+/// as `coverage` counts them.
+pub fn phi_tour<T: XMachine>(constraints: &PathConstraintsOf<T>) -> Tour<T> {
+    phi_tour_of::<T>(&defined_transitions::<T>(), constraints)
+}
+
+/// A tour exercising `targets`, greedily: each hop is the cheapest sequence
+/// the spec accepts from the current configuration whose last input takes a
+/// target left, searched as `search` does. Targets taken on the way count
+/// as exercised too. Phis are compared by `XMachine::phi_kind`.
+pub fn phi_tour_of<T: XMachine>(targets: &[(T::State, T::Phi)], constraints: &PathConstraintsOf<T>) -> Tour<T> {
+    let mut remaining: Vec<(T::State, T::Phi)> = Vec::new();
+    for target in targets {
+        if !remaining.contains(target) {
+            remaining.push(*target);
+        }
+    }
+    let mut tour = Tour {
+        legs: Vec::new(),
+        covered: Vec::new(),
+        missed: Vec::new(),
+    };
+
+    while !remaining.is_empty() {
+        let mut config = MachineRunner::<T>::new().into_configuration();
+        let mut leg = Vec::new();
+        while let Some(path) = nearest_target(&config, &remaining, constraints) {
+            for input in path {
+                let Ok((transition, next)) = config.step(&input) else {
+                    break;
+                };
+                let taken = (transition.from, T::phi_kind(transition.phi));
+                if let Some(idx) = remaining.iter().position(|target| *target == taken) {
+                    tour.covered.push(remaining.remove(idx));
+                }
+                config = next;
+                leg.push(input);
+            }
+        }
+        if leg.is_empty() {
+            break;
+        }
+        tour.legs.push(leg);
+    }
+    tour.missed = remaining;
+    tour
+}
+
+/// The cheapest accepted sequence from `start` whose last input takes one of `targets`.
+fn nearest_target<T: XMachine>(
+    start: &Configuration<T>,
+    targets: &[(T::State, T::Phi)],
+    constraints: &PathConstraintsOf<T>,
+) -> Option<Vec<T::Input>> {
+    let mut frontier = Frontier::new();
+    frontier.push(0, (start.clone(), Vec::new(), false));
+
+    let max_len = constraints.max_len.unwrap_or(DEFAULT_MAX_LEN);
+    let inputs = T::all_inputs();
+    let inputs = constraints.order(&inputs);
+    while let Some((cost, (config, path, hit))) = frontier.pop() {
+        if hit {
+            return Some(path);
+        }
+        if path.len() >= max_len {
+            continue;
+        }
+
+        for &input in &inputs {
+            if let Ok((transition, next)) = config.step(input) {
+                if !constraints.permits::<T>(&transition.phi, &transition.to) {
+                    continue;
+                }
+                let mut new_path = path.clone();
+                new_path.push(input.clone());
+                let hit = targets.contains(&(transition.from, T::phi_kind(transition.phi)));
+                let next_cost = cost + u64::from(T::transition_cost(transition.from, transition.phi));
+                frontier.push(next_cost, (next, new_path, hit));
+            }
+        }
+    }
+    None
+}
+
+/// Priority queue popping the cheapest node first and, among equally cheap
+/// ones, the earliest pushed, so unit costs search breadth-first.
+struct Frontier<N> {
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    nodes: Vec<Option<N>>,
+}
+
+impl<N> Frontier<N> {
+    fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, cost: u64, node: N) {
+        self.heap.push(Reverse((cost, self.nodes.len())));
+        self.nodes.push(Some(node));
+    }
+
+    fn pop(&mut self) -> Option<(u64, N)> {
+        let Reverse((cost, idx)) = self.heap.pop()?;
+        self.nodes[idx].take().map(|node| (cost, node))
+    }
+}
+
+impl<T: XMachine> Clone for Found<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            configuration: self.configuration.clone(),
+        }
+    }
+}
+
+impl<T: XMachine> Clone for Tour<T> {
+    fn clone(&self) -> Self {
+        Self {
+            legs: self.legs.clone(),
+            covered: self.covered.clone(),
+            missed: self.missed.clone(),
+        }
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Tour<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tour")
+            .field("legs", &self.legs)
+            .field("covered", &self.covered)
+            .field("missed", &self.missed)
+            .finish()
+    }
+}
+
+impl<T: XMachine> fmt::Debug for Found<T>
+where
+    T::Memory: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Found")
+            .field("path", &self.path)
+            .field("configuration", &self.configuration)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mbt::PathConstraints;
+    use std::borrow::Cow;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Node {
+        Start,
+        Detour,
+        Meet,
+        Target,
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Edge {
+        A,
+        B,
+        C,
+        D,
+    }
+
+    /// `Start -A-> Meet` costs 10, `Start -B-> Detour -C-> Meet` costs 2 and
+    /// `Meet -D-> Target` costs 1. `Detour` is left out of `all_states`.
+    struct Shortcut;
+
+    impl XMachine for Shortcut {
+        type Input = Edge;
+        type Output = ();
+        type State = Node;
+        type Memory = ();
+        type Phi = Edge;
+
+        fn next_state(state: Node, phi: Edge) -> Option<Node> {
+            match (state, phi) {
+                (Node::Start, Edge::A) => Some(Node::Meet),
+                (Node::Start, Edge::B) => Some(Node::Detour),
+                (Node::Detour, Edge::C) => Some(Node::Meet),
+                (Node::Meet, Edge::D) => Some(Node::Target),
+                _ => None,
+            }
+        }
+
+        fn initial_states() -> &'static [Node] {
+            &[Node::Start]
+        }
+
+        fn final_states() -> &'static [Node] {
+            &[Node::Target]
+        }
+
+        fn initial_store() {}
+
+        fn execute_phi(_phi: Edge, _store: &mut (), _input: &Edge) -> Result<Option<()>, ()> {
+            Ok(None)
+        }
+
+        fn all_inputs() -> Cow<'static, [Edge]> {
+            Cow::Borrowed(&[Edge::A, Edge::B, Edge::C, Edge::D])
+        }
+
+        fn all_outputs() -> Cow<'static, [()]> {
+            Cow::Borrowed(&[])
+        }
+
+        fn all_states() -> &'static [Node] {
+            &[Node::Start, Node::Meet, Node::Target]
+        }
+
+        fn all_phis() -> &'static [Edge] {
+            &[Edge::A, Edge::B, Edge::C, Edge::D]
+        }
+
+        fn get_phi_for_input(state: Node, input: &Edge) -> Option<Edge> {
+            Self::next_state(state, *input).map(|_| *input)
+        }
+
+        fn transition_cost(_state: Node, phi: Edge) -> u32 {
+            if phi == Edge::A {
+                10
+            } else {
+                1
+            }
+        }
+    }
+
+    #[test]
+    fn path_to_state_goes_through_undeclared_states() {
+        let path = path_to_state::<Shortcut>(Node::Target, &PathConstraints::new());
+        assert_eq!(path, Some(vec![Edge::B, Edge::C, Edge::D]));
+    }
+
+    #[test]
+    fn path_to_state_finds_dearer_paths_within_max_len() {
+        let path = path_to_state::<Shortcut>(Node::Target, &PathConstraints::new().max_len(2));
+        assert_eq!(path, Some(vec![Edge::A, Edge::D]));
+        assert_eq!(path_to_state::<Shortcut>(Node::Target, &PathConstraints::new().max_len(1)), None);
+    }
+
+    #[test]
+    fn search_settled_honours_guards() {
+        use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet::*, DigicodeState};
+
+        let found = search_settled::<Digicode>(&Goal::State(DigicodeState::CodeEntered), &PathConstraints::new());
+        assert_eq!(found.map(|found| found.path), Some(vec![Digit(4), Digit(9), Digit(2), OkEnter]));
+    }
+
+}
@@ -0,0 +1,202 @@
+use crate::monitor::{MonitorViolation, OutputMonitor};
+use crate::runner::MachineRunner;
+use crate::store::{FieldChange, Store};
+use crate::sut::SutAdapter;
+use crate::XMachine;
+
+/// A point where the production code disagreed with the spec.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence<Input, Output, State> {
+    /// Zero-based index of the input in the monitored stream.
+    pub step: usize,
+    pub input: Input,
+    /// Output predicted by the spec (`None` if the spec rejects the input).
+    pub expected_output: Option<Output>,
+    pub actual_output: Option<Output>,
+    /// State of the spec after the input.
+    pub expected_state: State,
+    /// State reported by the SUT, if it is observable.
+    pub actual_state: Option<State>,
+    /// Non-ghost fields on which the memory reported by the SUT, if it is
+    /// observable, differs from the spec's: `before` is the spec's value.
+    pub store_changes: Vec<FieldChange>,
+    /// Ghost fields the spec changed on this input, for context only: the
+    /// SUT is not expected to hold them.
+    pub ghost_changes: Vec<FieldChange>,
+}
+
+/// `Divergence` specialised to the symbols of machine `T`.
+pub type DivergenceOf<T> = Divergence<<T as XMachine>::Input, <T as XMachine>::Output, <T as XMachine>::State>;
+
+type Observer<T> = Box<dyn FnMut(&DivergenceOf<T>)>;
+
+type Monitor<T> = Box<dyn OutputMonitor<<T as XMachine>::Output>>;
+
+/// Contract mode: runs the spec machine side by side with the real implementation.
+///
+/// Every input is forwarded to both. The SUT's output is passed through to the
+/// caller unchanged, while any disagreement in output (or in state or
+/// non-ghost memory, when the SUT exposes them) is recorded as a `Divergence` and reported to the observer.
+/// Attached monitors watch the SUT's outputs and record the first violation
+/// of each.
+pub struct ShadowRunner<T: XMachine, S: SutAdapter<T>> {
+    spec: MachineRunner<T>,
+    sut: S,
+    steps: usize,
+    divergences: Vec<DivergenceOf<T>>,
+    observer: Option<Observer<T>>,
+    monitors: Vec<(Monitor<T>, bool)>,
+    violations: Vec<MonitorViolation<T::Output>>,
+}
+
+impl<T: XMachine, S: SutAdapter<T>> ShadowRunner<T, S> {
+    /// Shadows `sut` with the spec started in its initial configuration.
+    pub fn new(sut: S) -> Self {
+        Self::with_spec(MachineRunner::new(), sut)
+    }
+
+    /// Shadows `sut` with the spec started from an explicit configuration.
+    pub fn with_spec(spec: MachineRunner<T>, sut: S) -> Self {
+        Self {
+            spec,
+            sut,
+            steps: 0,
+            divergences: Vec::new(),
+            observer: None,
+            monitors: Vec::new(),
+            violations: Vec::new(),
+        }
+    }
+
+    /// Registers a callback invoked for every divergence as it is detected.
+    pub fn on_divergence(
+        mut self,
+        observer: impl FnMut(&DivergenceOf<T>) + 'static,
+    ) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Attaches a monitor, e.g. a `monitor::MonitorRunner`, to the SUT's outputs.
+    pub fn with_monitor(mut self, monitor: impl OutputMonitor<T::Output> + 'static) -> Self {
+        self.monitors.push((Box::new(monitor), false));
+        self
+    }
+
+    /// Feeds `input` to both sides and returns what the SUT produced.
+    pub fn step(&mut self, input: &T::Input) -> Option<T::Output>
+    where
+        T::Memory: Store,
+    {
+        let before = self.spec.store().clone();
+        let expected_output = self.spec.step(input).ok().and_then(|t| t.output);
+        let actual_output = self.sut.apply(input);
+        let expected_state = self.spec.state();
+        let actual_state = self.sut.observe_state();
+        let store_changes = match self.sut.observe_store() {
+            Some(store) => self.spec.store().diff(&store),
+            None => Vec::new(),
+        };
+
+        let state_diverged = actual_state.is_some_and(|s| s != expected_state);
+        if expected_output != actual_output || state_diverged || !store_changes.is_empty() {
+            let divergence = Divergence {
+                step: self.steps,
+                input: input.clone(),
+                expected_output,
+                actual_output: actual_output.clone(),
+                expected_state,
+                actual_state,
+                store_changes,
+                ghost_changes: before.ghost_diff(self.spec.store()),
+            };
+            if let Some(observer) = self.observer.as_mut() {
+                observer(&divergence);
+            }
+            self.divergences.push(divergence);
+        }
+
+        if let Some(output) = &actual_output {
+            for (monitor, reported) in &mut self.monitors {
+                if let Err(violation) = monitor.observe(output) {
+                    if !*reported {
+                        *reported = true;
+                        self.violations.push(violation);
+                    }
+                }
+            }
+        }
+
+        self.steps += 1;
+        actual_output
+    }
+
+    /// All divergences observed so far, in stream order.
+    pub fn divergences(&self) -> &[DivergenceOf<T>] {
+        &self.divergences
+    }
+
+    /// The first violation of each attached monitor, in detection order.
+    pub fn monitor_violations(&self) -> &[MonitorViolation<T::Output>] {
+        &self.violations
+    }
+
+    /// `true` while the SUT has matched the spec on every input.
+    pub fn is_conforming(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    pub fn spec(&self) -> &MachineRunner<T> {
+        &self.spec
+    }
+
+    pub fn sut(&self) -> &S {
+        &self.sut
+    }
+
+    pub fn sut_mut(&mut self) -> &mut S {
+        &mut self.sut
+    }
+
+    pub fn into_sut(self) -> S {
+        self.sut
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Door, DoorInputAlphabet as In, DoorOutputAlphabet as Out, DoorPhi, DoorState};
+    use crate::mutant::{Fault, MutantRunner};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[test]
+    fn a_conforming_implementation_passes_through() {
+        let mut shadow = ShadowRunner::<Door, _>::new(MachineRunner::<Door>::new());
+        assert_eq!(shadow.step(&In::Open), Some(Out::DoorOpens));
+        assert_eq!(shadow.step(&In::Close), Some(Out::DoorCloses));
+        assert!(shadow.is_conforming());
+    }
+
+    #[test]
+    fn divergences_are_recorded_and_observed() {
+        let stuck = MutantRunner::<Door>::new(vec![Fault::WrongTarget {
+            from: DoorState::Closed,
+            phi: DoorPhi::OpenDoor,
+            to: DoorState::Closed,
+        }]);
+        let seen = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&seen);
+        let mut shadow = ShadowRunner::<Door, _>::new(stuck).on_divergence(move |_| counter.set(counter.get() + 1));
+
+        assert_eq!(shadow.step(&In::Open), Some(Out::DoorOpens));
+        assert_eq!(shadow.step(&In::Open), Some(Out::DoorOpens));
+        assert_eq!(seen.get(), 2);
+        let [stayed_closed, opened_again] = shadow.divergences() else { panic!("expected two divergences") };
+        assert_eq!(stayed_closed.expected_state, DoorState::Opened);
+        assert_eq!(stayed_closed.actual_state, Some(DoorState::Closed));
+        assert_eq!(opened_again.expected_output, Some(Out::OpenIgnored));
+        assert_eq!(opened_again.actual_output, Some(Out::DoorOpens));
+    }
+}
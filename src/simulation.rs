@@ -0,0 +1,505 @@
+//! Long-running simulations on a virtual clock, with a periodic tick input
+//! injected between the inputs of the environment.
+//!
+//! Timeout-driven transitions only fire if something delivers the timer
+//! events the spec waits for. A `TickSimulation` keeps a virtual time and,
+//! whenever it advances past a multiple of the tick period, applies the tick
+//! input, so a script of timed environment inputs plays out as it would on
+//! the target. Nothing is logged per step: counters and an optional observer
+//! keep soak runs spanning days of virtual time in constant memory.
+//!
+//! The clock is virtual by default: ticks due are applied at once, so tests
+//! run as fast as the spec does. With `Pacing::WallClock` the same
+//! simulation sleeps until each step is due, for demos and hardware in the
+//! loop. There is no async runner in the crate; pacing blocks the thread.
+//!
+//! A `TimedNetwork` owns one virtual clock for both components of a
+//! `MachineNetwork`: their ticks fall at the same instants, and routed
+//! messages take a fixed latency, so ticks interleave with the micro-steps
+//! delivering them in a deterministic order.
+
+use crate::network::{Adapters, MachineNetwork, StepReport, Tagged, Wiring};
+use crate::runner::{MachineRunner, StepResultOf};
+use crate::XMachine;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where a simulated input came from.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SimulatedInput {
+    /// The periodic tick.
+    Tick,
+    /// An input supplied by the caller.
+    External,
+}
+
+/// What a `TickSimulation` has processed so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimulationStats {
+    pub ticks: u64,
+    /// Ticks the state had no transition for, or whose guard did not hold.
+    pub ignored_ticks: u64,
+    pub external_inputs: u64,
+    pub rejected_inputs: u64,
+}
+
+/// How the virtual clock of a `TickSimulation` relates to wall-clock time.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Pacing {
+    /// Time only passes when told to; ticks due are applied at once.
+    #[default]
+    Virtual,
+    /// Steps wait for their wall-clock time, virtual time running `speed`
+    /// times as fast: 1.0 is real time, 10.0 a ten times accelerated demo.
+    WallClock { speed: f64 },
+}
+
+type Observer<T> = Box<dyn FnMut(Duration, SimulatedInput, &<T as XMachine>::Input, &StepResultOf<T>)>;
+
+/// Runs the spec on a virtual clock, applying `tick` every `period`.
+///
+/// Ticks fall at every positive multiple of the period. An external input
+/// applied at a tick instant is processed after the tick.
+pub struct TickSimulation<T: XMachine> {
+    runner: MachineRunner<T>,
+    tick: T::Input,
+    period: Duration,
+    now: Duration,
+    next_tick: Duration,
+    stats: SimulationStats,
+    observer: Option<Observer<T>>,
+    pacing: Pacing,
+    /// Wall-clock instant matching a virtual time, set by the first paced step.
+    epoch: Option<(Instant, Duration)>,
+}
+
+impl<T: XMachine> TickSimulation<T> {
+    /// Starts the spec in its initial configuration at time zero.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn new(tick: T::Input, period: Duration) -> Self {
+        Self::with_runner(MachineRunner::new(), tick, period)
+    }
+
+    /// A simulation ticking with `XMachine::tick_input`, if the machine has one.
+    pub fn for_machine(period: Duration) -> Option<Self> {
+        T::tick_input().map(|tick| Self::new(tick, period))
+    }
+
+    /// Starts from the configuration of `runner` at time zero.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn with_runner(runner: MachineRunner<T>, tick: T::Input, period: Duration) -> Self {
+        assert!(!period.is_zero(), "tick period must be positive");
+        Self {
+            runner,
+            tick,
+            period,
+            now: Duration::ZERO,
+            next_tick: period,
+            stats: SimulationStats::default(),
+            observer: None,
+            pacing: Pacing::Virtual,
+            epoch: None,
+        }
+    }
+
+    /// Sets how virtual time follows the wall clock.
+    ///
+    /// # Panics
+    ///
+    /// If a `Pacing::WallClock` speed is not positive and finite.
+    pub fn with_pacing(mut self, pacing: Pacing) -> Self {
+        if let Pacing::WallClock { speed } = pacing {
+            assert!(speed.is_finite() && speed > 0.0, "pacing speed must be positive and finite");
+        }
+        self.pacing = pacing;
+        self
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// Registers a callback invoked after every step with the virtual time,
+    /// the origin of the input, the input and its result.
+    pub fn on_step(
+        mut self,
+        observer: impl FnMut(Duration, SimulatedInput, &T::Input, &StepResultOf<T>) + 'static,
+    ) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Advances the clock by `duration`, applying every tick due meanwhile.
+    /// A long `duration` is a soak run.
+    pub fn advance(&mut self, duration: Duration) {
+        let until = self.now + duration;
+        while self.next_tick <= until {
+            self.pace(self.next_tick);
+            self.now = self.next_tick;
+            self.next_tick += self.period;
+            let tick = self.tick.clone();
+            let result = self.runner.step(&tick);
+            self.stats.ticks += 1;
+            if result.is_err() {
+                self.stats.ignored_ticks += 1;
+            }
+            if let Some(observer) = self.observer.as_mut() {
+                observer(self.now, SimulatedInput::Tick, &tick, &result);
+            }
+        }
+        self.pace(until);
+        self.now = until;
+    }
+
+    /// Applies an environment input at the current time.
+    pub fn input(&mut self, input: &T::Input) -> StepResultOf<T> {
+        let result = self.runner.step(input);
+        self.stats.external_inputs += 1;
+        if result.is_err() {
+            self.stats.rejected_inputs += 1;
+        }
+        if let Some(observer) = self.observer.as_mut() {
+            observer(self.now, SimulatedInput::External, input, &result);
+        }
+        result
+    }
+
+    /// Advances the clock by `delay`, then applies `input`.
+    pub fn input_after(&mut self, delay: Duration, input: &T::Input) -> StepResultOf<T> {
+        self.advance(delay);
+        self.input(input)
+    }
+
+    /// Plays `script`, each input applied its delay after the previous one.
+    /// Returns the results of the script's inputs, ticks aside.
+    pub fn run<'a, I>(&mut self, script: I) -> Vec<StepResultOf<T>>
+    where
+        I: IntoIterator<Item = &'a (Duration, T::Input)>,
+        T::Input: 'a,
+    {
+        script
+            .into_iter()
+            .map(|(delay, input)| self.input_after(*delay, input))
+            .collect()
+    }
+
+    /// Virtual time elapsed since the start.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub fn stats(&self) -> SimulationStats {
+        self.stats
+    }
+
+    pub fn runner(&self) -> &MachineRunner<T> {
+        &self.runner
+    }
+
+    pub fn runner_mut(&mut self) -> &mut MachineRunner<T> {
+        &mut self.runner
+    }
+
+    /// Under `Pacing::WallClock`, sleeps until virtual time `at` is due. The
+    /// schedule is kept from the first paced step on, so slow steps do not
+    /// accumulate drift.
+    fn pace(&mut self, at: Duration) {
+        let Pacing::WallClock { speed } = self.pacing else {
+            return;
+        };
+        let (start, origin) = *self.epoch.get_or_insert((Instant::now(), self.now));
+        let due = start + (at - origin).div_f64(speed);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        }
+    }
+}
+
+/// One micro-step of a `TimedNetwork` and the virtual time it happened at.
+pub struct TimedStep<A: XMachine, B: XMachine> {
+    pub at: Duration,
+    /// `true` if the micro-step applied a component's `XMachine::tick_input`.
+    pub tick: bool,
+    /// The report of the single micro-step.
+    pub report: StepReport<A, B>,
+}
+
+/// A `MachineNetwork` on a shared virtual clock.
+///
+/// Every `period`, component A then component B receives its
+/// `XMachine::tick_input`, if it has one, as an environment input. A routed
+/// message is delivered `latency` after the micro-step before it, one
+/// message at a time, so a slow exchange spans several ticks. Events at the
+/// same instant run ticks first, then deliveries, then the input applied at
+/// that time. At most `MachineNetwork::DEFAULT_STEP_BUDGET` micro-steps run
+/// at one instant; with a zero latency, messages beyond that wait for the
+/// next tick instant.
+pub struct TimedNetwork<A: XMachine, B: XMachine, W = Adapters> {
+    network: MachineNetwork<A, B, W>,
+    period: Duration,
+    latency: Duration,
+    now: Duration,
+    next_tick: Duration,
+    next_delivery: Duration,
+    steps_now: usize,
+}
+
+impl<A, B, W> TimedNetwork<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    /// Starts both components in their initial configuration at time zero,
+    /// with messages delivered instantly.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn new(period: Duration) -> Self {
+        Self::with_network(MachineNetwork::new(), period)
+    }
+
+    /// Starts from `network` at time zero. Messages it has pending are delivered first.
+    ///
+    /// # Panics
+    ///
+    /// If `period` is zero.
+    pub fn with_network(network: MachineNetwork<A, B, W>, period: Duration) -> Self {
+        assert!(!period.is_zero(), "tick period must be positive");
+        Self {
+            network,
+            period,
+            latency: Duration::ZERO,
+            now: Duration::ZERO,
+            next_tick: period,
+            next_delivery: Duration::ZERO,
+            steps_now: 0,
+        }
+    }
+
+    /// Sets the time each routed message takes to be delivered.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Advances the clock by `duration`, running every tick and delivery due
+    /// meanwhile, in time order.
+    pub fn advance(&mut self, duration: Duration) -> Vec<TimedStep<A, B>> {
+        let until = self.now + duration;
+        let mut steps = Vec::new();
+        loop {
+            let delivery = (!self.network.is_stable()).then_some(self.next_delivery);
+            match delivery {
+                Some(at) if at < self.next_tick && at <= until => {
+                    self.move_to(at);
+                    if let Some(report) = self.network.deliver_next() {
+                        self.next_delivery = self.now + self.latency;
+                        self.record(report, false, &mut steps);
+                    }
+                }
+                _ if self.next_tick <= until => {
+                    self.move_to(self.next_tick);
+                    self.next_tick += self.period;
+                    let ticks = [A::tick_input().map(Tagged::A), B::tick_input().map(Tagged::B)];
+                    for tick in ticks.into_iter().flatten() {
+                        let report = self.feed(tick);
+                        self.record(report, true, &mut steps);
+                    }
+                }
+                _ => break,
+            }
+        }
+        self.move_to(until);
+        steps
+    }
+
+    /// Applies an environment input at the current time, then delivers the
+    /// messages due at once.
+    pub fn input(&mut self, input: Tagged<A::Input, B::Input>) -> Vec<TimedStep<A, B>> {
+        let report = self.feed(input);
+        let mut steps = Vec::new();
+        self.record(report, false, &mut steps);
+        steps.extend(self.advance(Duration::ZERO));
+        steps
+    }
+
+    /// Advances the clock by `delay`, then applies `input`.
+    pub fn input_after(&mut self, delay: Duration, input: Tagged<A::Input, B::Input>) -> Vec<TimedStep<A, B>> {
+        let mut steps = self.advance(delay);
+        steps.extend(self.input(input));
+        steps
+    }
+
+    /// Plays `script`, each input applied its delay after the previous one.
+    pub fn run<'a, I>(&mut self, script: I) -> Vec<TimedStep<A, B>>
+    where
+        I: IntoIterator<Item = &'a (Duration, Tagged<A::Input, B::Input>)>,
+        A::Input: 'a,
+        B::Input: 'a,
+    {
+        let mut steps = Vec::new();
+        for (delay, input) in script {
+            steps.extend(self.input_after(*delay, input.clone()));
+        }
+        steps
+    }
+
+    /// Virtual time elapsed since the start.
+    pub fn now(&self) -> Duration {
+        self.now
+    }
+
+    pub fn network(&self) -> &MachineNetwork<A, B, W> {
+        &self.network
+    }
+
+    pub fn network_mut(&mut self) -> &mut MachineNetwork<A, B, W> {
+        &mut self.network
+    }
+
+    fn move_to(&mut self, at: Duration) {
+        if at != self.now {
+            self.now = at;
+            self.steps_now = 0;
+        }
+    }
+
+    /// Feeds an environment input now. Messages it routes into empty
+    /// channels are due a latency later; others queue behind those pending.
+    fn feed(&mut self, input: Tagged<A::Input, B::Input>) -> StepReport<A, B> {
+        if self.network.is_stable() {
+            self.next_delivery = self.now + self.latency;
+        }
+        self.network.feed(input)
+    }
+
+    /// Logs a micro-step taken now, postponing deliveries to the next tick
+    /// instant once the step budget of this instant is spent.
+    fn record(&mut self, report: StepReport<A, B>, tick: bool, steps: &mut Vec<TimedStep<A, B>>) {
+        self.steps_now += 1;
+        if self.steps_now >= MachineNetwork::<A, B, W>::DEFAULT_STEP_BUDGET && self.next_delivery <= self.now {
+            self.next_delivery = self.next_tick;
+        }
+        steps.push(TimedStep {
+            at: self.now,
+            tick,
+            report,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Door, DoorInputAlphabet, DoorState};
+    use crate::examples::traffic_light::{LightInput, LightOutput, LightState, TrafficLight};
+    use crate::network::{Port, Ports};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    /// Green opens the door, red closes it.
+    struct Signal;
+
+    impl Port for Signal {
+        type From = TrafficLight;
+        type To = Door;
+
+        const NAME: &'static str = "signal";
+
+        fn carry(output: &LightOutput) -> Option<DoorInputAlphabet> {
+            match output {
+                LightOutput::ShowGreen => Some(DoorInputAlphabet::Open),
+                LightOutput::ShowRed => Some(DoorInputAlphabet::Close),
+                LightOutput::ShowYellow => None,
+            }
+        }
+    }
+
+    type Gate = TimedNetwork<TrafficLight, Door, Ports<Signal, ()>>;
+
+    fn trace(steps: &[TimedStep<TrafficLight, Door>]) -> Vec<(u128, bool)> {
+        steps.iter().map(|step| (step.at.as_millis(), step.tick)).collect()
+    }
+
+    #[test]
+    fn messages_arrive_a_latency_after_they_are_sent() {
+        let mut gate = Gate::new(10 * MS).with_latency(3 * MS);
+        assert_eq!(trace(&gate.advance(12 * MS)), [(10, true)]);
+        assert_eq!(gate.network().b().state(), DoorState::Closed);
+        assert_eq!(trace(&gate.advance(MS)), [(13, false)]);
+        assert_eq!(gate.network().b().state(), DoorState::Opened);
+    }
+
+    #[test]
+    fn ticks_interleave_with_slow_deliveries() {
+        let mut gate = Gate::new(10 * MS).with_latency(15 * MS);
+        let steps = gate.advance(40 * MS);
+        assert_eq!(trace(&steps), [(10, true), (20, true), (25, false), (30, true), (40, true)]);
+        assert_eq!(gate.network().b().state(), DoorState::Opened);
+        assert!(!gate.network().is_stable());
+    }
+
+    #[test]
+    fn instant_deliveries_follow_the_step_that_sent_them() {
+        let mut gate = Gate::new(10 * MS);
+        let steps = gate.input_after(5 * MS, Tagged::A(LightInput::Fault));
+        assert_eq!(trace(&steps), [(5, false), (5, false)]);
+        assert_eq!(gate.network().b().state(), DoorState::Closed);
+        assert_eq!(trace(&gate.advance(10 * MS)), [(10, true), (10, false)]);
+        assert_eq!(gate.network().b().state(), DoorState::Opened);
+    }
+
+    #[test]
+    fn a_scripted_run_interleaves_ticks_and_inputs_in_time_order() {
+        use LightOutput::*;
+        use SimulatedInput::{External, Tick};
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let observed = Rc::clone(&log);
+        let mut simulation = TickSimulation::<TrafficLight>::for_machine(10 * MS).unwrap().on_step(
+            move |at, origin, _input, result| {
+                let output = result.as_ref().ok().and_then(|t| t.output.clone());
+                observed.borrow_mut().push((at.as_millis(), origin, output));
+            },
+        );
+
+        let results = simulation.run(&[(10 * MS, LightInput::Fault), (15 * MS, LightInput::Fault)]);
+        assert!(results.iter().all(|result| result.is_ok()));
+        simulation.advance(20 * MS);
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                (10, Tick, Some(ShowGreen)),
+                (10, External, Some(ShowRed)),
+                (20, Tick, Some(ShowGreen)),
+                (25, External, Some(ShowRed)),
+                (30, Tick, Some(ShowGreen)),
+                (40, Tick, Some(ShowYellow)),
+            ]
+        );
+        assert_eq!(simulation.now(), 45 * MS);
+        assert_eq!(simulation.runner().state(), LightState::Yellow);
+        assert_eq!(*simulation.runner().store(), 2);
+        assert_eq!(
+            simulation.stats(),
+            SimulationStats {
+                ticks: 4,
+                ignored_ticks: 0,
+                external_inputs: 2,
+                rejected_inputs: 0,
+            }
+        );
+    }
+}
@@ -0,0 +1,244 @@
+//! Enumerable state types whose variants may carry a bounded parameter.
+//!
+//! `XMachine::State` must be `Copy + 'static` and listed in a static slice,
+//! which plain enums satisfy directly. A state such as `WaitingForDigit(n)`
+//! can be declared with `sxm_state_space!` instead, giving the parameter a
+//! `StateSpace` type like `Bounded<3>`: the macro enumerates every value once
+//! and numbers them densely, so counters live in the state rather than in
+//! memory.
+//!
+//! Such a state also implements `index::Indexed` through its ordinal, so a
+//! machine using it writes `sxm_index_tables!(states)` in its `XMachine`
+//! impl to get `all_states`, `state_index` and `state_from_index`, which
+//! keeps breadth-first searches over large parameter ranges free of linear
+//! scans. `graphviz::DotView::clusters` with
+//! `StateSpace::family` groups the values of each variant in diagrams.
+
+use std::fmt;
+
+/// A finite, densely numbered set of values.
+pub trait StateSpace: Copy + PartialEq + fmt::Debug + 'static {
+    /// Number of values.
+    const SIZE: usize;
+
+    /// Position of `self` in `0..SIZE`.
+    fn ordinal(self) -> usize;
+
+    /// Inverse of `ordinal`.
+    fn from_ordinal(ordinal: usize) -> Option<Self>;
+
+    /// Name shared by the values of one variant, e.g. `"WaitingForDigit"`
+    /// for every `WaitingForDigit(n)`.
+    fn family(self) -> &'static str;
+}
+
+/// Every value of `S`, in ordinal order.
+pub fn values<S: StateSpace>() -> impl Iterator<Item = S> {
+    (0..S::SIZE).filter_map(S::from_ordinal)
+}
+
+/// A parameter in `0..N`, such as a retry count or a digit position.
+///
+/// Rendered as the bare number, so `WaitingForDigit(Bounded(2))` reads as
+/// `WaitingForDigit(2)` in diagrams and test names.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Bounded<const N: usize>(usize);
+
+impl<const N: usize> Bounded<N> {
+    /// `None` if `value` is not below `N`.
+    pub fn new(value: usize) -> Option<Self> {
+        (value < N).then_some(Bounded(value))
+    }
+
+    pub fn get(self) -> usize {
+        self.0
+    }
+
+    /// The next value, or `None` at `N - 1`.
+    pub fn next(self) -> Option<Self> {
+        Self::new(self.0 + 1)
+    }
+}
+
+impl<const N: usize> fmt::Debug for Bounded<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<const N: usize> StateSpace for Bounded<N> {
+    const SIZE: usize = N;
+
+    fn ordinal(self) -> usize {
+        self.0
+    }
+
+    fn from_ordinal(ordinal: usize) -> Option<Self> {
+        Self::new(ordinal)
+    }
+
+    fn family(self) -> &'static str {
+        "Bounded"
+    }
+}
+
+impl StateSpace for bool {
+    const SIZE: usize = 2;
+
+    fn ordinal(self) -> usize {
+        self as usize
+    }
+
+    fn from_ordinal(ordinal: usize) -> Option<Self> {
+        match ordinal {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn family(self) -> &'static str {
+        "bool"
+    }
+}
+
+/// Declares a state enum implementing `state_space::StateSpace`, with `Copy`,
+/// `Clone`, `PartialEq` and `Debug` derived.
+///
+/// Variants are either plain or carry one parameter whose type implements
+/// `StateSpace`, e.g. `WaitingForDigit(Bounded<3>)`. Values are numbered in
+/// declaration order, parameters counting up within their variant. The enum
+/// also gets `all()`, returning every value as the static slice
+/// `XMachine::all_states` expects, and implements `index::Indexed`. Other
+/// attributes are kept.
+#[macro_export]
+macro_rules! sxm_state_space {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($variant:ident $(($param:ty))?),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Copy, Clone, PartialEq, Debug)]
+        $vis enum $name {
+            $($variant $(($param))?),*
+        }
+
+        impl $name {
+            /// Every value, in ordinal order.
+            pub fn all() -> &'static [Self] {
+                static ALL: ::std::sync::OnceLock<Vec<$name>> = ::std::sync::OnceLock::new();
+                ALL.get_or_init(|| $crate::state_space::values::<$name>().collect())
+            }
+        }
+
+        impl $crate::index::Indexed for $name {
+            fn all() -> &'static [Self] {
+                $name::all()
+            }
+
+            fn index(&self) -> usize {
+                $crate::state_space::StateSpace::ordinal(*self)
+            }
+        }
+
+        impl $crate::state_space::StateSpace for $name {
+            const SIZE: usize = 0 $(+ $crate::sxm_state_space!(@size $($param)?))*;
+
+            #[allow(unused_assignments)]
+            fn ordinal(self) -> usize {
+                let mut offset = 0;
+                $($crate::sxm_state_space!(@ordinal self, offset, $name::$variant $(($param))?);)*
+                unreachable!()
+            }
+
+            #[allow(unused_assignments)]
+            fn from_ordinal(mut ordinal: usize) -> Option<Self> {
+                $($crate::sxm_state_space!(@from_ordinal ordinal, $name::$variant $(($param))?);)*
+                None
+            }
+
+            fn family(self) -> &'static str {
+                match self {
+                    $($name::$variant { .. } => stringify!($variant)),*
+                }
+            }
+        }
+    };
+    (@size) => {
+        1
+    };
+    (@size $param:ty) => {
+        <$param as $crate::state_space::StateSpace>::SIZE
+    };
+    (@ordinal $state:ident, $offset:ident, $name:ident :: $variant:ident) => {
+        if let $name::$variant = $state {
+            return $offset;
+        }
+        $offset += 1;
+    };
+    (@ordinal $state:ident, $offset:ident, $name:ident :: $variant:ident ($param:ty)) => {
+        if let $name::$variant(value) = $state {
+            return $offset + <$param as $crate::state_space::StateSpace>::ordinal(value);
+        }
+        $offset += <$param as $crate::state_space::StateSpace>::SIZE;
+    };
+    (@from_ordinal $ordinal:ident, $name:ident :: $variant:ident) => {
+        if $ordinal == 0 {
+            return Some($name::$variant);
+        }
+        $ordinal -= 1;
+    };
+    (@from_ordinal $ordinal:ident, $name:ident :: $variant:ident ($param:ty)) => {
+        if $ordinal < <$param as $crate::state_space::StateSpace>::SIZE {
+            return <$param as $crate::state_space::StateSpace>::from_ordinal($ordinal).map($name::$variant);
+        }
+        $ordinal -= <$param as $crate::state_space::StateSpace>::SIZE;
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::index::Indexed;
+
+    crate::sxm_state_space! {
+        enum Lock {
+            Open,
+            WaitingForDigit(Bounded<3>),
+            Armed(bool),
+        }
+    }
+
+    #[test]
+    fn parameterised_states_are_numbered_densely() {
+        let first = Bounded::<3>::new(0).unwrap();
+        assert_eq!(Lock::SIZE, 6);
+        assert_eq!(
+            Lock::all(),
+            [
+                Lock::Open,
+                Lock::WaitingForDigit(first),
+                Lock::WaitingForDigit(first.next().unwrap()),
+                Lock::WaitingForDigit(Bounded::new(2).unwrap()),
+                Lock::Armed(false),
+                Lock::Armed(true),
+            ]
+        );
+        for (ordinal, &state) in Lock::all().iter().enumerate() {
+            assert_eq!((state.ordinal(), state.index()), (ordinal, ordinal));
+            assert_eq!(Lock::from_ordinal(ordinal), Some(state));
+        }
+        assert_eq!(Lock::from_ordinal(6), None);
+        assert_eq!(Lock::WaitingForDigit(first).family(), "WaitingForDigit");
+        assert_eq!(format!("{:?}", Lock::WaitingForDigit(first)), "WaitingForDigit(0)");
+    }
+
+    #[test]
+    fn bounded_values_stop_at_their_bound() {
+        assert_eq!(Bounded::<2>::new(2), None);
+        assert_eq!(Bounded::<2>::new(1).and_then(Bounded::next), None);
+        assert_eq!(values::<Bounded<2>>().map(Bounded::get).collect::<Vec<_>>(), [0, 1]);
+    }
+}
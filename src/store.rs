@@ -0,0 +1,283 @@
+//! Structured memory for X-machines.
+//!
+//! `Store` gathers what the crate asks of `XMachine::Memory` beyond `Clone`:
+//! `PartialEq`/`Eq` and `Hash` for deduplicating configurations, `Debug`, a
+//! field-wise diff for traces and reports, and a per-field abstraction for
+//! grouping configurations. Searches, analyses and the executor require it
+//! wherever they compare or render memories. A struct declared with
+//! `sxm_store!` implements it field by field; plain values such as integers,
+//! strings, `Vec`s and options implement it as one field named `value`, and
+//! pairs, such as the memories of `Extended` and `sxm_regions!` machines,
+//! as the fields of both halves.
+//!
+//! Fields marked `#[sxm(ghost)]` are spec-only bookkeeping, such as counters read
+//! by invariants, that no implementation is expected to hold. They still
+//! distinguish configurations, but `Store::diff` and `Store::observably_eq`
+//! leave them out, so they never show in diffs meant for implementers nor
+//! make a comparison with the SUT's state fail. `Store::ghost_diff` reports
+//! them separately.
+
+/// One field whose value differs between two stores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    /// `Debug` rendering of the old value.
+    pub before: String,
+    /// `Debug` rendering of the new value.
+    pub after: String,
+}
+
+/// A memory with named fields, usually implemented through `sxm_store!`.
+pub trait Store: Clone + Eq + std::hash::Hash + std::fmt::Debug {
+    /// Non-ghost fields that differ from `self` to `other`, in declaration order.
+    fn diff(&self, other: &Self) -> Vec<FieldChange>;
+
+    /// Ghost fields that differ from `self` to `other`, in declaration order.
+    fn ghost_diff(&self, _other: &Self) -> Vec<FieldChange> {
+        Vec::new()
+    }
+
+    /// Names of the ghost fields, in declaration order.
+    fn ghost_fields() -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// `true` if the stores agree on every non-ghost field, the equality to
+    /// check a store reported by an implementation against the spec's.
+    fn observably_eq(&self, other: &Self) -> bool {
+        self.diff(other).is_empty()
+    }
+
+    /// Every field with its abstracted value rendered with `Debug`, in
+    /// declaration order. Fields without an abstraction are rendered as is.
+    /// Equal abstractions mark configurations a diagram or report may merge.
+    fn abstraction(&self) -> Vec<(&'static str, String)>;
+}
+
+/// A value without fields of its own, reported as a single field `value`.
+macro_rules! value_store {
+    ($($ty:ty $(, $param:ident)?;)*) => {
+        $(
+            impl$(<$param: Clone + Eq + std::hash::Hash + std::fmt::Debug>)? Store for $ty {
+                fn diff(&self, other: &Self) -> Vec<FieldChange> {
+                    if self == other {
+                        return Vec::new();
+                    }
+                    vec![FieldChange {
+                        field: "value",
+                        before: format!("{:?}", self),
+                        after: format!("{:?}", other),
+                    }]
+                }
+
+                fn abstraction(&self) -> Vec<(&'static str, String)> {
+                    vec![("value", format!("{:?}", self))]
+                }
+            }
+        )*
+    };
+}
+
+value_store! {
+    (); bool; char; String; &'static str;
+    u8; u16; u32; u64; u128; usize;
+    i8; i16; i32; i64; i128; isize;
+    Vec<V>, V; Option<V>, V;
+}
+
+/// The fields of both halves, the first half's first.
+impl<A: Store, B: Store> Store for (A, B) {
+    fn diff(&self, other: &Self) -> Vec<FieldChange> {
+        let mut changes = self.0.diff(&other.0);
+        changes.extend(self.1.diff(&other.1));
+        changes
+    }
+
+    fn ghost_diff(&self, other: &Self) -> Vec<FieldChange> {
+        let mut changes = self.0.ghost_diff(&other.0);
+        changes.extend(self.1.ghost_diff(&other.1));
+        changes
+    }
+
+    fn ghost_fields() -> Vec<&'static str> {
+        let mut fields = A::ghost_fields();
+        fields.extend(B::ghost_fields());
+        fields
+    }
+
+    fn observably_eq(&self, other: &Self) -> bool {
+        self.0.observably_eq(&other.0) && self.1.observably_eq(&other.1)
+    }
+
+    fn abstraction(&self) -> Vec<(&'static str, String)> {
+        let mut fields = self.0.abstraction();
+        fields.extend(self.1.abstraction());
+        fields
+    }
+}
+
+#[doc(hidden)]
+pub fn abstract_field<V, A: std::fmt::Debug>(value: &V, abstraction: impl FnOnce(&V) -> A) -> String {
+    format!("{:?}", abstraction(value))
+}
+
+/// Declares a memory struct implementing `store::Store`, with `Clone`,
+/// `PartialEq`, `Eq` and `Hash` derived. `Debug` must be derived or
+/// implemented as well.
+///
+/// A field may be followed by `=> abstraction`, a closure from a reference to
+/// the field to the value `Store::abstraction` reports, e.g.
+/// `pub current_sequence: Vec<u8> => |digits| digits.len(),` to keep only the
+/// number of digits. A field may carry `#[sxm(ghost)]` to mark it spec-only.
+/// Other attributes, on the struct and on its fields, such as doc comments,
+/// `#[allow]` or `#[cfg]`, are kept.
+#[macro_export]
+macro_rules! sxm_store {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($fields:tt)*
+        }
+    ) => {
+        $crate::sxm_store!(@field [$(#[$meta])*] [$vis] $name [] [] false $($fields)*);
+    };
+    // Fields are read one attribute at a time, setting aside `#[sxm(ghost)]`
+    // and keeping the others, then collected as
+    // `{ [attributes] ghost visibility name [type] [abstraction] }`.
+    (@field $meta:tt $vis:tt $name:ident [$($done:tt)*] [$($attrs:tt)*] $ghost:tt) => {
+        $crate::sxm_store!(@emit $meta $vis $name $($done)*);
+    };
+    (@field $meta:tt $vis:tt $name:ident $done:tt $attrs:tt $ghost:tt #[sxm(ghost)] $($rest:tt)*) => {
+        $crate::sxm_store!(@field $meta $vis $name $done $attrs true $($rest)*);
+    };
+    (@field $meta:tt $vis:tt $name:ident $done:tt [$($attrs:tt)*] $ghost:tt #[$($attr:tt)*] $($rest:tt)*) => {
+        $crate::sxm_store!(@field $meta $vis $name $done [$($attrs)* #[$($attr)*]] $ghost $($rest)*);
+    };
+    (
+        @field $meta:tt $vis:tt $name:ident [$($done:tt)*] $attrs:tt $ghost:tt
+        $fvis:vis $field:ident : $ty:ty $(=> $abstraction:expr)? $(, $($rest:tt)*)?
+    ) => {
+        $crate::sxm_store!(
+            @field $meta $vis $name
+            [$($done)* { $attrs $ghost [$fvis] $field [$ty] [$($abstraction)?] }]
+            [] false $($($rest)*)?
+        );
+    };
+    (
+        @emit [$($meta:tt)*] [$vis:vis] $name:ident
+        $({ [$($attrs:tt)*] $ghost:tt [$fvis:vis] $field:ident [$ty:ty] [$($abstraction:expr)?] })*
+    ) => {
+        $($meta)*
+        #[derive(Clone, PartialEq, Eq, Hash)]
+        $vis struct $name {
+            $($($attrs)* $fvis $field: $ty),*
+        }
+
+        impl $crate::store::Store for $name {
+            fn diff(&self, other: &Self) -> Vec<$crate::store::FieldChange> {
+                let mut changes = Vec::new();
+                $(
+                    if !$ghost && self.$field != other.$field {
+                        changes.push($crate::store::FieldChange {
+                            field: stringify!($field),
+                            before: format!("{:?}", self.$field),
+                            after: format!("{:?}", other.$field),
+                        });
+                    }
+                )*
+                changes
+            }
+
+            fn ghost_diff(&self, other: &Self) -> Vec<$crate::store::FieldChange> {
+                let mut changes = Vec::new();
+                $(
+                    if $ghost && self.$field != other.$field {
+                        changes.push($crate::store::FieldChange {
+                            field: stringify!($field),
+                            before: format!("{:?}", self.$field),
+                            after: format!("{:?}", other.$field),
+                        });
+                    }
+                )*
+                changes
+            }
+
+            fn ghost_fields() -> Vec<&'static str> {
+                let mut fields = Vec::new();
+                $(
+                    if $ghost {
+                        fields.push(stringify!($field));
+                    }
+                )*
+                fields
+            }
+
+            fn abstraction(&self) -> Vec<(&'static str, String)> {
+                vec![$((stringify!($field), $crate::sxm_store!(@abstract self.$field $(, $abstraction)?))),*]
+            }
+        }
+    };
+    (@abstract $value:expr) => {
+        format!("{:?}", $value)
+    };
+    (@abstract $value:expr, $abstraction:expr) => {
+        $crate::store::abstract_field(&$value, $abstraction)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::sxm_store! {
+        #[derive(Debug)]
+        struct Counter {
+            count: u32,
+            #[sxm(ghost)]
+            calls: u32,
+        }
+    }
+
+    crate::sxm_store! {
+        /// A store whose fields carry their own attributes.
+        #[derive(Debug)]
+        pub(crate) struct Gauge {
+            /// Reported by the implementation.
+            pub(crate) level: u8 => |level| *level > 3,
+            /// Spec-only: how often the level was checked.
+            #[allow(dead_code)]
+            #[sxm(ghost)]
+            pub(crate) checks: u8,
+        }
+    }
+
+    #[test]
+    fn pairs_report_the_fields_of_both_halves() {
+        let before = (Counter { count: 1, calls: 1 }, 7u8);
+        let after = (Counter { count: 2, calls: 2 }, 7u8);
+        let fields: Vec<&str> = before.diff(&after).iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["count"]);
+        assert_eq!(before.ghost_diff(&after)[0].field, "calls");
+        assert_eq!(<(Counter, u8)>::ghost_fields(), ["calls"]);
+        let abstraction: Vec<String> =
+            before.abstraction().iter().map(|(field, value)| format!("{}={}", field, value)).collect();
+        assert_eq!(abstraction, ["count=1", "calls=1", "value=7"]);
+    }
+
+    #[test]
+    fn documented_fields_keep_their_attributes() {
+        assert_eq!(Gauge::ghost_fields(), ["checks"]);
+        let (low, high) = (Gauge { level: 1, checks: 0 }, Gauge { level: 5, checks: 1 });
+        let fields: Vec<&str> = low.diff(&high).iter().map(|change| change.field).collect();
+        assert_eq!(fields, ["level"]);
+        assert_eq!(high.abstraction(), [("level", "true".to_string()), ("checks", "1".to_string())]);
+    }
+
+    #[test]
+    fn plain_values_are_one_field() {
+        assert!(3u32.diff(&3).is_empty());
+        let change = &3u32.diff(&4)[0];
+        assert_eq!((change.field, change.before.as_str(), change.after.as_str()), ("value", "3", "4"));
+        assert!(!3u32.observably_eq(&4));
+    }
+}
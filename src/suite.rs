@@ -0,0 +1,476 @@
+use crate::feasibility::{FeasibilityOracle, NoOracle};
+use crate::isolation::{self, PhiPanicOf};
+use crate::mbt::{PathConstraints, PathConstraintsOf, SxMTester, TestCase, TestCaseOf};
+use crate::runner::{MachineRunner, StepError};
+use crate::store::Store;
+use crate::XMachine;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// Seed for every randomized generation step of a suite.
+///
+/// Randomness comes from a crate-internal, fully specified PRNG, so a given
+/// seed yields the same suite on every run and platform.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GenerationSeed(pub u64);
+
+impl From<u64> for GenerationSeed {
+    fn from(seed: u64) -> Self {
+        GenerationSeed(seed)
+    }
+}
+
+impl fmt::Display for GenerationSeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#018x}", self.0)
+    }
+}
+
+/// A generated suite together with the seed it was generated from.
+#[derive(Debug)]
+pub struct TestSuite<Input, Output, State, Memory> {
+    pub seed: GenerationSeed,
+    pub cases: Vec<TestCase<Input, Output, State, Memory>>,
+}
+
+/// `TestSuite` specialised to the symbols and memory of machine `T`.
+pub type TestSuiteOf<T> = TestSuite<
+    <T as XMachine>::Input,
+    <T as XMachine>::Output,
+    <T as XMachine>::State,
+    <T as XMachine>::Memory,
+>;
+
+/// What a case covers, for `TestSuite::minimize`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CoverageCriterion {
+    /// States the spec visits.
+    States,
+    /// (state, phi) pairs the spec takes.
+    Transitions,
+    /// Phis the spec applies, in any state.
+    Phis,
+    /// (state, phi) pairs together with whether the guard accepted or rejected the input.
+    Guards,
+}
+
+/// Order in which `TestSuite::prioritize` puts cases, so time-boxed runs
+/// execute the most valuable ones first. Ties keep the generation order.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Prioritization {
+    /// Cases exercising the transitions fewest other cases exercise come first.
+    RareTransitionsFirst,
+    /// Cases with the shortest setup sequence come first.
+    ShortestSetupFirst,
+    /// Greedily picks the case adding the most transitions not yet covered;
+    /// once everything is covered the rest follow in generation order.
+    IncrementalCoverage,
+}
+
+impl<Input, Output, State, Memory> TestSuite<Input, Output, State, Memory> {
+    /// Reorders the cases according to `strategy`.
+    ///
+    /// The transitions of a case are those the spec `T` takes replaying the
+    /// case from the initial configuration, as in `minimize`.
+    pub fn prioritize<T>(&mut self, strategy: Prioritization)
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+    {
+        let mut cases: Vec<(BTreeSet<Item>, TestCaseOf<T>)> = std::mem::take(&mut self.cases)
+            .into_iter()
+            .map(|case| (coverage::<T>(&case, CoverageCriterion::Transitions), case))
+            .collect();
+
+        match strategy {
+            Prioritization::RareTransitionsFirst => {
+                let mut frequency: BTreeMap<Item, usize> = BTreeMap::new();
+                for (transitions, _) in &cases {
+                    for &transition in transitions {
+                        *frequency.entry(transition).or_default() += 1;
+                    }
+                }
+                cases.sort_by_key(|(transitions, _)| {
+                    transitions.iter().map(|t| frequency[t]).min().unwrap_or(usize::MAX)
+                });
+            }
+            Prioritization::ShortestSetupFirst => {
+                cases.sort_by_key(|(_, case)| case.setup_sequence.len());
+            }
+            Prioritization::IncrementalCoverage => {
+                let mut covered = BTreeSet::new();
+                let mut ordered = Vec::with_capacity(cases.len());
+                loop {
+                    let best = cases
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, (transitions, _))| (idx, transitions.difference(&covered).count()))
+                        .filter(|&(_, new)| new > 0)
+                        .max_by_key(|&(idx, new)| (new, std::cmp::Reverse(idx)));
+                    let Some((idx, _)) = best else {
+                        break;
+                    };
+                    let (transitions, case) = cases.remove(idx);
+                    covered.extend(transitions.iter().copied());
+                    ordered.push((transitions, case));
+                }
+                ordered.append(&mut cases);
+                cases = ordered;
+            }
+        }
+        self.cases = cases.into_iter().map(|(_, case)| case).collect();
+    }
+
+    /// Drops cases that add nothing to `criterion`, keeping the others in
+    /// generation order, and returns how many were dropped.
+    ///
+    /// Cases are kept by greedy set cover: the case covering the most items
+    /// not yet covered first, ties going to the earlier case. What a case
+    /// covers is what the spec `T` does replaying its setup sequence, test
+    /// input and verification sequence from the initial configuration. A test
+    /// input the spec rejects, as in robustness cases, covers its (state,
+    /// input) pair under every criterion, so such a case is only dropped for
+    /// another checking the same rejection. Cases the replay covers nothing
+    /// for, e.g. as the spec rejects their setup sequence, are kept, since
+    /// what they check cannot be told.
+    pub fn minimize<T>(&mut self, criterion: CoverageCriterion) -> usize
+    where
+        T: XMachine<Input = Input, Output = Output, State = State, Memory = Memory>,
+    {
+        let items: Vec<BTreeSet<Item>> = self.cases.iter().map(|case| coverage::<T>(case, criterion)).collect();
+        let mut keep: Vec<bool> = items.iter().map(BTreeSet::is_empty).collect();
+        let mut covered = BTreeSet::new();
+        loop {
+            let best = items
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| !keep[*idx])
+                .map(|(idx, case_items)| (idx, case_items.difference(&covered).count()))
+                .filter(|&(_, new)| new > 0)
+                .max_by_key(|&(idx, new)| (new, std::cmp::Reverse(idx)));
+            let Some((idx, _)) = best else {
+                break;
+            };
+            keep[idx] = true;
+            covered.extend(items[idx].iter().copied());
+        }
+
+        let before = self.cases.len();
+        let mut keep = keep.into_iter();
+        self.cases.retain(|_| keep.next().unwrap_or(false));
+        before - self.cases.len()
+    }
+}
+
+/// Something a case covers, by the ordinals of its symbols.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Item {
+    /// A state visited, under `CoverageCriterion::States`.
+    State(usize),
+    /// A phi applied, under `CoverageCriterion::Phis`.
+    Phi(usize),
+    /// A (state, phi) pair taken, under `CoverageCriterion::Transitions`.
+    Transition { from: usize, phi: usize },
+    /// A (state, phi) pair with the outcome of its guard, under
+    /// `CoverageCriterion::Guards`.
+    Guard { from: usize, phi: usize, accepted: bool },
+    /// A test input the spec rejects, under every criterion.
+    Rejection { state: usize, input: usize },
+}
+
+/// What the spec covers replaying the case's setup sequence, test input and
+/// verification sequence from the initial configuration. A rejected test
+/// input leaves the configuration as it is, like on the SUT, and the replay
+/// goes on; a rejected verification input ends it. Nothing is covered if the
+/// spec rejects the setup sequence.
+fn coverage<T: XMachine>(case: &TestCaseOf<T>, criterion: CoverageCriterion) -> BTreeSet<Item> {
+    let state_index = |state| T::state_index(state).map_or(usize::MAX, |idx| idx.0);
+    let phi_index = |phi| T::phi_index(phi).map_or(usize::MAX, |idx| idx.0);
+
+    let mut runner = MachineRunner::<T>::new();
+    let mut items = BTreeSet::new();
+    if criterion == CoverageCriterion::States {
+        items.insert(Item::State(state_index(runner.state())));
+    }
+    let inputs = case.setup_sequence.iter().chain(std::iter::once(&case.test_input)).chain(&case.verification_sequence);
+    for (idx, input) in inputs.enumerate() {
+        let state = runner.state();
+        match runner.step(input) {
+            Ok(transition) => {
+                let (from, phi) = (state_index(transition.from), phi_index(transition.phi));
+                items.insert(match criterion {
+                    CoverageCriterion::States => Item::State(state_index(transition.to)),
+                    CoverageCriterion::Transitions => Item::Transition { from, phi },
+                    CoverageCriterion::Phis => Item::Phi(phi),
+                    CoverageCriterion::Guards => Item::Guard { from, phi, accepted: true },
+                });
+                continue;
+            }
+            Err(StepError::GuardRejected { state, phi }) if criterion == CoverageCriterion::Guards => {
+                items.insert(Item::Guard {
+                    from: state_index(state),
+                    phi: phi_index(phi),
+                    accepted: false,
+                });
+            }
+            Err(_) => {}
+        }
+        if idx < case.setup_sequence.len() {
+            return BTreeSet::new();
+        }
+        if idx > case.setup_sequence.len() {
+            break;
+        }
+        items.insert(Item::Rejection {
+            state: state_index(state),
+            input: T::input_index(input).map_or(usize::MAX, |idx| idx.0),
+        });
+    }
+    items
+}
+
+/// Operational profile used for weighted random walks.
+pub type Profile<'a, T> = &'a dyn Fn(<T as XMachine>::State, &<T as XMachine>::Input) -> f64;
+
+/// Distinguishing sequences used to verify the state reached by a test.
+pub type Distinguishing<'a, T> = &'a dyn Fn(<T as XMachine>::State) -> Vec<<T as XMachine>::Input>;
+
+/// Assembles a suite from the `SxMTester` generators.
+///
+/// Cases always appear in the same order, whatever order the builder methods
+/// were called in: logic, phi coverage, robustness, ignores, then random
+/// walks. Within a generator, cases follow the declaration order of
+/// `all_states()`, `all_inputs()` and `all_phis()`, and random walks depend
+/// only on the seed.
+/// Building twice with the same configuration therefore yields identical suites.
+pub struct TestSuiteBuilder<'a, T: XMachine> {
+    seed: GenerationSeed,
+    logic: Option<Distinguishing<'a, T>>,
+    phi_coverage: Option<Distinguishing<'a, T>>,
+    oracle: &'a dyn FeasibilityOracle<T>,
+    constraints: PathConstraintsOf<T>,
+    robustness: bool,
+    ignores: bool,
+    walks: Option<(Profile<'a, T>, usize, usize)>,
+}
+
+impl<'a, T: XMachine> TestSuiteBuilder<'a, T> {
+    /// An empty builder seeded with `GenerationSeed::default()`.
+    pub fn new() -> Self {
+        Self {
+            seed: GenerationSeed::default(),
+            logic: None,
+            phi_coverage: None,
+            oracle: &NoOracle,
+            constraints: PathConstraints::new(),
+            robustness: false,
+            ignores: false,
+            walks: None,
+        }
+    }
+
+    pub fn seed(mut self, seed: impl Into<GenerationSeed>) -> Self {
+        self.seed = seed.into();
+        self
+    }
+
+    /// Includes `SxMTester::generate_logic_tests`.
+    pub fn logic(mut self, distinguishing_sequences: Distinguishing<'a, T>) -> Self {
+        self.logic = Some(distinguishing_sequences);
+        self
+    }
+
+    /// Includes `SxMTester::generate_phi_coverage_tests`.
+    pub fn phi_coverage(mut self, distinguishing_sequences: Distinguishing<'a, T>) -> Self {
+        self.phi_coverage = Some(distinguishing_sequences);
+        self
+    }
+
+    /// Guides the phi coverage search, as in
+    /// `SxMTester::generate_phi_coverage_tests_with_oracle`.
+    pub fn feasibility_oracle(mut self, oracle: &'a dyn FeasibilityOracle<T>) -> Self {
+        self.oracle = oracle;
+        self
+    }
+
+    /// Restricts the setup sequences of the logic, phi coverage, robustness
+    /// and ignore cases, as in `SxMTester::generate_logic_tests_with_constraints`.
+    /// Random walks are not affected.
+    pub fn path_constraints(mut self, constraints: PathConstraintsOf<T>) -> Self {
+        self.constraints = constraints;
+        self
+    }
+
+    /// Includes `SxMTester::generate_robustness_tests`.
+    pub fn robustness(mut self) -> Self {
+        self.robustness = true;
+        self
+    }
+
+    /// Includes `SxMTester::generate_ignore_tests`, checking inputs handled
+    /// by an explicit ignore apart from those with no phi.
+    pub fn ignores(mut self) -> Self {
+        self.ignores = true;
+        self
+    }
+
+    /// Includes `cases` random walks of up to `walk_length` inputs drawn from
+    /// `profile`, as in `SxMTester::generate_profile_tests`.
+    pub fn profile_walks(mut self, profile: Profile<'a, T>, cases: usize, walk_length: usize) -> Self {
+        self.walks = Some((profile, cases, walk_length));
+        self
+    }
+
+    /// Includes `cases` random walks that pick inputs uniformly.
+    pub fn random_walks(self, cases: usize, walk_length: usize) -> Self {
+        self.profile_walks(&|_, _| 1.0, cases, walk_length)
+    }
+
+    /// Like `build`, but a phi that panics during generation is treated as
+    /// rejecting its input instead of aborting the build. Returns the suite
+    /// without the cases that needed it, and the panics caught, as
+    /// `isolation::isolate_panics` reports them.
+    pub fn build_isolated(&self) -> (TestSuiteOf<T>, Vec<PhiPanicOf<T>>)
+    where
+        T::Memory: Store,
+    {
+        isolation::isolate_panics::<T, _>(|| self.build())
+    }
+
+    pub fn build(&self) -> TestSuiteOf<T>
+    where
+        T::Memory: Store,
+    {
+        let mut cases: Vec<TestCaseOf<T>> = Vec::new();
+        if let Some(distinguishing) = self.logic {
+            cases.extend(SxMTester::generate_logic_tests_with_constraints::<T>(distinguishing, &self.constraints));
+        }
+        if let Some(distinguishing) = self.phi_coverage {
+            cases.extend(SxMTester::generate_phi_coverage_tests_with_constraints::<T>(
+                distinguishing,
+                self.oracle,
+                &self.constraints,
+            ));
+        }
+        if self.robustness {
+            cases.extend(SxMTester::generate_robustness_tests_with_constraints::<T>(&self.constraints));
+        }
+        if self.ignores {
+            cases.extend(SxMTester::generate_ignore_tests_with_constraints::<T>(&self.constraints));
+        }
+        if let Some((profile, count, walk_length)) = self.walks {
+            cases.extend(SxMTester::generate_profile_tests::<T>(profile, count, walk_length, self.seed));
+        }
+        TestSuite {
+            seed: self.seed,
+            cases,
+        }
+    }
+}
+
+impl<T: XMachine> Default for TestSuiteBuilder<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::Digicode;
+    use crate::examples::traffic_light::{LightInput, LightOutput, TrafficLight};
+
+    #[test]
+    fn minimize_counts_the_verification_sequence() {
+        let case = |name: &str, setup: Vec<LightInput>, output: LightOutput, verify: Vec<LightInput>| {
+            TestCase::builder::<TrafficLight>(name)
+                .setup(setup)
+                .input(LightInput::Tick)
+                .expect(Some(output))
+                .verify(verify)
+                .build()
+                .unwrap()
+        };
+        let mut suite = TestSuite {
+            seed: GenerationSeed::default(),
+            cases: vec![
+                case("verified cycle", vec![], LightOutput::ShowGreen, vec![LightInput::Tick, LightInput::Tick]),
+                case("two ticks", vec![LightInput::Tick], LightOutput::ShowYellow, vec![]),
+            ],
+        };
+        assert_eq!(suite.minimize::<TrafficLight>(CoverageCriterion::Transitions), 1);
+        assert_eq!(suite.cases[0].name, "verified cycle");
+    }
+
+    #[test]
+    fn prioritize_orders_by_the_transitions_each_case_takes() {
+        use LightInput::{Fault, Tick};
+
+        let case = |name: &str, setup: Vec<LightInput>, input: LightInput, output: LightOutput| {
+            TestCase::builder::<TrafficLight>(name).setup(setup).input(input).expect(Some(output)).build().unwrap()
+        };
+        // Red --ToGreen--> is taken by all four cases, Green --ToYellow--> by
+        // two, Yellow --ToRed--> and Green --FailSafe--> by one each.
+        let cases = || {
+            vec![
+                case("first tick", vec![], Tick, LightOutput::ShowGreen),
+                case("full cycle", vec![Tick, Tick], Tick, LightOutput::ShowRed),
+                case("fault when green", vec![Tick], Fault, LightOutput::ShowRed),
+                case("second tick", vec![Tick], Tick, LightOutput::ShowYellow),
+            ]
+        };
+        let order = |strategy| {
+            let mut suite = TestSuite {
+                seed: GenerationSeed::default(),
+                cases: cases(),
+            };
+            suite.prioritize::<TrafficLight>(strategy);
+            suite.cases.into_iter().map(|case| case.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            order(Prioritization::RareTransitionsFirst),
+            ["full cycle", "fault when green", "second tick", "first tick"]
+        );
+        assert_eq!(
+            order(Prioritization::ShortestSetupFirst),
+            ["first tick", "fault when green", "second tick", "full cycle"]
+        );
+        assert_eq!(
+            order(Prioritization::IncrementalCoverage),
+            ["full cycle", "fault when green", "first tick", "second tick"]
+        );
+    }
+
+    #[test]
+    fn minimize_keeps_one_robustness_case_per_rejection() {
+        let builder = TestSuiteBuilder::<Digicode>::new().robustness();
+        let mut suite = builder.build();
+        let count = suite.cases.len();
+        suite.minimize::<Digicode>(CoverageCriterion::Transitions);
+        assert_eq!(suite.cases.len(), count);
+        suite.cases.extend(builder.build().cases);
+        assert_eq!(suite.minimize::<Digicode>(CoverageCriterion::Transitions), count);
+    }
+
+    fn walks(seed: u64) -> String {
+        let suite = TestSuiteBuilder::<Digicode>::new().seed(seed).robustness().random_walks(5, 8).build();
+        assert_eq!(suite.seed, GenerationSeed(seed));
+        format!("{:?}", suite.cases)
+    }
+
+    #[test]
+    fn the_same_seed_builds_the_same_suite() {
+        assert_eq!(walks(42), walks(42));
+        assert_ne!(walks(42), walks(43));
+    }
+
+    #[test]
+    fn generators_without_randomness_ignore_the_seed() {
+        let distinguishing = |_| vec![LightInput::Tick];
+        let build = |seed: u64| {
+            let suite = TestSuiteBuilder::<TrafficLight>::new().seed(seed).logic(&distinguishing).robustness().build();
+            format!("{:?}", suite.cases)
+        };
+        assert_eq!(build(1), build(2));
+    }
+}
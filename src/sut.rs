@@ -0,0 +1,217 @@
+use crate::configuration::Configuration;
+use crate::network::{MachineNetwork, Tagged, Wiring};
+use crate::runner::MachineRunner;
+use crate::XMachine;
+
+/// Connects a system under test (SUT) to the spec machine `T`.
+///
+/// Implementations translate spec-level input symbols into whatever drives
+/// the real system and report back what it emitted, as an output symbol.
+pub trait SutAdapter<T: XMachine> {
+    /// Applies one input to the implementation.
+    /// Returns `None` if it rejected the input or produced no output.
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output>;
+
+    /// The implementation's current state, when the SUT exposes it.
+    fn observe_state(&self) -> Option<T::State> {
+        None
+    }
+
+    /// The implementation's current memory, when the SUT exposes it. The
+    /// executor and `ShadowRunner` compare it with the spec's through
+    /// `Store::observably_eq`, so fields listed by `Store::ghost_fields` may
+    /// hold anything.
+    fn observe_store(&self) -> Option<T::Memory> {
+        None
+    }
+
+    /// Puts the implementation directly into the given configuration, e.g. on a
+    /// simulator. Returns `false` if injection is unsupported, in which case
+    /// the executor replays the setup sequence instead.
+    fn inject(&mut self, _state: T::State, _store: &T::Memory) -> bool {
+        false
+    }
+
+    /// Returns the implementation to its initial configuration, e.g. by a
+    /// hardware reset line. Returns `false` if unsupported, in which case the
+    /// executor falls back on `XMachine::reset_input`.
+    fn reset(&mut self) -> bool {
+        false
+    }
+}
+
+/// The spec itself as a SUT: a reference implementation that conforms by construction.
+impl<T: XMachine> SutAdapter<T> for MachineRunner<T> {
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output> {
+        self.step(input).ok().and_then(|t| t.output)
+    }
+
+    fn observe_state(&self) -> Option<T::State> {
+        Some(self.state())
+    }
+
+    fn observe_store(&self) -> Option<T::Memory> {
+        Some(self.store().clone())
+    }
+
+    fn inject(&mut self, state: T::State, store: &T::Memory) -> bool {
+        *self = MachineRunner::from_parts(state, store.clone());
+        true
+    }
+
+    fn reset(&mut self) -> bool {
+        MachineRunner::reset(self);
+        true
+    }
+}
+
+/// Bridges spec-level symbols and the concrete data a real system exchanges,
+/// e.g. `Digit(4)` to a CAN frame and back.
+pub trait Concretizer<T: XMachine> {
+    /// What is sent to the implementation.
+    type Stimulus;
+    /// What the implementation sends back.
+    type Response;
+
+    fn concretize(&mut self, input: &T::Input) -> Self::Stimulus;
+
+    /// Maps a response to the output symbol it represents.
+    /// `None` if it stands for no output or a rejection.
+    fn abstract_response(&mut self, response: &Self::Response) -> Option<T::Output>;
+}
+
+/// A `SutAdapter` made of a `Concretizer` and a driver that delivers one
+/// stimulus to the implementation and returns its response, if any.
+pub struct ConcretizedSut<C, D> {
+    concretizer: C,
+    driver: D,
+}
+
+impl<C, D> ConcretizedSut<C, D> {
+    pub fn new(concretizer: C, driver: D) -> Self {
+        Self { concretizer, driver }
+    }
+
+    pub fn concretizer(&self) -> &C {
+        &self.concretizer
+    }
+
+    pub fn driver_mut(&mut self) -> &mut D {
+        &mut self.driver
+    }
+}
+
+impl<T, C, D> SutAdapter<T> for ConcretizedSut<C, D>
+where
+    T: XMachine,
+    C: Concretizer<T>,
+    D: FnMut(C::Stimulus) -> Option<C::Response>,
+{
+    fn apply(&mut self, input: &T::Input) -> Option<T::Output> {
+        let stimulus = self.concretizer.concretize(input);
+        let response = (self.driver)(stimulus)?;
+        self.concretizer.abstract_response(&response)
+    }
+}
+
+/// Connects an implementation of a two-component system to the spec network
+/// of `A` and `B`, seen from the environment.
+pub trait NetworkSutAdapter<A: XMachine, B: XMachine> {
+    /// Applies one environment input and returns the outputs that left the
+    /// implementation, in emission order, once it has settled.
+    fn apply(&mut self, input: &Tagged<A::Input, B::Input>) -> Vec<Tagged<A::Output, B::Output>>;
+
+    /// Whether no internal message is pending, when the SUT exposes it.
+    fn is_stable(&self) -> Option<bool> {
+        None
+    }
+
+    /// The configurations of both components, when the SUT exposes them.
+    /// `Executor::run_network_case` checks that a rejected input leaves them
+    /// unchanged, ghost fields aside.
+    fn observe_configurations(&self) -> Option<(Configuration<A>, Configuration<B>)> {
+        None
+    }
+}
+
+/// The spec network itself as a SUT.
+impl<A, B, W> NetworkSutAdapter<A, B> for MachineNetwork<A, B, W>
+where
+    A: XMachine,
+    B: XMachine,
+    W: Wiring<A, B>,
+{
+    fn apply(&mut self, input: &Tagged<A::Input, B::Input>) -> Vec<Tagged<A::Output, B::Output>> {
+        self.process_input(input.clone()).environment_outputs
+    }
+
+    fn is_stable(&self) -> Option<bool> {
+        Some(MachineNetwork::is_stable(self))
+    }
+
+    fn observe_configurations(&self) -> Option<(Configuration<A>, Configuration<B>)> {
+        Some((self.a().configuration().clone(), self.b().configuration().clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Door, DoorInputAlphabet, DoorOutputAlphabet, DoorState};
+    use crate::executor::Executor;
+    use crate::suite::TestSuiteBuilder;
+
+    /// The door's wire protocol: one command byte out, one status line back.
+    struct Wire;
+
+    impl Concretizer<Door> for Wire {
+        type Stimulus = u8;
+        type Response = &'static str;
+
+        fn concretize(&mut self, input: &DoorInputAlphabet) -> u8 {
+            match input {
+                DoorInputAlphabet::Open => b'O',
+                DoorInputAlphabet::Close => b'C',
+            }
+        }
+
+        fn abstract_response(&mut self, response: &&'static str) -> Option<DoorOutputAlphabet> {
+            match *response {
+                "opening" => Some(DoorOutputAlphabet::DoorOpens),
+                "closing" => Some(DoorOutputAlphabet::DoorCloses),
+                "already open" => Some(DoorOutputAlphabet::OpenIgnored),
+                "already closed" => Some(DoorOutputAlphabet::CloseIgnored),
+                _ => None,
+            }
+        }
+    }
+
+    /// A door controller speaking the wire protocol.
+    fn controller() -> impl FnMut(u8) -> Option<&'static str> {
+        let mut open = false;
+        move |command| {
+            let response = match (command, open) {
+                (b'O', false) => "opening",
+                (b'O', true) => "already open",
+                (b'C', true) => "closing",
+                (b'C', false) => "already closed",
+                _ => return None,
+            };
+            open = command == b'O';
+            Some(response)
+        }
+    }
+
+    #[test]
+    fn a_concretized_implementation_passes_the_suite() {
+        let distinguishing = |_| vec![DoorInputAlphabet::Open];
+        let cases = TestSuiteBuilder::<Door>::new().logic(&distinguishing).build().cases;
+        let report = Executor::run_suite::<Door, _>(&cases, || ConcretizedSut::new(Wire, controller()));
+        assert_eq!(report.passed(), cases.len());
+
+        let mut sut = ConcretizedSut::new(Wire, controller());
+        let closed = SutAdapter::<Door>::apply(&mut sut, &DoorInputAlphabet::Close);
+        assert_eq!(closed, Some(DoorOutputAlphabet::CloseIgnored));
+        assert!(!SutAdapter::<Door>::inject(&mut sut, DoorState::Opened, &0));
+    }
+}
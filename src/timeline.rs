@@ -0,0 +1,248 @@
+//! Sequence-diagram export of `MachineNetwork` traces, the usual way to
+//! review communication between components.
+//!
+//! Each `StepReport` becomes a section headed by the environment input. Its
+//! micro-steps become messages between the environment and the components,
+//! in execution order: an input arrives from the environment or from the
+//! peer that routed it, the transition it takes is noted over the component,
+//! and outputs leaving the network go back to the environment. Rejected
+//! inputs are drawn as lost messages, and `OutputKind::Error` outputs as
+//! dotted (Mermaid) or red (PlantUML) messages.
+
+use crate::labels;
+use crate::network::{StepReport, Tagged};
+use crate::runner::StepResultOf;
+use crate::{OutputKind, Visibility, XMachine};
+use std::fmt::Write;
+
+/// Sequence-diagram syntax produced by `sequence_diagram`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SequenceFormat {
+    Mermaid,
+    PlantUml,
+}
+
+/// Renders `reports`, one per environment input in processing order, as a
+/// sequence diagram with the components named `a_name` and `b_name`.
+pub fn sequence_diagram<A: XMachine, B: XMachine>(
+    a_name: &str,
+    b_name: &str,
+    reports: &[StepReport<A, B>],
+    format: SequenceFormat,
+) -> String {
+    let mut diagram = Diagram {
+        format,
+        output: String::new(),
+    };
+    diagram.header(a_name, b_name);
+    for report in reports {
+        let Some(first) = report.micro_steps.first() else {
+            continue;
+        };
+        diagram.section(&match &first.input {
+            Tagged::A(input) => format!("{} receives {}", a_name, labels::input::<A>(input)),
+            Tagged::B(input) => format!("{} receives {}", b_name, labels::input::<B>(input)),
+        });
+        for step in &report.micro_steps {
+            let from = if step.external { ENVIRONMENT } else { peer(&step.input) };
+            let (to, input) = match &step.input {
+                Tagged::A(input) => ("A", labels::input::<A>(input)),
+                Tagged::B(input) => ("B", labels::input::<B>(input)),
+            };
+            let input = match step.port {
+                Some(port) if !step.external => format!("{} via {}", input, port),
+                _ => input,
+            };
+            match &step.result {
+                Tagged::A(result) => diagram.step::<A>(from, to, &input, result, step.routed),
+                Tagged::B(result) => diagram.step::<B>(from, to, &input, result, step.routed),
+            }
+        }
+        for message in &report.dropped {
+            let (to, label) = match message {
+                Tagged::A(input) => ("A", labels::input::<A>(input)),
+                Tagged::B(input) => ("B", labels::input::<B>(input)),
+            };
+            diagram.note(to, &format!("dropped {}", label));
+        }
+        if !report.stable {
+            diagram.note_all(&format!("step budget exhausted after {} micro-steps", report.micro_steps.len()));
+        }
+    }
+    diagram.footer();
+    diagram.output
+}
+
+const ENVIRONMENT: &str = "Env";
+
+/// The component that routed a message delivered to the owner of `input`.
+fn peer<XA, XB>(input: &Tagged<XA, XB>) -> &'static str {
+    match input {
+        Tagged::A(_) => "B",
+        Tagged::B(_) => "A",
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Arrow {
+    Normal,
+    /// A rejected input.
+    Lost,
+    /// An `OutputKind::Error` output.
+    Error,
+}
+
+struct Diagram {
+    format: SequenceFormat,
+    output: String,
+}
+
+impl Diagram {
+    fn header(&mut self, a_name: &str, b_name: &str) {
+        let (a_name, b_name) = (self.escape(a_name), self.escape(b_name));
+        match self.format {
+            SequenceFormat::Mermaid => {
+                writeln!(self.output, "sequenceDiagram").unwrap();
+                writeln!(self.output, "    actor {} as Environment", ENVIRONMENT).unwrap();
+                writeln!(self.output, "    participant A as {}", a_name).unwrap();
+                writeln!(self.output, "    participant B as {}", b_name).unwrap();
+            }
+            SequenceFormat::PlantUml => {
+                writeln!(self.output, "@startuml").unwrap();
+                writeln!(self.output, "actor Environment as {}", ENVIRONMENT).unwrap();
+                writeln!(self.output, "participant \"{}\" as A", a_name).unwrap();
+                writeln!(self.output, "participant \"{}\" as B", b_name).unwrap();
+            }
+        }
+    }
+
+    fn footer(&mut self) {
+        if self.format == SequenceFormat::PlantUml {
+            writeln!(self.output, "@enduml").unwrap();
+        }
+    }
+
+    /// Starts the messages of one environment input.
+    fn section(&mut self, title: &str) {
+        let title = self.escape(title);
+        match self.format {
+            SequenceFormat::Mermaid => {
+                writeln!(self.output, "    Note over {},B: {}", ENVIRONMENT, title).unwrap();
+            }
+            SequenceFormat::PlantUml => writeln!(self.output, "== {} ==", title).unwrap(),
+        }
+    }
+
+    /// One micro-step of component `to` of machine `M`, consuming `input` sent by `from`.
+    fn step<M: XMachine>(&mut self, from: &str, to: &str, input: &str, result: &StepResultOf<M>, routed: bool) {
+        let transition = match result {
+            Ok(transition) => transition,
+            Err(_) => {
+                self.message(from, to, input, Arrow::Lost);
+                self.note(to, "rejected");
+                return;
+            }
+        };
+        self.message(from, to, input, Arrow::Normal);
+        self.note(
+            to,
+            &format!(
+                "{} to {} by {}",
+                labels::state::<M>(transition.from),
+                labels::state::<M>(transition.to),
+                labels::phi::<M>(transition.phi)
+            ),
+        );
+        // Routed outputs are drawn when the peer consumes them.
+        if let Some(output) = &transition.output {
+            if !routed || M::output_visibility(output) == Some(Visibility::Shared) {
+                let arrow = match M::output_kind(output) {
+                    OutputKind::Regular => Arrow::Normal,
+                    OutputKind::Error => Arrow::Error,
+                };
+                self.message(to, ENVIRONMENT, &labels::output::<M>(output), arrow);
+            }
+        }
+    }
+
+    fn message(&mut self, from: &str, to: &str, label: &str, arrow: Arrow) {
+        let label = self.escape(label);
+        let arrow = match (self.format, arrow) {
+            (SequenceFormat::Mermaid, Arrow::Normal) => "->>",
+            (SequenceFormat::Mermaid, Arrow::Lost) => "-x",
+            (SequenceFormat::Mermaid, Arrow::Error) => "-->>",
+            (SequenceFormat::PlantUml, Arrow::Normal) => " -> ",
+            (SequenceFormat::PlantUml, Arrow::Lost) => " ->x ",
+            (SequenceFormat::PlantUml, Arrow::Error) => " -[#red]> ",
+        };
+        match self.format {
+            SequenceFormat::Mermaid => writeln!(self.output, "    {}{}{}: {}", from, arrow, to, label),
+            SequenceFormat::PlantUml => writeln!(self.output, "{}{}{} : {}", from, arrow, to, label),
+        }
+        .unwrap();
+    }
+
+    fn note(&mut self, over: &str, text: &str) {
+        let text = self.escape(text);
+        match self.format {
+            SequenceFormat::Mermaid => writeln!(self.output, "    Note over {}: {}", over, text),
+            SequenceFormat::PlantUml => writeln!(self.output, "note over {} : {}", over, text),
+        }
+        .unwrap();
+    }
+
+    fn note_all(&mut self, text: &str) {
+        let text = self.escape(text);
+        match self.format {
+            SequenceFormat::Mermaid => writeln!(self.output, "    Note over {},B: {}", ENVIRONMENT, text),
+            SequenceFormat::PlantUml => writeln!(self.output, "note over {}, B : {}", ENVIRONMENT, text),
+        }
+        .unwrap();
+    }
+
+    /// Mermaid ends a message at `;` and a line break ends both syntaxes.
+    fn escape(&self, text: &str) -> String {
+        let text = text.replace('\n', " ");
+        match self.format {
+            SequenceFormat::Mermaid => text.replace(';', "#59;"),
+            SequenceFormat::PlantUml => text,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet as In, Door, SecureDoor};
+
+    fn run(inputs: &[In]) -> Vec<StepReport<Digicode, Door>> {
+        let mut door = SecureDoor::new();
+        inputs.iter().map(|input| door.process_input(Tagged::A(input.clone()))).collect()
+    }
+
+    #[test]
+    fn routed_messages_go_from_component_to_component() {
+        let reports = run(&[In::Digit(4), In::Digit(9), In::Digit(2), In::OkEnter]);
+        assert_eq!(
+            sequence_diagram("Keypad", "Door", &reports[3..], SequenceFormat::PlantUml),
+            "@startuml\n\
+             actor Environment as Env\n\
+             participant \"Keypad\" as A\n\
+             participant \"Door\" as B\n\
+             == Keypad receives OkEnter ==\n\
+             Env -> A : OkEnter\n\
+             note over A : Accepting to CodeEntered by Finish\n\
+             A -> B : Open\n\
+             note over B : Closed to Opened by OpenDoor\n\
+             B -> Env : DoorOpens\n\
+             @enduml\n"
+        );
+    }
+
+    #[test]
+    fn rejected_inputs_are_lost_messages() {
+        let diagram = sequence_diagram("Keypad; front", "Door", &run(&[In::DoorCloses]), SequenceFormat::Mermaid);
+        assert!(diagram.contains("    participant A as Keypad#59; front\n"));
+        assert!(diagram.contains("    Env-xA: DoorCloses\n    Note over A: rejected\n"));
+    }
+}
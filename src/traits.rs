@@ -1,8 +1,65 @@
+use crate::index::{InputIndex, PhiIndex, StateIndex};
+use crate::labels::{Labeling, Symbol};
+use std::borrow::Cow;
+
+/// Where a symbol travels when machines are composed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Visibility {
+    /// Exchanged only between the composed machines.
+    Internal,
+    /// Exchanged with the environment.
+    External,
+    /// Delivered to the peer machine and also emitted to the environment.
+    Shared,
+}
+
+/// What an output signals, for machines that report errors on a channel of
+/// their own rather than as regular symbols of Γ.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    Regular,
+    /// The transition reports an error condition, e.g. a fault code.
+    Error,
+}
+
+/// How the specification treats an input in a given state.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TransitionSpec<Phi> {
+    /// The input triggers `Phi`.
+    Defined(Phi),
+    /// The input has no transition and the implementation must reject it.
+    Forbidden,
+    /// "Don't care": the spec leaves the behaviour open.
+    Unspecified,
+}
+
+/// A property of the memory that must hold whenever the machine is in a given state.
+pub struct StateInvariant<Memory> {
+    /// What the state guarantees, in prose.
+    pub doc: &'static str,
+    pub check: fn(&Memory) -> bool,
+}
+
+impl<Memory> Clone for StateInvariant<Memory> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Memory> Copy for StateInvariant<Memory> {}
+
+impl<Memory> core::fmt::Debug for StateInvariant<Memory> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StateInvariant").field("doc", &self.doc).finish_non_exhaustive()
+    }
+}
+
 /// The core X-Machine Trait.
 ///
 /// In theory, an X-Machine is M = (Sigma, Gamma, Q, M, Phi, F, m0, q0).
-/// This trait maps those greek letters to Rust types.
-pub trait XMachine {
+/// This trait maps those greek letters to Rust types. Machines are types
+/// without borrowed data, so tables derived from them can be kept per type.
+pub trait XMachine: 'static {
     /// Sigma (Σ): The Input Alphabet.
     type Input: Clone + core::fmt::Debug + PartialEq + 'static;
 
@@ -13,7 +70,7 @@ pub trait XMachine {
     type State: Copy + Clone + PartialEq + core::fmt::Debug + 'static;
 
     /// M: Possibly infinite set called memory.
-    type Memory: Clone;
+    type Memory: Clone + 'static;
 
     /// Phi: The finite set of partial functions.
     type Phi: Copy + Clone + PartialEq + core::fmt::Debug + 'static;
@@ -31,15 +88,21 @@ pub trait XMachine {
     fn initial_store() -> Self::Memory;
 
     /// Describes how Phi is executed
+    #[allow(clippy::result_unit_err)]
     fn execute_phi(
         phi: Self::Phi,
         store: &mut Self::Memory,
         input: &Self::Input,
     ) -> Result<Option<Self::Output>, ()>;
 
-    fn all_inputs() -> &'static [Self::Input];
-    fn all_outputs() -> &'static [Self::Output];
-    
+    /// Returns the input alphabet (Σ).
+    /// Borrow a `static` slice for plain enums; build an owned `Vec` when
+    /// symbols carry runtime data such as `String`s.
+    fn all_inputs() -> Cow<'static, [Self::Input]>;
+
+    /// Returns the output alphabet (Γ).
+    fn all_outputs() -> Cow<'static, [Self::Output]>;
+
     /// Returns a list of all possible states (Q)
     fn all_states() -> &'static [Self::State];
 
@@ -47,4 +110,187 @@ pub trait XMachine {
     fn all_phis() -> &'static [Self::Phi];
 
     fn get_phi_for_input(state: Self::State, input: &Self::Input) -> Option<Self::Phi>;
-}
\ No newline at end of file
+
+    /// Distinguishes inputs the implementation must reject from inputs the spec
+    /// does not constrain. The default treats every input without a phi as `Forbidden`.
+    fn transition_spec(state: Self::State, input: &Self::Input) -> TransitionSpec<Self::Phi> {
+        match Self::get_phi_for_input(state, input) {
+            Some(phi) => TransitionSpec::Defined(phi),
+            None => TransitionSpec::Forbidden,
+        }
+    }
+
+    /// Human-readable description of the data condition under which `phi` applies,
+    /// e.g. "entered sequence equals the valid code". Embedded into test names.
+    fn phi_guard_doc(_phi: Self::Phi) -> Option<&'static str> {
+        None
+    }
+
+    /// Human-readable description of what `state` stands for.
+    /// Rendered in generated documentation.
+    fn state_doc(_state: Self::State) -> Option<&'static str> {
+        None
+    }
+
+    /// Invariants holding on entry to `state`. Rendered in state diagrams and
+    /// checked by `analysis::invariant_violations`.
+    fn state_invariants(_state: Self::State) -> Vec<StateInvariant<Self::Memory>> {
+        Vec::new()
+    }
+
+    /// Identifiers of the requirements that the transition `(state, phi)` implements.
+    /// Propagated into generated test cases for traceability.
+    fn requirement_ids(_state: Self::State, _phi: Self::Phi) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Cost of running `phi` in `state` on the real system, e.g. high for a
+    /// transition that moves a physical door. The searches behind generated
+    /// setup sequences minimise the total cost; with the default of 1 they
+    /// return shortest sequences.
+    fn transition_cost(_state: Self::State, _phi: Self::Phi) -> u32 {
+        1
+    }
+
+    /// Classifies an input for compositions.
+    /// `None` defers to inference from the `TryFrom` adapters.
+    fn input_visibility(_input: &Self::Input) -> Option<Visibility> {
+        None
+    }
+
+    /// Classifies an output for compositions.
+    /// `None` defers to inference from the `TryFrom` adapters.
+    ///
+    /// `Visibility::Internal` also marks an output the real system does not
+    /// expose, e.g. one that only drives composition routing: the executor
+    /// does not assert on it and context diagrams leave it out.
+    fn output_visibility(_output: &Self::Output) -> Option<Visibility> {
+        None
+    }
+
+    /// An input returning the machine to an initial configuration from any
+    /// state, e.g. a power-cycle command. The executor falls back on it when
+    /// the SUT has no reset of its own, and `SxMTester::generate_reset_tests`
+    /// checks the implementation honours it.
+    fn reset_input() -> Option<Self::Input> {
+        None
+    }
+
+    /// Marks `phi` as an explicit ignore, returning the output it must emit:
+    /// a self-loop acknowledging an input without acting on it, as opposed
+    /// to an input with no phi at all. `analysis::input_handling` tells the
+    /// two apart and `SxMTester::generate_ignore_tests` checks the output.
+    fn ignore_output(_phi: Self::Phi) -> Option<Self::Output> {
+        None
+    }
+
+    /// An input the environment sends periodically, e.g. a timer tick that
+    /// drives timeouts. `simulation::TickSimulation::for_machine` injects it
+    /// between the inputs of a simulation run.
+    fn tick_input() -> Option<Self::Input> {
+        None
+    }
+
+    /// Whether `output` belongs to the error channel. Runner metrics count
+    /// error outputs, sequence and configuration diagrams draw them apart,
+    /// and the executor reports an unexpected one as `Verdict::UnexpectedError`.
+    fn output_kind(_output: &Self::Output) -> OutputKind {
+        OutputKind::Regular
+    }
+
+    /// How symbols are named in diagrams, documents and generated test
+    /// names. `Labeling::display` keeps Rust syntax out of stakeholder-facing
+    /// artifacts.
+    fn labeling() -> Labeling<Self> {
+        Labeling::Debug
+    }
+
+    /// The active sub-state of each concurrent region of `state`, labelled,
+    /// for states composed of regions: `sxm_regions!` lists its regions'
+    /// sub-states and `sxm_composite!` its top-level state followed by those
+    /// of its regions. A plain state is its own single entry.
+    fn active_states(state: Self::State) -> Vec<String> {
+        vec![Self::labeling().label(Symbol::State(state))]
+    }
+
+    /// Ordinal of `state` within `all_states()`.
+    /// Override with a direct cast when `State` is a dense enum.
+    fn state_index(state: Self::State) -> Option<StateIndex> {
+        Self::all_states().iter().position(|&s| s == state).map(StateIndex)
+    }
+
+    /// Inverse of `state_index`.
+    fn state_from_index(index: StateIndex) -> Option<Self::State> {
+        Self::all_states().get(index.0).copied()
+    }
+
+    /// The kind `phi` is an instance of, for phis that bind a parameter of
+    /// the input, e.g. `InputDigit(4)` selected by `Digit(4)`.
+    ///
+    /// `all_phis()` lists one representative per kind, and topology is keyed
+    /// on kinds: phi indices, diagrams, coverage and runner metrics. The
+    /// bound instance is what `get_phi_for_input` returns and `execute_phi`
+    /// receives, so `next_state` and the other per-phi hooks must accept any
+    /// instance of a kind. The default makes every phi its own kind.
+    fn phi_kind(phi: Self::Phi) -> Self::Phi {
+        phi
+    }
+
+    /// Ordinal of the kind of `phi` within `all_phis()`.
+    fn phi_index(phi: Self::Phi) -> Option<PhiIndex> {
+        let kind = Self::phi_kind(phi);
+        Self::all_phis().iter().position(|&p| p == kind).map(PhiIndex)
+    }
+
+    /// Inverse of `phi_index`.
+    fn phi_from_index(index: PhiIndex) -> Option<Self::Phi> {
+        Self::all_phis().get(index.0).copied()
+    }
+
+    /// Ordinal of `input` within `all_inputs()`.
+    /// The default scans the alphabet, which an owned `all_inputs()` builds
+    /// only once per thread.
+    fn input_index(input: &Self::Input) -> Option<InputIndex> {
+        crate::index::inputs::<Self>().iter().position(|i| i == input).map(InputIndex)
+    }
+
+    /// Inverse of `input_index`.
+    fn input_from_index(index: InputIndex) -> Option<Self::Input> {
+        crate::index::inputs::<Self>().get(index.0).cloned()
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodePhi, DigicodeState};
+    use crate::examples::vending_machine::{VendingInput, VendingMachine, VendingPhi, VendingState};
+
+    #[test]
+    fn default_indices_round_trip() {
+        for (idx, input) in VendingMachine::all_inputs().iter().enumerate() {
+            assert_eq!(VendingMachine::input_index(input), Some(InputIndex(idx)));
+            assert_eq!(VendingMachine::input_from_index(InputIndex(idx)).as_ref(), Some(input));
+        }
+        assert_eq!(VendingMachine::input_index(&VendingInput::Coin(3)), None);
+        for (idx, &phi) in VendingMachine::all_phis().iter().enumerate() {
+            assert_eq!(VendingMachine::phi_index(phi), Some(PhiIndex(idx)));
+            assert_eq!(VendingMachine::phi_from_index(PhiIndex(idx)), Some(phi));
+        }
+        let coin = VendingMachine::phi_index(VendingPhi::InsertCoin(25));
+        assert_eq!(coin, VendingMachine::phi_index(VendingPhi::InsertCoin(0)));
+        let credit = VendingMachine::state_index(VendingState::HasCredit).unwrap();
+        assert_eq!(VendingMachine::state_from_index(credit), Some(VendingState::HasCredit));
+    }
+
+    #[test]
+    fn inputs_without_a_phi_are_forbidden_by_default() {
+        let ready = DigicodeState::Ready;
+        assert_eq!(
+            Digicode::transition_spec(ready, &DigicodeInputAlphabet::Digit(4)),
+            TransitionSpec::Defined(DigicodePhi::InputDigit)
+        );
+        assert_eq!(Digicode::transition_spec(ready, &DigicodeInputAlphabet::DoorCloses), TransitionSpec::Forbidden);
+        assert_eq!(Digicode::active_states(ready), ["Ready"]);
+        assert_eq!(Digicode::transition_cost(ready, DigicodePhi::InputDigit), 1);
+    }
+}
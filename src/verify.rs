@@ -0,0 +1,370 @@
+use crate::configuration::Configuration;
+use crate::store::Store;
+use crate::XMachine;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Why a concrete trace has no abstract counterpart.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RefinementError<AbstractState> {
+    /// A concrete initial state maps to a state that is not initial in the abstract machine.
+    InitialState { mapped: AbstractState },
+    /// The abstract machine rejects the mapped input.
+    AbstractRejects { abstract_state: AbstractState },
+    /// Both machines take the step, but the concrete target maps to a
+    /// different state than the abstract target.
+    StateMismatch {
+        expected: AbstractState,
+        mapped: AbstractState,
+    },
+}
+
+/// A concrete trace whose last step breaks the refinement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefinementViolation<ConcreteInput, AbstractState> {
+    /// Concrete inputs from the initial configuration; the last one is the offending step.
+    pub trace: Vec<ConcreteInput>,
+    pub error: RefinementError<AbstractState>,
+}
+
+/// `RefinementViolation` specialised to the machines `A` (abstract) and `C` (concrete).
+pub type RefinementViolationOf<A, C> =
+    RefinementViolation<<C as XMachine>::Input, <A as XMachine>::State>;
+
+/// Checks that every concrete trace of at most `depth` inputs maps to a trace
+/// of the abstract machine.
+///
+/// `state_map` abstracts concrete states. `input_map` abstracts concrete inputs;
+/// `None` marks an internal concrete step, which must not change the mapped
+/// state (stuttering). Rejections by the concrete machine are always allowed:
+/// refinement is trace inclusion, so the concrete machine may do less.
+/// Only states are compared: outputs of the two machines are not.
+///
+/// Returns one violation per offending (configuration, input) pair; traces are
+/// not extended past a violation.
+pub fn refines<A, C>(
+    state_map: impl Fn(C::State) -> A::State,
+    input_map: impl Fn(&C::Input) -> Option<A::Input>,
+    depth: usize,
+) -> Vec<RefinementViolationOf<A, C>>
+where
+    A: XMachine,
+    C: XMachine,
+    A::Memory: Store,
+    C::Memory: Store,
+{
+    let mut violations = Vec::new();
+    // States need not be hashable, so visited pairs are keyed by their ordinals.
+    let mut seen = HashSet::new();
+    let mut first_visit = |concrete: &Configuration<C>, abstract_config: &Configuration<A>| {
+        seen.insert((
+            C::state_index(concrete.state),
+            concrete.store.clone(),
+            A::state_index(abstract_config.state),
+            abstract_config.store.clone(),
+        ))
+    };
+    let mut queue = VecDeque::new();
+
+    for &start in C::initial_states() {
+        let mapped = state_map(start);
+        if !A::initial_states().contains(&mapped) {
+            violations.push(RefinementViolation {
+                trace: Vec::new(),
+                error: RefinementError::InitialState { mapped },
+            });
+            continue;
+        }
+        let pair = (
+            Configuration::<C>::new(start, C::initial_store()),
+            Configuration::<A>::new(mapped, A::initial_store()),
+        );
+        if first_visit(&pair.0, &pair.1) {
+            queue.push_back((pair, Vec::new()));
+        }
+    }
+
+    let inputs = C::all_inputs();
+    while let Some(((concrete, abstract_config), trace)) = queue.pop_front() {
+        if trace.len() >= depth {
+            continue;
+        }
+        for input in inputs.iter() {
+            let Ok((_, next_concrete)) = concrete.step(input) else {
+                continue;
+            };
+            let mut next_trace = trace.clone();
+            next_trace.push(input.clone());
+
+            let mapped = state_map(next_concrete.state);
+            let next_abstract = match input_map(input) {
+                None if mapped != abstract_config.state => Err(RefinementError::StateMismatch {
+                    expected: abstract_config.state,
+                    mapped,
+                }),
+                None => Ok(abstract_config.clone()),
+                Some(abstract_input) => match abstract_config.step(&abstract_input) {
+                    Err(_) => Err(RefinementError::AbstractRejects {
+                        abstract_state: abstract_config.state,
+                    }),
+                    Ok((t, _)) if t.to != mapped => Err(RefinementError::StateMismatch {
+                        expected: t.to,
+                        mapped,
+                    }),
+                    Ok((_, next)) => Ok(next),
+                },
+            };
+
+            match next_abstract {
+                Err(error) => violations.push(RefinementViolation {
+                    trace: next_trace,
+                    error,
+                }),
+                Ok(next_abstract) => {
+                    if first_visit(&next_concrete, &next_abstract) {
+                        queue.push_back(((next_concrete, next_abstract), next_trace));
+                    }
+                }
+            }
+        }
+    }
+    violations
+}
+
+/// Shortest input trace of at most `bound` inputs from an initial configuration
+/// to one satisfying `predicate`, or `None` if no such trace exists.
+pub fn eventually_reaches<T>(predicate: impl Fn(&Configuration<T>) -> bool, bound: usize) -> Option<Vec<T::Input>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    shortest_trace(Configuration::<T>::initial(), &predicate, bound)
+}
+
+/// Checks that `state` stays reachable: every configuration reachable within
+/// `bound` inputs is in `state` or can enter it within `bound` inputs.
+///
+/// Explores the configurations reachable within twice the bound once, then
+/// computes the distance to `state` of all of them with a single backward
+/// search over the explored steps.
+///
+/// On failure, returns a trace to a configuration from which `state` cannot
+/// be reached within the bound.
+pub fn always_can_reach<T>(state: T::State, bound: usize) -> Result<(), Vec<T::Input>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut nodes: Vec<Node<T>> = Vec::new();
+    let mut index: HashMap<T::Memory, Vec<(T::State, usize)>> = HashMap::new();
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    let mut intern = |config: Configuration<T>, parent: Option<(usize, T::Input)>, depth: usize, nodes: &mut Vec<_>| {
+        let known = index.entry(config.store.clone()).or_default();
+        if let Some(&(_, i)) = known.iter().find(|(s, _)| *s == config.state) {
+            return i;
+        }
+        known.push((config.state, nodes.len()));
+        nodes.push(Node { config, parent, depth });
+        nodes.len() - 1
+    };
+
+    for config in Configuration::<T>::initial() {
+        intern(config, None, 0, &mut nodes);
+    }
+    let horizon = bound.saturating_mul(2);
+    let inputs = T::all_inputs();
+    let mut next_node = 0;
+    while next_node < nodes.len() {
+        let (from, depth) = (next_node, nodes[next_node].depth);
+        next_node += 1;
+        if depth >= horizon {
+            continue;
+        }
+        for input in inputs.iter() {
+            let Ok((_, next)) = nodes[from].config.step(input) else {
+                continue;
+            };
+            let to = intern(next, Some((from, input.clone())), depth + 1, &mut nodes);
+            edges.push((from, to));
+        }
+    }
+
+    let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); nodes.len()];
+    for (from, to) in edges {
+        predecessors[to].push(from);
+    }
+
+    let mut distance: Vec<Option<usize>> = vec![None; nodes.len()];
+    let mut queue = VecDeque::new();
+    for (i, node) in nodes.iter().enumerate() {
+        if node.config.state == state {
+            distance[i] = Some(0);
+            queue.push_back(i);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        let d = distance[i].unwrap_or(0);
+        if d >= bound {
+            continue;
+        }
+        for &p in &predecessors[i] {
+            if distance[p].is_none() {
+                distance[p] = Some(d + 1);
+                queue.push_back(p);
+            }
+        }
+    }
+
+    // Nodes are in breadth-first order, so the first failure has a shortest trace.
+    let Some(stuck) = (0..nodes.len()).find(|&i| nodes[i].depth <= bound && distance[i].is_none()) else {
+        return Ok(());
+    };
+    let mut trace = Vec::new();
+    let mut at = stuck;
+    while let Some((parent, input)) = &nodes[at].parent {
+        trace.push(input.clone());
+        at = *parent;
+    }
+    trace.reverse();
+    Err(trace)
+}
+
+/// A configuration explored by `always_can_reach`, with the step it was first
+/// reached by and its depth.
+struct Node<T: XMachine> {
+    config: Configuration<T>,
+    parent: Option<(usize, T::Input)>,
+    depth: usize,
+}
+
+/// Breadth-first search from `starts` for a configuration satisfying `predicate`.
+fn shortest_trace<T>(
+    starts: Vec<Configuration<T>>,
+    predicate: &dyn Fn(&Configuration<T>) -> bool,
+    bound: usize,
+) -> Option<Vec<T::Input>>
+where
+    T: XMachine,
+    T::Memory: Store,
+{
+    let mut seen: Vec<Configuration<T>> = Vec::new();
+    let mut queue = VecDeque::new();
+    for config in starts {
+        if predicate(&config) {
+            return Some(Vec::new());
+        }
+        if !seen.contains(&config) {
+            seen.push(config.clone());
+            queue.push_back((config, Vec::new()));
+        }
+    }
+
+    let inputs = T::all_inputs();
+    while let Some((config, trace)) = queue.pop_front() {
+        if trace.len() >= bound {
+            continue;
+        }
+        for input in inputs.iter() {
+            let Ok((_, next)) = config.step(input) else {
+                continue;
+            };
+            let mut next_trace = trace.clone();
+            next_trace.push(input.clone());
+            if predicate(&next) {
+                return Some(next_trace);
+            }
+            if !seen.contains(&next) {
+                seen.push(next.clone());
+                queue.push_back((next, next_trace));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::examples::admin_digicode::{AdminDigicode, AdminInput, AdminState};
+    use crate::examples::secure_door::{Digicode, DigicodeInputAlphabet, DigicodeState};
+    use crate::examples::traffic_light::{LightState, TrafficLight};
+    use crate::extend::Ext;
+
+    fn base_state(state: Ext<DigicodeState, AdminState>) -> DigicodeState {
+        match state {
+            Ext::Base(state) => state,
+            Ext::Own(own) => match own {},
+        }
+    }
+
+    #[test]
+    fn digicode_refines_its_admin_extension() {
+        let violations = refines::<AdminDigicode, Digicode>(Ext::Base, |input| Some(Ext::Base(input.clone())), 5);
+        assert_eq!(violations, Vec::new());
+    }
+
+    #[test]
+    fn an_admin_key_taken_as_internal_step_changes_the_state() {
+        let violations = refines::<Digicode, AdminDigicode>(
+            base_state,
+            |input| match input {
+                Ext::Base(input) => Some(input.clone()),
+                Ext::Own(AdminInput::AdminKey) => None,
+            },
+            1,
+        );
+        assert_eq!(
+            violations,
+            [RefinementViolation {
+                trace: vec![Ext::Own(AdminInput::AdminKey)],
+                error: RefinementError::StateMismatch {
+                    expected: DigicodeState::Ready,
+                    mapped: DigicodeState::CodeEntered,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn an_admin_key_mapped_to_an_unexpected_input_is_rejected() {
+        let violations = refines::<Digicode, AdminDigicode>(
+            base_state,
+            |input| match input {
+                Ext::Base(input) => Some(input.clone()),
+                Ext::Own(AdminInput::AdminKey) => Some(DigicodeInputAlphabet::DoorCloses),
+            },
+            1,
+        );
+        assert_eq!(
+            violations,
+            [RefinementViolation {
+                trace: vec![Ext::Own(AdminInput::AdminKey)],
+                error: RefinementError::AbstractRejects {
+                    abstract_state: DigicodeState::Ready,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn eventually_reaches_finds_the_shortest_witness() {
+        use DigicodeInputAlphabet::*;
+        let unlocked = |config: &Configuration<Digicode>| config.state == DigicodeState::CodeEntered;
+        assert_eq!(eventually_reaches(unlocked, 6), Some(vec![Digit(4), Digit(9), Digit(2), OkEnter]));
+        assert_eq!(eventually_reaches(unlocked, 4), Some(vec![Digit(4), Digit(9), Digit(2), OkEnter]));
+    }
+
+    #[test]
+    fn eventually_reaches_gives_up_at_the_bound() {
+        let unlocked = |config: &Configuration<Digicode>| config.state == DigicodeState::CodeEntered;
+        assert_eq!(eventually_reaches(unlocked, 3), None);
+        assert_eq!(eventually_reaches::<TrafficLight>(|config| config.state == LightState::Yellow, 1), None);
+        assert_eq!(eventually_reaches::<TrafficLight>(|config| config.state == LightState::Red, 0), Some(Vec::new()));
+    }
+
+    #[test]
+    fn always_can_reach_needs_every_reachable_configuration_within_the_bound() {
+        assert_eq!(always_can_reach::<TrafficLight>(LightState::Yellow, 2), Ok(()));
+        assert_eq!(always_can_reach::<TrafficLight>(LightState::Yellow, 1), Err(Vec::new()));
+        assert_eq!(always_can_reach::<TrafficLight>(LightState::Red, 1), Ok(()));
+    }
+}
@@ -0,0 +1,299 @@
+//! The YAML subset used by machine descriptions: block maps and lists,
+//! flow lists of scalars, quoted and plain scalars, and `#` comments.
+//! Anchors, multi-line scalars and flow maps are not supported.
+
+/// A parsed YAML node. Map entries keep their document order.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Value {
+    Scalar(String),
+    List(Vec<Value>),
+    Map(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_scalar(&self) -> Option<&str> {
+        match self {
+            Value::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+/// Parse failure: 1-based line number and description.
+pub(crate) type ParseError = (usize, String);
+
+struct Line {
+    number: usize,
+    indent: usize,
+    content: String,
+}
+
+pub(crate) fn parse(text: &str) -> Result<Value, ParseError> {
+    let mut lines: Vec<Line> = Vec::new();
+    for (idx, raw) in text.lines().enumerate() {
+        let content = strip_comment(raw).trim_end();
+        if content.trim().is_empty() || content == "---" {
+            continue;
+        }
+        if content.starts_with('\t') {
+            return Err((idx + 1, "tabs are not allowed for indentation".to_string()));
+        }
+        let indent = content.len() - content.trim_start().len();
+        lines.push(Line {
+            number: idx + 1,
+            indent,
+            content: content.trim_start().to_string(),
+        });
+    }
+    if lines.is_empty() {
+        return Ok(Value::Map(Vec::new()));
+    }
+    let mut pos = 0;
+    let indent = lines[0].indent;
+    let value = parse_block(&mut lines, &mut pos, indent)?;
+    match lines.get(pos) {
+        Some(line) => Err((line.number, "unexpected indentation".to_string())),
+        None => Ok(value),
+    }
+}
+
+fn parse_block(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Value, ParseError> {
+    if is_list_item(&lines[*pos].content) {
+        parse_list(lines, pos, indent)
+    } else {
+        parse_map(lines, pos, indent)
+    }
+}
+
+fn parse_list(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Value, ParseError> {
+    let mut items = Vec::new();
+    while *pos < lines.len() && lines[*pos].indent == indent && is_list_item(&lines[*pos].content) {
+        let rest = lines[*pos].content[1..].trim_start().to_string();
+        if rest.is_empty() {
+            *pos += 1;
+            items.push(parse_nested(lines, pos, indent)?);
+        } else if split_key(&rest).is_some() {
+            // `- key: value` starts a map whose entries align with `key`.
+            let offset = lines[*pos].content.len() - rest.len();
+            lines[*pos].indent += offset;
+            lines[*pos].content = rest;
+            let map_indent = lines[*pos].indent;
+            items.push(parse_map(lines, pos, map_indent)?);
+        } else {
+            items.push(parse_inline(&rest, lines[*pos].number)?);
+            *pos += 1;
+        }
+    }
+    Ok(Value::List(items))
+}
+
+fn parse_map(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Value, ParseError> {
+    let mut entries: Vec<(String, Value)> = Vec::new();
+    while *pos < lines.len() && lines[*pos].indent == indent && !is_list_item(&lines[*pos].content) {
+        let number = lines[*pos].number;
+        let Some((key, rest)) = split_key(&lines[*pos].content) else {
+            return Err((number, format!("expected `key: value`, found {:?}", lines[*pos].content)));
+        };
+        if entries.iter().any(|(k, _)| *k == key) {
+            return Err((number, format!("duplicate key {:?}", key)));
+        }
+        *pos += 1;
+        let value = if rest.is_empty() {
+            let same_level_list = *pos < lines.len()
+                && lines[*pos].indent == indent
+                && is_list_item(&lines[*pos].content);
+            if same_level_list {
+                parse_list(lines, pos, indent)?
+            } else {
+                parse_nested(lines, pos, indent)?
+            }
+        } else {
+            parse_inline(&rest, number)?
+        };
+        entries.push((key, value));
+    }
+    Ok(Value::Map(entries))
+}
+
+/// The block indented below the line just consumed, or an empty scalar.
+fn parse_nested(lines: &mut [Line], pos: &mut usize, parent_indent: usize) -> Result<Value, ParseError> {
+    match lines.get(*pos) {
+        Some(line) if line.indent > parent_indent => {
+            let indent = line.indent;
+            parse_block(lines, pos, indent)
+        }
+        _ => Ok(Value::Scalar(String::new())),
+    }
+}
+
+fn parse_inline(text: &str, number: usize) -> Result<Value, ParseError> {
+    let Some(inner) = text.strip_prefix('[') else {
+        return parse_scalar(text, number).map(Value::Scalar);
+    };
+    let Some(inner) = inner.strip_suffix(']') else {
+        return Err((number, "unterminated flow list".to_string()));
+    };
+    if inner.trim().is_empty() {
+        return Ok(Value::List(Vec::new()));
+    }
+    split_outside_quotes(inner, ',')
+        .iter()
+        .map(|item| parse_scalar(item.trim(), number).map(Value::Scalar))
+        .collect::<Result<_, _>>()
+        .map(Value::List)
+}
+
+fn parse_scalar(text: &str, number: usize) -> Result<String, ParseError> {
+    if let Some(inner) = text.strip_prefix('"') {
+        let Some(inner) = inner.strip_suffix('"') else {
+            return Err((number, "unterminated double-quoted scalar".to_string()));
+        };
+        let mut out = String::new();
+        let mut chars = inner.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                other => return Err((number, format!("unsupported escape \\{}", other.unwrap_or(' ')))),
+            }
+        }
+        Ok(out)
+    } else if let Some(inner) = text.strip_prefix('\'') {
+        match inner.strip_suffix('\'') {
+            Some(inner) => Ok(inner.replace("''", "'")),
+            None => Err((number, "unterminated single-quoted scalar".to_string())),
+        }
+    } else {
+        Ok(text.trim().to_string())
+    }
+}
+
+/// Renders `text` as a double-quoted scalar.
+pub(crate) fn quote(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn is_list_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+/// Characters of `text` outside quoted scalars, with their byte offsets.
+fn unquoted(text: &str) -> Vec<(usize, char)> {
+    let mut out = Vec::new();
+    let mut quote = None;
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => out.push((idx, c)),
+        }
+    }
+    out
+}
+
+/// Splits `key: value` at the first colon outside quotes that ends the line
+/// or is followed by a space.
+fn split_key(content: &str) -> Option<(String, String)> {
+    let idx = unquoted(content).into_iter().find_map(|(idx, c)| {
+        let rest = &content[idx + 1..];
+        (c == ':' && (rest.is_empty() || rest.starts_with(' '))).then_some(idx)
+    })?;
+    let key = parse_scalar(content[..idx].trim(), 0).ok()?;
+    Some((key, content[idx + 1..].trim().to_string()))
+}
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (idx, c) in unquoted(text) {
+        if c == separator {
+            parts.push(&text[start..idx]);
+            start = idx + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Cuts a `#` comment that starts the line or follows whitespace.
+fn strip_comment(line: &str) -> &str {
+    for (idx, c) in unquoted(line) {
+        if c == '#' && line[..idx].chars().last().map_or(true, char::is_whitespace) {
+            return &line[..idx];
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(text: &str) -> Value {
+        Value::Scalar(text.to_string())
+    }
+
+    #[test]
+    fn quoted_scalars_parse_back_to_themselves() {
+        for text in ["plain", "a: \"colon\" # not a comment", "back\\slash", "tab\tand\nnewline"] {
+            assert_eq!(parse(&format!("key: {}", quote(text))).unwrap().get("key"), Some(&scalar(text)));
+        }
+    }
+
+    #[test]
+    fn block_maps_and_lists_nest() {
+        let doc = parse(
+            "# a machine\n\
+             name: door\n\
+             states: [Open, 'Closed']\n\
+             transitions:\n\
+             - from: Open\n  \
+               to: Closed # shut\n\
+             - from: Closed\n  \
+               to: Open\n",
+        )
+        .unwrap();
+        assert_eq!(doc.get("name").and_then(Value::as_scalar), Some("door"));
+        assert_eq!(doc.get("states"), Some(&Value::List(vec![scalar("Open"), scalar("Closed")])));
+        let transitions = doc.get("transitions").and_then(Value::as_list).unwrap();
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].get("to"), Some(&scalar("Closed")));
+        assert_eq!(transitions[1].get("from"), Some(&scalar("Closed")));
+        assert_eq!(parse("a: 1\na: 2").map_err(|(line, _)| line), Err(2));
+    }
+}